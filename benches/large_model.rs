@@ -0,0 +1,62 @@
+//! Parses generated models of growing size and checks the time per line stays flat, so
+//! parsing stays linear, with the character parser and in two phases. Run with
+//! `cargo bench --bench large_model`, with `--features memchr` to compare faster scanning.
+
+use std::time::{Duration, Instant};
+
+use concerto_nom::{
+    parser::{options::ParserOptions, parse_with},
+    testgen::{generate, Shape},
+};
+
+/// How much slower per line the largest model may parse than the smallest one
+const MAX_SLOWDOWN: f64 = 2.5;
+
+/// Fastest of a few runs, to keep noise out of the comparison
+fn time(source: &str, options: &ParserOptions) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            let model = parse_with(source, options).expect("generated model should parse");
+            let elapsed = start.elapsed();
+            drop(model);
+            elapsed
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let two_phase = ParserOptions {
+        two_phase: true,
+        ..ParserOptions::default()
+    };
+    for (name, options) in [
+        ("character", ParserOptions::default()),
+        ("two-phase", two_phase),
+    ] {
+        println!("{name}");
+        let mut per_line = Vec::new();
+        for declarations in [1_000, 10_000, 100_000] {
+            let shape = Shape {
+                declarations,
+                properties: 8,
+                depth: 5,
+                validator_density: 0.5,
+                seed: 1,
+            };
+            let source = generate("bench", &shape);
+            let lines = source.lines().count();
+            let elapsed = time(&source, &options);
+            let nanos = elapsed.as_nanos() as f64 / lines as f64;
+            println!("{lines:>9} lines  {elapsed:>12.2?}  {nanos:>8.1} ns/line");
+            per_line.push(nanos);
+        }
+
+        let slowdown = per_line[per_line.len() - 1] / per_line[0];
+        assert!(
+            slowdown < MAX_SLOWDOWN,
+            "{name} parsing got {slowdown:.1}x slower per line on the largest model"
+        );
+    }
+}
@@ -0,0 +1,543 @@
+//! Analyses over the models loaded in a `ModelManager`
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    manager::ModelManager,
+    parser::{
+        common::identifier::is_confusable,
+        declaration::{Declaration, Property, TypeReference},
+        import::Import,
+        namespace::{FullyQualifiedName, Namespace},
+        property::CommonProperty,
+        version::{SemanticVersion, VersionRequirement},
+        Model,
+    },
+};
+
+/// An import, and the namespace of the model it is written in
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportSite {
+    pub model: Namespace,
+    pub import: Import,
+}
+
+/// Two imports of the same namespace that no single loaded version can satisfy
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportConflict {
+    pub first: ImportSite,
+    pub second: ImportSite,
+}
+
+impl std::fmt::Display for ImportConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}@{} requires ^{} but {}@{} requires ^{}",
+            self.first.model.name(),
+            self.first.model.version(),
+            self.first.import.namespace().version(),
+            self.second.model.name(),
+            self.second.model.version(),
+            self.second.import.namespace().version()
+        )
+    }
+}
+
+/// Declarations no property, `extends` clause or import of the loaded models refers to, in
+/// the order the models were added. References of a declaration to itself don't count.
+/// Declarations named in `entry_points` are used from outside the models, and never
+/// reported.
+pub fn unreferenced<'a>(
+    manager: &'a ModelManager,
+    entry_points: &[FullyQualifiedName],
+) -> Vec<(&'a Model, &'a Declaration)> {
+    let mut referenced: HashSet<*const Declaration> = HashSet::new();
+    for model in manager.models() {
+        for declaration in &model.declarations {
+            let property_types = declaration.properties.iter().filter_map(|p| match p {
+                Property::Concept(p) => manager.resolve_property_in(model, p),
+                _ => None,
+            });
+            let targets = manager
+                .resolve_extended_in(model, declaration)
+                .into_iter()
+                .chain(property_types);
+            for (_, target) in targets {
+                if !std::ptr::eq(target, declaration) {
+                    referenced.insert(target);
+                }
+            }
+        }
+        for import in &model.imports {
+            if let Some(target) = manager.resolve_import(import) {
+                referenced.extend(
+                    target
+                        .declarations
+                        .iter()
+                        .filter(|d| import.local_name(&d.name).is_some())
+                        .map(|d| d as *const Declaration),
+                );
+            }
+        }
+    }
+
+    let is_entry_point = |model: &Model, declaration: &Declaration| {
+        entry_points.iter().any(|fqn| {
+            fqn.namespace_name() == model.namespace.name()
+                && fqn.version() == model.namespace.version()
+                && fqn.type_name() == declaration.name
+        })
+    };
+    manager
+        .models()
+        .flat_map(|m| m.declarations.iter().map(move |d| (m, d)))
+        .filter(|(m, d)| !referenced.contains(&(*d as *const _)) && !is_entry_point(m, d))
+        .collect()
+}
+
+/// Pairs of imports across the loaded models that require incompatible versions of the same
+/// namespace. Imports are caret requirements as in `ModelManager::resolve_import`, so
+/// `1.0.0` and `1.2.0` are compatible, while `1.0.0` and `2.1.0` are not.
+pub fn import_conflicts(manager: &ModelManager) -> Vec<ImportConflict> {
+    let sites: Vec<_> = manager
+        .models()
+        .flat_map(|m| m.imports.iter().map(move |i| (m, i)))
+        .collect();
+    let compatible = |a: &Namespace, b: &Namespace| {
+        VersionRequirement::caret(a.version()).matches(b.version())
+            || VersionRequirement::caret(b.version()).matches(a.version())
+    };
+
+    let mut conflicts = Vec::new();
+    for (index, (first_model, first)) in sites.iter().enumerate() {
+        for (second_model, second) in &sites[index + 1..] {
+            let (a, b) = (first.namespace(), second.namespace());
+            if a.name() == b.name() && !compatible(a, b) {
+                conflicts.push(ImportConflict {
+                    first: ImportSite {
+                        model: first_model.namespace.clone(),
+                        import: (*first).clone(),
+                    },
+                    second: ImportSite {
+                        model: second_model.namespace.clone(),
+                        import: (*second).clone(),
+                    },
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Imports, property types and super types of a model that don't resolve to a loaded
+/// declaration
+pub fn unresolved(manager: &ModelManager, model: &Model) -> Vec<String> {
+    unresolved_with(
+        model,
+        |import| manager.resolve_import(import).is_some(),
+        |reference| {
+            manager
+                .resolve_reference_in(model, &reference.name, reference.namespace.as_ref())
+                .is_some()
+        },
+    )
+}
+
+/// Properties of a model whose types are declarations decorated with `@deprecated`, and
+/// declarations extending those. Deprecated declarations aren't reported for using other
+/// deprecated types.
+pub fn deprecated(manager: &ModelManager, model: &Model) -> Vec<String> {
+    let mut messages = Vec::new();
+    for declaration in model.declarations.iter().filter(|d| !d.is_deprecated()) {
+        match manager.resolve_extended_in(model, declaration) {
+            Some((_, extended)) if extended.is_deprecated() => messages.push(format!(
+                "deprecated super type {} of {}",
+                extended.name, declaration.name
+            )),
+            _ => (),
+        }
+        for property in &declaration.properties {
+            if let Property::Concept(p) = property {
+                match manager.resolve_property_in(model, p) {
+                    Some((_, used)) if used.is_deprecated() => messages.push(format!(
+                        "deprecated type {} of {}.{}",
+                        p.type_reference(),
+                        declaration.name,
+                        p.name
+                    )),
+                    _ => (),
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Names of a model that could be mistaken for one another, like `Paul` and `Раul` with a
+/// Cyrillic `Р`: declarations, and the properties of each declaration
+pub fn confusables(model: &Model) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut report = |names: Vec<&str>, of: &str| {
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                if is_confusable(a, b) {
+                    messages.push(format!("confusable names {} and {}{}", a, b, of));
+                }
+            }
+        }
+    };
+    report(
+        model.declarations.iter().map(|d| d.name.as_str()).collect(),
+        "",
+    );
+    for declaration in &model.declarations {
+        report(
+            declaration.properties.iter().map(|p| p.name()).collect(),
+            &format!(" of {}", declaration.name),
+        );
+    }
+    messages
+}
+
+fn unresolved_with(
+    model: &Model,
+    resolves_import: impl Fn(&Import) -> bool,
+    resolves_type: impl Fn(&TypeReference) -> bool,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+    for import in &model.imports {
+        if !resolves_import(import) {
+            messages.push(format!("unresolved import {}", import));
+        }
+    }
+    for declaration in &model.declarations {
+        if let Some(super_type) = &declaration.super_type {
+            if !resolves_type(super_type) {
+                messages.push(format!(
+                    "unknown super type {} of {}",
+                    super_type, declaration.name
+                ));
+            }
+        }
+        for property in &declaration.properties {
+            if let Property::Concept(p) = property {
+                if !resolves_type(&p.type_reference()) {
+                    messages.push(format!(
+                        "unknown type {} of {}.{}",
+                        p.type_reference(),
+                        declaration.name,
+                        p.name
+                    ));
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Names of the declarations of every namespace, enough to validate one model at a time
+/// without the others loaded. Resolves imports like `ModelManager::resolve_import`,
+/// including those of the system namespace.
+#[derive(Debug, Clone)]
+pub struct SymbolIndex {
+    namespaces: HashMap<String, Vec<(SemanticVersion, HashSet<String>)>>,
+}
+
+impl Default for SymbolIndex {
+    fn default() -> Self {
+        let mut index = Self {
+            namespaces: HashMap::new(),
+        };
+        index.add(crate::system::model());
+        index
+    }
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes the declarations of a model, replacing those of the same namespace
+    pub fn add(&mut self, model: &Model) {
+        let versions = self
+            .namespaces
+            .entry(model.namespace.name().to_string())
+            .or_default();
+        versions.retain(|(version, _)| version != model.namespace.version());
+        versions.push((
+            model.namespace.version().clone(),
+            model.declarations.iter().map(|d| d.name.clone()).collect(),
+        ));
+    }
+
+    /// Declaration names of the namespace an import resolves to
+    fn resolve_import(&self, import: &Import) -> Option<&HashSet<String>> {
+        let namespace = import.namespace();
+        let requirement = VersionRequirement::caret(namespace.version());
+        self.namespaces
+            .get(namespace.name())?
+            .iter()
+            .filter(|(version, _)| requirement.matches(version))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, names)| names)
+    }
+
+    /// Whether the exact version of a namespace is indexed with a declaration
+    fn declares(&self, namespace: &Namespace, type_name: &str) -> bool {
+        self.namespaces
+            .get(namespace.name())
+            .is_some_and(|versions| {
+                versions.iter().any(|(version, names)| {
+                    version == namespace.version() && names.contains(type_name)
+                })
+            })
+    }
+
+    /// Like `unresolved`, with the other models known only by their index
+    pub fn unresolved(&self, model: &Model) -> Vec<String> {
+        unresolved_with(
+            model,
+            |import| self.resolve_import(import).is_some(),
+            |reference| match &reference.namespace {
+                Some(namespace) => self.declares(namespace, &reference.name),
+                None => {
+                    model.declaration(&reference.name).is_some()
+                        || model
+                            .imports
+                            .iter()
+                            .filter_map(|i| {
+                                Some((self.resolve_import(i)?, i.imported_name(&reference.name)?))
+                            })
+                            .any(|(names, name)| names.contains(name))
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        manager::ModelManager,
+        parser::{model, namespace::fqn},
+    };
+
+    #[test]
+    fn test_unreferenced() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+
+            concept Person {
+              o String name
+              o Person[] friends
+              o Address address
+            }
+
+            concept Address {
+              o String street
+            }
+
+            concept Animal {
+              o String species
+            }
+
+            concept Pet extends Animal {
+              o String name
+            }
+            ",
+            "namespace orders@1.0.0
+            import people@1.0.0.Pet
+
+            concept Order {
+              o String id
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let names = |entry_points: &[_]| {
+            super::unreferenced(&manager, entry_points)
+                .into_iter()
+                .map(|(m, d)| format!("{}.{}", m.namespace.name(), d.name))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(&[]),
+            vec!["people.Person", "orders.Order"],
+            "Should ignore references of a declaration to itself and count super types"
+        );
+        assert_eq!(
+            names(&[fqn("orders@1.0.0.Order").unwrap().1]),
+            vec!["people.Person"],
+            "Should not report entry points"
+        );
+    }
+
+    #[test]
+    fn test_import_conflicts() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o Address address
+            }
+            ",
+            "namespace shops@1.0.0
+            import places@1.2.0.Address
+
+            concept Shop {
+              o Address address
+            }
+            ",
+            "namespace orders@1.0.0
+            import places@2.1.0.Address
+
+            concept Order {
+              o Address delivery
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+
+        assert_eq!(
+            super::import_conflicts(&manager)
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "people@1.0.0 requires ^1.0.0 but orders@1.0.0 requires ^2.1.0",
+                "shops@1.0.0 requires ^1.2.0 but orders@1.0.0 requires ^2.1.0",
+            ],
+            "Should report imports of incompatible major versions only"
+        );
+    }
+
+    #[test]
+    fn test_symbol_index() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace places@1.2.0\nconcept Address {\n  o String street\n}\n",
+            "namespace places@2.0.0\nconcept Location {\n  o String street\n}\n",
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+            import places@1.0.0.Location
+            import shops@1.0.0.Shop
+
+            concept Person {
+              o Address address
+              o Location location
+              o Person[] friends
+              o Pet pet
+            }
+
+            concept Employee extends Worker {
+              o String id
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let mut index = super::SymbolIndex::new();
+        for model in manager.models() {
+            index.add(model);
+        }
+
+        for model in manager.models() {
+            assert_eq!(
+                index.unresolved(model),
+                super::unresolved(&manager, model),
+                "Should resolve like the manager"
+            );
+        }
+        assert_eq!(
+            index.unresolved(manager.models().last().unwrap()),
+            vec![
+                "unresolved import import shops@1.0.0.Shop",
+                "unknown type Location of Person.location",
+                "unknown type Pet of Person.pet",
+                "unknown super type Worker of Employee",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deprecated() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace places@1.0.0
+            @deprecated(\"use Location\")
+            concept Address {
+              o String street
+            }
+            ",
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              @deprecated
+              o Address address
+              o Person[] friends
+            }
+
+            @deprecated
+            concept Customer {
+              o Address billing
+            }
+
+            concept Office extends Address {
+              o Integer floor
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let people = manager.models().last().unwrap();
+        assert!(people.declaration("Customer").unwrap().is_deprecated());
+        assert!(people
+            .declaration("Person")
+            .unwrap()
+            .is_property_deprecated("address"));
+        assert_eq!(
+            super::deprecated(&manager, people),
+            vec![
+                "deprecated type Address of Person.address",
+                "deprecated super type Address of Office",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_confusables() {
+        // `Раul` and `раyer` start with Cyrillic letters
+        let (_, model) = model(
+            "namespace people@1.0.0
+
+            concept Paul {
+              o String name
+            }
+
+            concept \u{420}aul {
+              o String payer
+              o String \u{440}ayer
+              o String player
+            }
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::confusables(&model),
+            vec![
+                "confusable names Paul and \u{420}aul",
+                "confusable names payer and \u{440}ayer of \u{420}aul"
+            ]
+        );
+    }
+}
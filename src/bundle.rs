@@ -0,0 +1,402 @@
+//! Packages the models of a `ModelManager` into a single tar archive, so deployments ship one
+//! artifact instead of a directory of files. A bundle has
+//!
+//! - `manifest.json`, with the namespace, the files and the SHA-256 of the source of each model
+//! - `models/<name>@<version>.cto`, the sources in the canonical style of `format`
+//! - `metamodel/<name>@<version>.json`, the metamodel JSON of each model with its content hash
+//!   embedded, for other tools
+//!
+//! Bundles can be compressed with `write_compressed`, and are read either way.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    analysis::SymbolIndex,
+    compression::{self, Compression},
+    format::format,
+    manager::ModelManager,
+    parser::{error::SourceError, namespace::Namespace, parse},
+    resolver::hex_sha256,
+    serialize::{print_with_integrity, verify_integrity},
+};
+
+/// Version of the layout of bundles, bumped whenever it changes
+pub const BUNDLE_VERSION: u64 = 1;
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub bundle_version: u64,
+    /// Crate and version that wrote the bundle, like `concerto-nom 0.0.3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<String>,
+    /// Models in the order they were loaded in
+    pub models: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// As `name@version`
+    pub namespace: String,
+    /// Path of the CTO source in the archive
+    pub source: String,
+    /// Path of the metamodel JSON in the archive
+    pub metamodel: String,
+    /// SHA-256 of the CTO source, in hex
+    pub sha256: String,
+}
+
+#[derive(Debug)]
+pub enum BundleError {
+    Io(io::Error),
+    Manifest(serde_json::Error),
+    /// The manifest lists a file the archive doesn't have
+    Missing(String),
+    /// The content of a file doesn't match its hash in the manifest
+    Corrupt(String),
+    Parse {
+        path: String,
+        error: SourceError,
+    },
+    /// The bundle was written with a layout this version doesn't read
+    Version(u64),
+}
+
+impl std::fmt::Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Manifest(e) => write!(f, "{}: {}", MANIFEST, e),
+            Self::Missing(path) => write!(f, "{} is missing", path),
+            Self::Corrupt(path) => write!(f, "{} doesn't match its hash", path),
+            Self::Parse { path, error } => write!(f, "{}:{}", path, error),
+            Self::Version(version) => write!(f, "unsupported bundle version {}", version),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Manifest(e) => Some(e),
+            Self::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BundleError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Writes the models of a manager as a bundle. The same models always give the same bytes.
+pub fn write(manager: &ModelManager, writer: impl Write) -> io::Result<()> {
+    let mut archive = tar::Builder::new(writer);
+    let mut manifest = Manifest {
+        bundle_version: BUNDLE_VERSION,
+        producer: Some(format!("concerto-nom {}", env!("CARGO_PKG_VERSION"))),
+        models: Vec::new(),
+    };
+    // Models are printed in parallel with the `rayon` feature, and archived in order
+    let printed = manager.map_models(|model| {
+        let metamodel = print_with_integrity(model);
+        (model, format(model), metamodel)
+    });
+    for (model, source, metamodel) in printed {
+        let namespace = format!("{}@{}", model.namespace.name(), model.namespace.version());
+        let entry = ManifestEntry {
+            source: format!("models/{}.cto", namespace),
+            metamodel: format!("metamodel/{}.json", namespace),
+            sha256: String::new(),
+            namespace,
+        };
+        let metamodel = metamodel.map_err(io::Error::other)?;
+        append(&mut archive, &entry.source, source.as_bytes())?;
+        append(&mut archive, &entry.metamodel, metamodel.as_bytes())?;
+        manifest.models.push(ManifestEntry {
+            sha256: hex_sha256(&source),
+            ..entry
+        });
+    }
+    let manifest = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    append(&mut archive, MANIFEST, manifest.as_bytes())?;
+    archive.into_inner()?.flush()
+}
+
+/// Writes the models of a manager as a compressed bundle
+pub fn write_compressed(
+    manager: &ModelManager,
+    writer: impl Write,
+    compression: Compression,
+) -> io::Result<()> {
+    let mut archive = Vec::new();
+    write(manager, &mut archive)?;
+    compression::write_all(writer, &archive, compression)
+}
+
+fn append(archive: &mut tar::Builder<impl Write>, path: &str, data: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    archive.append_data(&mut header, path, data)
+}
+
+/// Reads a bundle back into a manager, checking the sources against their hashes, and the
+/// metamodel JSON against the hashes embedded in it
+pub fn read(reader: impl Read) -> Result<ModelManager, BundleError> {
+    let archive = compression::read_all(reader)?;
+    let mut files = Vec::new();
+    for entry in tar::Archive::new(archive.as_slice()).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        files.push((path, content));
+    }
+    let file = |path: &str| {
+        files
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, content)| content.as_str())
+            .ok_or_else(|| BundleError::Missing(path.to_string()))
+    };
+
+    let manifest: Manifest =
+        serde_json::from_str(file(MANIFEST)?).map_err(BundleError::Manifest)?;
+    if manifest.bundle_version != BUNDLE_VERSION {
+        return Err(BundleError::Version(manifest.bundle_version));
+    }
+    let mut manager = ModelManager::new();
+    for entry in &manifest.models {
+        let source = file(&entry.source)?;
+        if hex_sha256(source) != entry.sha256 {
+            return Err(BundleError::Corrupt(entry.source.clone()));
+        }
+        if verify_integrity(file(&entry.metamodel)?).is_err() {
+            return Err(BundleError::Corrupt(entry.metamodel.clone()));
+        }
+        let model = parse(source).map_err(|error| BundleError::Parse {
+            path: entry.source.clone(),
+            error,
+        })?;
+        manager.add_model(model);
+    }
+    Ok(manager)
+}
+
+/// Validates the models of a bundle like `analysis::unresolved`, holding one model in
+/// memory at a time. The archive is read three times: to check the sources against the
+/// manifest, to index their declarations, then to validate each model against the index.
+/// Returns the problems of every model, in the order they are archived in. Compressed
+/// bundles are decompressed into memory first.
+pub fn validate(
+    mut reader: impl Read + Seek,
+) -> Result<Vec<(Namespace, Vec<String>)>, BundleError> {
+    let mut magic = [0; 4];
+    let length = reader.read(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+    match compression::is_compressed(&magic[..length]) {
+        true => validate_archive(io::Cursor::new(compression::read_all(reader)?)),
+        false => validate_archive(reader),
+    }
+}
+
+fn validate_archive(
+    mut reader: impl Read + Seek,
+) -> Result<Vec<(Namespace, Vec<String>)>, BundleError> {
+    let mut manifest = None;
+    let mut hashes = Vec::new();
+    let mut corrupt_metamodels = Vec::new();
+    entries(&mut reader, |path, content| {
+        match path == MANIFEST {
+            true => manifest = Some(content),
+            false if path.ends_with(".json") && verify_integrity(&content).is_err() => {
+                corrupt_metamodels.push(path.to_string())
+            }
+            false => hashes.push((path.to_string(), hex_sha256(&content))),
+        }
+        Ok(())
+    })?;
+    let manifest = manifest.ok_or_else(|| BundleError::Missing(MANIFEST.to_string()))?;
+    let manifest: Manifest = serde_json::from_str(&manifest).map_err(BundleError::Manifest)?;
+    if manifest.bundle_version != BUNDLE_VERSION {
+        return Err(BundleError::Version(manifest.bundle_version));
+    }
+    for entry in &manifest.models {
+        match hashes.iter().find(|(path, _)| path == &entry.source) {
+            None => return Err(BundleError::Missing(entry.source.clone())),
+            Some((_, sha256)) if sha256 != &entry.sha256 => {
+                return Err(BundleError::Corrupt(entry.source.clone()))
+            }
+            Some(_) => {}
+        }
+        if corrupt_metamodels.contains(&entry.metamodel) {
+            return Err(BundleError::Corrupt(entry.metamodel.clone()));
+        }
+    }
+
+    let is_source = |path: &str| manifest.models.iter().any(|e| e.source == path);
+    let parse_entry = |path: &str, source: &str| {
+        parse(source).map_err(|error| BundleError::Parse {
+            path: path.to_string(),
+            error,
+        })
+    };
+    let mut index = SymbolIndex::new();
+    entries(&mut reader, |path, source| {
+        if is_source(path) {
+            index.add(&parse_entry(path, &source)?);
+        }
+        Ok(())
+    })?;
+    let mut problems = Vec::with_capacity(manifest.models.len());
+    entries(&mut reader, |path, source| {
+        if is_source(path) {
+            let model = parse_entry(path, &source)?;
+            problems.push((model.namespace.clone(), index.unresolved(&model)));
+        }
+        Ok(())
+    })?;
+    Ok(problems)
+}
+
+/// Reads every file of the archive from the start, one at a time
+fn entries(
+    reader: &mut (impl Read + Seek),
+    mut f: impl FnMut(&str, String) -> Result<(), BundleError>,
+) -> Result<(), BundleError> {
+    reader.seek(SeekFrom::Start(0))?;
+    for entry in tar::Archive::new(&mut *reader).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        f(&path, content)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{manager::ModelManager, parser::parse};
+
+    use super::BundleError;
+
+    #[test]
+    fn test_bundle() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace places@1.0.0\nconcept Address {\n  o String city\n}\n").unwrap(),
+        );
+        manager.add_model(
+            parse(
+                "namespace people@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o Address address
+}
+",
+            )
+            .unwrap(),
+        );
+
+        let mut bundle = Vec::new();
+        super::write(&manager, &mut bundle).unwrap();
+        let mut again = Vec::new();
+        super::write(&manager, &mut again).unwrap();
+        assert_eq!(bundle, again, "Should write the same bytes every time");
+
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        for compression in [
+            #[cfg(feature = "gzip")]
+            super::Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            super::Compression::Zstd,
+        ] {
+            let mut compressed = Vec::new();
+            super::write_compressed(&manager, &mut compressed, compression).unwrap();
+            assert!(compressed.len() < bundle.len());
+            assert_eq!(
+                super::read(compressed.as_slice())
+                    .unwrap()
+                    .models()
+                    .collect::<Vec<_>>(),
+                manager.models().collect::<Vec<_>>(),
+                "Should read compressed bundles"
+            );
+            assert_eq!(
+                super::validate(std::io::Cursor::new(&compressed))
+                    .unwrap()
+                    .len(),
+                2
+            );
+        }
+
+        let read = super::read(bundle.as_slice()).unwrap();
+        assert_eq!(
+            read.models().collect::<Vec<_>>(),
+            manager.models().collect::<Vec<_>>(),
+            "Should read the models back in order"
+        );
+
+        manager.add_model(
+            parse("namespace orders@1.0.0\nconcept Order {\n  o Person buyer\n}\n").unwrap(),
+        );
+        let mut with_orders = Vec::new();
+        super::write(&manager, &mut with_orders).unwrap();
+        assert_eq!(
+            super::validate(std::io::Cursor::new(&with_orders))
+                .unwrap()
+                .into_iter()
+                .map(|(namespace, problems)| (namespace.to_string(), problems))
+                .collect::<Vec<_>>(),
+            vec![
+                ("namespace places@1.0.0".to_string(), vec![]),
+                ("namespace people@1.0.0".to_string(), vec![]),
+                (
+                    "namespace orders@1.0.0".to_string(),
+                    vec!["unknown type Person of Order.buyer".to_string()]
+                ),
+            ],
+            "Should validate each model against the others"
+        );
+
+        let position = bundle
+            .windows(4)
+            .position(|w| w == b"city")
+            .expect("Sources should be stored as they are");
+        bundle[position] = b'C';
+        assert!(matches!(
+            super::read(bundle.as_slice()),
+            Err(BundleError::Corrupt(path)) if path == "models/places@1.0.0.cto"
+        ));
+        assert!(matches!(
+            super::validate(std::io::Cursor::new(&bundle)),
+            Err(BundleError::Corrupt(path)) if path == "models/places@1.0.0.cto"
+        ));
+
+        bundle[position] = b'c';
+        let position = bundle
+            .windows(6)
+            .position(|w| w == b"\"city\"")
+            .expect("Metamodel JSON should be stored as it is");
+        bundle[position + 1] = b'C';
+        assert!(matches!(
+            super::read(bundle.as_slice()),
+            Err(BundleError::Corrupt(path)) if path == "metamodel/places@1.0.0.json"
+        ));
+        assert!(matches!(
+            super::validate(std::io::Cursor::new(&bundle)),
+            Err(BundleError::Corrupt(path)) if path == "metamodel/places@1.0.0.json"
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+//! Markdown changelogs between two versions of a model, for release notes
+
+use crate::{
+    diff::{diff, Change, Severity},
+    parser::Model,
+};
+
+/// Lists the changes from one version of a model to another under a heading of the new
+/// version, grouped into added, removed and changed types and properties. Breaking changes
+/// are called out at the top, and marked in the lists.
+pub fn changelog(old: &Model, new: &Model) -> String {
+    let diff = diff(old, new);
+    let mut output = format!(
+        "## {} {}\n\nChanges since {} {}",
+        new.namespace.name(),
+        new.namespace.version(),
+        old.namespace.name(),
+        old.namespace.version()
+    );
+    match diff.severity() {
+        None => {
+            output.push_str(", none.\n");
+            return output;
+        }
+        Some(severity) => output.push_str(&format!(", calling for a {} version.\n", severity)),
+    }
+    let breaking = diff.changes.iter().filter(|c| is_breaking(c)).count();
+    if breaking > 0 {
+        output.push_str(&format!(
+            "\n> **Breaking:** {} {} can make instances of the previous version invalid.\n",
+            breaking,
+            if breaking == 1 { "change" } else { "changes" }
+        ));
+    }
+
+    for title in ["Added", "Removed", "Changed"] {
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .filter(|c| section(c) == title)
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("\n### {}\n\n", title));
+        for change in changes {
+            let marker = if is_breaking(change) {
+                "**Breaking:** "
+            } else {
+                ""
+            };
+            output.push_str(&format!("- {}{}\n", marker, entry(change)));
+        }
+    }
+    output
+}
+
+fn is_breaking(change: &Change) -> bool {
+    change.severity() == Severity::Major
+}
+
+fn section(change: &Change) -> &'static str {
+    match change {
+        Change::DeclarationAdded { .. } | Change::PropertyAdded { .. } => "Added",
+        Change::DeclarationRemoved { .. } | Change::PropertyRemoved { .. } => "Removed",
+        _ => "Changed",
+    }
+}
+
+/// A change as a list entry, with names as code
+fn entry(change: &Change) -> String {
+    match change {
+        Change::NamespaceRenamed { from, to } => {
+            format!("Renamed the namespace `{}` to `{}`", from, to)
+        }
+        Change::DeclarationAdded { declaration } | Change::DeclarationRemoved { declaration } => {
+            format!("`{}`", declaration)
+        }
+        Change::DeclarationKindChanged { declaration } => {
+            format!("Changed the kind of `{}`", declaration)
+        }
+        Change::AbstractChanged {
+            declaration,
+            is_abstract,
+        } => format!(
+            "Made `{}` {}",
+            declaration,
+            if *is_abstract { "abstract" } else { "concrete" }
+        ),
+        Change::IdentifiedChanged {
+            declaration,
+            from,
+            to,
+        } => match (from, to) {
+            (Some(from), Some(to)) => {
+                format!("`{}` is `{}` instead of `{}`", declaration, to, from)
+            }
+            (None, Some(to)) => format!("`{}` is `{}`", declaration, to),
+            (Some(from), None) => format!("`{}` is no longer `{}`", declaration, from),
+            (None, None) => format!("Changed how `{}` is identified", declaration),
+        },
+        Change::SuperTypeChanged {
+            declaration,
+            from,
+            to,
+        } => match (from, to) {
+            (Some(from), Some(to)) => format!(
+                "Changed the super type of `{}` from `{}` to `{}`",
+                declaration, from, to
+            ),
+            (None, Some(to)) => format!("`{}` extends `{}`", declaration, to),
+            (Some(from), None) => format!("`{}` no longer extends `{}`", declaration, from),
+            (None, None) => format!("Changed the super type of `{}`", declaration),
+        },
+        Change::PropertyAdded {
+            declaration,
+            property,
+            optional,
+        } => format!(
+            "`{}.{}`, {}",
+            declaration,
+            property,
+            if *optional { "optional" } else { "required" }
+        ),
+        Change::PropertyRemoved {
+            declaration,
+            property,
+        } => format!("`{}.{}`", declaration, property),
+        Change::PropertyTypeChanged {
+            declaration,
+            property,
+            from,
+            to,
+        } => format!(
+            "Changed the type of `{}.{}` from `{}` to `{}`",
+            declaration, property, from, to
+        ),
+        Change::PropertyOptionalityChanged {
+            declaration,
+            property,
+            optional,
+        } => format!(
+            "Made `{}.{}` {}",
+            declaration,
+            property,
+            if *optional { "optional" } else { "required" }
+        ),
+        Change::PropertyArrayChanged {
+            declaration,
+            property,
+            array,
+        } => format!(
+            "Made `{}.{}` {}",
+            declaration,
+            property,
+            if *array { "an array" } else { "a single value" }
+        ),
+        Change::PropertyRelationshipChanged {
+            declaration,
+            property,
+            relationship,
+        } => format!(
+            "Made `{}.{}` {}",
+            declaration,
+            property,
+            if *relationship {
+                "a relationship"
+            } else {
+                "contained"
+            }
+        ),
+        Change::PropertyValidatorsChanged {
+            declaration,
+            property,
+        } => format!("Changed the validators of `{}.{}`", declaration, property),
+        Change::PropertyChanged {
+            declaration,
+            property,
+        } => format!("Changed `{}.{}`", declaration, property),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse;
+
+    #[test]
+    fn test_changelog() {
+        let old = parse(
+            "namespace people@1.0.0
+concept Person {
+  o String name
+  o Integer age optional
+}
+concept Pet {
+  o String name
+}
+",
+        )
+        .unwrap();
+        let new = parse(
+            "namespace people@2.0.0
+concept Person {
+  o String name optional
+  o Long age optional
+  o String email optional
+}
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::changelog(&old, &new),
+            "## people 2.0.0
+
+Changes since people 1.0.0, calling for a major version.
+
+> **Breaking:** 2 changes can make instances of the previous version invalid.
+
+### Added
+
+- `Person.email`, optional
+
+### Removed
+
+- **Breaking:** `Pet`
+
+### Changed
+
+- Made `Person.name` optional
+- **Breaking:** Changed the type of `Person.age` from `Integer` to `Long`
+"
+        );
+        assert_eq!(
+            super::changelog(&old, &old),
+            "## people 1.0.0\n\nChanges since people 1.0.0, none.\n"
+        );
+    }
+}
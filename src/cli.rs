@@ -0,0 +1,1198 @@
+//! Command line interface of the `concerto-nom` binary, kept in the library so it can be
+//! tested without spawning processes
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use serde_derive::Serialize;
+
+use crate::{
+    format,
+    manager::ModelManager,
+    parser::{
+        cst::Cst,
+        file::{parse_file, parse_reader, FileError},
+        Model,
+    },
+    serialize,
+};
+
+/// Everything went fine
+pub const EXIT_OK: u8 = 0;
+/// Some model is invalid, or couldn't be read
+pub const EXIT_INVALID: u8 = 1;
+/// The command line couldn't be understood
+pub const EXIT_USAGE: u8 = 2;
+/// `diff` found breaking changes
+pub const EXIT_BREAKING: u8 = 3;
+
+const USAGE: &str = "usage: concerto-nom <command> [options]
+
+commands:
+  parse <file> [--out json|cto|tree] print the parsed model
+  parse --batch                      parse a JSON string holding a model per input line,
+                                     and print a JSON result per line
+  validate <path>...                 check models and the types they refer to, bundles
+                                     ending in .tar are checked a model at a time with
+                                     the `bundle` feature, compressed ones ending in
+                                     .tar.gz or .tar.zst with the `gzip` or `zstd`
+                                     feature, warns about uses of @deprecated types
+  format <file> [--write|--check]    print the model formatted, rewrite the file, or
+                                     print what formatting would change
+  convert <file> [--from cto|metamodel-json] --to cto|json|jsonschema
+                                     convert a model, reading metamodel JSON needs the
+                                     `deserialize` feature, and the `gzip` or `zstd`
+                                     feature when it is compressed
+  convert <file> --to json --source-map <map>
+                                     also write a source map linking the JSON to the
+                                     CTO source
+  convert <file> --to json --integrity
+                                     embed the content hash of the JSON, checked when
+                                     metamodel JSON is read
+  diff <old> <new> [--out text|json|markdown]
+                                     print the changes between two versions of a model,
+                                     or a changelog, and fail on breaking ones
+  stats <path>... [--out text|json]  print counts over the models
+  dictionary <path>... [--out csv|xlsx]
+                                     print a data dictionary with a row per property,
+                                     writing Excel workbooks needs the `xlsx` feature
+  repl                               parse snippets as they are typed
+  explain <file> <declaration>       print a declaration with its types resolved against
+                                     the models next to the file
+  watch <path>...                    validate again whenever a model changes, needs the
+                                     `watch` feature
+
+options:
+  --format text|json                 how diagnostics are reported
+
+The file - reads the model from the input.
+";
+
+/// Options that take a value, every other option is a flag
+const VALUE_OPTIONS: [&str; 5] = ["--out", "--from", "--to", "--format", "--source-map"];
+
+/// A problem found in a model, located as precisely as known
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Diagnostic {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Errors make a command fail, warnings are only reported
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Diagnostic {
+    fn in_file(path: &Path, message: String) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+            line: None,
+            column: None,
+            severity: Severity::Error,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}:", path.display())?,
+            None => write!(f, "<input>:")?,
+        }
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}:", line, column)?;
+        }
+        write!(f, " {} {}", self.severity, self.message)
+    }
+}
+
+impl From<FileError> for Diagnostic {
+    fn from(value: FileError) -> Self {
+        let path = value.path().map(Path::to_path_buf);
+        match value {
+            FileError::Io { source, .. } => Self {
+                path,
+                line: None,
+                column: None,
+                severity: Severity::Error,
+                message: source.to_string(),
+            },
+            FileError::Parse { error, .. } => Self {
+                path,
+                line: Some(error.line),
+                column: Some(error.column),
+                severity: Severity::Error,
+                message: format!("{:?}", error.kind),
+            },
+        }
+    }
+}
+
+/// Command line split into its parts
+#[derive(Debug, Default)]
+struct Arguments {
+    command: String,
+    paths: Vec<String>,
+    flags: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl Arguments {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut arguments = Arguments {
+            command: args.next().ok_or("missing command")?,
+            ..Default::default()
+        };
+        while let Some(arg) = args.next() {
+            if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = args.next().ok_or(format!("missing value of {}", arg))?;
+                arguments.values.insert(arg, value);
+            } else if arg.starts_with("--") {
+                arguments.flags.push(arg);
+            } else {
+                arguments.paths.push(arg);
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn value(&self, option: &str) -> Option<&str> {
+        self.values.get(option).map(String::as_str)
+    }
+
+    /// Fails on options the command doesn't know about
+    fn expect_options(&self, flags: &[&str], values: &[&str]) -> Result<(), String> {
+        if let Some(flag) = self.flags.iter().find(|f| !flags.contains(&f.as_str())) {
+            return Err(format!("unknown option {} for {}", flag, self.command));
+        }
+        match self
+            .values
+            .keys()
+            .find(|v| v.as_str() != "--format" && !values.contains(&v.as_str()))
+        {
+            Some(value) => Err(format!("unknown option {} for {}", value, self.command)),
+            None => Ok(()),
+        }
+    }
+
+    /// The single file the command works on
+    fn file(&self) -> Result<&Path, String> {
+        match self.paths.as_slice() {
+            [path] => Ok(Path::new(path)),
+            _ => Err(format!("{} expects a single file", self.command)),
+        }
+    }
+}
+
+/// Outcome of a command that ran
+enum Outcome {
+    Done,
+    /// Ran without errors, with warnings to report
+    Warned(Vec<Diagnostic>),
+    Invalid(Vec<Diagnostic>),
+    Breaking,
+}
+
+impl Outcome {
+    /// Invalid if any of the diagnostics is an error
+    fn of(diagnostics: Vec<Diagnostic>) -> Self {
+        if diagnostics.is_empty() {
+            Self::Done
+        } else if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            Self::Invalid(diagnostics)
+        } else {
+            Self::Warned(diagnostics)
+        }
+    }
+}
+
+/// Reasons a command couldn't run
+enum Failure {
+    Usage(String),
+    Output(std::io::Error),
+}
+
+impl From<String> for Failure {
+    fn from(value: String) -> Self {
+        Self::Usage(value)
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(value: std::io::Error) -> Self {
+        Self::Output(value)
+    }
+}
+
+/// Runs the command line, without the program name, and returns the exit code. The input
+/// is read for the file `-`, and in batch mode.
+pub fn run(
+    args: impl IntoIterator<Item = String>,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> u8 {
+    let arguments = match Arguments::parse(args) {
+        Ok(arguments) => arguments,
+        Err(message) => return usage(err, &message),
+    };
+    let json_diagnostics = match arguments.value("--format") {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(other) => return usage(err, &format!("unknown diagnostics format {}", other)),
+    };
+
+    let outcome = match arguments.command.as_str() {
+        "parse" if arguments.flags.iter().any(|f| f == "--batch") => batch(&arguments, input, out),
+        "parse" => parse(&arguments, input, out),
+        "validate" => validate(&arguments),
+        "format" => format(&arguments, input, out),
+        "convert" => convert(&arguments, input, out),
+        "diff" => diff(&arguments, input, out),
+        "explain" => explain(&arguments, input, out),
+        "stats" => stats(&arguments, out),
+        "dictionary" => dictionary(&arguments, out),
+        "repl" => repl(&arguments, input, out, err),
+        #[cfg(feature = "watch")]
+        "watch" => watch(&arguments, json_diagnostics, out, err),
+        "help" | "--help" => {
+            let _ = write!(out, "{}", USAGE);
+            return EXIT_OK;
+        }
+        other => Err(Failure::Usage(format!("unknown command {}", other))),
+    };
+
+    match outcome {
+        Err(Failure::Usage(message)) => usage(err, &message),
+        Err(Failure::Output(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => EXIT_OK,
+        Err(Failure::Output(e)) => {
+            let _ = writeln!(err, "error {}", e);
+            EXIT_INVALID
+        }
+        Ok(outcome) => report(outcome, json_diagnostics, out, err),
+    }
+}
+
+/// Prints the diagnostics of a command, and returns the exit code
+fn report(
+    outcome: Outcome,
+    json_diagnostics: bool,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> u8 {
+    match outcome {
+        Outcome::Done => {
+            if json_diagnostics {
+                let _ = writeln!(out, "[]");
+            }
+            EXIT_OK
+        }
+        Outcome::Warned(diagnostics) => {
+            print_diagnostics(&diagnostics, json_diagnostics, out, err);
+            EXIT_OK
+        }
+        Outcome::Invalid(diagnostics) => {
+            print_diagnostics(&diagnostics, json_diagnostics, out, err);
+            EXIT_INVALID
+        }
+        Outcome::Breaking => {
+            if json_diagnostics {
+                let _ = writeln!(out, "[]");
+            }
+            EXIT_BREAKING
+        }
+    }
+}
+
+fn print_diagnostics(
+    diagnostics: &[Diagnostic],
+    json_diagnostics: bool,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) {
+    if json_diagnostics {
+        let json = serde_json::to_string(diagnostics).unwrap_or_default();
+        let _ = writeln!(out, "{}", json);
+    } else {
+        for diagnostic in diagnostics {
+            let _ = writeln!(err, "{}", diagnostic);
+        }
+    }
+}
+
+fn usage(err: &mut dyn Write, message: &str) -> u8 {
+    let _ = write!(err, "{}\n\n{}", message, USAGE);
+    EXIT_USAGE
+}
+
+/// Parses the file the command works on, or the input for `-`
+fn parse_input(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+) -> Result<Result<Model, Diagnostic>, Failure> {
+    let file = arguments.file()?;
+    let result = match file == Path::new("-") {
+        true => parse_reader(input),
+        false => parse_file(file),
+    };
+    Ok(result.map_err(Diagnostic::from))
+}
+
+fn parse(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &["--out"])?;
+    if arguments.value("--out") == Some("tree") {
+        return tree(arguments, input, out);
+    }
+    let model = match parse_input(arguments, input)? {
+        Ok(model) => model,
+        Err(e) => return Ok(Outcome::Invalid(vec![e])),
+    };
+    match arguments.value("--out").unwrap_or("json") {
+        "json" => print_json(out, &serde_json::to_value(&model).unwrap_or_default())?,
+        "cto" => write_output(out, &model.to_string())?,
+        other => return Err(format!("unknown output {}", other).into()),
+    }
+    Ok(Outcome::Done)
+}
+
+/// Loads the model files and the `.cto` files under the directories among the paths
+fn load(paths: &[String]) -> (ModelManager, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut manager = ModelManager::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            diagnostics.extend(manager.add_dir(path).into_iter().map(Diagnostic::from));
+        } else if let Err(e) = manager.add_file(path) {
+            diagnostics.push(e.into());
+        }
+    }
+    (manager, diagnostics)
+}
+
+/// Prints the parsed model as a tree, with the spans of the nodes
+fn tree(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    let file = arguments.file()?;
+    let stdin = file == Path::new("-");
+    let path = Some(file.to_path_buf()).filter(|_| !stdin);
+    let mut source = String::new();
+    let read = match stdin {
+        true => input.read_to_string(&mut source).map(|_| ()),
+        false => std::fs::read_to_string(file).map(|s| source = s),
+    };
+    let cst = match read {
+        Ok(()) => Cst::parse(source).map_err(|error| FileError::Parse { path, error }),
+        Err(source) => Err(FileError::Io { path, source }),
+    };
+    match cst {
+        Ok(cst) => write_output(out, &serialize::tree(cst.model(), Some(cst.spans())))?,
+        Err(e) => return Ok(Outcome::Invalid(vec![e.into()])),
+    }
+    Ok(Outcome::Done)
+}
+
+fn validate(arguments: &Arguments) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &[])?;
+    if arguments.paths.is_empty() {
+        return Err(String::from("validate expects files or directories").into());
+    }
+    let (bundles, paths): (Vec<_>, Vec<_>) = arguments.paths.iter().cloned().partition(|p| {
+        [".tar", ".tar.gz", ".tar.zst"]
+            .iter()
+            .any(|e| p.ends_with(e))
+    });
+    let (manager, mut diagnostics) = load(&paths);
+    for bundle in &bundles {
+        diagnostics.extend(validate_bundle(Path::new(bundle))?);
+    }
+    for model in manager.models() {
+        if let Some(path) = manager.path(&model.namespace) {
+            diagnostics.extend(
+                crate::analysis::unresolved(&manager, model)
+                    .into_iter()
+                    .map(|message| Diagnostic::in_file(path, message)),
+            );
+            diagnostics.extend(
+                crate::analysis::deprecated(&manager, model)
+                    .into_iter()
+                    .chain(crate::analysis::confusables(model))
+                    .map(|message| Diagnostic {
+                        severity: Severity::Warning,
+                        ..Diagnostic::in_file(path, message)
+                    }),
+            );
+        }
+    }
+
+    Ok(Outcome::of(diagnostics))
+}
+
+/// Validates the models of a bundle a model at a time, however many it has
+#[cfg(feature = "bundle")]
+fn validate_bundle(path: &Path) -> Result<Vec<Diagnostic>, Failure> {
+    let validated = std::fs::File::open(path)
+        .map_err(crate::bundle::BundleError::from)
+        .and_then(|file| crate::bundle::validate(std::io::BufReader::new(file)));
+    Ok(match validated {
+        Ok(problems) => problems
+            .into_iter()
+            .flat_map(|(namespace, messages)| {
+                messages.into_iter().map(move |message| {
+                    let message =
+                        format!("{}@{}: {}", namespace.name(), namespace.version(), message);
+                    Diagnostic::in_file(path, message)
+                })
+            })
+            .collect(),
+        Err(e) => vec![Diagnostic::in_file(path, e.to_string())],
+    })
+}
+
+#[cfg(not(feature = "bundle"))]
+fn validate_bundle(_: &Path) -> Result<Vec<Diagnostic>, Failure> {
+    Err(String::from("validating bundles needs the `bundle` feature").into())
+}
+
+/// Compares two versions of a model, either of them can be the input
+fn diff(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &["--out"])?;
+    let [old, new] = arguments.paths.as_slice() else {
+        return Err(String::from("diff expects two files").into());
+    };
+    let mut models = Vec::new();
+    let mut diagnostics = Vec::new();
+    for path in [old, new] {
+        let result = match path.as_str() {
+            "-" => parse_reader(&mut *input),
+            path => parse_file(path),
+        };
+        match result {
+            Ok(model) => models.push(model),
+            Err(e) => diagnostics.push(e.into()),
+        }
+    }
+    let [old, new] = models.as_slice() else {
+        return Ok(Outcome::Invalid(diagnostics));
+    };
+
+    let diff = crate::diff::diff(old, new);
+    match arguments.value("--out").unwrap_or("text") {
+        "text" => {
+            let mut text = String::new();
+            for change in &diff.changes {
+                text.push_str(&format!("{}: {}\n", change.severity(), change));
+            }
+            write_output(out, &text)?
+        }
+        "json" => print_json(
+            out,
+            &serde_json::json!({ "severity": diff.severity(), "changes": diff.changes }),
+        )?,
+        "markdown" => write_output(out, &crate::changelog::changelog(old, new))?,
+        other => return Err(format!("unknown output {}", other).into()),
+    }
+    Ok(match diff.is_breaking() {
+        true => Outcome::Breaking,
+        false => Outcome::Done,
+    })
+}
+
+/// Prints statistics of the models that could be loaded, files that couldn't are reported
+fn stats(arguments: &Arguments, out: &mut dyn Write) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &["--out"])?;
+    if arguments.paths.is_empty() {
+        return Err(String::from("stats expects files or directories").into());
+    }
+    let (manager, diagnostics) = load(&arguments.paths);
+    let stats = manager.stats();
+    match arguments.value("--out").unwrap_or("text") {
+        "text" => write_output(out, &stats.to_string())?,
+        "json" => print_json(out, &serde_json::to_value(&stats).unwrap_or_default())?,
+        other => return Err(format!("unknown output {}", other).into()),
+    }
+    Ok(match diagnostics.is_empty() {
+        true => Outcome::Done,
+        false => Outcome::Invalid(diagnostics),
+    })
+}
+
+fn dictionary(arguments: &Arguments, out: &mut dyn Write) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &["--out"])?;
+    if arguments.paths.is_empty() {
+        return Err(String::from("dictionary expects files or directories").into());
+    }
+    let (manager, diagnostics) = load(&arguments.paths);
+    let entries = crate::dictionary::entries(&manager);
+    match arguments.value("--out").unwrap_or("csv") {
+        "csv" => write_output(out, &crate::dictionary::to_csv(&entries))?,
+        #[cfg(feature = "xlsx")]
+        "xlsx" => {
+            let workbook = crate::dictionary::to_xlsx(&entries).map_err(std::io::Error::other)?;
+            out.write_all(&workbook)?
+        }
+        other => return Err(format!("unknown output {}", other).into()),
+    }
+    Ok(match diagnostics.is_empty() {
+        true => Outcome::Done,
+        false => Outcome::Invalid(diagnostics),
+    })
+}
+
+/// Runs an interactive session on the input, prompts go to the error output so the output
+/// only holds results
+fn repl(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &[])?;
+    if !arguments.paths.is_empty() {
+        return Err(String::from("repl reads the input only").into());
+    }
+    let mut repl = crate::repl::Repl::new();
+    writeln!(err, "Type :help for help")?;
+    let mut line = String::new();
+    loop {
+        write!(err, "{}", if repl.is_pending() { ". " } else { "> " })?;
+        err.flush()?;
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(Outcome::Done);
+        }
+        match repl.feed(line.trim_end_matches(['\r', '\n'])) {
+            crate::repl::Step::Pending => {}
+            crate::repl::Step::Print(text) => {
+                write_output(out, &text)?;
+                out.flush()?;
+            }
+            crate::repl::Step::Quit => return Ok(Outcome::Done),
+        }
+    }
+}
+
+fn explain(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&[], &[])?;
+    let [file, name] = arguments.paths.as_slice() else {
+        return Err(String::from("explain expects a file and a declaration").into());
+    };
+    let file = Path::new(file);
+    let (mut manager, model) = match file == Path::new("-") {
+        true => (ModelManager::new(), parse_reader(input)),
+        // Errors of the other models only show as unresolved types
+        false => {
+            let directory = file
+                .parent()
+                .filter(|d| d.is_dir())
+                .unwrap_or(Path::new("."));
+            (ModelManager::from_dir(directory).0, parse_file(file))
+        }
+    };
+    let model = match model {
+        Ok(model) => model,
+        Err(e) => return Ok(Outcome::Invalid(vec![e.into()])),
+    };
+    let namespace = model.namespace.clone();
+    manager.add_model(model);
+    let model = manager.model(&namespace).expect("Model was just added");
+    match crate::explain::explain(&manager, model, name) {
+        Some(explanation) => write_output(out, &explanation.to_string())?,
+        None => {
+            return Ok(Outcome::Invalid(vec![Diagnostic::in_file(
+                file,
+                format!("unknown declaration {}", name),
+            )]))
+        }
+    }
+    Ok(Outcome::Done)
+}
+
+/// Parses a document per line of the input, each line being a JSON string holding the
+/// source of a model. Prints a line per document, the metamodel JSON of the model as
+/// `{"model": ...}`, or `{"diagnostics": [...]}` if it isn't valid.
+fn batch(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&["--batch"], &[])?;
+    if !arguments.paths.is_empty() {
+        return Err(String::from("parse --batch reads the input only").into());
+    }
+    let mut invalid = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let diagnostic = |message: String, line, column| Diagnostic {
+            path: None,
+            line,
+            column,
+            severity: Severity::Error,
+            message,
+        };
+        let result = match serde_json::from_str::<String>(&line) {
+            Ok(source) => crate::parser::parse(&source)
+                .map_err(|e| diagnostic(format!("{:?}", e.kind), Some(e.line), Some(e.column))),
+            Err(e) => Err(diagnostic(
+                format!("document {} isn't a JSON string: {}", number + 1, e),
+                None,
+                None,
+            )),
+        };
+        let json = match result {
+            Ok(model) => serde_json::json!({ "model": model }),
+            Err(diagnostic) => {
+                invalid.push(diagnostic.clone());
+                serde_json::json!({ "diagnostics": [diagnostic] })
+            }
+        };
+        serde_json::to_writer(&mut *out, &json).map_err(std::io::Error::from)?;
+        writeln!(out)?;
+    }
+    // Diagnostics are already part of the output
+    Ok(match invalid.is_empty() {
+        true => Outcome::Done,
+        false => Outcome::Invalid(Vec::new()),
+    })
+}
+
+/// Validates the paths again whenever a model file under them changes, until interrupted
+#[cfg(feature = "watch")]
+fn watch(
+    arguments: &Arguments,
+    json_diagnostics: bool,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    let (first, others) = arguments
+        .paths
+        .split_first()
+        .ok_or_else(|| String::from("watch expects files or directories"))?;
+    let mut watcher = crate::watch::ModelWatcher::new(first).map_err(|e| e.to_string())?;
+    for path in others {
+        watcher.add(path).map_err(|e| e.to_string())?;
+    }
+
+    loop {
+        let code = report(validate(arguments)?, json_diagnostics, out, err);
+        if code == EXIT_OK && !json_diagnostics {
+            writeln!(out, "ok")?;
+        }
+        out.flush()?;
+        match watcher.recv() {
+            Some(_) => continue,
+            None => return Ok(Outcome::Done),
+        }
+    }
+}
+
+fn format(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&["--write", "--check"], &[])?;
+    let path = arguments.file()?;
+    let stdin = path == Path::new("-");
+    let in_file = |e: &dyn ToString| {
+        let path = Some(path.to_path_buf()).filter(|_| !stdin);
+        Outcome::Invalid(vec![Diagnostic {
+            path,
+            line: None,
+            column: None,
+            severity: Severity::Error,
+            message: e.to_string(),
+        }])
+    };
+    if stdin && arguments.flags.iter().any(|f| f == "--write") {
+        return Err(String::from("format can't --write the input").into());
+    }
+    let mut source = String::new();
+    let read = match stdin {
+        true => input.read_to_string(&mut source).map(|_| ()),
+        false => std::fs::read_to_string(path).map(|s| source = s),
+    };
+    if let Err(e) = read {
+        return Ok(in_file(&e));
+    }
+    let parse_error = |error| {
+        let error = FileError::Parse {
+            path: Some(path.to_path_buf()).filter(|_| !stdin),
+            error,
+        };
+        Outcome::Invalid(vec![error.into()])
+    };
+
+    if arguments.flags.iter().any(|f| f == "--check") {
+        let report = match format::check(&source) {
+            Ok(report) => report,
+            Err(e) => return Ok(parse_error(e)),
+        };
+        write_output(out, &report.diff)?;
+        return Ok(match report.formatted {
+            true => Outcome::Done,
+            false => in_file(&"not formatted"),
+        });
+    }
+    let formatted = match Cst::parse(source) {
+        Ok(cst) => format::format_cst(&cst, &Default::default()),
+        Err(e) => return Ok(parse_error(e)),
+    };
+    if arguments.flags.iter().any(|f| f == "--write") {
+        if let Err(e) = std::fs::write(path, formatted) {
+            return Ok(in_file(&e));
+        }
+    } else {
+        write_output(out, &formatted)?;
+    }
+    Ok(Outcome::Done)
+}
+
+fn convert(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Outcome, Failure> {
+    arguments.expect_options(&["--integrity"], &["--from", "--to", "--source-map"])?;
+    if let Some(map) = arguments.value("--source-map") {
+        write_source_map(arguments, Path::new(map))?;
+    }
+    let model = match arguments.value("--from").unwrap_or("cto") {
+        "cto" => parse_input(arguments, input)?,
+        "metamodel-json" => read_metamodel_json(arguments, input)?,
+        other => return Err(format!("unknown input format {}", other).into()),
+    };
+    let model = match model {
+        Ok(model) => model,
+        Err(e) => return Ok(Outcome::Invalid(vec![e])),
+    };
+    let converted = match arguments.value("--to") {
+        Some("cto") => {
+            write_output(out, &format::format(&model))?;
+            return Ok(Outcome::Done);
+        }
+        Some("json") => {
+            let mut json = serde_json::to_value(&model).unwrap_or_default();
+            if arguments.flags.iter().any(|f| f == "--integrity") {
+                serialize::embed_integrity(&mut json);
+            }
+            json
+        }
+        Some("jsonschema") => serialize::json_schema(&model),
+        Some(other) => return Err(format!("unknown conversion {}", other).into()),
+        None => return Err(String::from("convert expects --to").into()),
+    };
+    print_json(out, &converted)?;
+    Ok(Outcome::Done)
+}
+
+/// Writes the source map of the JSON `convert` prints, the model is parsed again with spans
+fn write_source_map(arguments: &Arguments, map: &Path) -> Result<(), Failure> {
+    let file = arguments.file()?;
+    if arguments.value("--from").unwrap_or("cto") != "cto"
+        || arguments.value("--to") != Some("json")
+        || file == Path::new("-")
+    {
+        return Err(String::from("--source-map needs a CTO file converted --to json").into());
+    }
+    // Parse errors are reported when the model is parsed for the conversion
+    let Ok(source) = std::fs::read_to_string(file) else {
+        return Ok(());
+    };
+    if let Ok((_, source_map)) = serialize::print_with_source_map(&source) {
+        let json = serde_json::to_string_pretty(&source_map).unwrap_or_default();
+        std::fs::write(map, json)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "deserialize")]
+fn read_metamodel_json(
+    arguments: &Arguments,
+    input: &mut dyn BufRead,
+) -> Result<Result<Model, Diagnostic>, Failure> {
+    let file = arguments.file()?;
+    let stdin = file == Path::new("-");
+    let mut source = Vec::new();
+    let read = match stdin {
+        true => input.read_to_end(&mut source).map(|_| ()),
+        false => std::fs::read(file).map(|s| source = s),
+    };
+    let path = Some(file.to_path_buf()).filter(|_| !stdin);
+    if let Err(e) = read {
+        return Ok(Err(Diagnostic {
+            path,
+            line: None,
+            column: None,
+            severity: Severity::Error,
+            message: e.to_string(),
+        }));
+    }
+    Ok(
+        // Content hashes that don't match have no position
+        serialize::read_metamodel_json(source.as_slice()).map_err(|e| Diagnostic {
+            path,
+            line: Some(e.line()).filter(|line| *line > 0),
+            column: Some(e.column()).filter(|_| e.line() > 0),
+            severity: Severity::Error,
+            message: e.to_string(),
+        }),
+    )
+}
+
+#[cfg(not(feature = "deserialize"))]
+fn read_metamodel_json(
+    _: &Arguments,
+    _: &mut dyn BufRead,
+) -> Result<Result<Model, Diagnostic>, Failure> {
+    Err(String::from("--from metamodel-json needs the `deserialize` feature").into())
+}
+
+fn print_json(out: &mut dyn Write, value: &serde_json::Value) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)
+}
+
+fn write_output(out: &mut dyn Write, output: &str) -> std::io::Result<()> {
+    write!(out, "{}", output)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    fn run(args: &[&str]) -> (u8, String, String) {
+        run_with_input(args, "")
+    }
+
+    fn run_with_input(args: &[&str], input: &str) -> (u8, String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = super::run(
+            args.iter().map(|a| a.to_string()),
+            &mut input.as_bytes(),
+            &mut out,
+            &mut err,
+        );
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cli() {
+        let dir = std::env::temp_dir().join(format!("concerto-cli-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        let people = dir.join("people.cto");
+        let orders = dir.join("nested").join("orders.cto");
+        std::fs::write(
+            &people,
+            "namespace people@1.0.0\nconcept Person {\n    o String name\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &orders,
+            "namespace orders@1.0.0\nimport people@1.0.0.Person\n\nconcept Order {\n  o Person buyer\n  o Item[] items\n}\n",
+        )
+        .unwrap();
+        let path = |p: &Path| p.display().to_string();
+
+        let (code, out, _) = run(&["parse", &path(&people)]);
+        assert_eq!(code, super::EXIT_OK);
+        assert!(out.starts_with("{\n  \"$class\": \"Model\""));
+
+        let (code, out, _) = run(&["format", &path(&people)]);
+        assert_eq!(code, super::EXIT_OK);
+        assert_eq!(
+            out, "namespace people@1.0.0\n\nconcept Person {\n  o String name\n}\n",
+            "Should print the model formatted"
+        );
+
+        let (code, out, err) = run(&["format", &path(&people), "--check"]);
+        assert_eq!(
+            code,
+            super::EXIT_INVALID,
+            "Should fail on unformatted files"
+        );
+        assert_eq!(
+            out,
+            "@@ -2,0 +2,1 @@\n+\n@@ -3,1 +4,1 @@\n-    o String name\n+  o String name\n"
+        );
+        assert_eq!(err, format!("{}: error not formatted\n", path(&people)));
+        assert_eq!(
+            run(&["format", &path(&people), "--write"]).0,
+            super::EXIT_OK
+        );
+        assert_eq!(
+            run(&["format", &path(&people), "--check"]),
+            (super::EXIT_OK, String::new(), String::new())
+        );
+
+        let (code, out, _) = run(&["convert", &path(&people), "--to", "jsonschema"]);
+        assert_eq!(code, super::EXIT_OK);
+        assert!(out.contains("\"const\": \"people@1.0.0.Person\""));
+
+        let map = dir.join("people.map.json");
+        let args = ["convert", &path(&people), "--to", "json", "--source-map"];
+        assert_eq!(run(&[&args[..], &[&path(&map)]].concat()).0, super::EXIT_OK);
+        let source_map: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&map).unwrap()).unwrap();
+        assert_eq!(
+            source_map["mappings"][1],
+            serde_json::json!(["/declarations/0", { "start": 24, "end": 58 }]),
+            "Should write the source map next to the JSON"
+        );
+        std::fs::remove_file(&map).unwrap();
+        assert_eq!(
+            run(&[
+                "convert",
+                &path(&people),
+                "--to",
+                "cto",
+                "--source-map",
+                "x"
+            ])
+            .0,
+            super::EXIT_USAGE
+        );
+        #[cfg(feature = "deserialize")]
+        {
+            let (_, json, _) = run(&["convert", &path(&people), "--to", "json"]);
+            let (code, cto, _) = run_with_input(
+                &["convert", "-", "--from", "metamodel-json", "--to", "cto"],
+                &json,
+            );
+            assert_eq!(code, super::EXIT_OK);
+            assert_eq!(
+                cto,
+                std::fs::read_to_string(&people).unwrap(),
+                "Should turn metamodel JSON back into CTO"
+            );
+
+            let (_, json, _) = run(&["convert", &path(&people), "--to", "json", "--integrity"]);
+            let from_json = ["convert", "-", "--from", "metamodel-json", "--to", "cto"];
+            assert_eq!(run_with_input(&from_json, &json).0, super::EXIT_OK);
+            let (code, _, err) = run_with_input(&from_json, &json.replace("name", "nom"));
+            assert_eq!(code, super::EXIT_INVALID);
+            assert!(
+                err.contains("changed or corrupted"),
+                "Should reject JSON that doesn't match its hash"
+            );
+        }
+
+        let (code, _, err) = run(&["validate", &path(&dir)]);
+        assert_eq!(code, super::EXIT_INVALID, "Should fail on unknown types");
+        assert_eq!(
+            err,
+            format!(
+                "{}: error unknown type Item of Order.items\n",
+                path(&orders)
+            )
+        );
+
+        let (code, out, _) = run(&["validate", &path(&people), "--format", "json"]);
+        assert_eq!((code, out.as_str()), (super::EXIT_OK, "[]\n"));
+
+        let (code, out, _) = run(&["dictionary", &path(&people)]);
+        assert_eq!(code, super::EXIT_OK);
+        assert!(out.starts_with("namespace,type,property,"));
+        assert!(out.contains("\r\npeople@1.0.0,Person,name,String,false,"));
+
+        let (code, out, _) = run(&["stats", &path(&dir), "--out", "json"]);
+        assert_eq!(code, super::EXIT_OK);
+        let stats: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            (&stats["models"], &stats["fan_in"]["people@1.0.0"]),
+            (&serde_json::json!(2), &serde_json::json!(1)),
+            "Should count over all the models"
+        );
+
+        let changed = dir.join("people-2.cto");
+        std::fs::write(
+            &changed,
+            "namespace people@1.1.0\nconcept Person {\n  o String name\n  o Integer age optional\n}\n",
+        )
+        .unwrap();
+        let (code, out, _) = run(&["diff", &path(&people), &path(&changed)]);
+        assert_eq!(
+            (code, out.as_str()),
+            (super::EXIT_OK, "minor: added optional Person.age\n"),
+            "Should pass on compatible changes"
+        );
+        let (code, out, _) = run(&["diff", &path(&changed), &path(&people), "--out", "json"]);
+        assert_eq!(
+            code,
+            super::EXIT_BREAKING,
+            "Should fail on breaking changes"
+        );
+        let diff: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(diff["severity"], "major");
+        assert_eq!(diff["changes"][0]["change"], "propertyRemoved");
+
+        let pets = dir.join("pets.cto");
+        std::fs::write(
+            &pets,
+            "namespace pets@1.0.0\nimport people@1.0.0.Person\nconcept Pet {\n  o Person owner\n}\n",
+        )
+        .unwrap();
+        let (code, out, _) = run(&["explain", &path(&pets), "Pet"]);
+        assert_eq!(code, super::EXIT_OK);
+        assert!(
+            out.contains("  o Person owner\n      type people@1.1.0.Person\n"),
+            "Should resolve types against the models next to the file, got {}",
+            out
+        );
+        assert_eq!(
+            run(&["explain", &path(&pets), "Cat"]).0,
+            super::EXIT_INVALID
+        );
+
+        std::fs::write(&people, "namespace people@1.0.0\nconcept {\n}\n").unwrap();
+        let (code, out, _) = run(&["validate", &path(&people), "--format", "json"]);
+        assert_eq!(code, super::EXIT_INVALID);
+        let diagnostics: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            (&diagnostics[0]["line"], &diagnostics[0]["column"]),
+            (&serde_json::json!(2), &serde_json::json!(1)),
+            "Should locate parse errors"
+        );
+
+        let (code, _, err) = run(&["parse", &path(&people), "--write"]);
+        assert_eq!(code, super::EXIT_USAGE, "Should reject unknown options");
+        assert!(err.starts_with("unknown option --write for parse"));
+        assert_eq!(run(&["frobnicate"]).0, super::EXIT_USAGE);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cli_warnings() {
+        let dir = std::env::temp_dir().join(format!("concerto-warnings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let people = dir.join("people.cto");
+        std::fs::write(
+            &people,
+            "namespace people@1.0.0\n@deprecated\nconcept Name {\n  o String first\n}\nconcept Person {\n  o Name name\n}\n",
+        )
+        .unwrap();
+
+        let (code, _, err) = run(&["validate", &people.display().to_string()]);
+        assert_eq!(code, super::EXIT_OK, "Should pass with warnings");
+        assert_eq!(
+            err,
+            format!(
+                "{}: warning deprecated type Name of Person.name\n",
+                people.display()
+            )
+        );
+        let (_, out, _) = run(&[
+            "validate",
+            &people.display().to_string(),
+            "--format",
+            "json",
+        ]);
+        let diagnostics: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(diagnostics[0]["severity"], "warning");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cli_input() {
+        let source = "namespace people@1.0.0\nconcept Person {\n    o String name\n}\n";
+
+        let (code, out, _) = run_with_input(&["parse", "-", "--out", "cto"], source);
+        assert_eq!(
+            (code, out.as_str()),
+            (
+                super::EXIT_OK,
+                "namespace people@1.0.0\n\nconcept Person {\n  o String name\n}\n"
+            ),
+            "Should read the model from the input"
+        );
+        let (code, out, _) = run_with_input(&["repl"], ":json\no String name\n:quit\n");
+        assert_eq!(code, super::EXIT_OK);
+        assert!(
+            out.contains("\"$class\": \"StringProperty\""),
+            "Should print parsed snippets, got {}",
+            out
+        );
+
+        let (code, out, _) = run_with_input(&["parse", "-", "--out", "tree"], source);
+        assert_eq!(code, super::EXIT_OK);
+        assert!(
+            out.starts_with("Model\n  Namespace people@1.0.0 @0..22\n"),
+            "Should print the tree with spans, got {}",
+            out
+        );
+        let (code, _, err) = run_with_input(&["format", "-"], "namespace people@1.0.0\nconcept {");
+        assert_eq!(code, super::EXIT_INVALID);
+        assert!(err.starts_with("<input>:2:1: error"), "Got {}", err);
+        assert_eq!(
+            run_with_input(&["format", "-", "--write"], source).0,
+            super::EXIT_USAGE
+        );
+
+        let input = format!(
+            "{}\n\n{}\nnot json\n",
+            serde_json::to_string(source).unwrap(),
+            serde_json::to_string("namespace broken").unwrap()
+        );
+        let (code, out, _) = run_with_input(&["parse", "--batch"], &input);
+        assert_eq!(code, super::EXIT_INVALID);
+        let results: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(results.len(), 3, "Should print a line per document");
+        assert_eq!(results[0]["model"]["namespace"], "people@1.0.0");
+        assert_eq!(results[1]["diagnostics"][0]["line"], 1);
+        assert!(results[2]["diagnostics"][0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("document 4 isn't a JSON string"));
+    }
+}
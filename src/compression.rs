@@ -0,0 +1,131 @@
+//! Compressed bundles and metamodel JSON, with gzip behind the `gzip` feature and zstd behind
+//! the `zstd` feature. Readers recognize compressed input by its first bytes, so callers
+//! don't need to know how a file was written.
+
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// The compression a file name ends with, `.gz` or `.zst`
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Self::Gzip,
+            #[cfg(feature = "zstd")]
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Compresses data with the default level of the format
+pub fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::encode_all(data, 0),
+    }
+}
+
+/// Data starting like gzip or zstd, whether their features are on or not
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC) || data.starts_with(&ZSTD_MAGIC)
+}
+
+/// Decompresses data if it starts like gzip or zstd, and borrows it otherwise. Fails on
+/// compressed data whose format's feature is off.
+pub fn decompress(data: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    if data.starts_with(&GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        {
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            return Ok(Cow::Owned(decompressed));
+        }
+        #[cfg(not(feature = "gzip"))]
+        return Err(unsupported("gzip"));
+    }
+    if data.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return zstd::decode_all(data).map(Cow::Owned);
+        #[cfg(not(feature = "zstd"))]
+        return Err(unsupported("zstd"));
+    }
+    Ok(Cow::Borrowed(data))
+}
+
+/// Reads all of a reader, decompressed
+pub fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(decompress(&data)?.into_owned())
+}
+
+/// Compresses data and writes it
+pub fn write_all(mut writer: impl Write, data: &[u8], compression: Compression) -> io::Result<()> {
+    match compression == Compression::None {
+        true => writer.write_all(data),
+        false => writer.write_all(&compress(data, compression)?),
+    }
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn unsupported(format: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} compressed input needs the `{}` feature", format, format),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::Compression;
+
+    #[test]
+    fn test_compression() {
+        let data = b"namespace test@1.0.0\n";
+        assert!(matches!(
+            super::decompress(data).unwrap(),
+            std::borrow::Cow::Borrowed(_)
+        ));
+        #[cfg(feature = "gzip")]
+        {
+            let compressed = super::compress(data, Compression::Gzip).unwrap();
+            assert_ne!(compressed, data);
+            assert_eq!(super::decompress(&compressed).unwrap(), &data[..]);
+        }
+        #[cfg(feature = "zstd")]
+        {
+            let compressed = super::compress(data, Compression::Zstd).unwrap();
+            assert_eq!(super::read_all(compressed.as_slice()).unwrap(), data);
+        }
+        #[cfg(not(feature = "gzip"))]
+        assert!(super::decompress(&[0x1f, 0x8b, 0]).is_err());
+        assert_eq!(
+            Compression::from_path(std::path::Path::new("models.tar")),
+            Compression::None
+        );
+    }
+}
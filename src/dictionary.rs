@@ -0,0 +1,170 @@
+//! Flat data dictionaries of the loaded models, a row per property, for data governance
+
+use crate::{
+    explain::explain,
+    manager::ModelManager,
+    parser::{property::CommonProperty, Model},
+};
+
+/// Column headers, in the order of the fields of `Entry`
+pub const COLUMNS: [&str; 8] = [
+    "namespace",
+    "type",
+    "property",
+    "data type",
+    "optional",
+    "validators",
+    "default",
+    "documentation",
+];
+
+/// A property of a declaration
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Entry {
+    /// As `name@version`
+    pub namespace: String,
+    pub declaration: String,
+    pub property: String,
+    /// Fully qualified when the type is a declaration that resolves, with `[]` for arrays
+    pub data_type: String,
+    pub optional: bool,
+    /// Validators and meta properties as written in CTO, separated by spaces
+    pub validators: String,
+    pub default: Option<String>,
+    /// Empty until the parser keeps doc comments
+    pub documentation: String,
+}
+
+impl Entry {
+    fn fields(&self) -> [String; 8] {
+        [
+            self.namespace.clone(),
+            self.declaration.clone(),
+            self.property.clone(),
+            self.data_type.clone(),
+            self.optional.to_string(),
+            self.validators.clone(),
+            self.default.clone().unwrap_or_default(),
+            self.documentation.clone(),
+        ]
+    }
+}
+
+/// Entries of every property of the loaded models, in the order the models were loaded and
+/// the properties are defined in. Models are explained in parallel with the `rayon` feature.
+pub fn entries(manager: &ModelManager) -> Vec<Entry> {
+    manager
+        .map_models(|model| model_entries(manager, model))
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn model_entries(manager: &ModelManager, model: &Model) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let namespace = format!("{}@{}", model.namespace.name(), model.namespace.version());
+    for declaration in &model.declarations {
+        let Some(explanation) = explain(manager, model, &declaration.name) else {
+            continue;
+        };
+        for explained in explanation.properties {
+            let property = explained.property;
+            let mut data_type = explained
+                .resolved_type
+                .map_or_else(|| property.type_name().to_string(), |t| t.to_string());
+            if property.is_array() {
+                data_type.push_str("[]");
+            }
+            entries.push(Entry {
+                namespace: namespace.clone(),
+                declaration: declaration.name.clone(),
+                property: property.name().to_string(),
+                data_type,
+                optional: property.is_optional(),
+                validators: explained.validators.join(" "),
+                default: explained.default,
+                documentation: String::new(),
+            });
+        }
+    }
+    entries
+}
+
+/// Writes entries as CSV with a header line, quoting fields as RFC 4180 does
+pub fn to_csv(entries: &[Entry]) -> String {
+    let mut csv = format!("{}\r\n", COLUMNS.join(","));
+    for entry in entries {
+        let fields: Vec<_> = entry.fields().iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Writes entries as an Excel workbook with a single sheet, and returns its bytes
+#[cfg(feature = "xlsx")]
+pub fn to_xlsx(entries: &[Entry]) -> Result<Vec<u8>, rust_xlsxwriter::XlsxError> {
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Data dictionary")?;
+    let bold = rust_xlsxwriter::Format::new().set_bold();
+    for (column, header) in COLUMNS.iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *header, &bold)?;
+    }
+    for (row, entry) in entries.iter().enumerate() {
+        for (column, field) in entry.fields().iter().enumerate() {
+            sheet.write_string(row as u32 + 1, column as u16, field)?;
+        }
+    }
+    workbook.save_to_buffer()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{manager::ModelManager, parser::parse};
+
+    #[test]
+    fn test_dictionary() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace places@1.0.0\nconcept Address {\n  o String city\n}\n").unwrap(),
+        );
+        manager.add_model(
+            parse(
+                "namespace people@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name default=\"Jane, \\\"J\\\"\" length=[1,20]
+  o Address[] addresses optional
+}
+",
+            )
+            .unwrap(),
+        );
+
+        let entries = super::entries(&manager);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[2].data_type, "places@1.0.0.Address[]",
+            "Should resolve types"
+        );
+        assert_eq!(
+            super::to_csv(&entries),
+            "namespace,type,property,data type,optional,validators,default,documentation\r
+places@1.0.0,Address,city,String,false,,,\r
+people@1.0.0,Person,name,String,false,\"length=[1, 20]\",\"\"\"Jane, \\\"\"J\\\"\"\"\"\",\r
+people@1.0.0,Person,addresses,places@1.0.0.Address[],true,,,\r
+"
+        );
+
+        #[cfg(feature = "xlsx")]
+        assert!(super::to_xlsx(&entries).unwrap().starts_with(b"PK"));
+    }
+}
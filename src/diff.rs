@@ -0,0 +1,507 @@
+//! Structured differences between two versions of a model, classified by how they affect
+//! existing instances and users of the model
+
+use serde_derive::Serialize;
+
+use crate::parser::{
+    declaration::{Declaration, Property},
+    property::CommonProperty,
+    Model,
+};
+
+/// Version bump a change calls for, in semantic versioning terms
+#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Doesn't change which instances are valid, e.g. a changed default value
+    Patch,
+    /// Only accepts more instances, e.g. an added optional property
+    Minor,
+    /// Breaking, instances valid before may not be anymore
+    Major,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Patch => write!(f, "patch"),
+            Self::Minor => write!(f, "minor"),
+            Self::Major => write!(f, "major"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "camelCase")]
+pub enum Change {
+    NamespaceRenamed {
+        from: String,
+        to: String,
+    },
+    DeclarationAdded {
+        declaration: String,
+    },
+    DeclarationRemoved {
+        declaration: String,
+    },
+    DeclarationKindChanged {
+        declaration: String,
+    },
+    /// The declaration became abstract, or concrete when `is_abstract` is false
+    AbstractChanged {
+        declaration: String,
+        is_abstract: bool,
+    },
+    /// How instances are identified changed, as written like `identified by vin`, `None`
+    /// for declarations that aren't identified
+    IdentifiedChanged {
+        declaration: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// The declaration extends another type, `None` for no `extends` clause
+    SuperTypeChanged {
+        declaration: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    PropertyAdded {
+        declaration: String,
+        property: String,
+        optional: bool,
+    },
+    PropertyRemoved {
+        declaration: String,
+        property: String,
+    },
+    PropertyTypeChanged {
+        declaration: String,
+        property: String,
+        from: String,
+        to: String,
+    },
+    /// The property became optional, or required when `optional` is false
+    PropertyOptionalityChanged {
+        declaration: String,
+        property: String,
+        optional: bool,
+    },
+    /// The property became an array, or a single value when `array` is false
+    PropertyArrayChanged {
+        declaration: String,
+        property: String,
+        array: bool,
+    },
+    /// The property became a relationship, or contains its value when `relationship` is false
+    PropertyRelationshipChanged {
+        declaration: String,
+        property: String,
+        relationship: bool,
+    },
+    /// Validators of the property changed, they may reject existing values
+    PropertyValidatorsChanged {
+        declaration: String,
+        property: String,
+    },
+    /// Anything else about the property changed, e.g. its default value
+    PropertyChanged {
+        declaration: String,
+        property: String,
+    },
+}
+
+impl Change {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::DeclarationAdded { .. } => Severity::Minor,
+            Self::AbstractChanged { is_abstract, .. } => match is_abstract {
+                true => Severity::Major,
+                false => Severity::Minor,
+            },
+            Self::PropertyAdded { optional, .. }
+            | Self::PropertyOptionalityChanged { optional, .. } => match optional {
+                true => Severity::Minor,
+                false => Severity::Major,
+            },
+            Self::PropertyChanged { .. } => Severity::Patch,
+            Self::NamespaceRenamed { .. }
+            | Self::DeclarationRemoved { .. }
+            | Self::DeclarationKindChanged { .. }
+            | Self::SuperTypeChanged { .. }
+            | Self::IdentifiedChanged { .. }
+            | Self::PropertyRemoved { .. }
+            | Self::PropertyTypeChanged { .. }
+            | Self::PropertyArrayChanged { .. }
+            | Self::PropertyRelationshipChanged { .. }
+            | Self::PropertyValidatorsChanged { .. } => Severity::Major,
+        }
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NamespaceRenamed { from, to } => {
+                write!(f, "namespace renamed from {} to {}", from, to)
+            }
+            Self::DeclarationAdded { declaration } => write!(f, "added {}", declaration),
+            Self::DeclarationRemoved { declaration } => write!(f, "removed {}", declaration),
+            Self::DeclarationKindChanged { declaration } => {
+                write!(f, "changed the kind of {}", declaration)
+            }
+            Self::AbstractChanged {
+                declaration,
+                is_abstract,
+            } => match is_abstract {
+                true => write!(f, "made {} abstract", declaration),
+                false => write!(f, "made {} concrete", declaration),
+            },
+            Self::IdentifiedChanged {
+                declaration,
+                from,
+                to,
+            } => match (from, to) {
+                (Some(from), Some(to)) => {
+                    write!(f, "made {} {} instead of {}", declaration, to, from)
+                }
+                (None, Some(to)) => write!(f, "made {} {}", declaration, to),
+                (Some(_), None) => write!(f, "made {} unidentified", declaration),
+                (None, None) => write!(f, "changed how {} is identified", declaration),
+            },
+            Self::SuperTypeChanged {
+                declaration,
+                from,
+                to,
+            } => match (from, to) {
+                (Some(from), Some(to)) => write!(
+                    f,
+                    "changed the super type of {} from {} to {}",
+                    declaration, from, to
+                ),
+                (None, Some(to)) => write!(f, "made {} extend {}", declaration, to),
+                (Some(from), None) => write!(f, "made {} no longer extend {}", declaration, from),
+                (None, None) => write!(f, "changed the super type of {}", declaration),
+            },
+            Self::PropertyAdded {
+                declaration,
+                property,
+                optional,
+            } => match optional {
+                true => write!(f, "added optional {}.{}", declaration, property),
+                false => write!(f, "added required {}.{}", declaration, property),
+            },
+            Self::PropertyRemoved {
+                declaration,
+                property,
+            } => write!(f, "removed {}.{}", declaration, property),
+            Self::PropertyTypeChanged {
+                declaration,
+                property,
+                from,
+                to,
+            } => write!(
+                f,
+                "changed the type of {}.{} from {} to {}",
+                declaration, property, from, to
+            ),
+            Self::PropertyOptionalityChanged {
+                declaration,
+                property,
+                optional,
+            } => match optional {
+                true => write!(f, "made {}.{} optional", declaration, property),
+                false => write!(f, "made {}.{} required", declaration, property),
+            },
+            Self::PropertyArrayChanged {
+                declaration,
+                property,
+                array,
+            } => match array {
+                true => write!(f, "made {}.{} an array", declaration, property),
+                false => write!(f, "made {}.{} a single value", declaration, property),
+            },
+            Self::PropertyRelationshipChanged {
+                declaration,
+                property,
+                relationship,
+            } => match relationship {
+                true => write!(f, "made {}.{} a relationship", declaration, property),
+                false => write!(f, "made {}.{} contained", declaration, property),
+            },
+            Self::PropertyValidatorsChanged {
+                declaration,
+                property,
+            } => write!(f, "changed the validators of {}.{}", declaration, property),
+            Self::PropertyChanged {
+                declaration,
+                property,
+            } => write!(f, "changed {}.{}", declaration, property),
+        }
+    }
+}
+
+/// Changes from one version of a model to another
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize)]
+pub struct ModelDiff {
+    pub changes: Vec<Change>,
+}
+
+impl ModelDiff {
+    /// The highest severity of the changes, `None` when nothing changed
+    pub fn severity(&self) -> Option<Severity> {
+        self.changes.iter().map(Change::severity).max()
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.severity() == Some(Severity::Major)
+    }
+}
+
+/// Compares two versions of a model. Declarations and properties are matched by name, the
+/// order they are defined in doesn't matter.
+pub fn diff(old: &Model, new: &Model) -> ModelDiff {
+    let mut changes = Vec::new();
+    if old.namespace.name() != new.namespace.name() {
+        changes.push(Change::NamespaceRenamed {
+            from: old.namespace.name().to_string(),
+            to: new.namespace.name().to_string(),
+        });
+    }
+    for declaration in &old.declarations {
+        match new.declaration(&declaration.name) {
+            Some(other) => diff_declaration(declaration, other, &mut changes),
+            None => changes.push(Change::DeclarationRemoved {
+                declaration: declaration.name.clone(),
+            }),
+        }
+    }
+    for declaration in &new.declarations {
+        if old.declaration(&declaration.name).is_none() {
+            changes.push(Change::DeclarationAdded {
+                declaration: declaration.name.clone(),
+            });
+        }
+    }
+    ModelDiff { changes }
+}
+
+pub(crate) fn diff_declaration(old: &Declaration, new: &Declaration, changes: &mut Vec<Change>) {
+    let declaration = || old.name.clone();
+    if old.kind != new.kind || old.extension != new.extension {
+        changes.push(Change::DeclarationKindChanged {
+            declaration: declaration(),
+        });
+        return;
+    }
+    if old.is_abstract != new.is_abstract {
+        changes.push(Change::AbstractChanged {
+            declaration: declaration(),
+            is_abstract: new.is_abstract,
+        });
+    }
+    if old.identified != new.identified {
+        changes.push(Change::IdentifiedChanged {
+            declaration: declaration(),
+            from: old.identified.as_ref().map(ToString::to_string),
+            to: new.identified.as_ref().map(ToString::to_string),
+        });
+    }
+    if old.super_type != new.super_type {
+        changes.push(Change::SuperTypeChanged {
+            declaration: declaration(),
+            from: old.super_type.as_ref().map(ToString::to_string),
+            to: new.super_type.as_ref().map(ToString::to_string),
+        });
+    }
+    for property in &old.properties {
+        match new.property(property.name()) {
+            Some(other) => {
+                let metas = |d: &Declaration| -> Vec<_> {
+                    d.meta_extensions
+                        .iter()
+                        .filter(|m| m.property == property.name())
+                        .cloned()
+                        .collect()
+                };
+                if let Some(change) = diff_property(property, other, declaration()) {
+                    changes.push(change);
+                } else if metas(old) != metas(new) {
+                    changes.push(Change::PropertyChanged {
+                        declaration: declaration(),
+                        property: property.name().to_string(),
+                    });
+                }
+            }
+            None => changes.push(Change::PropertyRemoved {
+                declaration: declaration(),
+                property: property.name().to_string(),
+            }),
+        }
+    }
+    for property in &new.properties {
+        if old.property(property.name()).is_none() {
+            changes.push(Change::PropertyAdded {
+                declaration: declaration(),
+                property: property.name().to_string(),
+                optional: property.is_optional(),
+            });
+        }
+    }
+}
+
+/// Type of a property as it is written, fully qualified if it is
+fn written_type(property: &Property) -> String {
+    match property {
+        Property::Concept(p) => p.type_reference().to_string(),
+        p => p.type_name().to_string(),
+    }
+}
+
+/// The most severe difference between two versions of a property
+fn diff_property(old: &Property, new: &Property, declaration: String) -> Option<Change> {
+    let property = old.name().to_string();
+    if written_type(old) != written_type(new) {
+        Some(Change::PropertyTypeChanged {
+            declaration,
+            property,
+            from: written_type(old),
+            to: written_type(new),
+        })
+    } else if old.is_relationship() != new.is_relationship() {
+        Some(Change::PropertyRelationshipChanged {
+            declaration,
+            property,
+            relationship: new.is_relationship(),
+        })
+    } else if old.is_array() != new.is_array() {
+        Some(Change::PropertyArrayChanged {
+            declaration,
+            property,
+            array: new.is_array(),
+        })
+    } else if old.is_optional() && !new.is_optional() {
+        Some(Change::PropertyOptionalityChanged {
+            declaration,
+            property,
+            optional: false,
+        })
+    } else if old.validators() != new.validators() {
+        Some(Change::PropertyValidatorsChanged {
+            declaration,
+            property,
+        })
+    } else if !old.is_optional() && new.is_optional() {
+        Some(Change::PropertyOptionalityChanged {
+            declaration,
+            property,
+            optional: true,
+        })
+    } else if old != new {
+        Some(Change::PropertyChanged {
+            declaration,
+            property,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Severity};
+    use crate::parser::parse;
+
+    #[test]
+    fn test_diff() {
+        let old = parse(
+            "namespace test@1.0.0
+concept Person extends Party {
+  o String name
+  o Integer age optional
+  o String email regex=/.+@.+/
+  o String[] tags
+  o Double score default=1.0
+  o Person[] friends
+  o places@1.0.0.Address home
+}
+concept Pet {
+  o String name
+}
+concept Shape {}
+abstract concept Animal {}
+asset Car identified by vin {
+  o String vin
+  o String plate
+}
+concept Tag {}
+",
+        )
+        .unwrap();
+        let new = parse(
+            "namespace test@2.0.0
+concept Person extends Entity {
+  o String name optional
+  o String email regex=/.+@.+[.].+/
+  o String tags
+  o Double score default=2.0
+  o Long age
+  o String nickname optional
+  --> Person[] friends
+  o places@2.0.0.Address home
+}
+concept Address {
+  o String city
+}
+abstract concept Shape {}
+concept Animal {}
+asset Car identified by plate {
+  o String vin
+  o String plate
+}
+concept Tag identified {}
+",
+        )
+        .unwrap();
+
+        let diff = super::diff(&old, &new);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|c| format!("{}: {}", c.severity(), c))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "major: changed the super type of Person from Party to Entity",
+                "minor: made Person.name optional",
+                "major: changed the type of Person.age from Integer to Long",
+                "major: changed the validators of Person.email",
+                "major: made Person.tags a single value",
+                "patch: changed Person.score",
+                "major: made Person.friends a relationship",
+                "major: changed the type of Person.home from places@1.0.0.Address to \
+                 places@2.0.0.Address",
+                "minor: added optional Person.nickname",
+                "major: removed Pet",
+                "major: made Shape abstract",
+                "minor: made Animal concrete",
+                "major: made Car identified by plate instead of identified by vin",
+                "major: made Tag identified",
+                "minor: added Address",
+            ]
+        );
+        assert!(diff.is_breaking());
+
+        let diff = super::diff(&old, &old);
+        assert_eq!(diff.severity(), None, "Should find no changes");
+        assert_eq!(
+            super::diff(&old, &new).changes[1],
+            Change::PropertyOptionalityChanged {
+                declaration: String::from("Person"),
+                property: String::from("name"),
+                optional: true,
+            }
+        );
+        assert!(Severity::Minor < Severity::Major);
+    }
+}
@@ -0,0 +1,252 @@
+//! Resolved view of a declaration, with what each of its properties is made of and where the
+//! pieces come from
+
+use crate::{
+    format::default_value,
+    manager::ModelManager,
+    parser::{
+        declaration::{Declaration, Property},
+        namespace::{FullyQualifiedName, Namespace},
+        property::CommonProperty,
+        Model,
+    },
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Explanation<'a> {
+    pub model: &'a Model,
+    pub declaration: &'a Declaration,
+    /// The declaration named in the `extends` clause, `None` without one or if it doesn't
+    /// resolve
+    pub super_type: Option<FullyQualifiedName>,
+    /// Properties inherited from the declarations it extends first, then its own
+    pub properties: Vec<ExplainedProperty<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExplainedProperty<'a> {
+    pub property: &'a Property,
+    /// Namespace of the declaration the property is defined in
+    pub declared_in: &'a Namespace,
+    /// Declaration the property is defined in, a super type for inherited properties
+    pub declared_by: &'a Declaration,
+    /// Type of concept properties, `None` for primitive types and types that don't resolve
+    pub resolved_type: Option<FullyQualifiedName>,
+    /// Default value as written in CTO
+    pub default: Option<String>,
+    /// Validators and meta properties, as written in CTO
+    pub validators: Vec<String>,
+}
+
+fn fully_qualified_name(model: &Model, declaration: &Declaration) -> FullyQualifiedName {
+    FullyQualifiedName::from((
+        model.namespace.name().to_string(),
+        model.namespace.version().clone(),
+        declaration.name.clone(),
+    ))
+}
+
+/// Explains a declaration of a model, looking its types up in the loaded models. Properties
+/// inherited through `extends` are explained along with its own.
+pub fn explain<'a>(
+    manager: &'a ModelManager,
+    model: &'a Model,
+    name: &str,
+) -> Option<Explanation<'a>> {
+    let declaration = model.declaration(name)?;
+    let super_type = manager
+        .resolve_extended_in(model, declaration)
+        .map(|(m, d)| fully_qualified_name(m, d));
+    let hierarchy = manager.hierarchy(model, declaration);
+    let properties = hierarchy
+        .into_iter()
+        .flat_map(|(model, declaration)| {
+            declaration
+                .properties
+                .iter()
+                .map(move |p| (model, declaration, p))
+        })
+        .map(|(model, declaration, property)| {
+            let resolved_type = match property {
+                Property::Concept(p) => manager
+                    .resolve_property_in(model, p)
+                    .map(|(m, d)| fully_qualified_name(m, d)),
+                _ => None,
+            };
+            let mut validators: Vec<_> = property
+                .validators()
+                .iter()
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty())
+                .collect();
+            validators.extend(
+                declaration
+                    .meta_extensions
+                    .iter()
+                    .filter(|m| m.property == property.name())
+                    .map(|m| m.to_string()),
+            );
+            ExplainedProperty {
+                property,
+                declared_in: &model.namespace,
+                declared_by: declaration,
+                resolved_type,
+                default: default_value(property),
+                validators,
+            }
+        })
+        .collect();
+    Some(Explanation {
+        model,
+        declaration,
+        super_type,
+        properties,
+    })
+}
+
+impl std::fmt::Display for Explanation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let namespace = &self.model.namespace;
+        write!(
+            f,
+            "{} {}@{}.{}",
+            self.declaration.kind,
+            namespace.name(),
+            namespace.version(),
+            self.declaration.name
+        )?;
+        match (&self.super_type, &self.declaration.super_type) {
+            (Some(fqn), _) => writeln!(f, " extends {}", fqn)?,
+            (None, Some(written)) => writeln!(f, " extends {} unresolved", written)?,
+            (None, None) => writeln!(f)?,
+        }
+        for explained in &self.properties {
+            let property = explained.property;
+            write!(
+                f,
+                "  {}",
+                if property.is_relationship() {
+                    "-->"
+                } else {
+                    "o"
+                }
+            )?;
+            // Values of enums have no type
+            if !property.type_name().is_empty() {
+                write!(f, " {}", property.type_name())?;
+            }
+            if property.is_array() {
+                write!(f, "[]")?;
+            }
+            write!(f, " {}", property.name())?;
+            if property.is_optional() {
+                write!(f, " optional")?;
+            }
+            writeln!(f)?;
+            if !std::ptr::eq(explained.declared_by, self.declaration) {
+                let from = explained.declared_in;
+                writeln!(
+                    f,
+                    "      from {}@{}.{}",
+                    from.name(),
+                    from.version(),
+                    explained.declared_by.name
+                )?;
+            }
+            match (&explained.resolved_type, property) {
+                (Some(fqn), _) => writeln!(f, "      type {}", fqn)?,
+                (None, Property::Concept(_)) => writeln!(f, "      type unresolved")?,
+                (None, _) => {}
+            }
+            if let Some(default) = &explained.default {
+                writeln!(f, "      default={}", default)?;
+            }
+            for validator in &explained.validators {
+                writeln!(f, "      {}", validator)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{manager::ModelManager, parser::parse};
+
+    #[test]
+    fn test_explain() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace places@1.1.0\nconcept Address {\n  o String city\n}\n").unwrap(),
+        );
+        let model = parse(
+            "namespace people@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name default=\"J\" regex=/[A-Z].*/ length=[1,20]
+  o Address[] addresses optional
+  o Item item
+}
+",
+        )
+        .unwrap();
+
+        let explanation = super::explain(&manager, &model, "Person").unwrap();
+        assert_eq!(
+            explanation.to_string(),
+            "concept people@1.0.0.Person
+  o String name
+      default=\"J\"
+      regex=/[A-Z].*/
+      length=[1, 20]
+  o Address[] addresses optional
+      type places@1.1.0.Address
+  o Item item
+      type unresolved
+",
+            "Should resolve types through imports"
+        );
+        assert!(super::explain(&manager, &model, "Pet").is_none());
+    }
+
+    #[test]
+    fn test_explain_inherited() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace places@1.0.0\nconcept Address {\n  o String city\n}\n").unwrap(),
+        );
+        let model = parse(
+            "namespace people@1.0.0
+import places@1.0.0.Address
+concept Party {
+  o Address address
+}
+concept Person extends Party {
+  o String name
+}
+concept Robot extends Machine {
+  o String model
+}
+",
+        )
+        .unwrap();
+
+        let explanation = super::explain(&manager, &model, "Person").unwrap();
+        assert_eq!(
+            explanation.to_string(),
+            "concept people@1.0.0.Person extends people@1.0.0.Party
+  o Address address
+      from people@1.0.0.Party
+      type places@1.0.0.Address
+  o String name
+",
+            "Should explain inherited properties first"
+        );
+        assert_eq!(
+            super::explain(&manager, &model, "Robot")
+                .unwrap()
+                .to_string(),
+            "concept people@1.0.0.Robot extends Machine unresolved\n  o String model\n"
+        );
+    }
+}
@@ -0,0 +1,425 @@
+//! Prints models in a canonical CTO style, so formatting can be enforced, e.g. in pre-commit
+//! hooks. The output parses back to the same model.
+
+use crate::parser::{
+    common::string::escape_value,
+    cst::{Cst, NodeId},
+    declaration::{Declaration, Property},
+    dialect::MetaExtension,
+    error::SourceError,
+    property::CommonProperty,
+    Model,
+};
+
+/// Knobs of the canonical style. The default prints models the way their `Display` does.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct FormatOptions {
+    /// Spaces properties are indented with
+    pub indent: usize,
+    /// Writes meta properties as `default = 1` instead of `default=1`
+    pub space_around_equals: bool,
+    /// Sorts meta properties registered by dialects by their keyword, instead of keeping
+    /// the order they were written in. Built-in meta properties always come first, in a
+    /// fixed order.
+    pub sort_meta_extensions: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            space_around_equals: false,
+            sort_meta_extensions: false,
+        }
+    }
+}
+
+pub fn format(model: &Model) -> String {
+    format_with(model, &FormatOptions::default())
+}
+
+pub fn format_with(model: &Model, options: &FormatOptions) -> String {
+    write_model(model, options, &|_| None)
+}
+
+/// Formats a losslessly parsed model, keeping the comments written between its nodes on
+/// their own lines above the node. Blank lines are replaced by the canonical ones.
+pub fn format_cst(cst: &Cst, options: &FormatOptions) -> String {
+    let mut output = write_model(cst.model(), options, &|node| cst.leading_trivia(node));
+    output.push_str(&kept_trivia(Some(cst.trailing_trivia()), ""));
+    output
+}
+
+/// Whether a source is formatted, and what formatting would change
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FormatReport {
+    pub formatted: bool,
+    /// Changed lines in the unified diff style, without context lines. Empty when the
+    /// source is formatted.
+    pub diff: String,
+}
+
+/// Checks a source against the canonical style without rewriting it, for CI
+pub fn check(input: &str) -> Result<FormatReport, SourceError> {
+    check_with(input, &FormatOptions::default())
+}
+
+pub fn check_with(input: &str, options: &FormatOptions) -> Result<FormatReport, SourceError> {
+    let formatted = format_cst(&Cst::parse(input)?, options);
+    Ok(FormatReport {
+        formatted: formatted == input,
+        diff: diff(input, &formatted),
+    })
+}
+
+/// Line diff of two texts, as hunks of removed and added lines
+fn diff(old: &str, new: &str) -> String {
+    let (old, new): (Vec<_>, Vec<_>) = (old.lines().collect(), new.lines().collect());
+    // Lengths of the longest common subsequences of the suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (old_start, new_start) = (i, j);
+        while (i < old.len() || j < new.len())
+            && !(i < old.len() && j < new.len() && old[i] == new[j])
+        {
+            if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            i - old_start,
+            new_start + 1,
+            j - new_start
+        ));
+        for line in &old[old_start..i] {
+            output.push_str(&format!("-{}\n", line));
+        }
+        for line in &new[new_start..j] {
+            output.push_str(&format!("+{}\n", line));
+        }
+    }
+    output
+}
+
+/// Lines of trivia worth keeping, i.e. anything but whitespace, indented
+fn kept_trivia(trivia: Option<&str>, indent: &str) -> String {
+    trivia
+        .into_iter()
+        .flat_map(str::lines)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{}{}\n", indent, line))
+        .collect()
+}
+
+fn write_model<'t>(
+    model: &Model,
+    options: &FormatOptions,
+    trivia: &dyn Fn(NodeId) -> Option<&'t str>,
+) -> String {
+    let mut output = match &model.header {
+        Some(header) => format!("{}\n\n", header),
+        None => String::new(),
+    };
+    output.push_str(&kept_trivia(trivia(NodeId::Namespace), ""));
+    output.push_str(&format!("{}\n", model.namespace));
+    if !model.imports.is_empty() {
+        output.push('\n');
+        for (i, import) in model.imports.iter().enumerate() {
+            output.push_str(&kept_trivia(trivia(NodeId::Import(i)), ""));
+            output.push_str(&format!("{}\n", import));
+        }
+    }
+    for (d, declaration) in model.declarations.iter().enumerate() {
+        output.push('\n');
+        output.push_str(&kept_trivia(trivia(NodeId::Declaration(d)), ""));
+        output.push_str(&format_declaration(declaration, options, &|p| {
+            trivia(NodeId::Property(d, p))
+        }));
+        output.push('\n');
+    }
+    output
+}
+
+fn format_declaration<'t>(
+    declaration: &Declaration,
+    options: &FormatOptions,
+    trivia: &dyn Fn(usize) -> Option<&'t str>,
+) -> String {
+    if declaration.extension.is_some() {
+        // Bodies of extension declarations are kept as the dialect received them
+        return declaration.to_string();
+    }
+    let indent = " ".repeat(options.indent);
+    let mut output: String = declaration
+        .decorators_of(None)
+        .map(|d| format!("{}\n", d))
+        .collect();
+    output.push_str(&format!("{} {{\n", declaration.head()));
+    for (p, property) in declaration.properties.iter().enumerate() {
+        let mut metas: Vec<_> = declaration
+            .meta_extensions
+            .iter()
+            .filter(|m| m.property == property.name())
+            .collect();
+        if options.sort_meta_extensions {
+            metas.sort_by(|a, b| a.keyword.cmp(&b.keyword));
+        }
+        output.push_str(&kept_trivia(trivia(p), &indent));
+        for decorator in declaration.decorators_of(Some(property.name())) {
+            output.push_str(&format!("{}{}\n", indent, decorator));
+        }
+        output.push_str(&indent);
+        output.push_str(&format_property(property, &metas, options));
+        output.push('\n');
+    }
+    output.push('}');
+    output
+}
+
+fn format_property(
+    property: &Property,
+    metas: &[&MetaExtension],
+    options: &FormatOptions,
+) -> String {
+    if let Property::Enum(value) = property {
+        return value.to_string();
+    }
+    let array = if property.is_array() { "[]" } else { "" };
+    let marker = if property.is_relationship() {
+        "-->"
+    } else {
+        "o"
+    };
+    let type_name = match property {
+        Property::Concept(concept) => concept.type_reference().to_string(),
+        _ => property.type_name().to_string(),
+    };
+    let mut output = format!("{} {}{} {}", marker, type_name, array, property.name());
+
+    let meta = |keyword: &str, value: &str| match options.space_around_equals {
+        true => format!(" {} = {}", keyword, value),
+        false => format!(" {}={}", keyword, value),
+    };
+    if let Some(default) = default_value(property) {
+        output.push_str(&meta("default", &default));
+    }
+    for validator in property.validators() {
+        let written = validator.to_string();
+        // Keywords of validators don't contain `=`, unbounded validators print nothing
+        if let Some((keyword, value)) = written.split_once('=') {
+            output.push_str(&meta(keyword, value));
+        }
+    }
+    if property.is_optional() {
+        output.push_str(" optional");
+    }
+    for extension in metas {
+        match &extension.value {
+            Some(value) => output.push_str(&meta(&extension.keyword, value)),
+            None => output.push_str(&format!(" {}", extension.keyword)),
+        }
+    }
+    output
+}
+
+/// Default value of a property as written in CTO
+pub(crate) fn default_value(property: &Property) -> Option<String> {
+    match property {
+        Property::Boolean(p) => p.default_value.map(|v| v.to_string()),
+        Property::Integer(p) => p.default_value.map(|v| format!("{:?}", v)),
+        Property::Long(p) => p.default_value.map(|v| format!("{:?}", v)),
+        Property::Double(p) => p.default_value.map(|v| format!("{:?}", v)),
+        Property::DateTime(p) => p.default_value.clone(),
+        Property::String(p) => p
+            .default_value
+            .as_ref()
+            .map(|v| format!("\"{}\"", escape_value(v, '"'))),
+        Property::Concept(_) | Property::Enum(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{dialect::Dialect, options::ParserOptions, parse};
+
+    #[test]
+    fn test_format() {
+        let input = "namespace test@1.0.0
+import places@1.0.0.Address
+concept Person {
+      o String name   default = \"a=b\"  regex=/[a-z=]+/ length=[1,10]
+  o Integer age optional range=[0,  ]
+    o Address address
+}
+";
+        let model = parse(input).unwrap();
+
+        assert_eq!(
+            super::format(&model),
+            "namespace test@1.0.0
+
+import places@1.0.0.Address
+
+concept Person {
+  o String name default=\"a=b\" regex=/[a-z=]+/ length=[1, 10]
+  o Integer age range=[0,] optional
+  o Address address
+}
+",
+            "Should print in the canonical style"
+        );
+
+        let options = super::FormatOptions {
+            indent: 4,
+            space_around_equals: true,
+            ..Default::default()
+        };
+        let formatted = super::format_with(&model, &options);
+        assert_eq!(
+            formatted.lines().nth(5),
+            Some("    o String name default = \"a=b\" regex = /[a-z=]+/ length = [1, 10]"),
+            "Should follow the options"
+        );
+        assert_eq!(
+            parse(&formatted),
+            Ok(model),
+            "Should parse back to the same model"
+        );
+    }
+
+    #[test]
+    fn test_format_meta_extensions() {
+        let dialect = Dialect::default()
+            .meta("sensitive", |_| Ok(serde_json::Value::Null))
+            .meta("label", |_| Ok(serde_json::Value::Null));
+        let model = crate::parser::dialect::parse(
+            "namespace test@1.0.0
+
+concept Person {
+  o String name optional sensitive label=\"Name\"
+}
+",
+            &ParserOptions::default(),
+            &dialect,
+        )
+        .unwrap();
+
+        let options = super::FormatOptions {
+            sort_meta_extensions: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            super::format_with(&model, &options).lines().nth(3),
+            Some("  o String name optional label=\"Name\" sensitive"),
+            "Should sort meta properties of dialects"
+        );
+    }
+
+    #[test]
+    fn test_format_cst() {
+        let input = "
+namespace test@1.0.0
+
+
+concept Person {
+    o String name
+}
+";
+        let cst = crate::parser::cst::Cst::parse(input).unwrap();
+
+        assert_eq!(
+            super::format_cst(&cst, &Default::default()),
+            super::format(cst.model()),
+            "Should replace blank lines by the canonical ones"
+        );
+
+        let decorated = "namespace test@1.0.0
+
+@deprecated
+concept Person {
+  @Term(\"Name\")
+  o String name
+}
+";
+        let cst = crate::parser::cst::Cst::parse(decorated).unwrap();
+        assert_eq!(
+            super::format_cst(&cst, &Default::default()),
+            decorated,
+            "Should print decorators above what they decorate"
+        );
+
+        let event = "namespace test@1.0.0
+
+event OrderPlaced {
+  o String orderId
+}
+";
+        let cst = crate::parser::cst::Cst::parse(event).unwrap();
+        assert_eq!(
+            super::format_cst(&cst, &Default::default()),
+            event,
+            "Should format events"
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let formatted = "namespace test@1.0.0
+
+concept Person {
+  o String name
+  o Integer age
+}
+";
+        assert_eq!(
+            super::check(formatted),
+            Ok(super::FormatReport {
+                formatted: true,
+                diff: String::new()
+            })
+        );
+
+        let report = super::check(
+            "namespace test@1.0.0
+concept Person {
+    o String name
+  o Integer age
+}
+",
+        )
+        .unwrap();
+        assert!(!report.formatted);
+        assert_eq!(
+            report.diff,
+            "@@ -2,0 +2,1 @@
++
+@@ -3,1 +4,1 @@
+-    o String name
++  o String name
+",
+            "Should only list the changed lines"
+        );
+        assert!(super::check("namespace test@1.0.0\nconcept {").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! Declaration-level dependency graph of the models loaded in a `ModelManager`
+
+use crate::{
+    manager::ModelManager,
+    parser::{
+        declaration::{Declaration, Property},
+        namespace::FullyQualifiedName,
+        Model,
+    },
+};
+
+/// Why one declaration depends on another
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EdgeKind {
+    /// A property of the dependent declaration has the other declaration as its type
+    Property(String),
+    /// The dependent declaration extends the other declaration
+    Extends,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Edge {
+    /// Index of the dependent declaration
+    pub from: usize,
+    /// Index of the declaration depended on
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Declarations of all loaded models as nodes, indexed in the order the models were added,
+/// and the references between them as edges
+#[derive(Debug, Clone)]
+pub struct DependencyGraph<'a> {
+    nodes: Vec<(&'a Model, &'a Declaration)>,
+    edges: Vec<Edge>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    pub(crate) fn new(manager: &'a ModelManager) -> Self {
+        let nodes: Vec<_> = manager
+            .models()
+            .flat_map(|m| m.declarations.iter().map(move |d| (m, d)))
+            .collect();
+        let index_of = |declaration: &Declaration| {
+            nodes
+                .iter()
+                .position(|(_, d)| std::ptr::eq(*d, declaration))
+        };
+        let mut edges = Vec::new();
+        for (from, (model, declaration)) in nodes.iter().enumerate() {
+            let extended = manager.resolve_extended_in(model, declaration);
+            if let Some(to) = extended.and_then(|(_, d)| index_of(d)) {
+                edges.push(Edge {
+                    from,
+                    to,
+                    kind: EdgeKind::Extends,
+                });
+            }
+            for property in &declaration.properties {
+                if let Property::Concept(p) = property {
+                    let target = manager.resolve_property_in(model, p);
+                    if let Some(to) = target.and_then(|(_, d)| index_of(d)) {
+                        edges.push(Edge {
+                            from,
+                            to,
+                            kind: EdgeKind::Property(p.name.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        Self { nodes, edges }
+    }
+
+    pub fn nodes(&self) -> &[(&'a Model, &'a Declaration)] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Index of the declaration with the fully qualified name
+    pub fn index_of(&self, name: &FullyQualifiedName) -> Option<usize> {
+        self.nodes.iter().position(|(m, d)| {
+            m.namespace.name() == name.namespace_name()
+                && m.namespace.version() == name.version()
+                && d.name == name.type_name()
+        })
+    }
+
+    /// Declarations the given one depends on directly, without duplicates
+    pub fn dependencies(&self, node: usize) -> Vec<usize> {
+        let mut dependencies: Vec<_> = self
+            .edges
+            .iter()
+            .filter(|e| e.from == node)
+            .map(|e| e.to)
+            .collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        dependencies
+    }
+
+    /// Declarations that depend on the given one directly, without duplicates
+    pub fn dependents(&self, node: usize) -> Vec<usize> {
+        let mut dependents: Vec<_> = self
+            .edges
+            .iter()
+            .filter(|e| e.to == node)
+            .map(|e| e.from)
+            .collect();
+        dependents.sort_unstable();
+        dependents.dedup();
+        dependents
+    }
+
+    /// Declarations reachable from the given one, including itself, in ascending order
+    pub fn reachable_from(&self, node: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            if std::mem::replace(&mut visited[current], true) {
+                continue;
+            }
+            stack.extend(self.dependencies(current));
+        }
+        (0..self.nodes.len()).filter(|&i| visited[i]).collect()
+    }
+
+    /// Declarations that depend on the given one directly or indirectly, including itself,
+    /// in ascending order. These are the ones a change of the given one can break.
+    pub fn affected_by(&self, node: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            if std::mem::replace(&mut visited[current], true) {
+                continue;
+            }
+            stack.extend(self.dependents(current));
+        }
+        (0..self.nodes.len()).filter(|&i| visited[i]).collect()
+    }
+
+    /// Strongly connected components, with dependencies listed before their dependents.
+    /// Components with more than one declaration, or a declaration depending on itself,
+    /// are reference cycles.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        Tarjan::new(self).run()
+    }
+}
+
+/// State of Tarjan's algorithm
+struct Tarjan<'g, 'a> {
+    graph: &'g DependencyGraph<'a>,
+    index: usize,
+    indices: Vec<Option<usize>>,
+    low_links: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'g, 'a> Tarjan<'g, 'a> {
+    fn new(graph: &'g DependencyGraph<'a>) -> Self {
+        let len = graph.nodes.len();
+        Self {
+            graph,
+            index: 0,
+            indices: vec![None; len],
+            low_links: vec![0; len],
+            on_stack: vec![false; len],
+            stack: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Vec<Vec<usize>> {
+        for node in 0..self.graph.nodes.len() {
+            if self.indices[node].is_none() {
+                self.visit(node);
+            }
+        }
+        self.components
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.indices[node] = Some(self.index);
+        self.low_links[node] = self.index;
+        self.index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for next in self.graph.dependencies(node) {
+            match self.indices[next] {
+                None => {
+                    self.visit(next);
+                    self.low_links[node] = self.low_links[node].min(self.low_links[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.low_links[node] = self.low_links[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low_links[node]) == self.indices[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        manager::ModelManager,
+        parser::{model, namespace::fqn},
+    };
+
+    #[test]
+    fn test_dependency_graph() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o String name
+              o Address home
+              o Address work optional
+              o Company employer
+            }
+
+            concept Company {
+              o Person[] employees
+            }
+            ",
+            "namespace places@1.0.0
+
+            concept Address {
+              o String street
+            }
+
+            concept Country {
+              o String name
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let graph = manager.dependency_graph();
+        let index = |name: &str| graph.index_of(&fqn(name).unwrap().1).unwrap();
+        let (person, company, address, country) = (
+            index("people@1.0.0.Person"),
+            index("people@1.0.0.Company"),
+            index("places@1.0.0.Address"),
+            index("places@1.0.0.Country"),
+        );
+
+        assert_eq!(graph.nodes().len(), 4);
+        assert_eq!(graph.edges().len(), 4, "Should add an edge per property");
+        assert_eq!(
+            graph.dependencies(person),
+            vec![company, address],
+            "Should resolve imported types"
+        );
+        assert_eq!(graph.dependents(address), vec![person]);
+        assert_eq!(
+            graph.reachable_from(company),
+            vec![person, company, address]
+        );
+        assert_eq!(graph.reachable_from(country), vec![country]);
+        assert_eq!(
+            graph.affected_by(address),
+            vec![person, company, address],
+            "Should follow dependents transitively"
+        );
+        assert_eq!(
+            graph.strongly_connected_components(),
+            vec![vec![address], vec![person, company], vec![country]],
+            "Should group cycles, dependencies first"
+        );
+    }
+
+    #[test]
+    fn test_extends_edges() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace places@1.0.0
+
+            concept Address {
+              o String street
+            }
+            ",
+            "namespace offices@1.0.0
+            import places@1.0.0.Address
+
+            concept Office extends Address {
+              o Integer floor
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let graph = manager.dependency_graph();
+        let index = |name: &str| graph.index_of(&fqn(name).unwrap().1).unwrap();
+        let (address, office) = (index("places@1.0.0.Address"), index("offices@1.0.0.Office"));
+
+        assert_eq!(
+            graph.edges(),
+            &[super::Edge {
+                from: office,
+                to: address,
+                kind: super::EdgeKind::Extends,
+            }],
+            "Should add an edge to the extended declaration"
+        );
+        assert_eq!(graph.affected_by(address), vec![address, office]);
+    }
+}
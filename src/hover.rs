@@ -0,0 +1,192 @@
+//! What to show about the node under the cursor, e.g. in editor tooltips
+
+use std::ops::Range;
+
+use crate::{
+    format::default_value,
+    parser::{
+        declaration::Property, namespace::FullyQualifiedName, property::CommonProperty,
+        span::ModelSpans, Model,
+    },
+};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Hover {
+    /// Span of the node the information is about
+    pub span: Range<usize>,
+    /// The node as declared, e.g. `o Address[] addresses optional`
+    pub signature: String,
+    /// Type of the node, primitive types have none
+    pub resolved_type: Option<FullyQualifiedName>,
+    /// Default value as written in CTO
+    pub default: Option<String>,
+    /// Validators and meta properties, as written in CTO
+    pub validators: Vec<String>,
+}
+
+impl std::fmt::Display for Hover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.signature)?;
+        if let Some(fqn) = &self.resolved_type {
+            writeln!(f, "type {}", fqn)?;
+        }
+        if let Some(default) = &self.default {
+            writeln!(f, "default={}", default)?;
+        }
+        for validator in &self.validators {
+            writeln!(f, "{}", validator)?;
+        }
+        Ok(())
+    }
+}
+
+/// Information about the innermost node at a byte offset of the source, given the spans the
+/// model was parsed with. Returns `None` between nodes.
+pub fn hover_at(model: &Model, spans: &ModelSpans, offset: usize) -> Option<Hover> {
+    let at = |span: &Range<usize>| span.contains(&offset);
+    let fqn = |namespace: &crate::parser::namespace::Namespace, name: &str| {
+        FullyQualifiedName::from((
+            namespace.name().to_string(),
+            namespace.version().clone(),
+            name.to_string(),
+        ))
+    };
+
+    for (declaration, declaration_spans) in model.declarations.iter().zip(&spans.declarations) {
+        if !at(&declaration_spans.span) {
+            continue;
+        }
+        for (property, span) in declaration
+            .properties
+            .iter()
+            .zip(&declaration_spans.properties)
+        {
+            if !at(span) {
+                continue;
+            }
+            let array = if property.is_array() { "[]" } else { "" };
+            let optional = if property.is_optional() {
+                " optional"
+            } else {
+                ""
+            };
+            let mut validators: Vec<_> = property
+                .validators()
+                .iter()
+                .map(|v| v.to_string())
+                .filter(|v| !v.is_empty())
+                .collect();
+            validators.extend(
+                declaration
+                    .meta_extensions
+                    .iter()
+                    .filter(|m| m.property == property.name())
+                    .map(|m| m.to_string()),
+            );
+            return Some(Hover {
+                span: span.clone(),
+                signature: format!(
+                    "o {}{} {}{}",
+                    match property {
+                        Property::Concept(p) => p.type_reference().to_string(),
+                        _ => property.type_name().to_string(),
+                    },
+                    array,
+                    property.name(),
+                    optional
+                ),
+                resolved_type: match property {
+                    Property::Concept(p) => match &p.namespace {
+                        Some(namespace) => Some(FullyQualifiedName::from((
+                            namespace.name().to_string(),
+                            namespace.version().clone(),
+                            p.class.clone(),
+                        ))),
+                        None => model.fully_qualified_name(&p.class),
+                    },
+                    _ => None,
+                },
+                default: default_value(property),
+                validators,
+            });
+        }
+        let name = fqn(&model.namespace, &declaration.name);
+        let keyword = match &declaration.extension {
+            Some(extension) => extension.keyword.clone(),
+            None => declaration.kind.to_string(),
+        };
+        return Some(Hover {
+            span: declaration_spans.span.clone(),
+            signature: format!("{} {}", keyword, name),
+            resolved_type: Some(name),
+            default: None,
+            validators: Vec::new(),
+        });
+    }
+
+    if let Some((import, span)) = model
+        .imports
+        .iter()
+        .zip(&spans.imports)
+        .find(|(_, s)| at(s))
+    {
+        return Some(Hover {
+            span: span.clone(),
+            signature: import.to_string(),
+            resolved_type: None,
+            default: None,
+            validators: Vec::new(),
+        });
+    }
+    at(&spans.namespace).then(|| Hover {
+        span: spans.namespace.clone(),
+        signature: model.namespace.to_string(),
+        resolved_type: None,
+        default: None,
+        validators: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::cst::Cst;
+
+    #[test]
+    fn test_hover_at() {
+        let source = "namespace test@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name default=\"J\" regex=/[A-Z].*/
+  o Address[] addresses optional
+}
+";
+        let cst = Cst::parse(source).unwrap();
+        let hover = |needle: &str| {
+            let offset = source.find(needle).unwrap();
+            super::hover_at(cst.model(), cst.spans(), offset).map(|h| h.to_string())
+        };
+
+        assert_eq!(
+            hover("o String").as_deref(),
+            Some("o String name\ndefault=\"J\"\nregex=/[A-Z].*/\n")
+        );
+        assert_eq!(
+            hover("Address[]").as_deref(),
+            Some("o Address[] addresses optional\ntype places@1.0.0.Address\n"),
+            "Should resolve imported types"
+        );
+        assert_eq!(
+            hover("Person").as_deref(),
+            Some("concept test@1.0.0.Person\ntype test@1.0.0.Person\n")
+        );
+        assert_eq!(
+            hover("places").as_deref(),
+            Some("import places@1.0.0.Address\n")
+        );
+        assert_eq!(
+            super::hover_at(cst.model(), cst.spans(), source.len()),
+            None,
+            "Should find nothing between nodes"
+        );
+    }
+}
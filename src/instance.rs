@@ -0,0 +1,1226 @@
+//! Validation of JSON instances against the declarations of a `ModelManager`
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use nom::combinator::all_consuming;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    manager::ModelManager,
+    migration,
+    parser::{
+        common::datetime::datetime_value,
+        declaration::{Declaration, DeclarationKind, Property, MAP_KEY, MAP_VALUE},
+        decorator::{DecoratorArgument, EXAMPLE},
+        namespace::{fqn, Namespace},
+        property::{
+            long_property::LongValue, string_property::StringRegexValidator, CommonProperty,
+            Validator,
+        },
+        Model,
+    },
+    system,
+};
+
+/// Why an instance is invalid, at the path of the value like `address.lines[1]`
+#[derive(Debug, PartialEq, Clone)]
+pub struct InstanceError {
+    /// Empty for the instance itself
+    pub path: String,
+    pub kind: InstanceErrorKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum InstanceErrorKind {
+    /// Instances are objects with the fully qualified name of their type in `$class`
+    MissingClass,
+    /// `$class` isn't a loaded declaration
+    UnknownType(String),
+    /// `$class` is an abstract declaration or a base type of the system namespace, which only
+    /// types extending it have instances of
+    AbstractType(String),
+    /// A property that isn't optional has no value
+    MissingProperty,
+    /// The type has no property of this name
+    UnexpectedProperty,
+    /// Value isn't of the type of the property, the expected type
+    WrongType(String),
+    /// Value doesn't pass a validator of the property, the validator as written in CTO
+    Validator(String),
+    /// Pattern and flags of a regex validator that doesn't compile
+    InvalidRegex(String, String),
+}
+
+impl std::fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.kind {
+            InstanceErrorKind::MissingClass => write!(f, "missing $class"),
+            InstanceErrorKind::UnknownType(class) => write!(f, "unknown type {}", class),
+            InstanceErrorKind::AbstractType(class) => write!(f, "abstract type {}", class),
+            InstanceErrorKind::MissingProperty => write!(f, "missing value"),
+            InstanceErrorKind::UnexpectedProperty => write!(f, "unexpected property"),
+            InstanceErrorKind::WrongType(expected) => write!(f, "expected {}", expected),
+            InstanceErrorKind::Validator(validator) => write!(f, "doesn't match {}", validator),
+            InstanceErrorKind::InvalidRegex(pattern, flags) => {
+                write!(f, "invalid regex /{}/{}", pattern, flags)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstanceError {}
+
+/// Regexes by pattern then flags, `None` for the ones that don't compile
+type Compiled = HashMap<String, HashMap<String, Option<Regex>>>;
+
+/// Compiled regexes of `StringRegexValidator`s by pattern and flags, each compiled the first
+/// time it is used. Clones share the same cache.
+#[derive(Debug, Default, Clone)]
+pub struct RegexCache {
+    regexes: Arc<RwLock<Compiled>>,
+}
+
+impl RegexCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compiled regex of a validator, `None` if the pattern or its flags aren't supported
+    pub fn get(&self, validator: &StringRegexValidator) -> Option<Regex> {
+        let cached = self
+            .read()
+            .get(&validator.pattern)
+            .and_then(|by_flags| by_flags.get(&validator.flags))
+            .cloned();
+        if let Some(regex) = cached {
+            return regex;
+        }
+
+        // Compile without holding the lock, a regex compiled twice by racing threads is fine
+        let regex = compile(&validator.pattern, &validator.flags);
+        self.write()
+            .entry(validator.pattern.clone())
+            .or_default()
+            .insert(validator.flags.clone(), regex.clone());
+        regex
+    }
+
+    /// Number of pattern and flags combinations compiled so far
+    pub fn len(&self) -> usize {
+        self.read().values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Only whole entries are inserted under the lock, so poisoning is ignored
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Compiled> {
+        self.regexes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Compiled> {
+        self.regexes.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Compiles a pattern with JavaScript flags. `d`, `g` and `y` only matter to repeated matching
+/// and match indices and are ignored. `u` is always on, as is `v` whose class set operations
+/// like `[\w--\d]` the regex crate takes in any mode.
+fn compile(pattern: &str, flags: &str) -> Option<Regex> {
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'd' | 'g' | 'y' | 'u' | 'v' => &mut builder,
+            _ => return None,
+        };
+    }
+    builder.build().ok()
+}
+
+/// Validates JSON instances against the declarations of a `ModelManager`. Regexes are
+/// compiled once per validator, so one `InstanceValidator` can check many instances cheaply.
+#[derive(Debug, Clone)]
+pub struct InstanceValidator<'a> {
+    manager: &'a ModelManager,
+    regexes: RegexCache,
+}
+
+impl<'a> InstanceValidator<'a> {
+    pub fn new(manager: &'a ModelManager) -> Self {
+        Self::with_regexes(manager, RegexCache::new())
+    }
+
+    /// Uses a regex cache shared with other validators, e.g. of other threads
+    pub fn with_regexes(manager: &'a ModelManager, regexes: RegexCache) -> Self {
+        Self { manager, regexes }
+    }
+
+    /// Checks an instance of the declaration named by its `$class`, reporting every problem
+    pub fn validate(&self, instance: &Value) -> Result<(), Vec<InstanceError>> {
+        let mut errors = Vec::new();
+        match self.typed(instance) {
+            Ok((model, declaration)) => {
+                self.validate_object(model, declaration, instance, "", &mut errors)
+            }
+            Err(kind) => errors.push(InstanceError {
+                path: String::new(),
+                kind,
+            }),
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Declaration named by the `$class` of an object
+    fn typed(&self, instance: &Value) -> Result<(&'a Model, &'a Declaration), InstanceErrorKind> {
+        let class = instance
+            .get("$class")
+            .and_then(Value::as_str)
+            .ok_or(InstanceErrorKind::MissingClass)?;
+        let unknown = || InstanceErrorKind::UnknownType(class.to_string());
+        let (_, name) = all_consuming(fqn)(class).map_err(|_| unknown())?;
+        let namespace = Namespace::new(name.namespace_name(), name.version().clone());
+        if system::is_system(&namespace) {
+            return Err(InstanceErrorKind::AbstractType(class.to_string()));
+        }
+        let model = self.manager.model(&namespace).ok_or_else(unknown)?;
+        let declaration = model.declaration(name.type_name()).ok_or_else(unknown)?;
+        if declaration.is_abstract {
+            return Err(InstanceErrorKind::AbstractType(class.to_string()));
+        }
+        Ok((model, declaration))
+    }
+
+    fn validate_object(
+        &self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<InstanceError>,
+    ) {
+        let Some(object) = value.as_object() else {
+            return errors.push(error(path, wrong_type(&declaration.name)));
+        };
+        let hierarchy = self.manager.hierarchy(model, declaration);
+        let properties = hierarchy
+            .iter()
+            .flat_map(|&(model, d)| d.properties.iter().map(move |p| (model, p)));
+        for (model, property) in properties {
+            let path = join(path, property.name());
+            match object.get(property.name()) {
+                None | Some(Value::Null) if property.is_optional() => (),
+                None | Some(Value::Null) => {
+                    errors.push(error(&path, InstanceErrorKind::MissingProperty))
+                }
+                Some(Value::Array(items)) if property.is_array() => {
+                    for (i, item) in items.iter().enumerate() {
+                        let path = format!("{}[{}]", path, i);
+                        self.validate_value(model, property, item, &path, errors);
+                    }
+                }
+                Some(_) if property.is_array() => errors.push(error(
+                    &path,
+                    wrong_type(&format!("{}[]", property.type_name())),
+                )),
+                Some(value) => self.validate_value(model, property, value, &path, errors),
+            }
+        }
+        for name in undeclared(object, &hierarchy) {
+            errors.push(error(
+                &join(path, name),
+                InstanceErrorKind::UnexpectedProperty,
+            ));
+        }
+    }
+
+    fn validate_value(
+        &self,
+        model: &'a Model,
+        property: &'a Property,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<InstanceError>,
+    ) {
+        let typed = match property {
+            Property::Boolean(_) => value.is_boolean(),
+            Property::Integer(_) => value.as_i64().is_some_and(|v| i32::try_from(v).is_ok()),
+            Property::Long(_) => LongValue::deserialize(value).is_ok(),
+            Property::Double(_) => value.is_number(),
+            Property::DateTime(_) => value
+                .as_str()
+                .is_some_and(|v| all_consuming(datetime_value)(v).is_ok()),
+            Property::String(_) => value.is_string(),
+            // Values of enums are not properties of instances
+            Property::Enum(_) => false,
+            // Relationships are written as the identifier of the instance they point to
+            Property::Concept(p) if p.is_relationship => value.is_string(),
+            Property::Concept(p) => {
+                let declared = self.manager.resolve_property_in(model, p);
+                // Enum values are written as their name
+                if let Some((_, declaration)) =
+                    declared.filter(|(_, d)| d.kind == DeclarationKind::Enum)
+                {
+                    if value
+                        .as_str()
+                        .and_then(|v| declaration.property(v))
+                        .is_none()
+                    {
+                        errors.push(error(path, wrong_type(&p.class)));
+                    }
+                    return;
+                }
+                if let Some((model, declaration)) =
+                    declared.filter(|(_, d)| d.kind == DeclarationKind::Map)
+                {
+                    return self.validate_map(model, declaration, value, path, errors);
+                }
+                // Instances of a concept can name their type, otherwise it's the declared one
+                match (value.get("$class"), declared) {
+                    (Some(_), _) => match self.typed(value) {
+                        Ok((model, declaration)) if !self.extends(model, declaration, declared) => {
+                            errors.push(error(path, wrong_type(&p.class)))
+                        }
+                        Ok((model, declaration)) => {
+                            self.validate_object(model, declaration, value, path, errors)
+                        }
+                        Err(kind) => errors.push(error(path, kind)),
+                    },
+                    // Values of base and abstract types name the type extending it
+                    (None, Some((model, declaration)))
+                        if system::is_system(&model.namespace) || declaration.is_abstract =>
+                    {
+                        errors.push(error(path, InstanceErrorKind::MissingClass))
+                    }
+                    (None, Some((model, declaration))) => {
+                        self.validate_object(model, declaration, value, path, errors)
+                    }
+                    (None, None) => errors.push(error(
+                        path,
+                        InstanceErrorKind::UnknownType(p.type_reference().to_string()),
+                    )),
+                }
+                return;
+            }
+        };
+        if !typed {
+            return errors.push(error(path, wrong_type(property.type_name())));
+        }
+
+        for validator in property.validators() {
+            match self.passes(validator, value) {
+                Ok(true) => (),
+                Ok(false) => errors.push(error(
+                    path,
+                    InstanceErrorKind::Validator(validator.to_string()),
+                )),
+                Err(kind) => errors.push(error(path, kind)),
+            }
+        }
+    }
+
+    /// Checks the entries of a map, an object with its keys as property names. Keys are
+    /// validated as values of the key type, DateTime keys must be dates and times.
+    fn validate_map(
+        &self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<InstanceError>,
+    ) {
+        let (Some(object), Some(key), Some(entry)) = (
+            value.as_object(),
+            declaration.property(MAP_KEY),
+            declaration.property(MAP_VALUE),
+        ) else {
+            return errors.push(error(path, wrong_type(&declaration.name)));
+        };
+        for (name, value) in object.iter().filter(|(name, _)| *name != "$class") {
+            let path = join(path, name);
+            self.validate_value(model, key, &Value::String(name.clone()), &path, errors);
+            self.validate_value(model, entry, value, &path, errors);
+        }
+    }
+
+    /// Whether a declaration is the declared type of a property or extends it. Every
+    /// declaration extends the base types, and types that aren't loaded aren't checked.
+    fn extends(
+        &self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+        declared: Option<(&'a Model, &'a Declaration)>,
+    ) -> bool {
+        match declared {
+            Some((declared_in, declared)) if !system::is_system(&declared_in.namespace) => self
+                .manager
+                .hierarchy(model, declaration)
+                .iter()
+                .any(|(_, d)| std::ptr::eq(*d, declared)),
+            _ => true,
+        }
+    }
+
+    /// Sample instance of the declaration a `$class` names, for documentation and tests.
+    /// Properties take the value of their `@example("...")` decorator if it passes their
+    /// validators, then their default, then a placeholder of their type. Optional properties
+    /// without an example or a default are left out, as are concepts nested in themselves.
+    pub fn sample(&self, class: &str) -> Result<Value, InstanceErrorKind> {
+        let (model, declaration) = self.typed(&json!({ "$class": class }))?;
+        Ok(self.sample_object(model, declaration, &mut Vec::new()))
+    }
+
+    fn sample_object(
+        &self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+        nested_in: &mut Vec<&'a Declaration>,
+    ) -> Value {
+        nested_in.push(declaration);
+        let mut object = Map::new();
+        let class = format!(
+            "{}@{}.{}",
+            model.namespace.name(),
+            model.namespace.version(),
+            declaration.name
+        );
+        object.insert(String::from("$class"), Value::String(class));
+        for (model, declaring) in self.manager.hierarchy(model, declaration) {
+            for property in &declaring.properties {
+                let Some(value) = self.sample_value(model, declaring, property, nested_in) else {
+                    continue;
+                };
+                let value = match property.is_array() {
+                    true => Value::Array(vec![value]),
+                    false => value,
+                };
+                object.insert(property.name().to_string(), value);
+            }
+        }
+        nested_in.pop();
+        Value::Object(object)
+    }
+
+    fn sample_value(
+        &self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+        property: &'a Property,
+        nested_in: &mut Vec<&'a Declaration>,
+    ) -> Option<Value> {
+        let passes = |value: &Value| {
+            let mut errors = Vec::new();
+            self.validate_value(model, property, value, "", &mut errors);
+            errors.is_empty()
+        };
+        let example = declaration
+            .decorators_of(Some(property.name()))
+            .find(|d| d.name == EXAMPLE)
+            .and_then(|d| d.arguments.first())
+            .and_then(|argument| example_value(property, argument));
+        if let Some(example) = example.filter(passes) {
+            return Some(example);
+        }
+        if let Some(default) = migration::default(property) {
+            return Some(default);
+        }
+        if property.is_optional() {
+            return None;
+        }
+        let placeholders = match property {
+            Property::Concept(p) => {
+                let (model, declaration) = self.manager.resolve_property_in(model, p)?;
+                if p.is_relationship {
+                    return Some(json!(format!(
+                        "resource:{}@{}.{}#1",
+                        model.namespace.name(),
+                        model.namespace.version(),
+                        declaration.name
+                    )));
+                }
+                if declaration.kind == DeclarationKind::Enum {
+                    return declaration.properties.first().map(|v| json!(v.name()));
+                }
+                if declaration.kind == DeclarationKind::Map {
+                    return Some(json!({}));
+                }
+                if system::is_system(&model.namespace)
+                    || declaration.is_abstract
+                    || nested_in.iter().any(|d| std::ptr::eq(*d, declaration))
+                {
+                    return None;
+                }
+                return Some(self.sample_object(model, declaration, nested_in));
+            }
+            Property::Boolean(_) => vec![json!(false)],
+            Property::Integer(p) => bounds(p.domain_validator.as_ref().map(|r| (r.lower, r.upper))),
+            Property::Long(p) => bounds(p.domain_validator.as_ref().map(|r| (r.lower, r.upper))),
+            Property::Double(p) => bounds(p.domain_validator.as_ref().map(|r| (r.lower, r.upper))),
+            Property::DateTime(_) => vec![json!("2000-01-01T00:00:00.000Z")],
+            Property::Enum(_) => return None,
+            Property::String(p) => {
+                let min = p.length_validator.as_ref().and_then(|l| l.min_length);
+                vec![json!("text"), json!("a".repeat(min.unwrap_or(1) as usize))]
+            }
+        };
+        let first = placeholders.first().cloned();
+        placeholders.into_iter().find(passes).or(first)
+    }
+
+    /// Whether a value of the type of the validator's property passes it
+    fn passes(&self, validator: Validator<'_>, value: &Value) -> Result<bool, InstanceErrorKind> {
+        Ok(match validator {
+            Validator::Regex(regex) => {
+                let compiled = self.regexes.get(regex).ok_or_else(|| {
+                    InstanceErrorKind::InvalidRegex(regex.pattern.clone(), regex.flags.clone())
+                })?;
+                compiled.is_match(value.as_str().unwrap_or_default())
+            }
+            Validator::Length(length) => {
+                let len = value.as_str().unwrap_or_default().chars().count() as i64;
+                length.min_length.is_none_or(|min| i64::from(min) <= len)
+                    && length.max_length.is_none_or(|max| len <= i64::from(max))
+            }
+            Validator::IntegerDomain(range) => {
+                let v = value.as_i64().unwrap_or_default();
+                within(
+                    v,
+                    (range.lower.map(i64::from), range.lower_exclusive),
+                    (range.upper.map(i64::from), range.upper_exclusive),
+                )
+            }
+            Validator::LongDomain(range) => within(
+                LongValue::deserialize(value).unwrap_or_default(),
+                (range.lower, range.lower_exclusive),
+                (range.upper, range.upper_exclusive),
+            ),
+            Validator::DoubleDomain(range) => within(
+                value.as_f64().unwrap_or_default(),
+                (range.lower, range.lower_exclusive),
+                (range.upper, range.upper_exclusive),
+            ),
+        })
+    }
+}
+
+/// Value of a DateTime property of an instance as a timestamp, `None` if it isn't a string
+/// holding a real date and time. Dates without a time are midnight UTC.
+#[cfg(feature = "chrono")]
+pub fn timestamp(value: &Value) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    crate::parser::common::datetime::timestamp(value.as_str()?)
+}
+
+/// Whether a value is between optional bounds, each of which may exclude its end
+fn within<T: PartialOrd>(
+    value: T,
+    (lower, lower_exclusive): (Option<T>, bool),
+    (upper, upper_exclusive): (Option<T>, bool),
+) -> bool {
+    let above = lower.is_none_or(|l| {
+        if lower_exclusive {
+            l < value
+        } else {
+            l <= value
+        }
+    });
+    let below = upper.is_none_or(|u| {
+        if upper_exclusive {
+            value < u
+        } else {
+            value <= u
+        }
+    });
+    above && below
+}
+
+/// Value of an `@example` argument for a property, strings are unquoted and other types are
+/// read as JSON, like `@example("42")` or `@example(42)` for an Integer
+fn example_value(property: &Property, argument: &DecoratorArgument) -> Option<Value> {
+    let text = argument.text().into_owned();
+    match property {
+        Property::String(_) | Property::DateTime(_) => Some(Value::String(text)),
+        _ => serde_json::from_str(&text).ok(),
+    }
+}
+
+/// Zero, then the bounds of a range, as placeholders of numeric properties
+fn bounds<T: serde::Serialize + Default>(range: Option<(Option<T>, Option<T>)>) -> Vec<Value> {
+    let (lower, upper) = range.unwrap_or((None, None));
+    [Some(T::default()), lower, upper]
+        .into_iter()
+        .flatten()
+        .filter_map(|bound| serde_json::to_value(bound).ok())
+        .collect()
+}
+
+/// Fields of an object that no declaration of a hierarchy declares, `$` prefixed system
+/// keys like `$class` are allowed
+fn undeclared<'v>(
+    object: &'v Map<String, Value>,
+    hierarchy: &'v [(&Model, &Declaration)],
+) -> impl Iterator<Item = &'v String> {
+    object.keys().filter(move |k| {
+        !k.starts_with('$') && hierarchy.iter().all(|(_, d)| d.property(k).is_none())
+    })
+}
+
+fn join(path: &str, name: &str) -> String {
+    match path.is_empty() {
+        true => name.to_string(),
+        false => format!("{}.{}", path, name),
+    }
+}
+
+fn wrong_type(expected: &str) -> InstanceErrorKind {
+    InstanceErrorKind::WrongType(expected.to_string())
+}
+
+fn error(path: &str, kind: InstanceErrorKind) -> InstanceError {
+    InstanceError {
+        path: path.to_string(),
+        kind,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{InstanceError, InstanceErrorKind, InstanceValidator};
+    use crate::{manager::ModelManager, parser::parse};
+
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace test@1.0.0
+import places@1.0.0.Address
+
+concept Person {
+  o String name regex=/^[A-Za-z]+$/ length=[1,10]
+  o Integer age range=[0,150] optional
+  o DateTime born optional
+  o Address[] addresses optional
+}
+",
+            )
+            .unwrap(),
+        );
+        manager.add_model(
+            parse(
+                "namespace places@1.0.0
+
+concept Address {
+  o String city
+  o Double lat range=[-90.0,90.0] optional
+}
+",
+            )
+            .unwrap(),
+        );
+        manager
+    }
+
+    #[test]
+    fn test_validate() {
+        let manager = manager();
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "test@1.0.0.Person",
+                "name": "Ada",
+                "age": 36,
+                "born": "1815-12-10",
+                "addresses": [{ "$class": "places@1.0.0.Address", "city": "London" }],
+            })),
+            Ok(())
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "test@1.0.0.Person",
+                "name": "Ada Lovelace",
+                "age": 200,
+                "born": "10 December 1815",
+                "addresses": [{ "lat": 91.0 }],
+                "title": "Countess",
+            })),
+            Err(vec![
+                InstanceError {
+                    path: String::from("name"),
+                    kind: InstanceErrorKind::Validator(String::from("regex=/^[A-Za-z]+$/")),
+                },
+                InstanceError {
+                    path: String::from("name"),
+                    kind: InstanceErrorKind::Validator(String::from("length=[1, 10]")),
+                },
+                InstanceError {
+                    path: String::from("age"),
+                    kind: InstanceErrorKind::Validator(String::from("range=[0, 150]")),
+                },
+                InstanceError {
+                    path: String::from("born"),
+                    kind: InstanceErrorKind::WrongType(String::from("DateTime")),
+                },
+                InstanceError {
+                    path: String::from("addresses[0].city"),
+                    kind: InstanceErrorKind::MissingProperty,
+                },
+                InstanceError {
+                    path: String::from("addresses[0].lat"),
+                    kind: InstanceErrorKind::Validator(String::from("range=[-90.0, 90.0]")),
+                },
+                InstanceError {
+                    path: String::from("title"),
+                    kind: InstanceErrorKind::UnexpectedProperty,
+                },
+            ]),
+            "Should report every problem with its path"
+        );
+        assert_eq!(
+            validator
+                .validate(&json!({ "$class": "test@2.0.0.Person" }))
+                .map_err(|e| e[0].to_string()),
+            Err(String::from("unknown type test@2.0.0.Person"))
+        );
+    }
+
+    #[test]
+    fn test_system_types() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace test@1.0.0
+import concerto@1.0.0.Concept
+
+concept Envelope {
+  o Concept payload
+}
+
+concept Note {
+  o String text
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "test@1.0.0.Envelope",
+                "payload": { "$class": "test@1.0.0.Note", "text": "Hi" },
+            })),
+            Ok(()),
+            "Should take any concept for a property of the base type"
+        );
+        assert_eq!(
+            validator
+                .validate(&json!({ "$class": "test@1.0.0.Envelope", "payload": {} }))
+                .map_err(|e| e[0].to_string()),
+            Err(String::from("payload: missing $class"))
+        );
+        assert_eq!(
+            validator
+                .validate(&json!({ "$class": "concerto@1.0.0.Concept" }))
+                .map_err(|e| e[0].to_string()),
+            Err(String::from("abstract type concerto@1.0.0.Concept"))
+        );
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut manager = manager();
+        manager.add_model(
+            parse(
+                "namespace shop@1.0.0
+import places@1.0.0.Address
+
+concept Customer {
+  @example(\"Ann\")
+  o String name regex=/^[A-Z][a-z]+$/
+  @example(\"ann\")
+  o String nickname regex=/^[A-Z][a-z]+$/ default=\"Annie\"
+  @example(\"7\")
+  o Integer visits range=[1,10]
+  o Integer points range=[5,]
+  o Double[] ratings range=[0.5, 5.0]
+  o Address address
+  o Customer referrer optional
+  o Boolean verified optional
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+        let sample = validator.sample("shop@1.0.0.Customer").unwrap();
+
+        assert_eq!(
+            sample,
+            json!({
+                "$class": "shop@1.0.0.Customer",
+                "name": "Ann",
+                "nickname": "Annie",
+                "visits": 7,
+                "points": 5,
+                "ratings": [0.5],
+                "address": { "$class": "places@1.0.0.Address", "city": "text" },
+            }),
+            "Should prefer valid examples, then defaults"
+        );
+        assert_eq!(validator.validate(&sample), Ok(()));
+        assert_eq!(
+            validator.sample("shop@1.0.0.Unknown"),
+            Err(InstanceErrorKind::UnknownType(String::from(
+                "shop@1.0.0.Unknown"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_enum() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace paint@1.0.0
+
+enum Color {
+  o RED
+  o GREEN
+}
+
+concept Wall {
+  o Color color
+  o Color[] accents optional
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.sample("paint@1.0.0.Wall"),
+            Ok(json!({ "$class": "paint@1.0.0.Wall", "color": "RED" }))
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "paint@1.0.0.Wall",
+                "color": "GREEN",
+                "accents": ["RED", "BLUE"],
+            })),
+            Err(vec![InstanceError {
+                path: String::from("accents[1]"),
+                kind: InstanceErrorKind::WrongType(String::from("Color")),
+            }]),
+            "Should only take the values of the enum"
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace places@1.0.0
+
+concept Address {
+  o String city
+}
+
+map AddressBook {
+  o String
+  o Address
+}
+
+map Visits {
+  o DateTime
+  --> Address
+}
+
+concept Person {
+  o AddressBook addresses
+  o Visits visits optional
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.sample("places@1.0.0.Person"),
+            Ok(json!({ "$class": "places@1.0.0.Person", "addresses": {} }))
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "places@1.0.0.Person",
+                "addresses": { "home": { "city": "London" } },
+                "visits": { "2024-01-01T10:00:00Z": "resource:places@1.0.0.Address#1" },
+            })),
+            Ok(())
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "places@1.0.0.Person",
+                "addresses": { "home": { "town": "London" } },
+                "visits": { "yesterday": 1 },
+            })),
+            Err(vec![
+                InstanceError {
+                    path: String::from("addresses.home.city"),
+                    kind: InstanceErrorKind::MissingProperty,
+                },
+                InstanceError {
+                    path: String::from("addresses.home.town"),
+                    kind: InstanceErrorKind::UnexpectedProperty,
+                },
+                InstanceError {
+                    path: String::from("visits.yesterday"),
+                    kind: InstanceErrorKind::WrongType(String::from("DateTime")),
+                },
+                InstanceError {
+                    path: String::from("visits.yesterday"),
+                    kind: InstanceErrorKind::WrongType(String::from("Address")),
+                },
+            ]),
+            "Should check the keys and the values of the entries"
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "places@1.0.0.Person",
+                "addresses": ["home"],
+            })),
+            Err(vec![InstanceError {
+                path: String::from("addresses"),
+                kind: InstanceErrorKind::WrongType(String::from("AddressBook")),
+            }]),
+            "Should take maps as objects"
+        );
+    }
+
+    #[test]
+    fn test_relationship() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace org@1.0.0
+
+concept Person {
+  o String name
+}
+
+concept Team {
+  --> Person lead
+  --> Person[] members optional
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.sample("org@1.0.0.Team"),
+            Ok(json!({
+                "$class": "org@1.0.0.Team",
+                "lead": "resource:org@1.0.0.Person#1",
+            }))
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "org@1.0.0.Team",
+                "lead": "resource:org@1.0.0.Person#ann",
+                "members": [{ "$class": "org@1.0.0.Person", "name": "Bob" }],
+            })),
+            Err(vec![InstanceError {
+                path: String::from("members[0]"),
+                kind: InstanceErrorKind::WrongType(String::from("Person")),
+            }]),
+            "Should take identifiers rather than instances"
+        );
+    }
+
+    #[test]
+    fn test_abstract() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace parties@1.0.0
+
+abstract concept Party {
+  o String name
+}
+
+concept Contract {
+  o Party party
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.validate(&json!({ "$class": "parties@1.0.0.Party", "name": "Ada" })),
+            Err(vec![InstanceError {
+                path: String::new(),
+                kind: InstanceErrorKind::AbstractType(String::from("parties@1.0.0.Party")),
+            }])
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "parties@1.0.0.Contract",
+                "party": { "name": "Ada" },
+            })),
+            Err(vec![InstanceError {
+                path: String::from("party"),
+                kind: InstanceErrorKind::MissingClass,
+            }]),
+            "Should need the type extending an abstract property type"
+        );
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace parties@1.0.0
+
+abstract concept Party {
+  o String name
+}
+
+concept Company extends Party {
+  o String registration
+}
+
+concept Address {
+  o String city
+}
+
+concept Contract {
+  o Party party
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "parties@1.0.0.Contract",
+                "party": {
+                    "$class": "parties@1.0.0.Company",
+                    "name": "Acme",
+                    "registration": "123",
+                },
+            })),
+            Ok(()),
+            "Should take the properties of super types"
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "parties@1.0.0.Contract",
+                "party": { "$class": "parties@1.0.0.Company", "registration": "123" },
+            })),
+            Err(vec![InstanceError {
+                path: String::from("party.name"),
+                kind: InstanceErrorKind::MissingProperty,
+            }])
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "parties@1.0.0.Contract",
+                "party": { "$class": "parties@1.0.0.Address", "city": "Dublin" },
+            })),
+            Err(vec![InstanceError {
+                path: String::from("party"),
+                kind: InstanceErrorKind::WrongType(String::from("Party")),
+            }]),
+            "Should only take types extending the declared one"
+        );
+        assert_eq!(
+            validator.sample("parties@1.0.0.Company"),
+            Ok(json!({
+                "$class": "parties@1.0.0.Company",
+                "name": "text",
+                "registration": "text",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_regex_cache() {
+        let manager = manager();
+        let validator = InstanceValidator::new(&manager);
+        for name in ["Ada", "Grace", "Alan", "Barbara"] {
+            let person = json!({ "$class": "test@1.0.0.Person", "name": name });
+            assert_eq!(validator.validate(&person), Ok(()));
+        }
+        assert_eq!(
+            validator.regexes.len(),
+            1,
+            "Should compile the regex once for all instances"
+        );
+    }
+
+    #[test]
+    fn test_regex_flags() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse(
+                "namespace codes@1.0.0
+
+concept Code {
+  o String letters regex=/^[a-z--aeiou]+$/v
+  o String prefix regex=/^ab/di
+}
+",
+            )
+            .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "codes@1.0.0.Code",
+                "letters": "xyz",
+                "prefix": "ABC",
+            })),
+            Ok(()),
+            "Should compile patterns with the d and v flags"
+        );
+        assert_eq!(
+            validator.validate(&json!({
+                "$class": "codes@1.0.0.Code",
+                "letters": "abc",
+                "prefix": "ba",
+            })),
+            Err(vec![
+                InstanceError {
+                    path: String::from("letters"),
+                    kind: InstanceErrorKind::Validator(String::from("regex=/^[a-z--aeiou]+$/v")),
+                },
+                InstanceError {
+                    path: String::from("prefix"),
+                    kind: InstanceErrorKind::Validator(String::from("regex=/^ab/di")),
+                },
+            ])
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp() {
+        let manager = manager();
+        let validator = InstanceValidator::new(&manager);
+        let person = |born| json!({ "$class": "test@1.0.0.Person", "name": "Ada", "born": born });
+        assert_eq!(
+            validator
+                .validate(&person("1815-02-30"))
+                .map_err(|e| e[0].kind.clone()),
+            Err(InstanceErrorKind::WrongType(String::from("DateTime"))),
+            "Should reject dates that aren't in the calendar"
+        );
+        assert_eq!(
+            super::timestamp(&json!("1815-12-10T08:00:00+01:00")).map(|t| t.to_utc().to_rfc3339()),
+            Some(String::from("1815-12-10T07:00:00+00:00"))
+        );
+    }
+
+    #[test]
+    fn test_exclusive_range() {
+        let mut manager = ModelManager::new();
+        let source =
+            "namespace test@1.0.0\nconcept Sample {\n  o Double ratio range=(0.0, 1.0)\n}\n";
+        let options = crate::parser::options::ParserOptions {
+            exclusive_ranges: true,
+            ..Default::default()
+        };
+        manager.add_model(crate::parser::parse_with(source, &options).unwrap());
+        let validator = InstanceValidator::new(&manager);
+        let sample = |ratio: f64| json!({ "$class": "test@1.0.0.Sample", "ratio": ratio });
+
+        assert_eq!(validator.validate(&sample(0.5)), Ok(()));
+        for excluded in [0.0, 1.0] {
+            assert_eq!(
+                validator
+                    .validate(&sample(excluded))
+                    .map_err(|e| e[0].kind.clone()),
+                Err(InstanceErrorKind::Validator(String::from(
+                    "range=(0.0, 1.0)"
+                ))),
+                "Should exclude the ends of the range"
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_range() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace test@1.0.0\nconcept Sample {\n  o Long count range=[-1, 3147483647]\n}\n")
+                .unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+        let sample = |count: i64| json!({ "$class": "test@1.0.0.Sample", "count": count });
+
+        assert_eq!(validator.validate(&sample(3147483647)), Ok(()));
+        assert_eq!(
+            validator
+                .validate(&sample(3147483648))
+                .map_err(|e| e[0].kind.clone()),
+            Err(InstanceErrorKind::Validator(String::from(
+                "range=[-1, 3147483647]"
+            ))),
+            "Should check Long values against their range"
+        );
+    }
+
+    #[test]
+    fn test_long_width() {
+        let mut manager = ModelManager::new();
+        manager.add_model(
+            parse("namespace test@1.0.0\nconcept Sample {\n  o Long count\n}\n").unwrap(),
+        );
+        let validator = InstanceValidator::new(&manager);
+        let count = json!({ "$class": "test@1.0.0.Sample", "count": u64::MAX });
+
+        #[cfg(feature = "i128")]
+        assert_eq!(
+            validator.validate(&count),
+            Ok(()),
+            "Should accept values beyond i64"
+        );
+        #[cfg(not(feature = "i128"))]
+        assert_eq!(
+            validator.validate(&count).map_err(|e| e[0].kind.clone()),
+            Err(InstanceErrorKind::WrongType(String::from("Long"))),
+            "Should reject values beyond i64"
+        );
+        assert!(
+            validator
+                .validate(&json!({ "$class": "test@1.0.0.Sample", "count": 1.5 }))
+                .is_err(),
+            "Should reject fractions"
+        );
+    }
+}
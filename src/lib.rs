@@ -1,2 +1,34 @@
+pub mod analysis;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod changelog;
+pub mod cli;
+pub mod compression;
+pub mod dictionary;
+pub mod diff;
+pub mod explain;
+pub mod format;
+pub mod graph;
+pub mod hover;
+pub mod instance;
+pub mod manager;
+pub mod metrics;
+pub mod migration;
+pub mod navigation;
 pub mod parser;
+pub mod query;
+pub mod refactor;
+pub mod registry;
+pub mod repl;
+pub mod resolver;
+pub mod search;
 pub mod serialize;
+pub mod stats;
+pub mod symbols;
+pub mod system;
+pub mod testgen;
+pub mod testing;
+#[cfg(feature = "vocabulary")]
+pub mod vocabulary;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -1,26 +1,13 @@
-pub mod parser;
-pub mod serialize;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cto = "
-    namespace com.example.foo@1.3.5-pre
-
-    concept Person {
-      o String name
-      o Integer age optional
-      o Address mainAddress
-    }
-
-    concept Address {
-      o String street
-      o Integer number optional
-      o String city default=\"Dublin\"
-    }
-
-    ";
-    let (_, parsed) = parser::model(cto)?;
-
-    println!("{}", serialize::print(&parsed)?);
-
-    Ok(())
+use std::process::ExitCode;
+
+use concerto_nom::cli;
+
+fn main() -> ExitCode {
+    let code = cli::run(
+        std::env::args().skip(1),
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout(),
+        &mut std::io::stderr(),
+    );
+    ExitCode::from(code)
 }
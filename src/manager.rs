@@ -0,0 +1,1398 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::navigation::Location;
+use crate::parser::{
+    cst::NodeId,
+    declaration::{Declaration, Property},
+    file::{parse_file_with, FileError},
+    import::Import,
+    namespace::{FullyQualifiedName, Namespace},
+    options::ParserOptions,
+    version::VersionRequirement,
+    Model,
+};
+use crate::resolver::{ModelResolver, ResolveError};
+use crate::system;
+
+/// Namespaces whose imports refer to each other in a cycle
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportCycle {
+    /// Namespaces on or behind the cycle, in the order they were added
+    pub namespaces: Vec<Namespace>,
+}
+
+impl std::fmt::Display for ImportCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self
+            .namespaces
+            .iter()
+            .map(|n| format!("{}@{}", n.name(), n.version()))
+            .collect();
+        write!(f, "import cycle between {}", names.join(", "))
+    }
+}
+
+impl std::error::Error for ImportCycle {}
+
+/// Imports and properties referring to each declaration, keyed by the position of the model
+/// of the declaration and its name. Usages are the position of their model and their node.
+type Usages = HashMap<(usize, String), Vec<(usize, NodeId)>>;
+
+/// Holds models of several namespaces, so types can be looked up across them
+#[derive(Debug, Default, Clone)]
+pub struct ModelManager {
+    models: Vec<Model>,
+    /// Files the models were loaded from, by the position of the model
+    paths: Vec<Option<PathBuf>>,
+    /// Built on first use, and dropped whenever models are added, removed or changed
+    usages: OnceLock<Usages>,
+    /// Options the files added to the manager are parsed with
+    options: ParserOptions,
+}
+
+impl ModelManager {
+    pub fn new() -> Self {
+        Self::new_with(&ParserOptions::default())
+    }
+
+    /// A manager parsing the files added to it with the given options
+    pub fn new_with(options: &ParserOptions) -> Self {
+        Self {
+            models: Vec::new(),
+            paths: Vec::new(),
+            usages: OnceLock::new(),
+            options: *options,
+        }
+    }
+
+    /// Loads every `.cto` file under a directory, in the order of their paths. Files that
+    /// can't be read or parsed are left out, and their errors returned.
+    pub fn from_dir(path: impl AsRef<Path>) -> (Self, Vec<FileError>) {
+        Self::from_dir_with(path, &ParserOptions::default())
+    }
+
+    /// Like `from_dir`, parses the files with the given options
+    pub fn from_dir_with(
+        path: impl AsRef<Path>,
+        options: &ParserOptions,
+    ) -> (Self, Vec<FileError>) {
+        let mut manager = Self::new_with(options);
+        let errors = manager.add_dir(path);
+        (manager, errors)
+    }
+
+    /// Options the files added to the manager are parsed with
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Like `from_dir`, loads the files matching a glob pattern like `models/**/*.cto`
+    pub fn from_glob(pattern: &str) -> Result<(Self, Vec<FileError>), glob::PatternError> {
+        let mut manager = Self::new();
+        let mut errors = Vec::new();
+        for entry in glob::glob(pattern)? {
+            match entry {
+                Ok(path) if path.is_file() => {
+                    if let Err(e) = manager.add_file(&path) {
+                        errors.push(e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(FileError::Io {
+                    path: Some(e.path().to_path_buf()),
+                    source: e.into(),
+                }),
+            }
+        }
+        Ok((manager, errors))
+    }
+
+    /// Loads every `.cto` file under a directory, see `from_dir`
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) -> Vec<FileError> {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        model_files(path.as_ref(), &mut files, &mut errors);
+        for file in files {
+            if let Err(e) = self.add_file(&file) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+
+    /// Parses a model file and adds the model, remembering the path of the file
+    pub fn add_file(&mut self, path: impl AsRef<Path>) -> Result<&Model, FileError> {
+        let path = path.as_ref();
+        let model = parse_file_with(path, &self.options)?;
+        Ok(self.add_parsed_file(path, model))
+    }
+
+    /// Adds a model parsed from a file. A model loaded from the same file before is removed,
+    /// in case the namespace of the file changed.
+    pub(crate) fn add_parsed_file(&mut self, path: &Path, model: Model) -> &Model {
+        if let Some(previous) = self.model_at(path) {
+            if previous.namespace != model.namespace {
+                let namespace = previous.namespace.clone();
+                self.remove_model(&namespace);
+            }
+        }
+        let index = self.insert(model);
+        self.paths[index] = Some(path.to_path_buf());
+        &self.models[index]
+    }
+
+    /// Removes the model of a namespace, and returns it
+    pub fn remove_model(&mut self, namespace: &Namespace) -> Option<Model> {
+        let index = self.models.iter().position(|m| &m.namespace == namespace)?;
+        self.paths.remove(index);
+        self.usages.take();
+        Some(self.models.remove(index))
+    }
+
+    /// Fetches the models of imports that don't resolve to a loaded model, and then the
+    /// models of their imports. Returns the namespaces of the fetched models.
+    pub fn resolve_external(
+        &mut self,
+        resolver: &dyn ModelResolver,
+    ) -> Result<Vec<Namespace>, ResolveError> {
+        let mut fetched = Vec::new();
+        loop {
+            let mut missing: Vec<Namespace> = Vec::new();
+            for import in self.models.iter().flat_map(|m| &m.imports) {
+                let namespace = import.namespace();
+                if self.resolve_import(import).is_none() && !missing.contains(namespace) {
+                    missing.push(namespace.clone());
+                }
+            }
+            if missing.is_empty() {
+                return Ok(fetched);
+            }
+            for namespace in missing {
+                let source = resolver.resolve(&namespace)?;
+                let model = crate::parser::parse(&source).map_err(|error| ResolveError::Parse {
+                    namespace: namespace.clone(),
+                    error: Box::new(error),
+                })?;
+                let compatible = model.namespace.name() == namespace.name()
+                    && VersionRequirement::caret(namespace.version())
+                        .matches(model.namespace.version());
+                if !compatible {
+                    return Err(ResolveError::Mismatch {
+                        expected: namespace,
+                        found: Box::new(model.namespace),
+                    });
+                }
+                fetched.push(model.namespace.clone());
+                self.add_model(model);
+            }
+        }
+    }
+
+    /// Adds a model, replacing the model with the same namespace if there is one
+    pub fn add_model(&mut self, model: Model) {
+        self.insert(model);
+    }
+
+    /// Adds or replaces a model, and returns its position
+    fn insert(&mut self, model: Model) -> usize {
+        self.usages.take();
+        match self
+            .models
+            .iter()
+            .position(|m| m.namespace == model.namespace)
+        {
+            Some(index) => {
+                self.models[index] = model;
+                self.paths[index] = None;
+                index
+            }
+            None => {
+                self.models.push(model);
+                self.paths.push(None);
+                self.models.len() - 1
+            }
+        }
+    }
+
+    /// Counts over the loaded models, imports count towards the models they resolve to
+    pub fn stats(&self) -> crate::stats::Stats {
+        crate::stats::Stats::of_manager(self)
+    }
+
+    /// Complexity metrics of the loaded declarations and namespaces
+    pub fn metrics(&self) -> crate::metrics::Metrics {
+        crate::metrics::Metrics::of_manager(self)
+    }
+
+    /// Path of the file the model of the namespace was loaded from
+    pub fn path(&self, namespace: &Namespace) -> Option<&Path> {
+        let index = self.models.iter().position(|m| &m.namespace == namespace)?;
+        self.paths[index].as_deref()
+    }
+
+    /// Model loaded from a file
+    pub fn model_at(&self, path: &Path) -> Option<&Model> {
+        let index = self.paths.iter().position(|p| p.as_deref() == Some(path))?;
+        Some(&self.models[index])
+    }
+
+    pub fn models(&self) -> impl Iterator<Item = &Model> {
+        self.models.iter()
+    }
+
+    /// Runs `f` on every loaded model, with the `rayon` feature on all cores. Results are in
+    /// the order the models were added either way, so output assembled from them is stable.
+    pub fn map_models<'a, T, F>(&'a self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&'a Model) -> T + Sync + Send,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.models.par_iter().map(f).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.models.iter().map(f).collect()
+        }
+    }
+
+    pub(crate) fn models_mut(&mut self) -> &mut [Model] {
+        self.usages.take();
+        &mut self.models
+    }
+
+    /// Imports, properties and declarations extending it of the loaded models that refer to a
+    /// declaration, in the order the models were added. Nothing refers to a declaration
+    /// without usages, so it can be deleted safely. Wildcard imports only count when the
+    /// importing model refers to the declaration. The index behind it is built once until
+    /// the models change.
+    pub fn usages_of(&self, fqn: &FullyQualifiedName) -> Vec<Location<'_>> {
+        let Some(target) = self.models.iter().position(|m| {
+            m.namespace.name() == fqn.namespace_name() && m.namespace.version() == fqn.version()
+        }) else {
+            return Vec::new();
+        };
+        let usages = self.usages.get_or_init(|| self.index_usages());
+        usages
+            .get(&(target, fqn.type_name().to_string()))
+            .into_iter()
+            .flatten()
+            .map(|(model, node)| Location {
+                model: &self.models[*model],
+                node: *node,
+            })
+            .collect()
+    }
+
+    fn index_usages(&self) -> Usages {
+        let position = |model: &Model| self.models.iter().position(|m| std::ptr::eq(m, model));
+        let mut usages = Usages::new();
+        for (index, model) in self.models.iter().enumerate() {
+            let mut references = Vec::new();
+            for (d, declaration) in model.declarations.iter().enumerate() {
+                // Declarations extending another one use it in their head
+                let extended = self
+                    .resolve_extended_in(model, declaration)
+                    .and_then(|(m, used)| Some((position(m)?, used.name.clone())));
+                if let Some(key) = extended {
+                    references.push((key, NodeId::Declaration(d)));
+                }
+                for (p, property) in declaration.properties.iter().enumerate() {
+                    let Property::Concept(concept) = property else {
+                        continue;
+                    };
+                    let used = self
+                        .resolve_property_in(model, concept)
+                        .and_then(|(m, used)| Some((position(m)?, used.name.clone())));
+                    if let Some(key) = used {
+                        references.push((key, NodeId::Property(d, p)));
+                    }
+                }
+            }
+            for (i, import) in model.imports.iter().enumerate() {
+                let Some(imported) = self.resolve_import(import) else {
+                    continue;
+                };
+                let Some(at) = position(imported) else {
+                    continue;
+                };
+                for declaration in imported.declarations.iter() {
+                    if import.local_name(&declaration.name).is_none() {
+                        continue;
+                    }
+                    let key = (at, declaration.name.clone());
+                    // Wildcard imports bring every declaration in, they only use those the
+                    // model refers to
+                    if matches!(import, Import::All { .. })
+                        && !references.iter().any(|(used, _)| *used == key)
+                    {
+                        continue;
+                    }
+                    usages
+                        .entry(key)
+                        .or_default()
+                        .push((index, NodeId::Import(i)));
+                }
+            }
+            for (key, node) in references {
+                usages.entry(key).or_default().push((index, node));
+            }
+        }
+        usages
+    }
+
+    /// Loaded model of a namespace, or the system model for `concerto@1.0.0`
+    pub fn model(&self, namespace: &Namespace) -> Option<&Model> {
+        self.models
+            .iter()
+            .find(|m| &m.namespace == namespace)
+            .or_else(|| Some(system::model()).filter(|_| system::is_system(namespace)))
+    }
+
+    /// Picks the highest loaded version of a namespace that satisfies the requirement,
+    /// falling back to the system model for the system namespace
+    pub fn resolve(
+        &self,
+        namespace_name: &str,
+        requirement: &VersionRequirement,
+    ) -> Option<&Model> {
+        let system = system::model();
+        self.models
+            .iter()
+            .filter(|m| {
+                m.namespace.name() == namespace_name && requirement.matches(m.namespace.version())
+            })
+            .max_by(|a, b| a.namespace.version().cmp(b.namespace.version()))
+            .or_else(|| {
+                Some(system).filter(|s| {
+                    s.namespace.name() == namespace_name
+                        && requirement.matches(s.namespace.version())
+                })
+            })
+    }
+
+    /// Finds the model an import refers to. The imported version is treated as a caret
+    /// requirement, so a compatible newer version is used when one is loaded.
+    pub fn resolve_import(&self, import: &Import) -> Option<&Model> {
+        let namespace = import.namespace();
+        self.resolve(
+            namespace.name(),
+            &VersionRequirement::caret(namespace.version()),
+        )
+    }
+
+    /// Finds a declaration by name as seen from a model, either defined in it or imported
+    pub fn resolve_type<'a>(
+        &'a self,
+        model: &'a Model,
+        type_name: &str,
+    ) -> Option<&'a Declaration> {
+        self.resolve_type_in(model, type_name).map(|(_, d)| d)
+    }
+
+    /// Like `resolve_type`, also returns the model the declaration is defined in
+    pub(crate) fn resolve_type_in<'a>(
+        &'a self,
+        model: &'a Model,
+        type_name: &str,
+    ) -> Option<(&'a Model, &'a Declaration)> {
+        match model.declaration(type_name) {
+            Some(declaration) => Some((model, declaration)),
+            None => model
+                .imports
+                .iter()
+                .filter_map(|i| Some((self.resolve_import(i)?, i.imported_name(type_name)?)))
+                .find_map(|(m, name)| m.declaration(name).map(|d| (m, d))),
+        }
+    }
+
+    /// Like `resolve_type_in`, but names qualified with a namespace are looked up in the
+    /// model of that namespace instead of through the imports
+    pub(crate) fn resolve_reference_in<'a>(
+        &'a self,
+        model: &'a Model,
+        type_name: &str,
+        namespace: Option<&Namespace>,
+    ) -> Option<(&'a Model, &'a Declaration)> {
+        match namespace {
+            Some(namespace) => {
+                let model = self.model(namespace)?;
+                model.declaration(type_name).map(|d| (model, d))
+            }
+            None => self.resolve_type_in(model, type_name),
+        }
+    }
+
+    /// The declaration the type of a concept typed property refers to, with its model
+    pub(crate) fn resolve_property_in<'a>(
+        &'a self,
+        model: &'a Model,
+        property: &crate::parser::property::Property,
+    ) -> Option<(&'a Model, &'a Declaration)> {
+        self.resolve_reference_in(model, &property.class, property.namespace.as_ref())
+    }
+
+    /// The declaration named in the `extends` clause of a declaration, `None` without one
+    pub(crate) fn resolve_extended_in<'a>(
+        &'a self,
+        model: &'a Model,
+        declaration: &Declaration,
+    ) -> Option<(&'a Model, &'a Declaration)> {
+        let reference = declaration.super_type.as_ref()?;
+        self.resolve_reference_in(model, &reference.name, reference.namespace.as_ref())
+    }
+
+    /// The declaration a declaration of a model extends. Declarations without `extends`
+    /// extend the base type of their kind in the system namespace, like
+    /// `concerto@1.0.0.Concept`, implicitly.
+    pub fn supertype<'a>(
+        &'a self,
+        model: &'a Model,
+        declaration: &Declaration,
+    ) -> Option<(&'a Model, &'a Declaration)> {
+        match &declaration.super_type {
+            Some(_) => self.resolve_extended_in(model, declaration),
+            None => {
+                let system = system::model();
+                let name = system::implicit_supertype(model, declaration)?;
+                system.declaration(name).map(|d| (system, d))
+            }
+        }
+    }
+
+    /// A declaration and the declarations it extends with `extends`, super types first. The
+    /// implicit base types aren't included, and cyclic hierarchies stop where they loop.
+    pub fn hierarchy<'a>(
+        &'a self,
+        model: &'a Model,
+        declaration: &'a Declaration,
+    ) -> Vec<(&'a Model, &'a Declaration)> {
+        let mut chain = vec![(model, declaration)];
+        let mut current = (model, declaration);
+        while current.1.super_type.is_some() {
+            match self.supertype(current.0, current.1) {
+                Some(next) if !chain.iter().any(|(_, d)| std::ptr::eq(*d, next.1)) => {
+                    chain.push(next);
+                    current = next;
+                }
+                _ => break,
+            }
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Finds a property by a path of names like `Person/mainAddress/city`, walking through
+    /// concept properties whose types are imported from other loaded models
+    pub fn resolve_path<'a>(&'a self, model: &'a Model, path: &str) -> Option<&'a Property> {
+        crate::parser::resolve_path_with(model, path, |m, name| self.resolve_type_in(m, name))
+    }
+
+    /// Orders the loaded models so every model comes after the models its imports resolve
+    /// to. Models that don't depend on each other keep the order they were added in.
+    pub fn topological_order(&self) -> Result<Vec<&Model>, ImportCycle> {
+        let dependencies: Vec<Vec<usize>> = self
+            .models
+            .iter()
+            .enumerate()
+            .map(|(index, model)| {
+                let mut dependencies: Vec<_> = model
+                    .imports
+                    .iter()
+                    .filter_map(|i| self.resolve_import(i))
+                    .filter_map(|m| self.models.iter().position(|o| std::ptr::eq(o, m)))
+                    .filter(|&d| d != index)
+                    .collect();
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                dependencies
+            })
+            .collect();
+
+        let mut ordered = Vec::with_capacity(self.models.len());
+        let mut done = vec![false; self.models.len()];
+        while ordered.len() < self.models.len() {
+            let next = (0..self.models.len())
+                .find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d]));
+            match next {
+                Some(index) => {
+                    done[index] = true;
+                    ordered.push(&self.models[index]);
+                }
+                None => {
+                    return Err(ImportCycle {
+                        namespaces: (0..self.models.len())
+                            .filter(|&i| !done[i])
+                            .map(|i| self.models[i].namespace.clone())
+                            .collect(),
+                    })
+                }
+            }
+        }
+        Ok(ordered)
+    }
+
+    /// Copies of the models needed by a declaration: the declaration itself, the types of
+    /// its properties, the declaration it extends and, transitively, those of theirs. Only
+    /// the imports the kept declarations use are kept, so services can ship trimmed models.
+    /// `None` if the declaration isn't loaded.
+    pub fn extract(&self, type_fqn: &FullyQualifiedName) -> Option<ModelManager> {
+        let index = self.models.iter().position(|m| {
+            m.namespace.name() == type_fqn.namespace_name()
+                && m.namespace.version() == type_fqn.version()
+        })?;
+        let declaration = self.models[index].declaration(type_fqn.type_name())?;
+
+        let mut kept = vec![(index, declaration.name.as_str())];
+        let mut pending = vec![(&self.models[index], declaration)];
+        while let Some((model, declaration)) = pending.pop() {
+            let extended = self.resolve_extended_in(model, declaration);
+            let property_types = declaration.properties.iter().filter_map(|p| match p {
+                Property::Concept(p) => self.resolve_property_in(model, p),
+                _ => None,
+            });
+            for (used_model, used) in extended.into_iter().chain(property_types) {
+                // Types of the system namespace stay imported rather than extracted
+                let Some(used_index) = self.models.iter().position(|m| std::ptr::eq(m, used_model))
+                else {
+                    continue;
+                };
+                if !kept.contains(&(used_index, used.name.as_str())) {
+                    kept.push((used_index, used.name.as_str()));
+                    pending.push((used_model, used));
+                }
+            }
+        }
+
+        let mut extracted = ModelManager::new_with(&self.options);
+        for (index, model) in self.models.iter().enumerate() {
+            let names: Vec<&str> = kept
+                .iter()
+                .filter(|(i, _)| *i == index)
+                .map(|(_, name)| *name)
+                .collect();
+            if names.is_empty() {
+                continue;
+            }
+            let mut trimmed = model.clone();
+            trimmed
+                .declarations
+                .retain(|d| names.contains(&d.name.as_str()));
+            trimmed.reindex();
+            let references = trimmed.properties().filter_map(|(_, p)| match p {
+                Property::Concept(p) => Some((p.class.as_str(), p.namespace.as_ref())),
+                _ => None,
+            });
+            let super_types = trimmed
+                .declarations
+                .iter()
+                .filter_map(|d| d.super_type.as_ref())
+                .map(|s| (s.name.as_str(), s.namespace.as_ref()));
+            let imported: Vec<&str> = references
+                .chain(super_types)
+                .filter(|(name, namespace)| {
+                    namespace.is_none() && trimmed.declaration(name).is_none()
+                })
+                .map(|(name, _)| name)
+                .collect();
+            let imports = model
+                .imports
+                .iter()
+                .filter(|i| imported.iter().any(|name| i.imports(name)))
+                .cloned()
+                .collect();
+            trimmed.imports = imports;
+            extracted.add_model(trimmed);
+        }
+        Some(extracted)
+    }
+
+    /// Graph of all loaded declarations and the references between them
+    pub fn dependency_graph(&self) -> crate::graph::DependencyGraph<'_> {
+        crate::graph::DependencyGraph::new(self)
+    }
+
+    /// Properties of a declaration in the given namespace, along with those it inherits from
+    /// the declarations it extends, paired with the declaration they are defined in. See
+    /// [`ModelManager::own_properties_of`] for the declaration's own properties only
+    pub fn properties_of<'a>(
+        &'a self,
+        namespace: &Namespace,
+        type_name: &str,
+    ) -> impl Iterator<Item = (&'a Declaration, &'a Property)> {
+        self.model(namespace)
+            .and_then(|m| m.declaration(type_name).map(|d| self.hierarchy(m, d)))
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, d)| d.properties.iter().map(move |p| (d, p)))
+    }
+
+    /// Properties declared directly on a declaration in the given namespace, without walking
+    /// the declarations it extends
+    pub fn own_properties_of<'a>(
+        &'a self,
+        namespace: &Namespace,
+        type_name: &str,
+    ) -> impl Iterator<Item = &'a Property> {
+        self.model(namespace)
+            .and_then(|m| m.declaration(type_name))
+            .into_iter()
+            .flat_map(|d| d.properties.iter())
+    }
+}
+
+/// Finds the `.cto` files under a directory, in a stable order
+fn model_files(dir: &Path, files: &mut Vec<PathBuf>, errors: &mut Vec<FileError>) {
+    let entries = std::fs::read_dir(dir).and_then(|entries| {
+        entries
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(FileError::Io {
+                path: Some(dir.to_path_buf()),
+                source: e,
+            });
+            return;
+        }
+    };
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            model_files(&entry, files, errors);
+        } else if entry.extension().is_some_and(|e| e == "cto") {
+            files.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{model, property::CommonProperty, version::VersionRequirement};
+
+    #[test]
+    fn test_properties_of() {
+        let (_, person) = model(
+            "namespace people@1.0.0
+
+            concept Person {
+              o String name
+              o Address address
+            }
+            ",
+        )
+        .unwrap();
+        let (_, places) = model(
+            "namespace places@1.0.0
+
+            concept Address {
+              o String street
+            }
+            ",
+        )
+        .unwrap();
+
+        let mut manager = super::ModelManager::new();
+        manager.add_model(person.clone());
+        manager.add_model(places);
+
+        assert_eq!(
+            manager
+                .properties_of(&person.namespace, "Person")
+                .map(|(d, p)| (d.name.as_str(), p.name()))
+                .collect::<Vec<_>>(),
+            vec![("Person", "name"), ("Person", "address")],
+            "Should list properties of a declaration"
+        );
+        assert_eq!(
+            manager.properties_of(&person.namespace, "Address").count(),
+            0,
+            "Should not find declarations of other namespaces"
+        );
+        assert_eq!(
+            person
+                .properties()
+                .map(|(d, p)| (d.name.as_str(), p.name()))
+                .collect::<Vec<_>>(),
+            vec![("Person", "name"), ("Person", "address")],
+            "Should list properties of all declarations of a model"
+        );
+    }
+
+    #[test]
+    fn test_inherited_properties() {
+        let (_, people) = model(
+            "namespace people@1.0.0
+            import base@1.0.0.Party
+
+            concept Employee extends Party {
+              o String team
+            }
+
+            concept Manager extends Employee {
+              o Integer reports
+            }
+
+            concept Loop extends Loop {
+            }
+            ",
+        )
+        .unwrap();
+        let (_, base) = model(
+            "namespace base@1.0.0
+
+            abstract concept Party {
+              o String name
+            }
+            ",
+        )
+        .unwrap();
+
+        let mut manager = super::ModelManager::new();
+        manager.add_model(people.clone());
+        manager.add_model(base);
+
+        let manager_declaration = people.declaration("Manager").unwrap();
+        assert_eq!(
+            manager
+                .supertype(&people, manager_declaration)
+                .map(|(_, d)| d.name.as_str()),
+            Some("Employee")
+        );
+        assert_eq!(
+            manager
+                .properties_of(&people.namespace, "Manager")
+                .map(|(d, p)| (d.name.as_str(), p.name()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Party", "name"),
+                ("Employee", "team"),
+                ("Manager", "reports")
+            ],
+            "Should list inherited properties first"
+        );
+        assert_eq!(
+            manager
+                .own_properties_of(&people.namespace, "Manager")
+                .map(|p| p.name())
+                .collect::<Vec<_>>(),
+            vec!["reports"],
+            "Should skip inherited properties"
+        );
+        assert_eq!(
+            manager
+                .hierarchy(&people, people.declaration("Loop").unwrap())
+                .len(),
+            1,
+            "Should stop at cycles"
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut manager = super::ModelManager::new();
+        for input in [
+            "namespace places@1.0.0
+
+            concept Address {
+              o String street
+              o Country country
+            }
+
+            concept Country {
+              o String code
+            }
+
+            concept Building {
+              o Address address
+            }
+
+            concept Party {
+              o String id
+            }
+            ",
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+            import places@1.0.0.Building
+            import places@1.0.0.Party
+
+            concept Person extends Party {
+              o String name
+              o Address home
+              o Person[] friends optional
+            }
+
+            concept Company {
+              o Building office
+            }
+            ",
+            "namespace shops@1.0.0\nconcept Shop {\n  o String name\n}\n",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+
+        let person = crate::parser::namespace::fqn("people@1.0.0.Person")
+            .unwrap()
+            .1;
+        let extracted = manager.extract(&person).unwrap();
+        let names = |namespace: &str| {
+            extracted
+                .models()
+                .find(|m| m.namespace.name() == namespace)
+                .map(|m| {
+                    m.declarations
+                        .iter()
+                        .map(|d| d.name.as_str())
+                        .collect::<Vec<_>>()
+                })
+        };
+        assert_eq!(names("people"), Some(vec!["Person"]));
+        assert_eq!(
+            names("places"),
+            Some(vec!["Address", "Country", "Party"]),
+            "Should follow properties and super types"
+        );
+        assert_eq!(names("shops"), None, "Should leave unused models out");
+
+        let people = extracted.models().find(|m| m.namespace.name() == "people");
+        assert_eq!(
+            people.unwrap().imports.len(),
+            2,
+            "Should only keep the imports in use"
+        );
+        for model in extracted.models() {
+            assert!(crate::analysis::unresolved(&extracted, model).is_empty());
+        }
+
+        let missing = crate::parser::namespace::fqn("people@1.0.0.Pet").unwrap().1;
+        assert!(manager.extract(&missing).is_none());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut manager = super::ModelManager::new();
+        for version in ["1.0.0", "1.2.0", "1.3.0-beta", "2.0.0"] {
+            let (_, places) = model(&format!(
+                "namespace places@{}
+
+                concept Address {{
+                  o String street
+                }}
+                ",
+                version
+            ))
+            .unwrap();
+            manager.add_model(places);
+        }
+        let (_, person) = model(
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o Address address
+            }
+            ",
+        )
+        .unwrap();
+        manager.add_model(person.clone());
+
+        let resolved_version = |requirement: &str| {
+            manager
+                .resolve(
+                    "places",
+                    &requirement.parse::<VersionRequirement>().unwrap(),
+                )
+                .map(|m| m.namespace.version().to_string())
+        };
+        assert_eq!(
+            resolved_version("^1.0.0"),
+            Some("1.2.0".to_string()),
+            "Should pick the highest version satisfying the requirement"
+        );
+        assert_eq!(
+            resolved_version("*"),
+            Some("2.0.0".to_string()),
+            "Should pick the highest version of all"
+        );
+        assert_eq!(
+            resolved_version(">=1.3.0-beta <2"),
+            Some("1.3.0-beta".to_string()),
+            "Should pick pre-release versions when asked for"
+        );
+        assert_eq!(
+            resolved_version("^3"),
+            None,
+            "Should not resolve when no version satisfies the requirement"
+        );
+        assert_eq!(
+            manager
+                .resolve_import(&person.imports[0])
+                .map(|m| m.namespace.version().to_string()),
+            Some("1.2.0".to_string()),
+            "Should resolve imports to the highest compatible version"
+        );
+        assert_eq!(
+            manager
+                .resolve_type(&person, "Address")
+                .map(|d| d.name.as_str()),
+            Some("Address"),
+            "Should resolve imported types"
+        );
+        assert!(
+            manager.resolve_type(&person, "Country").is_none(),
+            "Should not resolve types that are not imported"
+        );
+    }
+
+    #[test]
+    fn test_resolve_import_kinds() {
+        let mut manager = super::ModelManager::new();
+        for source in [
+            "namespace places@1.0.0\n\nconcept Address {\n  o String street\n}\n",
+            "namespace people@1.0.0\n\nconcept Person {\n  o String name\n}\n\nconcept Pet {\n  o String name\n}\n",
+        ] {
+            manager.add_model(model(source).unwrap().1);
+        }
+        let (_, team) = model(
+            "namespace teams@1.0.0
+            import people@1.0.0.{Person, Pet as Mascot}
+            import places@1.0.0.*
+
+            concept Team {
+              o Person lead
+              o Mascot mascot
+              o Address office
+            }
+            ",
+        )
+        .unwrap();
+        manager.add_model(team.clone());
+
+        assert_eq!(
+            manager
+                .resolve_type(&team, "Address")
+                .map(|d| d.name.as_str()),
+            Some("Address"),
+            "Should resolve types through wildcard imports"
+        );
+        assert_eq!(
+            manager
+                .resolve_type(&team, "Person")
+                .map(|d| d.name.as_str()),
+            Some("Person"),
+            "Should resolve types listed in braced imports"
+        );
+        assert_eq!(
+            manager
+                .resolve_type(&team, "Mascot")
+                .map(|d| d.name.as_str()),
+            Some("Pet"),
+            "Should resolve aliased types to the type they import"
+        );
+        assert!(
+            manager.resolve_type(&team, "Pet").is_none(),
+            "Should not resolve aliased types by their imported name"
+        );
+        assert_eq!(
+            team.fully_qualified_name("Mascot")
+                .map(|fqn| fqn.to_string()),
+            Some(String::from("people@1.0.0.Pet"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_qualified_property() {
+        use crate::{analysis, parser::declaration::Property};
+
+        let mut manager = super::ModelManager::new();
+        for source in [
+            "namespace places@1.0.0\n\nconcept Address {\n  o String street\n}\n",
+            "namespace places@2.0.0\n\nconcept Location {\n  o String street\n}\n",
+        ] {
+            manager.add_model(model(source).unwrap().1);
+        }
+        let (_, office) = model(
+            "namespace offices@1.0.0
+
+            concept Office {
+              o places@1.0.0.Address address
+              o places@2.0.0.Address moved
+            }
+            ",
+        )
+        .unwrap();
+        manager.add_model(office.clone());
+
+        let resolved: Vec<_> = office
+            .properties()
+            .map(|(_, property)| match property {
+                Property::Concept(p) => manager
+                    .resolve_property_in(&office, p)
+                    .map(|(m, d)| format!("{}.{}", m.namespace.version(), d.name)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![Some(String::from("1.0.0.Address")), None],
+            "Should resolve qualified types in their exact namespace, without imports"
+        );
+        assert_eq!(
+            analysis::unresolved(&manager, &office),
+            vec![String::from(
+                "unknown type places@2.0.0.Address of Office.moved"
+            )],
+        );
+    }
+
+    #[test]
+    fn test_usages_of() {
+        use crate::parser::{cst::NodeId, namespace::fqn};
+
+        let mut manager = super::ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+
+            concept Person {
+              o String name
+              o Person[] friends
+            }
+
+            concept Pet {
+              o String name
+            }
+
+            concept Employee extends Person {
+              o String id
+            }
+            ",
+            "namespace orders@1.0.0
+            import people@1.0.0.Person
+
+            concept Order {
+              o Person buyer
+            }
+            ",
+            "namespace shop@1.0.0
+            import people@1.0.0.*
+
+            concept Customer extends Employee {
+              o String account
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let usages = |manager: &super::ModelManager, name: &str| {
+            manager
+                .usages_of(&fqn(name).unwrap().1)
+                .into_iter()
+                .map(|l| (l.model.namespace.name().to_string(), l.node))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            usages(&manager, "people@1.0.0.Person"),
+            vec![
+                (String::from("people"), NodeId::Property(0, 1)),
+                (String::from("people"), NodeId::Declaration(2)),
+                (String::from("orders"), NodeId::Import(0)),
+                (String::from("orders"), NodeId::Property(0, 0)),
+            ]
+        );
+        assert_eq!(
+            usages(&manager, "people@1.0.0.Employee"),
+            vec![
+                (String::from("shop"), NodeId::Import(0)),
+                (String::from("shop"), NodeId::Declaration(0)),
+            ],
+            "Should count wildcard imports of the declarations the model refers to"
+        );
+        assert!(
+            usages(&manager, "people@1.0.0.Pet").is_empty(),
+            "Should have no usages of declarations safe to delete, even imported by a wildcard"
+        );
+        assert!(usages(&manager, "people@2.0.0.Person").is_empty());
+
+        manager.remove_model(&model("namespace orders@1.0.0").unwrap().1.namespace);
+        assert_eq!(
+            usages(&manager, "people@1.0.0.Person").len(),
+            2,
+            "Should index the models again when they change"
+        );
+    }
+
+    #[test]
+    fn test_system_namespace() {
+        let mut manager = super::ModelManager::new();
+        let (_, person) = model(
+            "namespace people@1.0.0
+            import concerto@1.0.0.Concept
+
+            concept Person {
+              o Concept details
+            }
+            ",
+        )
+        .unwrap();
+        manager.add_model(person.clone());
+
+        assert_eq!(
+            manager.models().count(),
+            1,
+            "Should not load the system model"
+        );
+        assert!(
+            manager.resolve_import(&person.imports[0]).is_some(),
+            "Should resolve the system namespace without it being loaded"
+        );
+        let declaration = person.declaration("Person").unwrap();
+        let (system, supertype) = manager.supertype(&person, declaration).unwrap();
+        assert_eq!(
+            format!(
+                "{}@{}.{}",
+                system.namespace.name(),
+                system.namespace.version(),
+                supertype.name
+            ),
+            "concerto@1.0.0.Concept",
+            "Should report the implicit supertype"
+        );
+        assert!(
+            manager.supertype(system, supertype).is_none(),
+            "Should not give base types a supertype"
+        );
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let (_, places) = model(
+            "namespace places@1.0.0
+
+            concept Address {
+              o String city
+              o Country country
+            }
+
+            concept Country {
+              o String code
+            }
+            ",
+        )
+        .unwrap();
+        let (_, person) = model(
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o String name
+              o Address mainAddress
+            }
+            ",
+        )
+        .unwrap();
+        let mut manager = super::ModelManager::new();
+        manager.add_model(places);
+
+        assert_eq!(
+            person.resolve_path("Person/name").map(|p| p.name()),
+            Some("name"),
+            "Should find a property of a declaration"
+        );
+        assert!(
+            person.resolve_path("Person/mainAddress/city").is_none(),
+            "Should not walk imported types without a manager"
+        );
+        assert_eq!(
+            manager
+                .resolve_path(&person, "Person/mainAddress/country/code")
+                .map(|p| (p.name(), p.type_name())),
+            Some(("code", "String")),
+            "Should walk imported types and their own types"
+        );
+        assert!(
+            manager
+                .resolve_path(&person, "Person/name/length")
+                .is_none(),
+            "Should not walk into primitive properties"
+        );
+        assert!(
+            manager.resolve_path(&person, "Person").is_none(),
+            "Should need a property name"
+        );
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let mut manager = super::ModelManager::new();
+        for input in [
+            "namespace orders@1.0.0
+            import people@1.0.0.Person
+
+            concept Order {
+              o Person buyer
+            }
+            ",
+            "namespace people@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o Address address
+            }
+            ",
+            "namespace places@1.0.0
+
+            concept Address {
+              o String street
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        assert_eq!(
+            manager
+                .topological_order()
+                .unwrap()
+                .iter()
+                .map(|m| m.namespace.name())
+                .collect::<Vec<_>>(),
+            vec!["places", "people", "orders"],
+            "Should order dependencies before dependents"
+        );
+
+        let (_, places) = model(
+            "namespace places@1.0.0
+            import orders@1.0.0.Order
+
+            concept Address {
+              o Order lastOrder
+            }
+            ",
+        )
+        .unwrap();
+        manager.add_model(places);
+        assert_eq!(
+            manager.topological_order().unwrap_err().to_string(),
+            "import cycle between orders@1.0.0, people@1.0.0, places@1.0.0",
+            "Should fail on import cycles"
+        );
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = std::env::temp_dir().join(format!("concerto-manager-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("places")).unwrap();
+        let people = dir.join("people.cto");
+        let places = dir.join("places").join("places.cto");
+        let broken = dir.join("places").join("broken.cto");
+        std::fs::write(&people, "namespace people@1.0.0\n").unwrap();
+        std::fs::write(&places, "namespace places@1.0.0\n").unwrap();
+        std::fs::write(&broken, "namespace broken\n").unwrap();
+        std::fs::write(dir.join("README.md"), "# Models").unwrap();
+
+        let (manager, errors) = super::ModelManager::from_dir(&dir);
+        assert_eq!(
+            manager
+                .models()
+                .map(|m| manager.path(&m.namespace).unwrap())
+                .collect::<Vec<_>>(),
+            vec![people.as_path(), places.as_path()],
+            "Should load model files recursively, with their paths"
+        );
+        assert_eq!(
+            errors.iter().map(|e| e.path()).collect::<Vec<_>>(),
+            vec![Some(broken.as_path())],
+            "Should report files that don't parse"
+        );
+        let v2 = crate::parser::options::ParserOptions {
+            language_version: crate::parser::options::LanguageVersion::V2,
+            ..Default::default()
+        };
+        let (manager, errors) = super::ModelManager::from_dir_with(&dir, &v2);
+        assert_eq!(
+            (manager.models().count(), errors.len()),
+            (3, 0),
+            "Should parse the files with the options of the manager"
+        );
+
+        let pattern = format!("{}/**/p*.cto", dir.display());
+        let (manager, errors) = super::ModelManager::from_glob(&pattern).unwrap();
+        assert_eq!((manager.models().count(), errors.len()), (2, 0));
+
+        let (_, errors) = super::ModelManager::from_dir(dir.join("missing"));
+        assert_eq!(
+            errors.len(),
+            1,
+            "Should report directories that can't be read"
+        );
+
+        let mut manager = manager;
+        let namespace = manager.models().next().unwrap().namespace.clone();
+        manager.add_model(model("namespace people@1.0.0\n").unwrap().1);
+        assert_eq!(
+            manager.path(&namespace),
+            None,
+            "Should forget the path of replaced models"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_external() {
+        use crate::{parser::namespace::Namespace, resolver::ResolveError};
+
+        let resolver = |namespace: &Namespace| {
+            match namespace.name() {
+            "places" => Ok(String::from(
+                "namespace places@1.2.0\nimport geo@1.0.0.Point\nconcept Address {\n  o Point location\n}\n",
+            )),
+            "geo" => Ok(String::from("namespace geo@1.0.0\nconcept Point {\n  o Double lat\n}\n")),
+            "old" => Ok(String::from("namespace old@2.0.0\n")),
+            _ => Err(ResolveError::NotFound(namespace.clone())),
+        }
+        };
+        let mut manager = super::ModelManager::new();
+        manager.add_model(
+            model("namespace people@1.0.0\nimport places@1.0.0.Address\nconcept Person {\n  o Address address\n}\n")
+                .unwrap()
+                .1,
+        );
+
+        let fetched = manager.resolve_external(&resolver).unwrap();
+        assert_eq!(
+            fetched.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+            vec!["namespace places@1.2.0", "namespace geo@1.0.0"],
+            "Should fetch imports of fetched models too"
+        );
+        assert!(manager
+            .resolve_path(&manager.models[0], "Person/address/location")
+            .is_some());
+        assert_eq!(
+            manager.resolve_external(&resolver).unwrap(),
+            vec![],
+            "Should fetch nothing once imports resolve"
+        );
+
+        manager.add_model(
+            model("namespace test@1.0.0\nimport old@1.0.0.Thing\n")
+                .unwrap()
+                .1,
+        );
+        assert!(
+            matches!(
+                manager.resolve_external(&resolver),
+                Err(ResolveError::Mismatch { .. })
+            ),
+            "Should reject models of incompatible versions"
+        );
+    }
+}
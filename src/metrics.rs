@@ -0,0 +1,269 @@
+//! Complexity metrics of declarations and namespaces, for automating architecture reviews
+//! of large sets of models
+
+use serde_derive::Serialize;
+
+use crate::{
+    manager::ModelManager,
+    parser::{
+        declaration::{Declaration, Property},
+        namespace::{FullyQualifiedName, Namespace},
+        property::CommonProperty,
+        Model,
+    },
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct DeclarationMetrics {
+    pub name: FullyQualifiedName,
+    pub properties: usize,
+    /// Number of declarations above this one in its inheritance chain. Declarations can't
+    /// extend others yet, so this is always 0.
+    pub inheritance_depth: usize,
+    /// Number of other declarations depending on this one
+    pub afferent_coupling: usize,
+    /// Number of other declarations this one depends on
+    pub efferent_coupling: usize,
+    /// Properties of types that take validators, i.e. strings and numbers
+    pub validatable_properties: usize,
+    /// Validatable properties with at least one validator
+    pub validated_properties: usize,
+}
+
+/// Metrics of a namespace, coupling counts other namespaces. Namespaces are keyed as
+/// `name@version`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct NamespaceMetrics {
+    pub namespace: String,
+    pub declarations: usize,
+    pub properties: usize,
+    pub max_inheritance_depth: usize,
+    /// Number of other namespaces with declarations depending on declarations of this one
+    pub afferent_coupling: usize,
+    /// Number of other namespaces declarations of this one depend on
+    pub efferent_coupling: usize,
+    pub validatable_properties: usize,
+    pub validated_properties: usize,
+}
+
+/// Share of validatable properties with validators, none without validatable properties
+fn coverage(validatable: usize, validated: usize) -> Option<f64> {
+    (validatable > 0).then(|| validated as f64 / validatable as f64)
+}
+
+impl DeclarationMetrics {
+    pub fn validator_coverage(&self) -> Option<f64> {
+        coverage(self.validatable_properties, self.validated_properties)
+    }
+}
+
+impl NamespaceMetrics {
+    pub fn validator_coverage(&self) -> Option<f64> {
+        coverage(self.validatable_properties, self.validated_properties)
+    }
+
+    /// Efferent coupling over total coupling, from 0 for namespaces only depended on to 1
+    /// for namespaces only depending on others. None for namespaces without coupling.
+    pub fn instability(&self) -> Option<f64> {
+        let total = self.afferent_coupling + self.efferent_coupling;
+        (total > 0).then(|| self.efferent_coupling as f64 / total as f64)
+    }
+}
+
+/// Metrics of all loaded models, in the order the models and declarations were added
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Metrics {
+    pub declarations: Vec<DeclarationMetrics>,
+    pub namespaces: Vec<NamespaceMetrics>,
+}
+
+impl Metrics {
+    pub(crate) fn of_manager(manager: &ModelManager) -> Self {
+        let graph = manager.dependency_graph();
+        let nodes = graph.nodes();
+        let others =
+            |linked: Vec<usize>, node: usize| linked.iter().filter(|&&n| n != node).count();
+
+        let declarations: Vec<_> = nodes
+            .iter()
+            .enumerate()
+            .map(|(node, (model, declaration))| {
+                let (validatable, validated) = validation(declaration);
+                DeclarationMetrics {
+                    name: FullyQualifiedName::from((
+                        model.namespace.name().to_string(),
+                        model.namespace.version().clone(),
+                        declaration.name.clone(),
+                    )),
+                    properties: declaration.properties.len(),
+                    inheritance_depth: 0,
+                    afferent_coupling: others(graph.dependents(node), node),
+                    efferent_coupling: others(graph.dependencies(node), node),
+                    validatable_properties: validatable,
+                    validated_properties: validated,
+                }
+            })
+            .collect();
+
+        let namespaces = manager
+            .models()
+            .map(|model| {
+                let own = |node: &usize| std::ptr::eq(nodes[*node].0, model);
+                let mut metrics = NamespaceMetrics {
+                    namespace: key(&model.namespace),
+                    declarations: 0,
+                    properties: 0,
+                    max_inheritance_depth: 0,
+                    afferent_coupling: coupled(
+                        model,
+                        graph
+                            .edges()
+                            .iter()
+                            .filter(|e| own(&e.to))
+                            .map(|e| nodes[e.from].0),
+                    ),
+                    efferent_coupling: coupled(
+                        model,
+                        graph
+                            .edges()
+                            .iter()
+                            .filter(|e| own(&e.from))
+                            .map(|e| nodes[e.to].0),
+                    ),
+                    validatable_properties: 0,
+                    validated_properties: 0,
+                };
+                let own_declarations = declarations.iter().enumerate().filter(|(n, _)| own(n));
+                for (_, declaration) in own_declarations {
+                    metrics.declarations += 1;
+                    metrics.properties += declaration.properties;
+                    metrics.max_inheritance_depth = metrics
+                        .max_inheritance_depth
+                        .max(declaration.inheritance_depth);
+                    metrics.validatable_properties += declaration.validatable_properties;
+                    metrics.validated_properties += declaration.validated_properties;
+                }
+                metrics
+            })
+            .collect();
+
+        Self {
+            declarations,
+            namespaces,
+        }
+    }
+
+    pub fn declaration(&self, name: &FullyQualifiedName) -> Option<&DeclarationMetrics> {
+        self.declarations.iter().find(|d| &d.name == name)
+    }
+
+    /// Metrics of a namespace, by its `name@version`
+    pub fn namespace(&self, key: &str) -> Option<&NamespaceMetrics> {
+        self.namespaces.iter().find(|n| n.namespace == key)
+    }
+}
+
+/// Counts of validatable and validated properties of a declaration
+fn validation(declaration: &Declaration) -> (usize, usize) {
+    declaration
+        .properties
+        .iter()
+        .filter(|p| {
+            matches!(
+                p,
+                Property::String(_)
+                    | Property::Integer(_)
+                    | Property::Long(_)
+                    | Property::Double(_)
+            )
+        })
+        .fold((0, 0), |(validatable, validated), p| {
+            (
+                validatable + 1,
+                validated + usize::from(!p.validators().is_empty()),
+            )
+        })
+}
+
+/// Number of distinct models other than the given one
+fn coupled<'a>(model: &Model, linked: impl Iterator<Item = &'a Model>) -> usize {
+    let mut linked: Vec<_> = linked
+        .filter(|m| !std::ptr::eq(*m, model))
+        .map(|m| key(&m.namespace))
+        .collect();
+    linked.sort();
+    linked.dedup();
+    linked.len()
+}
+
+fn key(namespace: &Namespace) -> String {
+    format!("{}@{}", namespace.name(), namespace.version())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        manager::ModelManager,
+        parser::{namespace::fqn, parse},
+    };
+
+    #[test]
+    fn test_metrics() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name regex=/[A-Z].*/
+  o Integer age
+  o Boolean active
+  o Address home
+  o Person[] friends
+}
+concept Company {
+  o Person[] employees
+  o Address office
+}
+",
+            "namespace places@1.0.0
+concept Address {
+  o String street length=[1,]
+}
+",
+        ] {
+            manager.add_model(parse(input).unwrap());
+        }
+        let metrics = manager.metrics();
+        let declaration = |name: &str| metrics.declaration(&fqn(name).unwrap().1).unwrap().clone();
+
+        let person = declaration("people@1.0.0.Person");
+        assert_eq!(person.properties, 5);
+        assert_eq!(
+            (person.afferent_coupling, person.efferent_coupling),
+            (1, 1),
+            "Should not count references to itself"
+        );
+        assert_eq!(
+            (person.validatable_properties, person.validated_properties),
+            (2, 1)
+        );
+        assert_eq!(person.validator_coverage(), Some(0.5));
+        assert_eq!(declaration("places@1.0.0.Address").afferent_coupling, 2);
+        assert_eq!(
+            declaration("people@1.0.0.Company").validator_coverage(),
+            None
+        );
+
+        let people = metrics.namespace("people@1.0.0").unwrap();
+        assert_eq!((people.declarations, people.properties), (2, 7));
+        assert_eq!(
+            (people.afferent_coupling, people.efferent_coupling),
+            (0, 1),
+            "Should count namespaces, not declarations"
+        );
+        assert_eq!(people.instability(), Some(1.0));
+        let places = metrics.namespace("places@1.0.0").unwrap();
+        assert_eq!(places.instability(), Some(0.0));
+        assert_eq!(places.validator_coverage(), Some(1.0));
+    }
+}
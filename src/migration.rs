@@ -0,0 +1,459 @@
+//! Migrations of JSON instances from one version of a model to another, derived from the
+//! differences between the versions
+
+use nom::combinator::all_consuming;
+use serde_derive::Serialize;
+use serde_json::Value;
+
+use crate::{
+    diff::{diff, Change},
+    parser::{
+        declaration::{Declaration, Property},
+        namespace::{fqn, Namespace},
+        property::CommonProperty,
+        Model,
+    },
+};
+
+/// Something done to every instance of a declaration
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(tag = "step", rename_all = "camelCase")]
+pub enum Step {
+    /// A property was removed and one of the same type added, taken as a rename
+    PropertyRenamed {
+        declaration: String,
+        from: String,
+        to: String,
+    },
+    /// The property was added or became required, missing values are set to its default
+    DefaultAdded {
+        declaration: String,
+        property: String,
+        value: Value,
+    },
+    PropertyRemoved {
+        declaration: String,
+        property: String,
+    },
+    /// The type of the property accepts every value of the old one, e.g. `Integer` to `Long`
+    TypeWidened {
+        declaration: String,
+        property: String,
+        from: String,
+        to: String,
+    },
+    /// The property became an array, single values become arrays of one item
+    ArrayWrapped {
+        declaration: String,
+        property: String,
+    },
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PropertyRenamed {
+                declaration,
+                from,
+                to,
+            } => write!(f, "rename {}.{} to {}", declaration, from, to),
+            Self::DefaultAdded {
+                declaration,
+                property,
+                value,
+            } => write!(f, "default {}.{} to {}", declaration, property, value),
+            Self::PropertyRemoved {
+                declaration,
+                property,
+            } => write!(f, "remove {}.{}", declaration, property),
+            Self::TypeWidened {
+                declaration,
+                property,
+                from,
+                to,
+            } => write!(
+                f,
+                "widen {}.{} from {} to {}",
+                declaration, property, from, to
+            ),
+            Self::ArrayWrapped {
+                declaration,
+                property,
+            } => write!(f, "wrap {}.{} in an array", declaration, property),
+        }
+    }
+}
+
+/// How to upgrade instances of one version of a model to the next
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Migration {
+    pub from: Namespace,
+    pub to: Namespace,
+    /// Steps in the order they are applied
+    pub steps: Vec<Step>,
+    /// Changes instances can't be upgraded for automatically, e.g. a narrowed type or a
+    /// required property without a default
+    pub manual: Vec<Change>,
+}
+
+impl Migration {
+    /// Whether every instance valid for the old version can be upgraded automatically
+    pub fn is_complete(&self) -> bool {
+        self.manual.is_empty()
+    }
+
+    /// Upgrades an instance, and every object nested in it, naming a declaration of the old
+    /// version in its `$class`. Anything else is left as it is.
+    pub fn apply(&self, instance: &Value) -> Value {
+        let mut upgraded = instance.clone();
+        self.upgrade(&mut upgraded);
+        upgraded
+    }
+
+    fn upgrade(&self, value: &mut Value) {
+        let object = match value {
+            Value::Object(object) => object,
+            Value::Array(items) => return items.iter_mut().for_each(|i| self.upgrade(i)),
+            _ => return,
+        };
+        object.values_mut().for_each(|v| self.upgrade(v));
+
+        let Some(name) = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .and_then(|class| all_consuming(fqn)(class).ok())
+            .map(|(_, name)| name)
+            .filter(|name| {
+                name.namespace_name() == self.from.name() && name.version() == self.from.version()
+            })
+        else {
+            return;
+        };
+        let class = format!(
+            "{}@{}.{}",
+            self.to.name(),
+            self.to.version(),
+            name.type_name()
+        );
+        object.insert(String::from("$class"), Value::String(class));
+
+        for step in &self.steps {
+            match step {
+                Step::PropertyRenamed {
+                    declaration,
+                    from,
+                    to,
+                } if declaration == name.type_name() => {
+                    if let Some(value) = object.remove(from) {
+                        object.insert(to.clone(), value);
+                    }
+                }
+                Step::DefaultAdded {
+                    declaration,
+                    property,
+                    value,
+                } if declaration == name.type_name() => {
+                    let missing = object.get(property).is_none_or(Value::is_null);
+                    if missing {
+                        object.insert(property.clone(), value.clone());
+                    }
+                }
+                Step::PropertyRemoved {
+                    declaration,
+                    property,
+                } if declaration == name.type_name() => {
+                    object.remove(property);
+                }
+                Step::ArrayWrapped {
+                    declaration,
+                    property,
+                } if declaration == name.type_name() => {
+                    if let Some(value) = object.get_mut(property) {
+                        if !value.is_array() && !value.is_null() {
+                            *value = Value::Array(vec![value.take()]);
+                        }
+                    }
+                }
+                // JSON numbers are valid for the wider type as they are
+                _ => (),
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}@{} to {}@{}",
+            self.from.name(),
+            self.from.version(),
+            self.to.name(),
+            self.to.version()
+        )?;
+        for step in &self.steps {
+            writeln!(f, "  {}", step)?;
+        }
+        for change in &self.manual {
+            writeln!(f, "  manual: {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the migration of instances from one version of a model to another. A removed
+/// property is taken as renamed when exactly one property of the same type and arity was
+/// added to its declaration, and no other removed property could have been renamed to it.
+pub fn migration(old: &Model, new: &Model) -> Migration {
+    let changes = diff(old, new).changes;
+    let renames = renames(old, new, &changes);
+    let renamed = |declaration: &str, property: &str| {
+        renames.iter().any(|(d, from, to)| {
+            *d == declaration && (from.name() == property || to.name() == property)
+        })
+    };
+    let new_property = |declaration: &str, property: &str| {
+        new.declaration(declaration)
+            .and_then(|d| d.property(property))
+    };
+
+    let mut steps: Vec<_> = renames
+        .iter()
+        .map(|(declaration, from, to)| Step::PropertyRenamed {
+            declaration: declaration.to_string(),
+            from: from.name().to_string(),
+            to: to.name().to_string(),
+        })
+        .collect();
+    let mut manual = Vec::new();
+    for change in changes {
+        let step = match &change {
+            Change::NamespaceRenamed { .. }
+            | Change::DeclarationAdded { .. }
+            | Change::PropertyChanged { .. } => continue,
+            Change::PropertyOptionalityChanged { optional: true, .. } => continue,
+            Change::AbstractChanged {
+                is_abstract: false, ..
+            } => continue,
+            Change::PropertyAdded { optional: true, .. } => continue,
+            Change::PropertyRemoved {
+                declaration,
+                property,
+            }
+            | Change::PropertyAdded {
+                declaration,
+                property,
+                ..
+            } if renamed(declaration, property) => continue,
+            Change::PropertyRemoved {
+                declaration,
+                property,
+            } => Some(Step::PropertyRemoved {
+                declaration: declaration.clone(),
+                property: property.clone(),
+            }),
+            Change::PropertyAdded {
+                declaration,
+                property,
+                ..
+            }
+            | Change::PropertyOptionalityChanged {
+                declaration,
+                property,
+                ..
+            } => new_property(declaration, property)
+                .and_then(default)
+                .map(|value| Step::DefaultAdded {
+                    declaration: declaration.clone(),
+                    property: property.clone(),
+                    value,
+                }),
+            Change::PropertyTypeChanged {
+                declaration,
+                property,
+                from,
+                to,
+            } if widens(from, to) => Some(Step::TypeWidened {
+                declaration: declaration.clone(),
+                property: property.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            Change::PropertyArrayChanged {
+                declaration,
+                property,
+                array: true,
+            } => Some(Step::ArrayWrapped {
+                declaration: declaration.clone(),
+                property: property.clone(),
+            }),
+            Change::DeclarationRemoved { .. }
+            | Change::DeclarationKindChanged { .. }
+            | Change::AbstractChanged { .. }
+            | Change::SuperTypeChanged { .. }
+            | Change::IdentifiedChanged { .. }
+            | Change::PropertyTypeChanged { .. }
+            | Change::PropertyArrayChanged { .. }
+            | Change::PropertyRelationshipChanged { .. }
+            | Change::PropertyValidatorsChanged { .. } => None,
+        };
+        match step {
+            Some(step) => steps.push(step),
+            None => manual.push(change),
+        }
+    }
+
+    Migration {
+        from: old.namespace.clone(),
+        to: new.namespace.clone(),
+        steps,
+        manual,
+    }
+}
+
+/// Removed and added properties taken as renamed, by declaration
+fn renames<'a>(
+    old: &'a Model,
+    new: &'a Model,
+    changes: &[Change],
+) -> Vec<(&'a str, &'a Property, &'a Property)> {
+    let properties = |model: &'a Model, removed: bool| {
+        changes.iter().filter_map(move |change| {
+            let (declaration, property) = match change {
+                Change::PropertyRemoved {
+                    declaration,
+                    property,
+                } if removed => (declaration, property),
+                Change::PropertyAdded {
+                    declaration,
+                    property,
+                    ..
+                } if !removed => (declaration, property),
+                _ => return None,
+            };
+            let declaration: &Declaration = model.declaration(declaration)?;
+            Some((declaration.name.as_str(), declaration.property(property)?))
+        })
+    };
+    let same_shape =
+        |a: &Property, b: &Property| a.type_name() == b.type_name() && a.is_array() == b.is_array();
+
+    let (removed, added): (Vec<_>, Vec<_>) = (
+        properties(old, true).collect(),
+        properties(new, false).collect(),
+    );
+    let candidates = |declaration: &str, property: &Property, among: &[(&str, &'a Property)]| {
+        among
+            .iter()
+            .filter(|(d, p)| *d == declaration && same_shape(p, property))
+            .map(|(_, p)| *p)
+            .collect::<Vec<_>>()
+    };
+    removed
+        .iter()
+        .filter_map(
+            |&(declaration, from)| match candidates(declaration, from, &added)[..] {
+                [to] if candidates(declaration, to, &removed).len() == 1 => {
+                    Some((declaration, from, to))
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Default value of a property as it is written in instances
+pub(crate) fn default(property: &Property) -> Option<Value> {
+    serde_json::to_value(property)
+        .ok()?
+        .get_mut("defaultValue")
+        .map(Value::take)
+}
+
+/// Whether every value of a primitive type is a value of another
+fn widens(from: &str, to: &str) -> bool {
+    matches!(
+        (from, to),
+        ("Integer", "Long") | ("Integer", "Double") | ("Long", "Double")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::parser::parse;
+
+    #[test]
+    fn test_migration() {
+        let old = parse(
+            "namespace test@1.0.0
+concept Person {
+  o String name
+  o Integer age
+  o String[] nicknames optional
+  o String tag
+  o Boolean active
+  o Boolean verified
+  o Person[] friends optional
+}
+",
+        )
+        .unwrap();
+        let new = parse(
+            "namespace test@2.0.0
+concept Person {
+  o String fullName
+  o Long age
+  o String[] tag
+  o Boolean enabled
+  o Double score default=1.0
+  o Person[] friends optional
+}
+",
+        )
+        .unwrap();
+
+        let migration = super::migration(&old, &new);
+        assert_eq!(
+            migration.to_string(),
+            "test@1.0.0 to test@2.0.0
+  rename Person.name to fullName
+  widen Person.age from Integer to Long
+  remove Person.nicknames
+  wrap Person.tag in an array
+  remove Person.active
+  remove Person.verified
+  default Person.score to 1.0
+  manual: added required Person.enabled
+",
+            "Should not guess a rename among several candidates"
+        );
+        assert!(!migration.is_complete());
+
+        let instance = json!({
+            "$class": "test@1.0.0.Person",
+            "name": "Ann",
+            "age": 42,
+            "nicknames": null,
+            "tag": "vip",
+            "active": true,
+            "verified": false,
+            "friends": [{"$class": "test@1.0.0.Person", "name": "Bob", "age": 7}],
+        });
+        assert_eq!(
+            migration.apply(&instance),
+            json!({
+                "$class": "test@2.0.0.Person",
+                "fullName": "Ann",
+                "age": 42,
+                "tag": ["vip"],
+                "score": 1.0,
+                "friends": [{"$class": "test@2.0.0.Person", "fullName": "Bob", "age": 7, "score": 1.0}],
+            }),
+            "Should upgrade nested instances too"
+        );
+        let other = json!({"$class": "other@1.0.0.Person", "name": "Ann"});
+        assert_eq!(migration.apply(&other), other);
+    }
+}
@@ -0,0 +1,163 @@
+//! Go-to-definition and find-references across the models of a `ModelManager`
+
+use std::path::Path;
+
+use crate::{
+    manager::ModelManager,
+    parser::{
+        cst::{Cst, NodeId},
+        declaration::{Declaration, Property},
+        file::FileError,
+        namespace::FullyQualifiedName,
+        Model,
+    },
+};
+
+/// A node of a loaded model. The file of the model is `ModelManager::path`, and the span of
+/// the node is `Cst::span` of the parsed file.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Location<'a> {
+    pub model: &'a Model,
+    pub node: NodeId,
+}
+
+impl<'a> Location<'a> {
+    fn declaration(model: &'a Model, declaration: &Declaration) -> Self {
+        let index = model
+            .declarations
+            .iter()
+            .position(|d| std::ptr::eq(d, declaration))
+            .expect("Declaration is of the model");
+        Self {
+            model,
+            node: NodeId::Declaration(index),
+        }
+    }
+}
+
+/// Declaration the type at a byte offset of a loaded file refers to, for offsets in
+/// imports, concept properties and declarations. The file is read again to locate the
+/// offset, types are resolved against the models as they were loaded.
+pub fn definition_at<'a>(
+    manager: &'a ModelManager,
+    file: &Path,
+    offset: usize,
+) -> Result<Option<Location<'a>>, FileError> {
+    let source = std::fs::read_to_string(file).map_err(|source| FileError::Io {
+        path: Some(file.to_path_buf()),
+        source,
+    })?;
+    let cst = Cst::parse(source).map_err(|error| FileError::Parse {
+        path: Some(file.to_path_buf()),
+        error,
+    })?;
+    let Some(model) = manager.model(&cst.model().namespace) else {
+        return Ok(None);
+    };
+    let at = |node| cst.span(node).is_some_and(|s| s.contains(&offset));
+
+    for (i, import) in cst.model().imports.iter().enumerate() {
+        if at(NodeId::Import(i)) {
+            let target = manager.resolve_import(import).and_then(|m| {
+                m.declarations
+                    .iter()
+                    .find(|d| import.local_name(&d.name).is_some())
+                    .map(|d| Location::declaration(m, d))
+            });
+            return Ok(target);
+        }
+    }
+    for (d, declaration) in cst.model().declarations.iter().enumerate() {
+        if !at(NodeId::Declaration(d)) {
+            continue;
+        }
+        for (p, property) in declaration.properties.iter().enumerate() {
+            match property {
+                Property::Concept(concept) if at(NodeId::Property(d, p)) => {
+                    let target = manager
+                        .resolve_property_in(model, concept)
+                        .map(|(m, d)| Location::declaration(m, d));
+                    return Ok(target);
+                }
+                _ => {}
+            }
+        }
+        return Ok(model
+            .declaration(&declaration.name)
+            .map(|d| Location::declaration(model, d)));
+    }
+    Ok(None)
+}
+
+/// Imports and properties of the loaded models that refer to a declaration, in the order
+/// the models were added, see `ModelManager::usages_of`
+pub fn references_of<'a>(manager: &'a ModelManager, fqn: &FullyQualifiedName) -> Vec<Location<'a>> {
+    manager.usages_of(fqn)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        manager::ModelManager,
+        parser::{cst::NodeId, namespace::fqn},
+    };
+
+    #[test]
+    fn test_navigation() {
+        let dir = std::env::temp_dir().join(format!("concerto-navigation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let orders = dir.join("orders.cto");
+        let orders_source = "namespace orders@1.0.0
+import people@1.0.0.Person
+concept Order {
+  o Person buyer
+  o Person[] sellers
+  o String reference
+}
+";
+        std::fs::write(&orders, orders_source).unwrap();
+        std::fs::write(
+            dir.join("people.cto"),
+            "namespace people@1.0.0\nconcept Pet {\n  o String name\n}\nconcept Person {\n  o Person[] friends\n}\n",
+        )
+        .unwrap();
+        let (manager, errors) = ModelManager::from_dir(&dir);
+        assert!(errors.is_empty());
+
+        let offset = orders_source.find("Person buyer").unwrap();
+        let definition = super::definition_at(&manager, &orders, offset)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (definition.model.namespace.name(), definition.node),
+            ("people", NodeId::Declaration(1)),
+            "Should resolve property types across models"
+        );
+        let offset = orders_source.find("String").unwrap();
+        assert_eq!(
+            super::definition_at(&manager, &orders, offset).unwrap(),
+            Some(super::Location {
+                model: manager.models().next().unwrap(),
+                node: NodeId::Declaration(0)
+            }),
+            "Should fall back to the declaration for primitive types"
+        );
+
+        let person = fqn("people@1.0.0.Person").unwrap().1;
+        let references: Vec<_> = super::references_of(&manager, &person)
+            .into_iter()
+            .map(|l| (l.model.namespace.name(), l.node))
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                ("orders", NodeId::Import(0)),
+                ("orders", NodeId::Property(0, 0)),
+                ("orders", NodeId::Property(0, 1)),
+                ("people", NodeId::Property(1, 0)),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,395 @@
+//! Arena backed parsing. The input and all nodes of the model are allocated in a `Bump`,
+//! so parsing thousands of models in a build step only frees memory once, when the arena is
+//! dropped. Nodes hold no heap allocations of their own.
+//!
+//! Parsing with an `Interner` doesn't keep the input in the arena, and identifiers repeated
+//! across models, like type and property names, are only allocated once.
+
+use std::{cell::RefCell, collections::HashSet};
+
+use bumpalo::Bump;
+
+use super::{
+    borrowed::{self, IdentifiedRef, ImportRef, PropertyRef},
+    declaration::{self, DeclarationKind},
+    options::{with_options, ParserOptions},
+    CResult, Model,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ArenaModel<'b> {
+    pub header: Option<&'b str>,
+    pub namespace: &'b str,
+    /// `None` for namespaces written without a version
+    pub version: Option<&'b str>,
+    pub imports: &'b [ImportRef<'b>],
+    pub declarations: &'b [ArenaDeclaration<'b>],
+    /// Options the model was parsed with, as in `borrowed::ModelRef`
+    pub options: ParserOptions,
+}
+
+impl<'b> ArenaModel<'b> {
+    pub fn declaration(&self, name: &str) -> Option<&'b ArenaDeclaration<'b>> {
+        self.declarations.iter().find(|d| d.name == name)
+    }
+
+    /// Copies the model out of the arena into an owned `Model`
+    pub fn into_owned(&self) -> Model {
+        let mut model = borrowed::ModelRef {
+            header: self.header,
+            namespace: self.namespace,
+            version: self.version,
+            imports: self.imports.to_vec(),
+            declarations: Vec::new(),
+            options: self.options,
+        }
+        .into_owned();
+        with_options(&self.options, || {
+            for declaration in self.declarations.iter() {
+                model.add_declaration(declaration.into_owned());
+            }
+        });
+        model
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ArenaDeclaration<'b> {
+    /// Decorators as they are written
+    pub decorators: &'b [&'b str],
+    pub kind: DeclarationKind,
+    pub is_abstract: bool,
+    pub name: &'b str,
+    /// Super type as it is written
+    pub super_type: Option<&'b str>,
+    pub identified: Option<IdentifiedRef<'b>>,
+    pub properties: &'b [ArenaProperty<'b>],
+}
+
+impl<'b> ArenaDeclaration<'b> {
+    pub fn property(&self, name: &str) -> Option<&'b ArenaProperty<'b>> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Parses the kept parts as `borrowed::DeclarationRef::into_owned` does
+    pub fn into_owned(&self) -> declaration::Declaration {
+        let mut declaration = declaration::Declaration::new(
+            self.kind,
+            self.name,
+            self.properties
+                .iter()
+                .map(ArenaProperty::into_owned)
+                .collect(),
+        );
+        declaration.is_abstract = self.is_abstract;
+        declaration.super_type = self.super_type.and_then(borrowed::parse_type_reference);
+        declaration.identified = self.identified.map(IdentifiedRef::into_owned);
+        declaration.decorators = borrowed::parse_decorators(self.decorators);
+        declaration
+    }
+}
+
+/// Same as `borrowed::PropertyRef`, with decorators and validators kept in the arena
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ArenaProperty<'b> {
+    pub decorators: &'b [&'b str],
+    pub type_name: &'b str,
+    pub name: &'b str,
+    pub is_array: bool,
+    pub is_optional: bool,
+    pub is_relationship: bool,
+    pub default_value: Option<&'b str>,
+    pub validators: &'b [&'b str],
+    pub source: &'b str,
+}
+
+impl<'b> ArenaProperty<'b> {
+    pub fn into_owned(&self) -> declaration::Property {
+        let mut property = borrowed::property_into_owned(self.source, self.type_name, self.name);
+        *property.decorators_mut() = borrowed::parse_decorators(self.decorators);
+        property
+    }
+
+    fn alloc(bump: &'b Bump, property: PropertyRef<'b>) -> Self {
+        ArenaProperty {
+            decorators: bump.alloc_slice_copy(&property.decorators),
+            type_name: property.type_name,
+            name: property.name,
+            is_array: property.is_array,
+            is_optional: property.is_optional,
+            is_relationship: property.is_relationship,
+            default_value: property.default_value,
+            validators: bump.alloc_slice_copy(&property.validators),
+            source: property.source,
+        }
+    }
+
+    fn alloc_interned(interner: &Interner<'b>, property: PropertyRef<'_>) -> Self {
+        let source = interner.bump.alloc_str(property.source);
+        // Default values and validators are slices of the source, so they are taken from
+        // its copy
+        let rebase = |s: &str| {
+            let start = s.as_ptr() as usize - property.source.as_ptr() as usize;
+            &source[start..start + s.len()]
+        };
+        ArenaProperty {
+            decorators: interner.intern_all(&property.decorators),
+            type_name: interner.intern(property.type_name),
+            name: interner.intern(property.name),
+            is_array: property.is_array,
+            is_optional: property.is_optional,
+            is_relationship: property.is_relationship,
+            default_value: property.default_value.map(rebase),
+            validators: interner
+                .bump
+                .alloc_slice_fill_iter(property.validators.iter().map(|v| rebase(v))),
+            source,
+        }
+    }
+}
+
+/// Deduplicates the identifiers of the models parsed into an arena
+#[derive(Debug)]
+pub struct Interner<'b> {
+    bump: &'b Bump,
+    strings: RefCell<HashSet<&'b str>>,
+}
+
+impl<'b> Interner<'b> {
+    pub fn new(bump: &'b Bump) -> Self {
+        Self {
+            bump,
+            strings: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// The copy of the string in the arena, allocated the first time it is interned
+    pub fn intern(&self, s: &str) -> &'b str {
+        let mut strings = self.strings.borrow_mut();
+        if let Some(interned) = strings.get(s) {
+            return interned;
+        }
+        let interned = self.bump.alloc_str(s);
+        strings.insert(interned);
+        interned
+    }
+
+    /// Interns every string, decorators like `@deprecated` are repeated as often as
+    /// identifiers
+    fn intern_all(&self, strings: &[&str]) -> &'b [&'b str] {
+        self.bump
+            .alloc_slice_fill_iter(strings.iter().map(|s| self.intern(s)))
+    }
+
+    /// Number of distinct strings interned
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.borrow().is_empty()
+    }
+}
+
+/// Parses a model into the arena of an interner. Identifiers are interned, and only the
+/// source of each property is copied, for `ArenaProperty::into_owned`.
+pub fn model_interned<'b, 'i>(
+    interner: &Interner<'b>,
+    input: &'i str,
+) -> CResult<&'i str, ArenaModel<'b>> {
+    model_interned_with(interner, input, &ParserOptions::default())
+}
+
+/// Like `model_interned`, with options as `borrowed::model_with` takes them
+pub fn model_interned_with<'b, 'i>(
+    interner: &Interner<'b>,
+    input: &'i str,
+    options: &ParserOptions,
+) -> CResult<&'i str, ArenaModel<'b>> {
+    let bump = interner.bump;
+    let (remains, parsed) = borrowed::model_with(input, options)?;
+
+    let declarations = bump.alloc_slice_fill_iter(parsed.declarations.into_iter().map(|d| {
+        ArenaDeclaration {
+            decorators: interner.intern_all(&d.decorators),
+            kind: d.kind,
+            is_abstract: d.is_abstract,
+            name: interner.intern(d.name),
+            super_type: d.super_type.map(|s| interner.intern(s)),
+            identified: d.identified.map(|identified| match identified {
+                IdentifiedRef::By(name) => IdentifiedRef::By(interner.intern(name)),
+                IdentifiedRef::System => IdentifiedRef::System,
+            }),
+            properties: bump.alloc_slice_fill_iter(
+                d.properties
+                    .into_iter()
+                    .map(|p| ArenaProperty::alloc_interned(interner, p)),
+            ),
+        }
+    }));
+    let imports = bump.alloc_slice_fill_iter(parsed.imports.iter().map(|i| ImportRef {
+        namespace: interner.intern(i.namespace),
+        version: interner.intern(i.version),
+        name: interner.intern(i.name),
+    }));
+
+    Ok((
+        remains,
+        ArenaModel {
+            // License headers are usually the same in every model
+            header: parsed.header.map(|h| interner.intern(h)),
+            namespace: interner.intern(parsed.namespace),
+            version: parsed.version.map(|v| interner.intern(v)),
+            imports,
+            declarations,
+            options: parsed.options,
+        },
+    ))
+}
+
+/// Copies the input into the arena and parses a model out of it
+pub fn model<'b>(bump: &'b Bump, input: &str) -> CResult<&'b str, ArenaModel<'b>> {
+    model_with(bump, input, &ParserOptions::default())
+}
+
+/// Like `model`, with options as `borrowed::model_with` takes them
+pub fn model_with<'b>(
+    bump: &'b Bump,
+    input: &str,
+    options: &ParserOptions,
+) -> CResult<&'b str, ArenaModel<'b>> {
+    let input = bump.alloc_str(input);
+    let (remains, parsed) = borrowed::model_with(input, options)?;
+
+    let declarations = bump.alloc_slice_fill_iter(parsed.declarations.into_iter().map(|d| {
+        ArenaDeclaration {
+            decorators: bump.alloc_slice_copy(&d.decorators),
+            kind: d.kind,
+            is_abstract: d.is_abstract,
+            name: d.name,
+            super_type: d.super_type,
+            identified: d.identified,
+            properties: bump.alloc_slice_fill_iter(
+                d.properties
+                    .into_iter()
+                    .map(|p| ArenaProperty::alloc(bump, p)),
+            ),
+        }
+    }));
+
+    Ok((
+        remains,
+        ArenaModel {
+            header: parsed.header,
+            namespace: parsed.namespace,
+            version: parsed.version,
+            imports: bump.alloc_slice_copy(&parsed.imports),
+            declarations,
+            options: parsed.options,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_model() {
+        let bump = bumpalo::Bump::new();
+        let inputs = [
+            "namespace org.acme@1.0.0
+            import org.places@1.0.0.Address
+            import org.people@1.0.0.{Employee, Manager as Boss}
+
+            @Term(\"Person\")
+            concept Person {
+              @deprecated
+              o String name regex=/abc.*/ length=[1, 10]
+              o Address[] addresses optional
+            }
+            ",
+            "namespace org.places@1.0.0
+
+            concept Address {
+              o String street
+              o Integer number range=[1,]
+            }
+            ",
+        ];
+
+        let models: Vec<_> = inputs
+            .iter()
+            .map(|input| super::model(&bump, input).unwrap().1)
+            .collect();
+
+        assert_eq!(
+            models[0]
+                .declaration("Person")
+                .and_then(|d| d.property("name"))
+                .map(|p| p.validators),
+            Some(&["regex=/abc.*/", "length=[1, 10]"][..]),
+            "Should keep validators in the arena"
+        );
+        assert_eq!(
+            models[0].declarations[0].decorators,
+            &["@Term(\"Person\")"],
+            "Should keep decorators in the arena"
+        );
+        for (model, input) in models.iter().zip(inputs) {
+            assert_eq!(
+                model.into_owned(),
+                crate::parser::model(input).unwrap().1,
+                "Should convert into the same model the owned parser builds"
+            );
+        }
+    }
+
+    #[test]
+    fn test_model_interned() {
+        let bump = bumpalo::Bump::new();
+        let interner = super::Interner::new(&bump);
+        let inputs = [
+            "namespace org.places@1.0.0
+
+            concept Address {
+              @deprecated
+              o String name default=\"home\" length=[1, 10]
+            }
+            ",
+            "namespace org.acme@1.0.0
+            import org.places@1.0.0.Address
+
+            concept Person {
+              @deprecated
+              o String name
+              o Address address
+            }
+            ",
+        ];
+        let models: Vec<_> = inputs
+            .iter()
+            .map(|input| super::model_interned(&interner, input).unwrap().1)
+            .collect();
+
+        let address = models[0].declarations[0].properties[0];
+        let person = models[1].declarations[0].properties[0];
+        assert!(
+            std::ptr::eq(address.name, person.name)
+                && std::ptr::eq(models[0].namespace, models[1].imports[0].namespace),
+            "Should share repeated identifiers"
+        );
+        assert_eq!(address.default_value, Some("\"home\""));
+        assert_eq!(address.validators, &["length=[1, 10]"]);
+        assert!(
+            std::ptr::eq(address.decorators[0], person.decorators[0]),
+            "Should share repeated decorators"
+        );
+        assert_eq!(interner.len(), 9);
+        for (model, input) in models.iter().zip(inputs) {
+            assert_eq!(
+                model.into_owned(),
+                crate::parser::model(input).unwrap().1,
+                "Should convert into the same model the owned parser builds"
+            );
+        }
+    }
+}
@@ -0,0 +1,846 @@
+//! A model that borrows its identifiers from the parsed input instead of allocating them.
+//! Parsing large model repositories this way only allocates the lists holding declarations
+//! and properties. Meta properties are kept as they are written, and `into_owned` parses
+//! them into the owned AST when needed, and so are decorators.
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::char,
+    combinator::{all_consuming, consumed, opt, recognize, verify},
+    error::{context, ErrorKind},
+    multi::{fold_many0, fold_many_m_n, many0},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    Err as NomErr, Parser,
+};
+
+use super::{
+    comment::{header, trivia0},
+    common::{
+        boolean_value,
+        datetime::datetime_value,
+        keywords::{self, peek_keyword, Keyword},
+        numeric,
+        string::string_value,
+        token,
+    },
+    declaration::{
+        self, declaration_with, map_entry_type, type_reference, Declaration, DeclarationKind,
+        Identified, TypeReference,
+    },
+    decorator::{decorator, Decorator},
+    error::{BuildError, CError, CErrorKind},
+    import::{imported_types, types_import, Import},
+    namespace::{namespace_name, unversioned_namespaces, Namespace},
+    options::{self, with_options, ParserOptions},
+    property::{
+        double_property::double_domain_validator,
+        enum_property::enum_property,
+        integer_property::integer_domain_validator,
+        internal::{generic_property, relationship_property, PrimitiveType},
+        long_property::long_domain_validator,
+        string_property::{string_length_validator, string_regex_validator},
+    },
+    scan::{multispace0, space0, space1},
+    version::{pre_release, version_identifier, version_number, SemanticVersion},
+    CResult, Model,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ModelRef<'a> {
+    pub header: Option<&'a str>,
+    pub namespace: &'a str,
+    /// `None` for namespaces written without a version, with `LanguageVersion::V2`
+    pub version: Option<&'a str>,
+    pub imports: Vec<ImportRef<'a>>,
+    pub declarations: Vec<DeclarationRef<'a>>,
+    /// Options the model was parsed with, `into_owned` parses the borrowed parts with them
+    pub options: ParserOptions,
+}
+
+impl<'a> ModelRef<'a> {
+    pub fn declaration(&self, name: &str) -> Option<&DeclarationRef<'a>> {
+        self.declarations.iter().find(|d| d.name == name)
+    }
+
+    /// Parses the borrowed parts into an owned `Model`
+    pub fn into_owned(&self) -> Model {
+        with_options(&self.options, || self.parse_owned())
+    }
+
+    fn parse_owned(&self) -> Model {
+        let mut model = Model {
+            header: self.header.map(String::from),
+            namespace: Namespace::from((
+                self.namespace.to_string(),
+                self.version.map(parse_version),
+            )),
+            imports: self.imports.iter().map(ImportRef::into_owned).collect(),
+            declarations: Vec::with_capacity(self.declarations.len()),
+            index: Default::default(),
+        };
+        for declaration in self.declarations.iter() {
+            model.add_declaration(declaration.into_owned());
+        }
+        model
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ImportRef<'a> {
+    pub namespace: &'a str,
+    pub version: &'a str,
+    /// What is imported as it is written: a type name, `*` for every type of the namespace,
+    /// or a braced list of types like `{Address, Person as Owner}`
+    pub name: &'a str,
+}
+
+impl<'a> ImportRef<'a> {
+    pub fn into_owned(&self) -> Import {
+        let namespace = Namespace::new(self.namespace, parse_version(self.version));
+        match self.name {
+            "*" => Import::All {
+                namespace,
+                uri: None,
+            },
+            braced if braced.starts_with('{') => match all_consuming(imported_types)(braced) {
+                Ok((_, types)) => types_import(namespace, &types),
+                Err(_) => unreachable!(),
+            },
+            name => Import::Type {
+                namespace,
+                name: name.to_string(),
+                uri: None,
+            },
+        }
+    }
+}
+
+/// Same as `declaration::Identified`, with the name of the identifying property borrowed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IdentifiedRef<'a> {
+    System,
+    By(&'a str),
+}
+
+impl<'a> IdentifiedRef<'a> {
+    /// Name of the identifying property, none for the system identifier
+    pub fn name(self) -> Option<&'a str> {
+        match self {
+            IdentifiedRef::System => None,
+            IdentifiedRef::By(name) => Some(name),
+        }
+    }
+
+    pub fn into_owned(self) -> Identified {
+        match self {
+            IdentifiedRef::System => Identified::System,
+            IdentifiedRef::By(name) => Identified::By {
+                name: name.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeclarationRef<'a> {
+    /// Decorators as they are written, like `@Term("Person")`
+    pub decorators: Vec<&'a str>,
+    pub kind: DeclarationKind,
+    pub is_abstract: bool,
+    pub name: &'a str,
+    /// Super type as it is written
+    pub super_type: Option<&'a str>,
+    pub identified: Option<IdentifiedRef<'a>>,
+    pub properties: Vec<PropertyRef<'a>>,
+}
+
+impl<'a> DeclarationRef<'a> {
+    pub fn property(&self, name: &str) -> Option<&PropertyRef<'a>> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Parses the borrowed parts with the options of the running parse, `ModelRef::into_owned`
+    /// uses those the model was parsed with
+    pub fn into_owned(&self) -> Declaration {
+        let mut declaration = Declaration::new(
+            self.kind,
+            self.name,
+            self.properties
+                .iter()
+                .map(PropertyRef::into_owned)
+                .collect(),
+        );
+        declaration.is_abstract = self.is_abstract;
+        declaration.super_type = self.super_type.and_then(parse_type_reference);
+        declaration.identified = self.identified.map(IdentifiedRef::into_owned);
+        declaration.decorators = parse_decorators(&self.decorators);
+        declaration
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PropertyRef<'a> {
+    /// Decorators as they are written, they aren't part of the source
+    pub decorators: Vec<&'a str>,
+    pub type_name: &'a str,
+    pub name: &'a str,
+    pub is_array: bool,
+    pub is_optional: bool,
+    /// Written with `-->`
+    pub is_relationship: bool,
+    /// Default value as it is written, strings keep their quotes and escapes
+    pub default_value: Option<&'a str>,
+    /// Validators as they are written, like `regex=/abc/` or `range=[1, 10]`
+    pub validators: Vec<&'a str>,
+    /// The whole property definition
+    pub source: &'a str,
+}
+
+impl<'a> PropertyRef<'a> {
+    pub fn into_owned(&self) -> declaration::Property {
+        let mut property = property_into_owned(self.source, self.type_name, self.name);
+        *property.decorators_mut() = parse_decorators(&self.decorators);
+        property
+    }
+}
+
+/// Parses the source of a borrowed property, enum value or map entry with the owned property
+/// parsers. Enum values have no type, map entries have no name in their source.
+pub(super) fn property_into_owned(
+    source: &str,
+    type_name: &str,
+    name: &str,
+) -> declaration::Property {
+    // Same grammar parsed the source, so it is known to be valid
+    let parsed = match type_name {
+        "" => enum_property.map(declaration::Property::from).parse(source),
+        _ => alt((declaration::any_proeprty, declaration::map_entry(name))).parse(source),
+    };
+    match parsed {
+        Ok((_, property)) => property,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Decorators are kept as they were accepted by the parser
+pub(super) fn parse_decorators(written: &[&str]) -> Vec<Decorator> {
+    written
+        .iter()
+        .map(|written| match all_consuming(decorator)(written) {
+            Ok((_, decorator)) => decorator,
+            Err(_) => unreachable!(),
+        })
+        .collect()
+}
+
+fn parse_version(version: &str) -> SemanticVersion {
+    match version_identifier(version) {
+        Ok((_, version)) => version,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Super types are kept as they were accepted by the parser
+pub(crate) fn parse_type_reference(written: &str) -> Option<TypeReference> {
+    all_consuming(type_reference)(written)
+        .map(|(_, super_type)| super_type)
+        .ok()
+}
+
+enum MetaRef<'a> {
+    Default(&'a str),
+    Validator(&'a str),
+    Optional,
+}
+
+fn default_ref<'a, O, P>(value: P) -> impl Parser<&'a str, MetaRef<'a>, CError<&'a str>>
+where
+    P: Parser<&'a str, O, CError<&'a str>>,
+{
+    preceded(
+        tuple((keywords::default, space0, char('='), space0)),
+        recognize(value),
+    )
+    .map(MetaRef::Default)
+}
+
+fn optional_ref<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    keywords::optional.map(|_| MetaRef::Optional).parse(input)
+}
+
+fn string_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Length => recognize(string_length_validator)
+            .map(MetaRef::Validator)
+            .parse(input),
+        Keyword::Regex => recognize(string_regex_validator)
+            .map(MetaRef::Validator)
+            .parse(input),
+        Keyword::Default => default_ref(string_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+fn boolean_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Default => default_ref(boolean_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+fn integer_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Range => recognize(integer_domain_validator)
+            .map(MetaRef::Validator)
+            .parse(input),
+        Keyword::Default => default_ref(numeric::integer_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+fn long_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Range => recognize(long_domain_validator)
+            .map(MetaRef::Validator)
+            .parse(input),
+        Keyword::Default => default_ref(numeric::long_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+fn double_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Range => recognize(double_domain_validator)
+            .map(MetaRef::Validator)
+            .parse(input),
+        Keyword::Default => default_ref(numeric::double_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+fn datetime_meta<'a>(input: &'a str) -> CResult<&'a str, MetaRef<'a>> {
+    match peek_keyword(input)?.1 {
+        Keyword::Default => default_ref(datetime_value).parse(input),
+        _ => optional_ref(input),
+    }
+}
+
+type Head<'a> = (&'a str, &'a str, bool);
+
+type MetaParser<'a> = fn(&'a str) -> CResult<&'a str, MetaRef<'a>>;
+
+/// Parses a property with the given head and meta properties, the meta property parser runs
+/// at most `max_meta` times, the same as the owned property parsers
+fn property_ref<'a, H>(
+    head: H,
+    max_meta: usize,
+    meta: MetaParser<'a>,
+) -> impl Parser<&'a str, PropertyRef<'a>, CError<&'a str>>
+where
+    H: Parser<&'a str, Head<'a>, CError<&'a str>>,
+{
+    let metas = fold_many_m_n(
+        0,
+        max_meta,
+        preceded(space1, meta),
+        || (None, Vec::new(), false),
+        |(mut default_value, mut validators, mut is_optional), meta| {
+            match meta {
+                MetaRef::Default(x) => default_value = Some(x),
+                MetaRef::Validator(x) => validators.push(x),
+                MetaRef::Optional => is_optional = true,
+            }
+            (default_value, validators, is_optional)
+        },
+    );
+
+    consumed(head.and(metas)).map(
+        |(source, ((type_name, name, is_array), (default_value, validators, is_optional)))| {
+            PropertyRef {
+                decorators: Vec::new(),
+                type_name,
+                name,
+                is_array,
+                is_optional,
+                is_relationship: false,
+                default_value,
+                validators,
+                source,
+            }
+        },
+    )
+}
+
+/// Parses `o Type name` or `--> Type name` once, and then the meta properties the type
+/// takes, the same as the owned declaration parser
+fn any_property_ref<'a>(input: &'a str) -> CResult<&'a str, PropertyRef<'a>> {
+    context("ConceptProperty", |input: &'a str| {
+        let (remains, (is_relationship, head)) = alt((
+            generic_property.map(|head| (false, head)),
+            relationship_property.map(|head| (true, head)),
+        ))(input)?;
+        let (max_meta, meta): (usize, MetaParser<'a>) = match PrimitiveType::from_name(head.0) {
+            Some(PrimitiveType::StringPropertyType) => (4, string_meta),
+            Some(PrimitiveType::BooleanPropertyType) => (2, boolean_meta),
+            Some(PrimitiveType::IntegerPropertyType) => (3, integer_meta),
+            Some(PrimitiveType::LongPropertyType) => (3, long_meta),
+            Some(PrimitiveType::DateTimePropertyType) => (2, datetime_meta),
+            Some(PrimitiveType::DoublePropertyType) => (3, double_meta),
+            None => (1, optional_ref),
+        };
+        let parsed_head = move |_: &'a str| -> CResult<&'a str, Head<'a>> { Ok((remains, head)) };
+        property_ref(parsed_head, max_meta, meta)
+            .map(|property| PropertyRef {
+                is_relationship,
+                ..property
+            })
+            .parse(input)
+    })(input)
+}
+
+/// Parses a value of an enum like `o RED`, which has no type
+fn enum_value_ref<'a>(input: &'a str) -> CResult<&'a str, PropertyRef<'a>> {
+    context(
+        "EnumProperty",
+        consumed(preceded(tuple((space0, char('o'), space1)), token)),
+    )
+    .map(|(source, name)| PropertyRef {
+        decorators: Vec::new(),
+        type_name: "",
+        name,
+        is_array: false,
+        is_optional: false,
+        is_relationship: false,
+        default_value: None,
+        validators: Vec::new(),
+        source,
+    })
+    .parse(input)
+}
+
+/// Parses an entry of a map like `o String`, which has no name of its own
+fn map_entry_ref<'a>(name: &'static str) -> impl Fn(&'a str) -> CResult<&'a str, PropertyRef<'a>> {
+    move |input: &'a str| {
+        preceded(space0, consumed(map_entry_type))
+            .map(|(source, (type_name, is_relationship))| PropertyRef {
+                decorators: Vec::new(),
+                type_name,
+                name,
+                is_array: false,
+                is_optional: false,
+                is_relationship,
+                default_value: None,
+                validators: Vec::new(),
+                source,
+            })
+            .parse(input)
+    }
+}
+
+fn version_ref(input: &str) -> CResult<&str, &str> {
+    recognize(pair(version_number, opt(pre_release)))(input)
+}
+
+/// Namespaces leave out the version as the owned parser allows
+fn namespace_ref(input: &str) -> CResult<&str, (&str, Option<&str>)> {
+    context(
+        "NamespaceDefinition",
+        preceded(
+            pair(keywords::namespace, space1),
+            verify(
+                pair(namespace_name, opt(preceded(tag("@"), version_ref))),
+                |(_, version)| version.is_some() || unversioned_namespaces(),
+            ),
+        ),
+    )(input)
+}
+
+/// Splits the version and the type name at the last dot, since pre-release tags can have dots.
+/// Wildcards and braced lists of types follow the dot ending the version.
+fn import_ref<'a>(input: &'a str) -> CResult<&'a str, ImportRef<'a>> {
+    let (remains, (_, _, namespace, _, versioned_name)) = context(
+        "Import",
+        tuple((
+            keywords::import,
+            space1,
+            namespace_name,
+            tag("@"),
+            take_till1(|c: char| c.is_whitespace() || matches!(c, '*' | '{')),
+        )),
+    )(input)?;
+
+    let split = match versioned_name.strip_suffix('.') {
+        Some(version) => {
+            let (remains, name) = alt((tag("*"), recognize(imported_types)))(remains)?;
+            Some((remains, version, name))
+        }
+        None => versioned_name
+            .rsplit_once('.')
+            .filter(|(_, name)| all_consuming(token)(name).is_ok())
+            .map(|(version, name)| (remains, version, name)),
+    };
+    match split {
+        Some((remains, version, name)) if all_consuming(version_ref)(version).is_ok() => Ok((
+            remains,
+            ImportRef {
+                namespace,
+                version,
+                name,
+            },
+        )),
+        _ => Err(NomErr::Error(CError {
+            code: CErrorKind::NomError(ErrorKind::Verify),
+            input,
+        })),
+    }
+}
+
+/// Parses decorators and the whitespace after each of them, keeping them as they are written
+fn decorators_ref(input: &str) -> CResult<&str, Vec<&str>> {
+    many0(terminated(recognize(decorator), multispace0))(input)
+}
+
+/// Moves the decorators written above a property onto it
+fn decorated<'a, P>(property: P) -> impl Parser<&'a str, PropertyRef<'a>, CError<&'a str>>
+where
+    P: Parser<&'a str, PropertyRef<'a>, CError<&'a str>>,
+{
+    pair(decorators_ref, property).map(|(decorators, property)| PropertyRef {
+        decorators,
+        ..property
+    })
+}
+
+fn declaration_ref<'a>(input: &'a str) -> CResult<&'a str, DeclarationRef<'a>> {
+    // Decorators are taken before the shared head parser, which would parse them into owned
+    // decorators
+    let declaration = pair(
+        decorators_ref,
+        declaration_with(
+            decorated(any_property_ref),
+            decorated(enum_value_ref),
+            |name, input| decorated(map_entry_ref(name)).parse(input),
+        ),
+    );
+    context(
+        "Declaration",
+        verify(declaration, |(_, (head, properties))| {
+            head.super_type.is_some()
+                || head
+                    .identified
+                    .and_then(IdentifiedRef::name)
+                    .is_none_or(|identified| {
+                        properties
+                            .iter()
+                            .any(|p| p.name == identified && p.type_name == "String")
+                    })
+        })
+        .map(|(decorators, (head, properties))| DeclarationRef {
+            decorators,
+            kind: head.kind,
+            is_abstract: head.is_abstract,
+            name: head.name,
+            super_type: head.super_type.map(|(written, _)| written),
+            identified: head.identified,
+            properties,
+        }),
+    )(input)
+}
+
+enum DefinitionRef<'a> {
+    Namespace((&'a str, Option<&'a str>)),
+    Import(ImportRef<'a>),
+    Declaration(DeclarationRef<'a>),
+}
+
+/// Parses a model without copying identifiers out of the input
+pub fn model(input: &str) -> CResult<&str, ModelRef<'_>> {
+    model_with(input, &ParserOptions::default())
+}
+
+/// Parses a model like `model`, with the options the parsers read, like `language_version`.
+/// The checks `parse_with` makes on the whole source, like `crlf` and `trailing_input`, are
+/// left to the caller.
+pub fn model_with<'a>(input: &'a str, options: &ParserOptions) -> CResult<&'a str, ModelRef<'a>> {
+    with_options(options, || model_ref(input))
+}
+
+fn model_ref<'a>(input: &'a str) -> CResult<&'a str, ModelRef<'a>> {
+    let definition = alt((
+        namespace_ref.map(DefinitionRef::Namespace),
+        import_ref.map(DefinitionRef::Import),
+        declaration_ref.map(DefinitionRef::Declaration),
+    ));
+    let definitions = fold_many0(
+        delimited(trivia0, definition, trivia0),
+        || (None, Vec::new(), Vec::new()),
+        |(mut namespace, mut imports, mut declarations), item| {
+            match item {
+                DefinitionRef::Namespace(ns) => namespace = Some(ns),
+                DefinitionRef::Import(i) => imports.push(i),
+                DefinitionRef::Declaration(d) => declarations.push(d),
+            }
+            (namespace, imports, declarations)
+        },
+    );
+    let (after_header, header) = opt(header)(input)?;
+    let (remains, (namespace, imports, declarations)) =
+        context("Model", definitions)(after_header)?;
+
+    match namespace {
+        Some((namespace, version)) => Ok((
+            remains,
+            ModelRef {
+                header,
+                namespace,
+                version,
+                imports,
+                declarations,
+                options: options::current(),
+            },
+        )),
+        None => Err(NomErr::Error(CError {
+            code: CErrorKind::InvalidModel(BuildError::MissingNamespace),
+            input,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_model() {
+        let input = "namespace org.acme@1.0.0-beta
+        import org.places@1.0.0-rc.1.Address
+        import org.people@2.0.0.*
+        import org.things@1.2.0.{Car, Boat as Ship}
+
+        concept Person {
+          o String name regex=/abc.*/ length=[1, 10]
+          o String nickname default=\"Jo\\\"e\" optional
+          o Boolean active default=true
+          o Integer age optional range=[0,]
+          o Long id default=3147483647
+          o Double height range=[0.5, 3.0]
+          o DateTime born default=2024-01-04
+          o Address[] addresses optional
+        }
+
+        concept Empty {}
+        ";
+        let (_, borrowed) = super::model(input).unwrap();
+
+        assert_eq!(borrowed.namespace, "org.acme");
+        assert_eq!(borrowed.version, Some("1.0.0-beta"));
+        assert_eq!(
+            borrowed.imports,
+            vec![
+                super::ImportRef {
+                    namespace: "org.places",
+                    version: "1.0.0-rc.1",
+                    name: "Address",
+                },
+                super::ImportRef {
+                    namespace: "org.people",
+                    version: "2.0.0",
+                    name: "*",
+                },
+                super::ImportRef {
+                    namespace: "org.things",
+                    version: "1.2.0",
+                    name: "{Car, Boat as Ship}",
+                },
+            ],
+            "Should split import version and type name at the last dot, and keep wildcards and braced types as written"
+        );
+
+        let person = borrowed.declaration("Person").unwrap();
+        let name = person.property("name").unwrap();
+        assert_eq!(
+            name.validators,
+            vec!["regex=/abc.*/", "length=[1, 10]"],
+            "Should keep validators as they are written"
+        );
+        let nickname = person.property("nickname").unwrap();
+        assert_eq!(nickname.default_value, Some("\"Jo\\\"e\""));
+        assert!(nickname.is_optional);
+        let addresses = person.property("addresses").unwrap();
+        assert_eq!(
+            (
+                addresses.type_name,
+                addresses.is_array,
+                addresses.is_optional
+            ),
+            ("Address", true, true)
+        );
+
+        assert_eq!(
+            borrowed.into_owned(),
+            crate::parser::model(input).unwrap().1,
+            "Should convert into the same model the owned parser builds"
+        );
+    }
+
+    #[test]
+    fn test_unversioned_namespace() {
+        use crate::parser::options::{LanguageVersion, ParserOptions};
+
+        let input = "namespace org.acme\n\nconcept Person {\n  o String name\n}\n";
+        assert!(
+            super::model(input).is_err(),
+            "Should only take versioned namespaces by default"
+        );
+        let v2 = ParserOptions {
+            language_version: LanguageVersion::V2,
+            ..ParserOptions::default()
+        };
+        let (_, borrowed) = super::model_with(input, &v2).unwrap();
+        assert_eq!(borrowed.version, None);
+        assert_eq!(
+            borrowed.into_owned(),
+            crate::parser::parse_with(input, &v2).unwrap(),
+            "Should read unversioned namespaces as the owned parser does"
+        );
+    }
+
+    #[test]
+    fn test_declaration_kinds() {
+        let input = "namespace test@1.0.0
+
+        enum Color {
+          o RED
+          o GREEN
+        }
+
+        asset Car identified by vin {
+          o String vin
+          o Color color
+        }
+
+        event CarSold {
+          o Car car
+          --> Car[] previous optional
+        }
+
+        map Fleet {
+          o DateTime
+          --> Car
+        }
+        ";
+        let (remains, borrowed) = super::model(input).unwrap();
+
+        assert_eq!(remains, "", "Should parse past every kind of declaration");
+        let color = borrowed.declaration("Color").unwrap();
+        assert_eq!(color.kind, super::DeclarationKind::Enum);
+        assert_eq!(
+            color.properties.iter().map(|p| p.name).collect::<Vec<_>>(),
+            vec!["RED", "GREEN"],
+            "Should parse the values of enums"
+        );
+        assert_eq!(
+            borrowed.declaration("Car").map(|d| (d.kind, d.identified)),
+            Some((
+                super::DeclarationKind::Asset,
+                Some(super::IdentifiedRef::By("vin"))
+            )),
+            "Should parse assets with their identifying property"
+        );
+        assert_eq!(
+            borrowed.declaration("CarSold").map(|d| d.kind),
+            Some(super::DeclarationKind::Event),
+        );
+        let previous = borrowed
+            .declaration("CarSold")
+            .and_then(|d| d.property("previous"))
+            .unwrap();
+        assert_eq!(
+            (
+                previous.type_name,
+                previous.is_relationship,
+                previous.is_array,
+                previous.is_optional
+            ),
+            ("Car", true, true, true),
+            "Should parse relationships with array and optional flags"
+        );
+        let fleet = borrowed.declaration("Fleet").unwrap();
+        assert_eq!(
+            fleet
+                .properties
+                .iter()
+                .map(|p| (p.name, p.type_name))
+                .collect::<Vec<_>>(),
+            vec![("key", "DateTime"), ("value", "Car")],
+            "Should parse the key and the value of maps"
+        );
+        assert_eq!(
+            borrowed.into_owned().declarations,
+            crate::parser::model(input).unwrap().1.declarations,
+            "Should convert into the same declarations the owned parser builds"
+        );
+    }
+
+    #[test]
+    fn test_decorators() {
+        let input = "namespace test@1.0.0
+
+        @Term(\"Paint\")
+        enum Color {
+          @deprecated
+          o RED
+        }
+
+        @Term(\"Person\", 1)
+        @Resource
+        concept Person {
+          @Term(\"Full name\")
+          o String name
+        }
+
+        map Owners {
+          @Term(\"Registration\")
+          o String
+          o Person
+        }
+        ";
+        let (remains, borrowed) = super::model(input).unwrap();
+
+        assert_eq!(remains, "");
+        let person = borrowed.declaration("Person").unwrap();
+        assert_eq!(
+            person.decorators,
+            vec!["@Term(\"Person\", 1)", "@Resource"],
+            "Should keep decorators as they are written"
+        );
+        assert_eq!(
+            person.property("name").map(|p| &p.decorators[..]),
+            Some(&["@Term(\"Full name\")"][..])
+        );
+        assert_eq!(
+            borrowed.into_owned(),
+            crate::parser::model(input).unwrap().1,
+            "Should keep the decorators of declarations, properties, enum values and map entries"
+        );
+    }
+
+    #[test]
+    fn test_model_errors() {
+        assert!(
+            super::model("concept Person {}").is_err(),
+            "Should not parse a model without a namespace"
+        );
+        assert!(
+            super::import_ref("import org.acme@1.0.0").is_err(),
+            "Should not parse an import without a type name"
+        );
+        assert_eq!(
+            super::model(
+                "namespace test@1.0.0\n\nconcept Order identified by id {\n  o Long id\n}\n"
+            )
+            .map(|(_, model)| model.declarations.len()),
+            Ok(0),
+            "Should identify by a String property"
+        );
+    }
+}
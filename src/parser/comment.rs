@@ -0,0 +1,93 @@
+//! Comments. The block of comments leading a file is kept as the header of its model, so
+//! license and ownership headers survive printing and formatting. Other comments are skipped
+//! like whitespace.
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until},
+    character::complete::{line_ending, not_line_ending},
+    combinator::{opt, recognize},
+    error::context,
+    multi::{many0_count, many1},
+    sequence::{pair, preceded, terminated, tuple},
+    Parser,
+};
+
+use super::{
+    scan::{multispace0, space0},
+    CResult,
+};
+
+/// `/* ... */`, over any number of lines
+fn block_comment(input: &str) -> CResult<&str, &str> {
+    recognize(tuple((tag("/*"), take_until("*/"), tag("*/"))))(input)
+}
+
+/// `// ...` up to the end of the line
+fn line_comment(input: &str) -> CResult<&str, &str> {
+    recognize(pair(tag("//"), not_line_ending))(input)
+}
+
+/// Whitespace and comments, between definitions and between the properties of a declaration
+pub(crate) fn trivia0(input: &str) -> CResult<&str, &str> {
+    recognize(pair(
+        multispace0,
+        many0_count(terminated(alt((line_comment, block_comment)), multispace0)),
+    ))(input)
+}
+
+/// The end of a line, with the spaces and the `//` comment before it
+pub(crate) fn end_of_line(input: &str) -> CResult<&str, &str> {
+    recognize(tuple((space0, opt(line_comment), line_ending)))(input)
+}
+
+/// Comments leading a file, as written from the first to the last of them
+pub(crate) fn header(input: &str) -> CResult<&str, &str> {
+    context(
+        "Header",
+        preceded(
+            multispace0,
+            recognize(many1(terminated(
+                alt((block_comment, line_comment)),
+                multispace0,
+            ))),
+        ),
+    )
+    .map(str::trim_end)
+    .parse(input)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_header() {
+        let input =
+            "\n/*\n * Licensed under the Apache License\n */\n// Owned by Acme\n\nnamespace";
+        assert_eq!(
+            super::header(input),
+            Ok((
+                "namespace",
+                "/*\n * Licensed under the Apache License\n */\n// Owned by Acme"
+            ))
+        );
+        assert_eq!(super::header("/**//**/x"), Ok(("x", "/**//**/")));
+        assert!(super::header("namespace").is_err());
+        assert!(
+            super::header("/* unterminated").is_err(),
+            "Should need the end of a block comment"
+        );
+    }
+
+    #[test]
+    fn test_trivia() {
+        assert_eq!(
+            super::trivia0("\n  // a comment\n  /* another */ \n  o String name"),
+            Ok(("o String name", "\n  // a comment\n  /* another */ \n  "))
+        );
+        assert_eq!(super::trivia0("concept"), Ok(("concept", "")));
+        assert_eq!(
+            super::end_of_line(" // trailing\n  o"),
+            Ok(("  o", " // trailing\n"))
+        );
+    }
+}
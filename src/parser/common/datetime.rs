@@ -8,13 +8,15 @@ use nom::{
     Parser,
 };
 
+#[cfg(feature = "chrono")]
+use crate::parser::error::{CError, CErrorKind};
 use crate::parser::CResult;
 
-fn year<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn year(input: &str) -> CResult<&str, &str> {
     context("Year", recognize(count(one_of("1234567890"), 4)))(input)
 }
 
-fn month<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn month(input: &str) -> CResult<&str, &str> {
     context(
         "Month",
         recognize(alt((
@@ -24,7 +26,7 @@ fn month<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
     )(input)
 }
 
-fn day<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn day(input: &str) -> CResult<&str, &str> {
     context(
         "Day",
         recognize(alt((
@@ -34,7 +36,7 @@ fn day<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
     )(input)
 }
 
-fn hour<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn hour(input: &str) -> CResult<&str, &str> {
     context(
         "Hour",
         recognize(alt((
@@ -44,28 +46,28 @@ fn hour<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
     )(input)
 }
 
-fn minute<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn minute(input: &str) -> CResult<&str, &str> {
     context(
         "Minute",
         recognize(pair(one_of("012345"), one_of("1234567890"))),
     )(input)
 }
 
-fn second<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn second(input: &str) -> CResult<&str, &str> {
     context(
         "Second",
         recognize(pair(one_of("012345"), one_of("1234567890"))),
     )(input)
 }
 
-fn year_month_day<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn year_month_day(input: &str) -> CResult<&str, &str> {
     context(
         "YearMonthDay",
         recognize(tuple((year, char('-'), month, char('-'), day))),
     )(input)
 }
 
-fn hour_minute_second<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn hour_minute_second(input: &str) -> CResult<&str, &str> {
     context(
         "HourMinuteSecond",
         recognize(tuple((hour, char(':'), minute, char(':'), second))),
@@ -73,7 +75,33 @@ fn hour_minute_second<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
 }
 
 /// As described in the spec https://concerto.accordproject.org/docs/design/specification/model-properties/
-pub(crate) fn datetime_value<'a>(input: &'a str) -> CResult<&'a str, String> {
+/// With the `chrono` feature, values that aren't real dates and times, like `2023-02-30`,
+/// fail with `CErrorKind::InvalidDateTime`.
+pub(crate) fn datetime_value(input: &str) -> CResult<&str, String> {
+    let (rest, value) = datetime_pattern(input)?;
+    #[cfg(feature = "chrono")]
+    if timestamp(&value).is_none() {
+        return Err(nom::Err::Failure(CError {
+            code: CErrorKind::InvalidDateTime(value),
+            input,
+        }));
+    }
+    Ok((rest, value))
+}
+
+/// Timestamp of a DateTime value, dates without a time are midnight UTC
+#[cfg(feature = "chrono")]
+pub(crate) fn timestamp(value: &str) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    match value.len() {
+        10 => chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc().fixed_offset()),
+        _ => chrono::DateTime::parse_from_rfc3339(value).ok(),
+    }
+}
+
+fn datetime_pattern<'a>(input: &'a str) -> CResult<&'a str, String> {
     let ymd = context("YYYY-MM-DD", year_month_day);
     let ymd_hms = context(
         "YYYY-MM-DDTHH:mm:ssZ",
@@ -275,4 +303,30 @@ mod test {
             "Parses YYYY-MM-DDTHH:mm:ss.SSS-HH:mm"
         );
     }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp() {
+        use crate::parser::error::CErrorKind;
+
+        assert_eq!(
+            super::timestamp("2024-01-04T01:02:03.456-05:15").map(|t| t.to_rfc3339()),
+            Some(String::from("2024-01-04T01:02:03.456-05:15")),
+            "Should keep the offset"
+        );
+        assert_eq!(
+            super::timestamp("2024-02-29").map(|t| t.to_rfc3339()),
+            Some(String::from("2024-02-29T00:00:00+00:00")),
+            "Should read dates as midnight UTC"
+        );
+        assert_eq!(
+            super::datetime_value("2023-02-29").map_err(|e| match e {
+                nom::Err::Failure(e) => e.code,
+                _ => unreachable!(),
+            }),
+            Err(CErrorKind::InvalidDateTime(String::from("2023-02-29"))),
+            "Should reject dates that aren't in the calendar"
+        );
+        assert!(super::datetime_value("2024-04-31T00:00:00Z").is_err());
+    }
 }
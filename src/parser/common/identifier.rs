@@ -0,0 +1,64 @@
+//! Characters of identifiers, as Unicode defines them for programming languages, and their
+//! normal form. Names that look the same can be written with different code points, like
+//! `é` precomposed or as `e` and a combining accent, so parsed names are kept in NFC.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Letters and `_` start an identifier
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+/// Letters, digits, `_` and combining marks continue an identifier
+pub(crate) fn is_identifier_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// The NFC form of a name, borrowed when it is already normalized, as ASCII names are
+pub fn nfc(name: &str) -> Cow<'_, str> {
+    match is_nfc_quick(name.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(name),
+        _ => Cow::Owned(name.nfc().collect()),
+    }
+}
+
+/// Replaces a name with its NFC form, if it isn't in it already
+pub(crate) fn normalize(name: &mut String) {
+    if let Cow::Owned(normalized) = nfc(name) {
+        *name = normalized;
+    }
+}
+
+/// Two names that are different but could be mistaken for one another, like `Paul` written
+/// with the Cyrillic `Р`, by the confusable skeletons of Unicode's security mechanisms
+pub fn is_confusable(a: &str, b: &str) -> bool {
+    a != b && unicode_security::skeleton(a).eq(unicode_security::skeleton(b))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_identifier() {
+        assert!(super::is_identifier_start('é'));
+        assert!(super::is_identifier_start('名'));
+        assert!(super::is_identifier_start('_'));
+        assert!(!super::is_identifier_start('1'));
+        assert!(super::is_identifier_continue('1'));
+        assert!(
+            super::is_identifier_continue('\u{301}'),
+            "Should continue with combining marks"
+        );
+        assert!(!super::is_identifier_continue('-'));
+
+        assert_eq!(super::nfc("Cafe\u{301}"), "Café");
+        assert!(matches!(
+            super::nfc("Person"),
+            std::borrow::Cow::Borrowed(_)
+        ));
+        assert!(super::is_confusable("Paul", "\u{420}aul"));
+        assert!(!super::is_confusable("Paul", "Paul"));
+        assert!(!super::is_confusable("Paul", "Saul"));
+    }
+}
@@ -1,35 +1,223 @@
-use nom::{bytes::complete::tag, error::context};
+use nom::{
+    bytes::complete::take_while1,
+    error::{context, ErrorKind, ParseError},
+    Err as NomErr,
+};
 
-use crate::parser::CResult;
+use crate::parser::{error::CError, options, CResult};
 
-pub fn default<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("DefaultKeyword", tag("default"))(input)
+/// Words of the grammar, recognized by scanning a word once and looking it up
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Keyword {
+    Namespace,
+    Import,
+    Concept,
+    Enum,
+    Asset,
+    Event,
+    Map,
+    Extends,
+    Identified,
+    By,
+    Abstract,
+    Optional,
+    Default,
+    Regex,
+    Length,
+    Range,
 }
 
-pub fn length<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("LengthKeyword", tag("length"))(input)
+impl Keyword {
+    const ALL: [Keyword; 16] = [
+        Self::Namespace,
+        Self::Import,
+        Self::Concept,
+        Self::Enum,
+        Self::Asset,
+        Self::Event,
+        Self::Map,
+        Self::Extends,
+        Self::Identified,
+        Self::By,
+        Self::Abstract,
+        Self::Optional,
+        Self::Default,
+        Self::Regex,
+        Self::Length,
+        Self::Range,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Namespace => "namespace",
+            Self::Import => "import",
+            Self::Concept => "concept",
+            Self::Enum => "enum",
+            Self::Asset => "asset",
+            Self::Event => "event",
+            Self::Map => "map",
+            Self::Extends => "extends",
+            Self::Identified => "identified",
+            Self::By => "by",
+            Self::Abstract => "abstract",
+            Self::Optional => "optional",
+            Self::Default => "default",
+            Self::Regex => "regex",
+            Self::Length => "length",
+            Self::Range => "range",
+        }
+    }
+
+    /// The keyword a word spells, in any case if the parser options allow it
+    pub fn lookup(word: &str) -> Option<Self> {
+        let exact = match word {
+            "namespace" => Some(Self::Namespace),
+            "import" => Some(Self::Import),
+            "concept" => Some(Self::Concept),
+            "enum" => Some(Self::Enum),
+            "asset" => Some(Self::Asset),
+            "event" => Some(Self::Event),
+            "map" => Some(Self::Map),
+            "extends" => Some(Self::Extends),
+            "identified" => Some(Self::Identified),
+            "by" => Some(Self::By),
+            "abstract" => Some(Self::Abstract),
+            "optional" => Some(Self::Optional),
+            "default" => Some(Self::Default),
+            "regex" => Some(Self::Regex),
+            "length" => Some(Self::Length),
+            "range" => Some(Self::Range),
+            _ => None,
+        };
+        match exact {
+            None if options::current().case_insensitive_keywords => Self::ALL
+                .into_iter()
+                .find(|k| k.as_str().eq_ignore_ascii_case(word)),
+            found => found,
+        }
+    }
+}
+
+/// Scans the word at the start of the input and looks it up
+pub fn any_keyword(input: &str) -> CResult<&str, Keyword> {
+    let (remains, word) = take_while1(|c: char| c.is_ascii_alphabetic())(input)?;
+    match Keyword::lookup(word) {
+        Some(keyword) => Ok((remains, keyword)),
+        None => Err(NomErr::Error(CError::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        ))),
+    }
+}
+
+/// The keyword at the start of the input, without consuming it. Meta property parsers
+/// dispatch on it instead of trying each meta property in turn.
+pub(crate) fn peek_keyword(input: &str) -> CResult<&str, Keyword> {
+    any_keyword(input).map(|(_, keyword)| (input, keyword))
+}
+
+/// Matches a keyword, in any case if the parser options allow it
+fn keyword<'a>(expected: Keyword) -> impl Fn(&'a str) -> CResult<&'a str, &'a str> {
+    move |input: &'a str| match any_keyword(input) {
+        Ok((remains, found)) if found == expected => {
+            Ok((remains, &input[..input.len() - remains.len()]))
+        }
+        _ => Err(NomErr::Error(CError::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        ))),
+    }
+}
+
+pub fn default(input: &str) -> CResult<&str, &str> {
+    context("DefaultKeyword", keyword(Keyword::Default))(input)
+}
+
+pub fn length(input: &str) -> CResult<&str, &str> {
+    context("LengthKeyword", keyword(Keyword::Length))(input)
+}
+
+pub fn range(input: &str) -> CResult<&str, &str> {
+    context("RangeKeyword", keyword(Keyword::Range))(input)
+}
+
+pub fn regex(input: &str) -> CResult<&str, &str> {
+    context("RegexKeyword", keyword(Keyword::Regex))(input)
 }
 
-pub fn range<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("RangeKeyword", tag("range"))(input)
+pub fn namespace(input: &str) -> CResult<&str, &str> {
+    context("NamespaceKeyword", keyword(Keyword::Namespace))(input)
 }
 
-pub fn regex<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("RegexKeyword", tag("regex"))(input)
+pub fn import(input: &str) -> CResult<&str, &str> {
+    context("ImportKeyword", keyword(Keyword::Import))(input)
 }
 
-pub fn namespace<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("NamespaceKeyword", tag("namespace"))(input)
+pub fn optional(input: &str) -> CResult<&str, &str> {
+    context("OptionalKeyword", keyword(Keyword::Optional))(input)
 }
 
-pub fn optional<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("OptionalKeyword", tag("optional"))(input)
+pub fn concept(input: &str) -> CResult<&str, &str> {
+    context("ConceptKeyword", keyword(Keyword::Concept))(input)
 }
 
-pub fn concept<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("ConceptKeyword", tag("concept"))(input)
+pub fn enumeration(input: &str) -> CResult<&str, &str> {
+    context("EnumKeyword", keyword(Keyword::Enum))(input)
 }
 
-pub fn abstrakt<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("AbstractKeyword", tag("abstract"))(input)
+pub fn asset(input: &str) -> CResult<&str, &str> {
+    context("AssetKeyword", keyword(Keyword::Asset))(input)
+}
+
+pub fn event(input: &str) -> CResult<&str, &str> {
+    context("EventKeyword", keyword(Keyword::Event))(input)
+}
+
+pub fn map(input: &str) -> CResult<&str, &str> {
+    context("MapKeyword", keyword(Keyword::Map))(input)
+}
+
+pub fn extends(input: &str) -> CResult<&str, &str> {
+    context("ExtendsKeyword", keyword(Keyword::Extends))(input)
+}
+
+pub fn identified(input: &str) -> CResult<&str, &str> {
+    context("IdentifiedKeyword", keyword(Keyword::Identified))(input)
+}
+
+pub fn by(input: &str) -> CResult<&str, &str> {
+    context("ByKeyword", keyword(Keyword::By))(input)
+}
+
+pub fn abstrakt(input: &str) -> CResult<&str, &str> {
+    context("AbstractKeyword", keyword(Keyword::Abstract))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Keyword;
+    use crate::parser::options::{self, ParserOptions};
+
+    #[test]
+    fn test_any_keyword() {
+        assert_eq!(
+            super::any_keyword("length=[1,2]"),
+            Ok(("=[1,2]", Keyword::Length))
+        );
+        assert!(
+            super::any_keyword("optionally").is_err(),
+            "Should only match whole words"
+        );
+        assert!(super::any_keyword("OPTIONAL").is_err());
+        assert_eq!(super::peek_keyword("range"), Ok(("range", Keyword::Range)));
+
+        options::with_options(&ParserOptions::permissive(), || {
+            assert_eq!(
+                super::any_keyword("OPTIONAL"),
+                Ok(("", Keyword::Optional)),
+                "Should look words up in any case when the options allow it"
+            );
+            assert_eq!(super::optional("Optional"), Ok(("", "Optional")));
+        });
+    }
 }
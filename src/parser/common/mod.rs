@@ -1,30 +1,25 @@
-use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::{alpha1, alphanumeric0},
-    combinator::{recognize, value},
-    error::context,
-    sequence::pair,
-};
+use nom::{branch::alt, bytes::complete::tag, combinator::value, error::context};
 
 pub(crate) mod datetime;
+pub mod identifier;
 pub(crate) mod keywords;
 pub(crate) mod numeric;
 pub(crate) mod string;
 
-use crate::parser::CResult;
+use crate::parser::{scan::identifier, CResult};
 
 /// A `token` starts with a letter and includes alphanumerical characters
-pub(crate) fn boolean_value<'a>(input: &'a str) -> CResult<&'a str, bool> {
+pub(crate) fn boolean_value(input: &str) -> CResult<&str, bool> {
     context(
         "Boolean",
         alt((value(true, tag("true")), value(false, tag("false")))),
     )(input)
 }
 
-/// A `token` starts with a letter and includes alphanumerical characters
-pub(crate) fn token<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
-    context("Token", recognize(pair(alpha1, alphanumeric0)))(input)
+/// A `token` starts with a letter or `_` and continues with letters, digits, `_` and
+/// combining marks, in any script
+pub(crate) fn token(input: &str) -> CResult<&str, &str> {
+    context("Token", identifier)(input)
 }
 
 #[cfg(test)]
@@ -45,6 +40,11 @@ mod test {
             super::token("1foo").is_err(),
             "Should not parse token starting with number"
         );
+        assert_eq!(
+            super::token("Straße_2 x"),
+            Ok((" x", "Straße_2")),
+            "Should parse letters of other scripts and underscores"
+        );
     }
 
     #[test]
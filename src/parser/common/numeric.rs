@@ -2,17 +2,25 @@ use nom::{
     branch::alt,
     bytes::complete::tag_no_case,
     character::complete::{char, digit1, one_of},
-    combinator::{map_res, opt, recognize},
+    combinator::{opt, recognize},
     error::{context, ErrorKind, ParseError},
     sequence::{pair, preceded, tuple},
     Err as NomErr,
 };
-use std::str::FromStr;
+use std::{
+    num::{IntErrorKind, ParseIntError},
+    str::FromStr,
+};
 
-use crate::parser::CResult;
+use crate::parser::{
+    error::{CError, CErrorKind},
+    options::{self, NumericOverflow},
+    property::long_property::LongValue,
+    CResult,
+};
 
 /// Parse an optional sign followed by a number of digits.
-pub(crate) fn positive_decimal_value<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn positive_decimal_value(input: &str) -> CResult<&str, &str> {
     context(
         "PositiveDecimal",
         alt((
@@ -26,19 +34,19 @@ pub(crate) fn positive_decimal_value<'a>(input: &'a str) -> CResult<&'a str, &'a
 }
 
 /// Parse an optional sign followed by a number of digits.
-pub(crate) fn negative_decimal_value<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn negative_decimal_value(input: &str) -> CResult<&str, &str> {
     context("NegativeDecimal", recognize(pair(char('-'), digit1)))(input)
 }
 
 /// Parse an optional sign followed by a number of digits.
-pub(crate) fn decimal_value<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn decimal_value(input: &str) -> CResult<&str, &str> {
     context(
         "Decimal",
         alt((negative_decimal_value, positive_decimal_value)),
     )(input)
 }
 
-fn floating_point_value<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn floating_point_value(input: &str) -> CResult<&str, &str> {
     // In addition to the recipe in nom
     // https://doc.rust-lang.org/std/primitive.f64.html#impl-FromStr-for-f64
     context(
@@ -78,18 +86,44 @@ fn floating_point_value<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
     )(input)
 }
 
-/// Parse a decimal guarantied to be positive, into i32
-pub(crate) fn positive_integer_value<'a>(input: &'a str) -> CResult<&'a str, i32> {
-    let maybe_i32 = map_res(positive_decimal_value, |s: &str| i32::from_str_radix(s, 10))(input);
+/// Parses the digits recognized by `digits` into `T`, a number outside of `T` is handled as
+/// the options of the running parse say
+fn integral<'a, T>(
+    input: &'a str,
+    digits: fn(&'a str) -> CResult<&'a str, &'a str>,
+    type_name: &'static str,
+    (min, max): (T, T),
+) -> CResult<&'a str, T>
+where
+    T: FromStr<Err = ParseIntError>,
+{
+    let digit_error = || NomErr::Error(ParseError::from_error_kind(input, ErrorKind::Digit));
+    let (rest, literal) = digits(input).map_err(|_| digit_error())?;
 
-    let res: CResult<&'a str, i32> = match maybe_i32 {
-        Ok((rest, parsed)) => Ok((rest, parsed)),
-        _ => Err(NomErr::Error(ParseError::from_error_kind(
-            input,
-            ErrorKind::Digit,
-        ))),
-    };
-    res
+    match literal.parse::<T>() {
+        Ok(parsed) => Ok((rest, parsed)),
+        Err(e) => match (e.kind(), options::current().numeric_overflow) {
+            (IntErrorKind::PosOverflow, NumericOverflow::Saturate) => Ok((rest, max)),
+            (IntErrorKind::NegOverflow, NumericOverflow::Saturate) => Ok((rest, min)),
+            (IntErrorKind::PosOverflow | IntErrorKind::NegOverflow, NumericOverflow::Reject) => {
+                Err(NomErr::Failure(CError {
+                    code: CErrorKind::NumericOverflow(type_name, literal.to_string()),
+                    input,
+                }))
+            }
+            _ => Err(digit_error()),
+        },
+    }
+}
+
+/// Parse a decimal guarantied to be positive, into i32
+pub(crate) fn positive_integer_value(input: &str) -> CResult<&str, i32> {
+    integral(
+        input,
+        positive_decimal_value,
+        "Integer",
+        (i32::MIN, i32::MAX),
+    )
 }
 
 // /// Parse a decimal guarantied to be negative, into i32
@@ -107,45 +141,39 @@ pub(crate) fn positive_integer_value<'a>(input: &'a str) -> CResult<&'a str, i32
 // }
 
 /// Parse a decimal into i32
-pub(crate) fn integer_value<'a>(input: &'a str) -> CResult<&'a str, i32> {
-    let maybe_i32 = map_res(decimal_value, |s: &str| i32::from_str_radix(s, 10))(input);
-
-    let res: CResult<&'a str, i32> = match maybe_i32 {
-        Ok((rest, parsed)) => Ok((rest, parsed)),
-        _ => Err(NomErr::Error(ParseError::from_error_kind(
-            input,
-            ErrorKind::Digit,
-        ))),
-    };
-    res
+pub(crate) fn integer_value(input: &str) -> CResult<&str, i32> {
+    integral(input, decimal_value, "Integer", (i32::MIN, i32::MAX))
 }
 
-/// Parse a decimal into i64
-pub(crate) fn long_value<'a>(input: &'a str) -> CResult<&'a str, i64> {
-    let maybe_i64 = map_res(decimal_value, |s: &str| i64::from_str_radix(s, 10))(input);
-
-    let res: CResult<&'a str, i64> = match maybe_i64 {
-        Ok((rest, parsed)) => Ok((rest, parsed)),
-        _ => Err(NomErr::Error(ParseError::from_error_kind(
-            input,
-            ErrorKind::Digit,
-        ))),
-    };
-    res
+/// Parse a decimal into a Long, i64 or i128 with the `i128` feature
+pub(crate) fn long_value(input: &str) -> CResult<&str, LongValue> {
+    integral(
+        input,
+        decimal_value,
+        "Long",
+        (LongValue::MIN, LongValue::MAX),
+    )
 }
 
-/// Parse a floating point string into f64
-pub(crate) fn double_value<'a>(input: &'a str) -> CResult<&'a str, f64> {
-    let maybe_i64 = map_res(floating_point_value, |s: &str| f64::from_str(s))(input);
+/// Parse a floating point string into f64. A number written with digits that is too large
+/// for f64 is handled as the options of the running parse say, `inf` and `Infinity` are kept.
+pub(crate) fn double_value(input: &str) -> CResult<&str, f64> {
+    let (rest, literal) = floating_point_value(input)
+        .map_err(|_| NomErr::Error(ParseError::from_error_kind(input, ErrorKind::Digit)))?;
+    let parsed = f64::from_str(literal)
+        .map_err(|_| NomErr::Error(ParseError::from_error_kind(input, ErrorKind::Digit)))?;
 
-    let res: CResult<&'a str, f64> = match maybe_i64 {
-        Ok((rest, parsed)) => Ok((rest, parsed)),
-        _ => Err(NomErr::Error(ParseError::from_error_kind(
+    if parsed.is_finite() || !literal.bytes().any(|b| b.is_ascii_digit()) {
+        return Ok((rest, parsed));
+    }
+    match options::current().numeric_overflow {
+        NumericOverflow::Saturate if parsed > 0.0 => Ok((rest, f64::MAX)),
+        NumericOverflow::Saturate => Ok((rest, f64::MIN)),
+        NumericOverflow::Reject => Err(NomErr::Failure(CError {
+            code: CErrorKind::NumericOverflow("Double", literal.to_string()),
             input,
-            ErrorKind::Digit,
-        ))),
-    };
-    res
+        })),
+    }
 }
 
 #[cfg(test)]
@@ -232,17 +260,17 @@ mod test {
     fn test_integer() {
         assert_eq!(
             super::integer_value("-147483647"),
-            Ok(("", -147483647 as i32)),
+            Ok(("", -147483647_i32)),
             "Should parse negative integer"
         );
         assert_eq!(
             super::integer_value("147483647"),
-            Ok(("", 147483647 as i32)),
+            Ok(("", 147483647_i32)),
             "Should parse positive integer"
         );
         assert_eq!(
             super::integer_value("+147483647"),
-            Ok(("", 147483647 as i32)),
+            Ok(("", 147483647_i32)),
             "Should parse explicitly positive integer"
         );
         assert!(
@@ -263,18 +291,76 @@ mod test {
     fn test_long() {
         assert_eq!(
             super::long_value("-3147483647"),
-            Ok(("", -3147483647 as i64)),
+            Ok(("", -3147483647)),
             "Should parse negative long"
         );
         assert_eq!(
             super::long_value("3147483647"),
-            Ok(("", 3147483647 as i64)),
+            Ok(("", 3147483647)),
             "Should parse positive long"
         );
         assert_eq!(
             super::long_value("+3147483647"),
-            Ok(("", 3147483647 as i64)),
+            Ok(("", 3147483647)),
             "Should parse explicitly positive long"
         );
     }
+
+    #[test]
+    fn test_overflow() {
+        use crate::parser::{
+            error::{CError, CErrorKind},
+            options::{with_options, NumericOverflow, ParserOptions},
+            property::long_property::LongValue,
+        };
+
+        assert_eq!(
+            super::integer_value("3147483647 }"),
+            Err(nom::Err::Failure(CError {
+                code: CErrorKind::NumericOverflow("Integer", String::from("3147483647")),
+                input: "3147483647 }",
+            })),
+            "Should reject integer overflow"
+        );
+        assert!(
+            matches!(
+                super::long_value("-9999999999999999999999999999999999999999"),
+                Err(nom::Err::Failure(CError {
+                    code: CErrorKind::NumericOverflow("Long", _),
+                    ..
+                }))
+            ),
+            "Should reject long overflow"
+        );
+        assert_eq!(
+            super::double_value("1e400]"),
+            Err(nom::Err::Failure(CError {
+                code: CErrorKind::NumericOverflow("Double", String::from("1e400")),
+                input: "1e400]",
+            })),
+            "Should reject double overflow"
+        );
+
+        let saturate = ParserOptions {
+            numeric_overflow: NumericOverflow::Saturate,
+            ..ParserOptions::default()
+        };
+        with_options(&saturate, || {
+            assert_eq!(
+                super::integer_value("-3147483647"),
+                Ok(("", i32::MIN)),
+                "Should saturate negative integer"
+            );
+            assert_eq!(
+                super::long_value("9999999999999999999999999999999999999999"),
+                Ok(("", LongValue::MAX)),
+                "Should saturate positive long"
+            );
+            assert_eq!(
+                super::double_value("-1e400"),
+                Ok(("", f64::MIN)),
+                "Should saturate negative double"
+            );
+        });
+    }
 }
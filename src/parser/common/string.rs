@@ -4,21 +4,24 @@
 /// - Doesn't propagate `FromExternalError` when parsing integers that form a unicode character,
 ///   instead, it converts it to a `ParserError`.
 /// - In addition to double quoted strings, it also accepts and escapes single quotes strings.
+/// - Instead of folding fragments into a new `String`, it scans for the closing delimiter and
+///   borrows strings without escapes from the input.
+use std::borrow::Cow;
+
 use nom::{
     branch::alt,
-    bytes::streaming::{is_not, take_while_m_n},
+    bytes::complete::take_while_m_n,
     character::complete::{char, multispace1},
-    combinator::{map, map_opt, map_res, value, verify},
+    combinator::{map_opt, map_res, value},
     error::{context, ErrorKind, ParseError},
-    multi::fold_many0,
     sequence::{delimited, preceded},
     Err as NomErr,
 };
 
-use crate::parser::CResult;
+use crate::parser::{scan::find_delimiter, CResult};
 
 /// Collects hex digits within u{XXXX}
-fn delimited_hex<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn delimited_hex(input: &str) -> CResult<&str, &str> {
     // Collect all hex digits
     let hex = take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit());
 
@@ -42,18 +45,15 @@ fn u32_value<'a>(input: &'a str) -> CResult<&'a str, u32> {
 }
 
 /// Parses characters that start wuth `u` and followed by 3 to 6 integers
-fn unicode_char<'a>(input: &'a str) -> CResult<&'a str, char> {
+fn unicode_char(input: &str) -> CResult<&str, char> {
     // Convert them back to character, validating unicode character
-    let u32_validate = context(
-        "U32Validate",
-        map_opt(u32_value, |val| std::char::from_u32(val)),
-    );
+    let u32_validate = context("U32Validate", map_opt(u32_value, std::char::from_u32));
 
     context("UnicodeCharacter", u32_validate)(input)
 }
 
 /// Parses escaped characters
-fn escaped_char<'a>(input: &'a str) -> CResult<&'a str, char> {
+fn escaped_char(input: &str) -> CResult<&str, char> {
     context(
         "EscapedCharacter",
         preceded(
@@ -75,146 +75,140 @@ fn escaped_char<'a>(input: &'a str) -> CResult<&'a str, char> {
 }
 
 /// Parse escaped whitespace, trusting the wisdom of the example
-fn escaped_whitespace<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn escaped_whitespace(input: &str) -> CResult<&str, &str> {
     context("EscapedWhitespace", preceded(char('\\'), multispace1))(input)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum StringFragment<'a> {
-    /// Non-escaped chunks of string
-    Literal(&'a str),
-    /// Escaped chars
-    Escaped(char),
-    /// Escaped whitespace
-    EscapedWS,
-}
+/// Parses text between delimiters, unescaping it. The closing delimiter is scanned for first,
+/// text without escapes is borrowed from the input and only text with escapes is copied.
+fn delimited_value<'a>(delimiter: char, input: &'a str) -> CResult<&'a str, Cow<'a, str>> {
+    let (body, _) = char(delimiter)(input)?;
+    // A missing closing delimiter is expected at the end of the input
+    let unterminated = || {
+        NomErr::Error(ParseError::from_error_kind(
+            &body[body.len()..],
+            ErrorKind::Char,
+        ))
+    };
+    let end = find_delimiter(body, delimiter).ok_or_else(unterminated)?;
+    if let Some(rest) = body[end..].strip_prefix(delimiter) {
+        return Ok((rest, Cow::Borrowed(&body[..end])));
+    }
 
-fn single_quoted_string<'a>(input: &'a str) -> CResult<&'a str, String> {
-    let should_be_escaped = context("SingleQuotedStringShouldBeEscaped", is_not("'\\"));
-    let literal = context(
-        "SingleQuotedStringLiteral",
-        verify(should_be_escaped, |s: &str| !s.is_empty()),
-    );
-    let fragment = context(
-        "SingleQuotedStringFragment",
-        alt((
-            map(literal, StringFragment::Literal),
-            map(escaped_char, StringFragment::Escaped),
-            value(StringFragment::EscapedWS, escaped_whitespace),
-        )),
-    );
-    let build_string = context(
-        "BuildSingleQuotedString",
-        fold_many0(fragment, String::new, |mut acc, fragment| {
-            match fragment {
-                StringFragment::Escaped(c) => acc.push(c),
-                StringFragment::EscapedWS => {}
-                StringFragment::Literal(s) => acc.push_str(s),
+    let mut unescaped = String::from(&body[..end]);
+    let mut rest = &body[end..];
+    loop {
+        if let Some(rest) = rest.strip_prefix(delimiter) {
+            return Ok((rest, Cow::Owned(unescaped)));
+        }
+        if rest.starts_with('\\') {
+            rest = match escaped_char(rest) {
+                Ok((after, c)) => {
+                    unescaped.push(c);
+                    after
+                }
+                Err(_) => match escaped_whitespace(rest) {
+                    Ok((after, _)) => after,
+                    Err(_) => {
+                        return Err(NomErr::Error(ParseError::from_error_kind(
+                            rest,
+                            ErrorKind::Char,
+                        )))
+                    }
+                },
             };
-            acc
-        }),
-    );
-
-    context(
-        "SingleQuotedString",
-        delimited(char('\''), build_string, char('\'')),
-    )(input)
+            continue;
+        }
+        let end = find_delimiter(rest, delimiter).ok_or_else(unterminated)?;
+        unescaped.push_str(&rest[..end]);
+        rest = &rest[end..];
+    }
 }
 
-fn double_quoted_string<'a>(input: &'a str) -> CResult<&'a str, String> {
-    let should_be_escaped = context("DoubleQuotedStringShouldBeEscaped", is_not("\"\\"));
-    let literal = context(
-        "DoubleQuotedStringLiteral",
-        verify(should_be_escaped, |s: &str| !s.is_empty()),
-    );
-    let fragment = context(
-        "DoubleQuotedStringFragment",
-        alt((
-            map(literal, StringFragment::Literal),
-            map(escaped_char, StringFragment::Escaped),
-            value(StringFragment::EscapedWS, escaped_whitespace),
-        )),
-    );
-    let build_string = context(
-        "BuildDoubleQuotedString",
-        fold_many0(fragment, String::new, |mut acc, fragment| {
-            match fragment {
-                StringFragment::Escaped(c) => acc.push(c),
-                StringFragment::EscapedWS => {}
-                StringFragment::Literal(s) => acc.push_str(s),
-            };
-            acc
-        }),
-    );
+fn single_quoted_string<'a>(input: &'a str) -> CResult<&'a str, Cow<'a, str>> {
+    context("SingleQuotedString", |input| delimited_value('\'', input))(input)
+}
 
-    context(
-        "DoubleQuotedString",
-        delimited(char('"'), build_string, char('"')),
-    )(input)
+fn double_quoted_string<'a>(input: &'a str) -> CResult<&'a str, Cow<'a, str>> {
+    context("DoubleQuotedString", |input| delimited_value('"', input))(input)
 }
 
-pub(crate) fn string_value<'a>(input: &'a str) -> CResult<&'a str, String> {
+pub(crate) fn string_value<'a>(input: &'a str) -> CResult<&'a str, Cow<'a, str>> {
     context("String", alt((single_quoted_string, double_quoted_string)))(input)
 }
 
 /// Regex is pretty much a string, what differs is delimiters and should be escaped characters
-pub(crate) fn regex_value<'a>(input: &'a str) -> CResult<&'a str, String> {
-    let should_be_escaped = context("RegexStringShouldBeEscaped", is_not("/\\"));
-    let literal = context(
-        "RegexStringLiteral",
-        verify(should_be_escaped, |s: &str| !s.is_empty()),
-    );
-    let fragment = context(
-        "RegexStringFragment",
-        alt((
-            map(literal, StringFragment::Literal),
-            map(escaped_char, StringFragment::Escaped),
-            value(StringFragment::EscapedWS, escaped_whitespace),
-        )),
-    );
-    let build_string = context(
-        "BuildRegexString",
-        fold_many0(fragment, String::new, |mut acc, fragment| {
-            match fragment {
-                StringFragment::Escaped(c) => acc.push(c),
-                StringFragment::EscapedWS => {}
-                StringFragment::Literal(s) => acc.push_str(s),
-            };
-            acc
-        }),
-    );
+pub(crate) fn regex_value<'a>(input: &'a str) -> CResult<&'a str, Cow<'a, str>> {
+    context("RegexString", |input| delimited_value('/', input))(input)
+}
 
-    context("RegexString", delimited(char('/'), build_string, char('/')))(input)
+/// Escapes a value so it can be written back between the given delimiters,
+/// reverses what the string and regex parsers unescape.
+pub(crate) fn escape_value(value: &str, delimiter: char) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{08}' => escaped.push_str("\\b"),
+            '\u{0C}' => escaped.push_str("\\f"),
+            c if c == delimiter => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
 mod test {
+    use std::borrow::Cow;
+
     #[test]
     fn test_regex() {
         assert_eq!(
             super::regex_value("/abc.*/"),
-            Ok(("", String::from("abc.*"))),
+            Ok(("", Cow::from("abc.*"))),
             "Should parse a regex"
         );
 
         assert_eq!(
             super::regex_value("/abc.*\\//"),
-            Ok(("", String::from("abc.*/"))),
+            Ok(("", Cow::from("abc.*/"))),
             "Should parse a regex with escape"
         );
     }
 
+    #[test]
+    fn test_escape_value() {
+        let value = "an escaped \" and \t and ' \\ string";
+        assert_eq!(
+            super::string_value(&format!("\"{}\"", super::escape_value(value, '"'))),
+            Ok(("", Cow::from(value))),
+            "Should escape a string so it can be parsed back"
+        );
+        assert_eq!(
+            super::regex_value(&format!("/{}/", super::escape_value("abc/.*", '/'))),
+            Ok(("", Cow::from("abc/.*"))),
+            "Should escape a regex so it can be parsed back"
+        );
+    }
+
     #[test]
     fn test_simple_string() {
         assert_eq!(
             super::string_value("\"a simple string\""),
-            Ok(("", String::from("a simple string"))),
+            Ok(("", Cow::from("a simple string"))),
             "Should parse a string with double quotes"
         );
 
         assert_eq!(
             super::string_value("'a simple string'"),
-            Ok(("", String::from("a simple string"))),
+            Ok(("", Cow::from("a simple string"))),
             "Should parse a string with single quotes"
         );
     }
@@ -223,13 +217,13 @@ mod test {
     fn test_string_with_escaped() {
         assert_eq!(
             super::string_value("\"an escaped \\\" and \\t and ' \\\' string\""),
-            Ok(("", String::from("an escaped \" and \t and ' ' string"))),
+            Ok(("", Cow::from("an escaped \" and \t and ' ' string"))),
             "Should parse an escaped string with double quotes, espacing single quote optional"
         );
 
         assert_eq!(
             super::string_value("'an escaped \" \\\" and \\t and \\\' string'"),
-            Ok(("", String::from("an escaped \" \" and \t and ' string"))),
+            Ok(("", Cow::from("an escaped \" \" and \t and ' string"))),
             "Should parse an escaped string with single quotes, espacing double quote optional"
         );
     }
@@ -240,14 +234,37 @@ mod test {
             super::string_value(
                 "\"tab:\\tafter tab, newline:\\nnew line, quote: \\\", emoji: \\u{1F602}, newline:\\nescaped whitespace: \\    abc\""
             ),
-            Ok(("", String::from("tab:\tafter tab, newline:\nnew line, quote: \", emoji: 😂, newline:\nescaped whitespace: abc"))),
+            Ok(("", Cow::from("tab:\tafter tab, newline:\nnew line, quote: \", emoji: 😂, newline:\nescaped whitespace: abc"))),
             "Should parse nom example with single quotes"
         );
 
         assert_eq!(
             super::string_value("'tab:\\tafter tab, newline:\\nnew line, quote: \\\", emoji: \\u{1F602}, newline:\\nescaped whitespace: \\    abc'"),
-            Ok(("", String::from("tab:\tafter tab, newline:\nnew line, quote: \", emoji: 😂, newline:\nescaped whitespace: abc"))),
+            Ok(("", Cow::from("tab:\tafter tab, newline:\nnew line, quote: \", emoji: 😂, newline:\nescaped whitespace: abc"))),
             "Should parse nom example with single quotes"
         );
     }
+
+    #[test]
+    fn test_borrowed() {
+        assert!(
+            matches!(
+                super::string_value("\"plain\" rest"),
+                Ok((" rest", Cow::Borrowed("plain")))
+            ),
+            "Should borrow strings without escapes"
+        );
+        assert!(matches!(
+            super::string_value("'it\\'s'"),
+            Ok(("", Cow::Owned(s))) if s == "it's"
+        ));
+        assert!(
+            matches!(super::string_value("\"no end"), Err(nom::Err::Error(_))),
+            "Should fail, not ask for more input, when the closing quote is missing"
+        );
+        assert!(matches!(
+            super::regex_value("/a\\q/"),
+            Err(nom::Err::Error(_))
+        ));
+    }
 }
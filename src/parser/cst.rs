@@ -0,0 +1,141 @@
+use std::ops::Range;
+
+use super::{
+    error::SourceError, incremental::parse_all, options::ParserOptions, span::ModelSpans, Model,
+};
+
+/// A node of the model, addressed by its position in `Model`
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum NodeId {
+    Namespace,
+    Import(usize),
+    Declaration(usize),
+    /// Property of a declaration, by the positions of both
+    Property(usize, usize),
+}
+
+/// Lossless parse of a model. The source is kept next to the model and its spans, and the
+/// text between nodes is the trivia of the node that follows it, e.g. blank lines and
+/// comments. Rewrites that go through the trivia keep what authors wrote between nodes.
+#[derive(Debug, Clone)]
+pub struct Cst {
+    source: String,
+    model: Model,
+    spans: ModelSpans,
+}
+
+impl Cst {
+    pub fn parse(source: impl Into<String>) -> Result<Self, SourceError> {
+        Self::parse_with(source, &ParserOptions::default())
+    }
+
+    pub fn parse_with(
+        source: impl Into<String>,
+        options: &ParserOptions,
+    ) -> Result<Self, SourceError> {
+        let source = source.into();
+        let (model, spans) = parse_all(&source, options)?;
+        Ok(Self {
+            source,
+            model,
+            spans,
+        })
+    }
+
+    /// The source the model was parsed from, printing it gives back the file unchanged
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn spans(&self) -> &ModelSpans {
+        &self.spans
+    }
+
+    pub fn span(&self, node: NodeId) -> Option<Range<usize>> {
+        match node {
+            NodeId::Namespace => Some(self.spans.namespace.clone()),
+            NodeId::Import(i) => self.spans.imports.get(i).cloned(),
+            NodeId::Declaration(d) => self.spans.declarations.get(d).map(|d| d.span.clone()),
+            NodeId::Property(d, p) => self
+                .spans
+                .declarations
+                .get(d)
+                .and_then(|d| d.properties.get(p))
+                .cloned(),
+        }
+    }
+
+    /// Text between the node and the node before it. Properties are preceded by the
+    /// property before them, or the opening brace of their declaration.
+    pub fn leading_trivia(&self, node: NodeId) -> Option<&str> {
+        let span = self.span(node)?;
+        let start = match node {
+            NodeId::Property(d, 0) => {
+                let declaration = &self.spans.declarations[d].span;
+                let head = &self.source[declaration.start..span.start];
+                declaration.start + head.find('{').map_or(0, |i| i + 1)
+            }
+            NodeId::Property(d, p) => self.spans.declarations[d].properties[p - 1].end,
+            _ => self
+                .top_level()
+                .map(|s| s.end)
+                .filter(|&end| end <= span.start)
+                .max()
+                .unwrap_or(0),
+        };
+        Some(&self.source[start..span.start])
+    }
+
+    /// Text after the last node of the model
+    pub fn trailing_trivia(&self) -> &str {
+        let end = self.top_level().map(|s| s.end).max().unwrap_or(0);
+        &self.source[end..]
+    }
+
+    fn top_level(&self) -> impl Iterator<Item = &Range<usize>> {
+        self.spans
+            .header
+            .iter()
+            .chain(std::iter::once(&self.spans.namespace))
+            .chain(self.spans.imports.iter())
+            .chain(self.spans.declarations.iter().map(|d| &d.span))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NodeId;
+
+    #[test]
+    fn test_cst() {
+        let input = "
+namespace test@1.0.0
+import places@1.0.0.Address
+
+
+concept Person {
+  o String name
+    o Address address
+}
+";
+        let cst = super::Cst::parse(input).unwrap();
+
+        assert_eq!(cst.source(), input, "Should keep the source as it is");
+        assert_eq!(cst.model(), &crate::parser::parse(input).unwrap());
+        assert_eq!(cst.leading_trivia(NodeId::Namespace), Some("\n"));
+        assert_eq!(cst.leading_trivia(NodeId::Import(0)), Some("\n"));
+        assert_eq!(cst.leading_trivia(NodeId::Declaration(0)), Some("\n\n\n"));
+        assert_eq!(
+            cst.leading_trivia(NodeId::Property(0, 0)),
+            Some("\n  "),
+            "Should start the trivia of the first property after the brace"
+        );
+        assert_eq!(cst.leading_trivia(NodeId::Property(0, 1)), Some("\n    "));
+        assert_eq!(cst.leading_trivia(NodeId::Property(0, 2)), None);
+        assert_eq!(cst.trailing_trivia(), "\n");
+    }
+}
@@ -1,26 +1,614 @@
 use nom::{
     branch::alt,
-    character::complete::{char, line_ending, multispace0, space0, space1},
-    combinator::into,
+    bytes::complete::tag,
+    character::complete::char,
+    combinator::{all_consuming, consumed, into, opt, peek, recognize, value, verify},
     error::context,
     multi::fold_many0,
-    sequence::{delimited, tuple},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use std::collections::HashMap;
 
 use crate::parser::{
+    borrowed::IdentifiedRef,
+    comment::{end_of_line, trivia0},
     common::{keywords, token},
-    property, CResult,
+    decorator::{decorators, Decorator, DEPRECATED},
+    dialect::{meta_extensions, Extension, MetaExtension},
+    error::CError,
+    namespace::{fqn, Namespace},
+    options::{self, LanguageVersion},
+    property::{self, CommonProperty},
+    scan::{space0, space1},
+    CResult,
 };
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+/// Kinds of declarations, serialized as their metamodel class
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum DeclarationKind {
+    #[serde(rename = "ConceptDeclaration")]
+    Concept,
+    /// Its properties are the values of the enum
+    #[serde(rename = "EnumDeclaration")]
+    Enum,
+    #[serde(rename = "AssetDeclaration")]
+    Asset,
+    #[serde(rename = "EventDeclaration")]
+    Event,
+    /// Its two properties are the key and the value of its entries, named `MAP_KEY` and
+    /// `MAP_VALUE`, serialized as the `key` and the `value` of the declaration
+    #[serde(rename = "MapDeclaration")]
+    Map,
+    /// Declared with a keyword registered by a dialect
+    #[serde(rename = "ExtensionDeclaration")]
+    Extension,
+}
+
+/// Serialized as its metamodel declaration, see `DeclarationJson`
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "deserialize",
+    derive(Deserialize),
+    serde(from = "DeclarationDefinition")
+)]
 pub struct Declaration {
+    pub kind: DeclarationKind,
     pub name: String,
+    /// Declared `abstract`, only declarations extending it have instances
+    pub is_abstract: bool,
+    /// Declaration this one extends, written `extends Employee`
+    pub super_type: Option<TypeReference>,
+    /// How instances are identified, written `identified by vin` or `identified`
+    pub identified: Option<Identified>,
     pub properties: Vec<Property>,
+    /// Node built by a dialect, for declarations with an extension keyword
+    pub extension: Option<Extension>,
+    /// Meta properties with extension keywords, of all properties of the declaration
+    pub meta_extensions: Vec<MetaExtension>,
+    /// Decorators written above the declaration, those of properties are on the properties
+    pub decorators: Vec<Decorator>,
+    /// Property positions by name, built with the declaration
+    index: HashMap<String, usize>,
+}
+
+/// Fields of a `Declaration` as they are laid out in the metamodel. Map declarations have
+/// a `key` and a `value` instead of properties.
+#[derive(Serialize)]
+struct DeclarationJson<'a> {
+    #[serde(rename = "$class")]
+    kind: DeclarationKind,
+    name: &'a str,
+    #[serde(rename = "isAbstract", skip_serializing_if = "is_concrete")]
+    is_abstract: bool,
+    #[serde(rename = "superType", skip_serializing_if = "Option::is_none")]
+    super_type: Option<&'a TypeReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identified: Option<&'a Identified>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<&'a [Property]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<MapEntryType<MapKeyType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<MapEntryType<MapValueType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extension: Option<&'a Extension>,
+    #[serde(rename = "metaExtensions", skip_serializing_if = "<[_]>::is_empty")]
+    meta_extensions: &'a [MetaExtension],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    decorators: &'a [Decorator],
+}
+
+impl serde::Serialize for Declaration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let entries = match (self.kind, &self.properties[..]) {
+            (DeclarationKind::Map, [key, value]) => {
+                Some((MapEntryType::of(key), MapEntryType::of(value)))
+            }
+            _ => None,
+        };
+        DeclarationJson {
+            kind: self.kind,
+            name: &self.name,
+            is_abstract: self.is_abstract,
+            super_type: self.super_type.as_ref(),
+            identified: self.identified.as_ref(),
+            properties: entries.is_none().then_some(&self.properties[..]),
+            key: entries.as_ref().map(|(key, _)| key.clone()),
+            value: entries.map(|(_, value)| value),
+            extension: self.extension.as_ref(),
+            meta_extensions: &self.meta_extensions,
+            decorators: &self.decorators,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserialized fields of a `Declaration`, the lookup index is built when converting
+#[cfg(feature = "deserialize")]
+#[derive(Deserialize)]
+struct DeclarationDefinition {
+    #[serde(rename = "$class")]
+    kind: DeclarationKind,
+    name: String,
+    #[serde(rename = "isAbstract", default)]
+    is_abstract: bool,
+    #[serde(rename = "superType", default)]
+    super_type: Option<TypeReference>,
+    #[serde(default)]
+    identified: Option<Identified>,
+    /// Every declaration but maps has properties
+    #[serde(default)]
+    properties: Vec<Property>,
+    #[serde(default)]
+    key: Option<MapEntryType<MapKeyType>>,
+    #[serde(default)]
+    value: Option<MapEntryType<MapValueType>>,
+    #[serde(default)]
+    extension: Option<Extension>,
+    #[serde(rename = "metaExtensions", default)]
+    meta_extensions: Vec<MetaExtension>,
+    #[serde(default)]
+    decorators: Vec<Decorator>,
+}
+
+#[cfg(feature = "deserialize")]
+impl From<DeclarationDefinition> for Declaration {
+    fn from(value: DeclarationDefinition) -> Self {
+        let properties = match (value.key, value.value) {
+            (Some(key), Some(entry)) => vec![key.property(MAP_KEY), entry.property(MAP_VALUE)],
+            _ => value.properties,
+        };
+        let mut declaration = Declaration::new(value.kind, value.name, properties);
+        declaration.is_abstract = value.is_abstract;
+        declaration.super_type = value.super_type;
+        declaration.identified = value.identified;
+        declaration.extension = value.extension;
+        declaration.meta_extensions = value.meta_extensions;
+        declaration.decorators = value.decorators;
+        declaration
+    }
+}
+
+impl PartialEq for Declaration {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.name == other.name
+            && self.is_abstract == other.is_abstract
+            && self.super_type == other.super_type
+            && self.identified == other.identified
+            && self.properties == other.properties
+            && self.extension == other.extension
+            && self.meta_extensions == other.meta_extensions
+            && self.decorators == other.decorators
+    }
+}
+
+impl Declaration {
+    pub(crate) fn new(
+        kind: DeclarationKind,
+        name: impl Into<String>,
+        properties: Vec<Property>,
+    ) -> Self {
+        let mut declaration = Self {
+            kind,
+            name: name.into(),
+            is_abstract: false,
+            super_type: None,
+            identified: None,
+            properties,
+            extension: None,
+            meta_extensions: Vec::new(),
+            decorators: Vec::new(),
+            index: HashMap::new(),
+        };
+        declaration.reindex();
+        declaration
+    }
+
+    /// Finds a property by its name, using the index built with the declaration
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.index
+            .get(name)
+            .and_then(|i| self.properties.get(*i))
+            .filter(|p| p.name() == name)
+    }
+
+    /// Builds the lookup index again after `properties` were changed directly
+    pub fn reindex(&mut self) {
+        self.index = self
+            .properties
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.name().to_string(), i))
+            .collect();
+    }
+
+    /// Whether the declaration is decorated with `@deprecated`
+    pub fn is_deprecated(&self) -> bool {
+        self.decorators.iter().any(|d| d.name == DEPRECATED)
+    }
+
+    /// Whether the property with the given name is decorated with `@deprecated`
+    pub fn is_property_deprecated(&self, name: &str) -> bool {
+        self.decorators_of(Some(name)).any(|d| d.name == DEPRECATED)
+    }
+
+    /// Decorators written on the property with the given name, or on the declaration
+    pub fn decorators_of(&self, property: Option<&str>) -> impl Iterator<Item = &Decorator> {
+        match property {
+            Some(name) => self.property(name).map_or(&[][..], |p| p.decorators()),
+            None => &self.decorators[..],
+        }
+        .iter()
+    }
+
+    /// The modifier, the keyword, the name, the super type and the identifying property, as
+    /// the declaration starts
+    pub(crate) fn head(&self) -> String {
+        let mut head = match self.is_abstract {
+            true => format!("abstract {} {}", self.kind, self.name),
+            false => format!("{} {}", self.kind, self.name),
+        };
+        if let Some(super_type) = &self.super_type {
+            head.push_str(&format!(" extends {}", super_type));
+        }
+        if let Some(identified) = &self.identified {
+            head.push_str(&format!(" {}", identified));
+        }
+        head
+    }
+
+    /// Compares declarations by meaning, the order properties are defined in doesn't matter
+    pub fn semantically_equals(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.name == other.name
+            && self.is_abstract == other.is_abstract
+            && self.super_type == other.super_type
+            && self.identified == other.identified
+            && self.properties.len() == other.properties.len()
+            && self
+                .properties
+                .iter()
+                .all(|p| other.property(p.name()) == Some(p))
+            && self.extension == other.extension
+            && self.meta_extensions.len() == other.meta_extensions.len()
+            && self
+                .meta_extensions
+                .iter()
+                .all(|m| other.meta_extensions.contains(m))
+            && self.decorators.len() == other.decorators.len()
+            && self.decorators.iter().all(|d| other.decorators.contains(d))
+    }
+}
+
+impl std::fmt::Display for DeclarationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Concept => write!(f, "concept"),
+            Self::Enum => write!(f, "enum"),
+            Self::Asset => write!(f, "asset"),
+            Self::Event => write!(f, "event"),
+            Self::Map => write!(f, "map"),
+            Self::Extension => write!(f, "extension"),
+        }
+    }
+}
+
+impl std::fmt::Display for Declaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.extension {
+            Some(e) if e.body.is_empty() => return write!(f, "{} {}", e.keyword, self.name),
+            Some(e) => return write!(f, "{} {} {}", e.keyword, self.name, e.body),
+            None => (),
+        }
+        for decorator in self.decorators_of(None) {
+            writeln!(f, "{}", decorator)?;
+        }
+        writeln!(f, "{} {{", self.head())?;
+        for property in self.properties.iter() {
+            for decorator in self.decorators_of(Some(property.name())) {
+                writeln!(f, "  {}", decorator)?;
+            }
+            match self.kind {
+                DeclarationKind::Map => write!(f, "  {}", MapEntry(property))?,
+                _ => write!(f, "  {}", property)?,
+            }
+            for meta in self
+                .meta_extensions
+                .iter()
+                .filter(|m| m.property == property.name())
+            {
+                write!(f, " {}", meta)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Name of the property holding the key type of a map declaration
+pub const MAP_KEY: &str = "key";
+/// Name of the property holding the value type of a map declaration
+pub const MAP_VALUE: &str = "value";
+
+/// A property of a map declaration as it is written, its type without a name
+struct MapEntry<'a>(&'a Property);
+
+impl std::fmt::Display for MapEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Property::Concept(p) if p.is_relationship => write!(f, "--> {}", p.type_reference()),
+            Property::Concept(p) => write!(f, "o {}", p.type_reference()),
+            p => write!(f, "o {}", p.type_name()),
+        }
+    }
+}
+
+/// Key or value type of a map declaration in the metamodel, with the decorators of the entry
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+struct MapEntryType<T> {
+    #[serde(flatten)]
+    entry: T,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    decorators: Vec<Decorator>,
+}
+
+impl<T: MapEntryClass> MapEntryType<T> {
+    fn of(property: &Property) -> Self {
+        let reference = match property {
+            Property::Concept(p) => p.type_reference(),
+            p => TypeReference {
+                name: p.type_name().to_string(),
+                namespace: None,
+            },
+        };
+        Self {
+            entry: T::of(reference, property.is_relationship()),
+            decorators: property.decorators().to_vec(),
+        }
+    }
+
+    /// The property of the map declaration with the given name
+    #[cfg(feature = "deserialize")]
+    fn property(self, name: &str) -> Property {
+        let (reference, is_relationship) = self.entry.written();
+        let mut property = map_entry_property(name, reference, is_relationship);
+        *property.decorators_mut() = self.decorators;
+        property
+    }
+}
+
+/// Classes of map keys or values, the type of an entry in the metamodel
+trait MapEntryClass {
+    fn of(reference: TypeReference, is_relationship: bool) -> Self;
+
+    /// The type of the entry as it is written, and whether it is a relationship
+    #[cfg(feature = "deserialize")]
+    fn written(self) -> (TypeReference, bool);
+}
+
+/// Primitive types are written by name only
+#[cfg(feature = "deserialize")]
+fn primitive(name: &str) -> TypeReference {
+    TypeReference {
+        name: name.to_string(),
+        namespace: None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
+#[allow(clippy::enum_variant_names)]
+enum MapKeyType {
+    StringMapKeyType,
+    DateTimeMapKeyType,
+    ObjectMapKeyType {
+        #[serde(rename = "type")]
+        type_reference: TypeReference,
+    },
+}
+
+impl MapEntryClass for MapKeyType {
+    fn of(reference: TypeReference, _: bool) -> Self {
+        match reference.name.as_str() {
+            "String" => Self::StringMapKeyType,
+            "DateTime" => Self::DateTimeMapKeyType,
+            _ => Self::ObjectMapKeyType {
+                type_reference: reference,
+            },
+        }
+    }
+
+    #[cfg(feature = "deserialize")]
+    fn written(self) -> (TypeReference, bool) {
+        match self {
+            Self::StringMapKeyType => (primitive("String"), false),
+            Self::DateTimeMapKeyType => (primitive("DateTime"), false),
+            Self::ObjectMapKeyType { type_reference } => (type_reference, false),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
+#[allow(clippy::enum_variant_names)]
+enum MapValueType {
+    StringMapValueType,
+    BooleanMapValueType,
+    DateTimeMapValueType,
+    IntegerMapValueType,
+    LongMapValueType,
+    DoubleMapValueType,
+    ObjectMapValueType {
+        #[serde(rename = "type")]
+        type_reference: TypeReference,
+    },
+    RelationshipMapValueType {
+        #[serde(rename = "type")]
+        type_reference: TypeReference,
+    },
+}
+
+impl MapEntryClass for MapValueType {
+    fn of(reference: TypeReference, is_relationship: bool) -> Self {
+        match (reference.name.as_str(), is_relationship) {
+            (_, true) => Self::RelationshipMapValueType {
+                type_reference: reference,
+            },
+            ("String", _) => Self::StringMapValueType,
+            ("Boolean", _) => Self::BooleanMapValueType,
+            ("DateTime", _) => Self::DateTimeMapValueType,
+            ("Integer", _) => Self::IntegerMapValueType,
+            ("Long", _) => Self::LongMapValueType,
+            ("Double", _) => Self::DoubleMapValueType,
+            _ => Self::ObjectMapValueType {
+                type_reference: reference,
+            },
+        }
+    }
+
+    #[cfg(feature = "deserialize")]
+    fn written(self) -> (TypeReference, bool) {
+        match self {
+            Self::StringMapValueType => (primitive("String"), false),
+            Self::BooleanMapValueType => (primitive("Boolean"), false),
+            Self::DateTimeMapValueType => (primitive("DateTime"), false),
+            Self::IntegerMapValueType => (primitive("Integer"), false),
+            Self::LongMapValueType => (primitive("Long"), false),
+            Self::DoubleMapValueType => (primitive("Double"), false),
+            Self::ObjectMapValueType { type_reference } => (type_reference, false),
+            Self::RelationshipMapValueType { type_reference } => (type_reference, true),
+        }
+    }
+}
+
+/// Whether a declaration isn't abstract, for serde to leave `isAbstract` out
+fn is_concrete(is_abstract: &bool) -> bool {
+    !is_abstract
+}
+
+/// Reference to another declaration, by its name in the model or fully qualified, serialized
+/// as a `TypeIdentifier` of the metamodel
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class", rename = "TypeIdentifier")]
+pub struct TypeReference {
+    pub name: String,
+    /// Namespace of fully qualified references
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub namespace: Option<Namespace>,
+}
+
+impl std::fmt::Display for TypeReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(
+                f,
+                "{}@{}.{}",
+                namespace.name(),
+                namespace.version(),
+                self.name
+            ),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Parses a type name, or a fully qualified name like `org.acme@1.0.0.Employee`
+pub fn type_reference(input: &str) -> CResult<&str, TypeReference> {
+    context(
+        "TypeReference",
+        alt((
+            fqn.map(|fqn| TypeReference {
+                name: fqn.type_name().to_string(),
+                namespace: Some(Namespace::new(fqn.namespace_name(), fqn.version().clone())),
+            }),
+            token.map(|name: &str| TypeReference {
+                name: name.to_string(),
+                namespace: None,
+            }),
+        )),
+    )(input)
+}
+
+/// How instances of a declaration are identified, serialized as in the metamodel
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
+pub enum Identified {
+    /// Written `identified`, by the `$identifier` system property
+    #[serde(rename = "Identified")]
+    System,
+    /// Written `identified by vin`, by a String property of the declaration
+    #[serde(rename = "IdentifiedBy")]
+    By { name: String },
+}
+
+impl std::fmt::Display for Identified {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Identified::System => write!(f, "identified"),
+            Identified::By { name } => write!(f, "identified by {}", name),
+        }
+    }
 }
 
+impl Identified {
+    /// Name of the property holding the identifier of instances
+    pub fn property_name(&self) -> &str {
+        match self {
+            Identified::System => "$identifier",
+            Identified::By { name } => name,
+        }
+    }
+}
+
+/// Builds a `Declaration` in code, properties are kept in the order they are added.
+#[derive(Debug)]
+pub struct DeclarationBuilder {
+    kind: DeclarationKind,
+    name: String,
+    properties: Vec<Property>,
+}
+
+impl DeclarationBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            kind: DeclarationKind::Concept,
+            name: name.into(),
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn kind(mut self, kind: DeclarationKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn add_property(mut self, property: impl Into<Property>) -> Self {
+        self.properties.push(property.into());
+        self
+    }
+
+    pub fn build(self) -> Declaration {
+        Declaration::new(self.kind, self.name, self.properties)
+    }
+}
+
+/// Untagged, variants are told apart by their `$class` when deserializing
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 #[serde(untagged)]
 pub enum Property {
     Boolean(property::boolean_property::BooleanProperty),
@@ -30,6 +618,128 @@ pub enum Property {
     DateTime(property::datetime_property::DateTimeProperty),
     String(property::string_property::StringProperty),
     Concept(property::Property),
+    Enum(property::enum_property::EnumProperty),
+}
+
+impl Property {
+    fn inner(&self) -> &dyn CommonProperty {
+        match self {
+            Self::Boolean(p) => p,
+            Self::Integer(p) => p,
+            Self::Long(p) => p,
+            Self::Double(p) => p,
+            Self::DateTime(p) => p,
+            Self::String(p) => p,
+            Self::Concept(p) => p,
+            Self::Enum(p) => p,
+        }
+    }
+
+    /// Drops validators that don't restrict anything, like `range=[,]`
+    pub(crate) fn drop_unbounded_validators(&mut self) {
+        match self {
+            Self::Integer(p) => {
+                p.domain_validator = p
+                    .domain_validator
+                    .take()
+                    .filter(|v| v.lower.is_some() || v.upper.is_some())
+            }
+            Self::Long(p) => {
+                p.domain_validator = p
+                    .domain_validator
+                    .take()
+                    .filter(|v| v.lower.is_some() || v.upper.is_some())
+            }
+            Self::Double(p) => {
+                p.domain_validator = p
+                    .domain_validator
+                    .take()
+                    .filter(|v| v.lower.is_some() || v.upper.is_some())
+            }
+            Self::String(p) => {
+                p.length_validator = p
+                    .length_validator
+                    .take()
+                    .filter(|v| v.min_length.is_some() || v.max_length.is_some())
+            }
+            Self::Boolean(_) | Self::DateTime(_) | Self::Concept(_) | Self::Enum(_) => {}
+        }
+    }
+
+    pub(crate) fn decorators_mut(&mut self) -> &mut Vec<Decorator> {
+        match self {
+            Self::Boolean(p) => &mut p.decorators,
+            Self::Integer(p) => &mut p.decorators,
+            Self::Long(p) => &mut p.decorators,
+            Self::Double(p) => &mut p.decorators,
+            Self::DateTime(p) => &mut p.decorators,
+            Self::String(p) => &mut p.decorators,
+            Self::Concept(p) => &mut p.decorators,
+            Self::Enum(p) => &mut p.decorators,
+        }
+    }
+
+    pub(crate) fn name_mut(&mut self) -> &mut String {
+        match self {
+            Self::Boolean(p) => &mut p.name,
+            Self::Integer(p) => &mut p.name,
+            Self::Long(p) => &mut p.name,
+            Self::Double(p) => &mut p.name,
+            Self::DateTime(p) => &mut p.name,
+            Self::String(p) => &mut p.name,
+            Self::Concept(p) => &mut p.name,
+            Self::Enum(p) => &mut p.name,
+        }
+    }
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Boolean(p) => p.fmt(f),
+            Self::Integer(p) => p.fmt(f),
+            Self::Long(p) => p.fmt(f),
+            Self::Double(p) => p.fmt(f),
+            Self::DateTime(p) => p.fmt(f),
+            Self::String(p) => p.fmt(f),
+            Self::Concept(p) => p.fmt(f),
+            Self::Enum(p) => p.fmt(f),
+        }
+    }
+}
+
+impl CommonProperty for Property {
+    fn class(&self) -> &str {
+        self.inner().class()
+    }
+
+    fn type_name(&self) -> &str {
+        self.inner().type_name()
+    }
+
+    fn name(&self) -> &str {
+        self.inner().name()
+    }
+
+    fn is_optional(&self) -> bool {
+        self.inner().is_optional()
+    }
+
+    fn is_array(&self) -> bool {
+        self.inner().is_array()
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        self.inner().decorators()
+    }
+
+    fn is_relationship(&self) -> bool {
+        self.inner().is_relationship()
+    }
+
+    fn validators(&self) -> Vec<property::Validator<'_>> {
+        self.inner().validators()
+    }
 }
 
 impl From<property::boolean_property::BooleanProperty> for Property {
@@ -74,28 +784,309 @@ impl From<property::Property> for Property {
     }
 }
 
-fn any_proeprty<'a>(input: &'a str) -> CResult<&'a str, Property> {
+impl From<property::enum_property::EnumProperty> for Property {
+    fn from(value: property::enum_property::EnumProperty) -> Self {
+        Self::Enum(value)
+    }
+}
+
+/// Parses `o Type name` once, and then the meta properties the type takes, or a relationship
+pub(crate) fn any_proeprty<'a>(input: &'a str) -> CResult<&'a str, Property> {
+    let typed = |input: &'a str| {
+        let (input, (type_name, name, is_array)) = property::internal::generic_property(input)?;
+        property_metas(input, type_name, name, is_array)
+    };
     context(
         "ConceptProperty",
+        alt((typed, into(property::relationship))),
+    )(input)
+}
+
+/// Parses the meta properties following `o Type name` of a property of the given type
+pub(crate) fn property_metas<'a>(
+    input: &'a str,
+    type_name: &str,
+    name: &str,
+    is_array: bool,
+) -> CResult<&'a str, Property> {
+    use property::*;
+
+    let Some(primitive) = PrimitiveType::from_name(type_name) else {
+        return into(|i| concept_property_metas(i, type_name, name, is_array))(input);
+    };
+    match primitive {
+        PrimitiveType::StringPropertyType => {
+            into(|i| string_property::string_property_metas(i, name, is_array))(input)
+        }
+        PrimitiveType::BooleanPropertyType => {
+            into(|i| boolean_property::boolean_property_metas(i, name, is_array))(input)
+        }
+        PrimitiveType::IntegerPropertyType => {
+            into(|i| integer_property::integer_property_metas(i, name, is_array))(input)
+        }
+        PrimitiveType::LongPropertyType => {
+            into(|i| long_property::long_property_metas(i, name, is_array))(input)
+        }
+        PrimitiveType::DateTimePropertyType => {
+            into(|i| datetime_property::datetime_property_metas(i, name, is_array))(input)
+        }
+        PrimitiveType::DoublePropertyType => {
+            into(|i| double_property::double_property_metas(i, name, is_array))(input)
+        }
+    }
+}
+
+/// What a declaration starts with, up to its properties
+pub(crate) struct DeclarationHead<'a> {
+    pub(crate) decorators: Vec<Decorator>,
+    pub(crate) kind: DeclarationKind,
+    pub(crate) is_abstract: bool,
+    pub(crate) name: &'a str,
+    /// The super type as it is written, and parsed
+    pub(crate) super_type: Option<(&'a str, TypeReference)>,
+    pub(crate) identified: Option<IdentifiedRef<'a>>,
+}
+
+impl DeclarationHead<'_> {
+    /// Completes a declaration built from the properties following the head, with the
+    /// decorators of those properties in their order
+    pub(crate) fn declare(
+        self,
+        mut declaration: Declaration,
+        property_decorators: Vec<Vec<Decorator>>,
+    ) -> Declaration {
+        declaration.is_abstract = self.is_abstract;
+        declaration.super_type = self.super_type.map(|(_, super_type)| super_type);
+        declaration.identified = self.identified.map(IdentifiedRef::into_owned);
+        with_decorators(declaration, self.decorators, property_decorators)
+    }
+}
+
+/// Parses the decorators, the `abstract` modifier, the keyword, the name, the super type and
+/// the identifying property of a declaration
+fn declaration_head<'a, K>(keyword: K) -> impl Parser<&'a str, DeclarationHead<'a>, CError<&'a str>>
+where
+    K: Parser<&'a str, DeclarationKind, CError<&'a str>>,
+{
+    tuple((
+        decorators,
+        opt(terminated(keywords::abstrakt, space1)),
+        keyword,
+        space1,
+        token,
+        opt(preceded(
+            tuple((space1, keywords::extends, space1)),
+            consumed(type_reference),
+        )),
+        opt(preceded(
+            pair(space1, keywords::identified),
+            opt(preceded(tuple((space1, keywords::by, space1)), token)),
+        )),
+    ))
+    .map(
+        move |(decorators, is_abstract, kind, _, name, super_type, identified)| DeclarationHead {
+            decorators,
+            kind,
+            is_abstract: is_abstract.is_some(),
+            name,
+            super_type,
+            identified: identified.map(|name| match name {
+                Some(name) => IdentifiedRef::By(name),
+                None => IdentifiedRef::System,
+            }),
+        },
+    )
+}
+
+/// Parses the keyword a declaration starts with, returns the kind it declares
+fn declaration_keyword(input: &str) -> CResult<&str, DeclarationKind> {
+    alt((
+        value(DeclarationKind::Concept, keywords::concept),
+        value(DeclarationKind::Enum, keywords::enumeration),
+        value(DeclarationKind::Asset, keywords::asset),
+        value(DeclarationKind::Event, keywords::event),
+        map_keyword,
+    ))(input)
+}
+
+/// The `map` keyword, maps can only be declared since Concerto 3.x
+fn map_keyword(input: &str) -> CResult<&str, DeclarationKind> {
+    let map_declarations = options::current().language_version != LanguageVersion::V2;
+    value(
+        DeclarationKind::Map,
+        verify(keywords::map, move |_: &str| map_declarations),
+    )(input)
+}
+
+/// Enums and maps can't be abstract, extend another declaration or be identified
+fn is_valid_head(head: &DeclarationHead) -> bool {
+    !matches!(head.kind, DeclarationKind::Enum | DeclarationKind::Map)
+        || (!head.is_abstract && head.super_type.is_none() && head.identified.is_none())
+}
+
+/// Parses a declaration of any kind using the given property parser, the value parser for
+/// enums or the entry parser for maps, which is given the name of the entry. Returns the head
+/// and the parsed properties of the declaration. Decorators of properties are left to the
+/// property parser.
+pub(crate) fn declaration_with<'a, O, P, V, M>(
+    mut property: P,
+    mut enum_value: V,
+    mut map_entry: M,
+) -> impl FnMut(&'a str) -> CResult<&'a str, (DeclarationHead<'a>, Vec<O>)>
+where
+    P: Parser<&'a str, O, CError<&'a str>>,
+    V: Parser<&'a str, O, CError<&'a str>>,
+    M: FnMut(&'static str, &'a str) -> CResult<&'a str, O>,
+{
+    move |input: &'a str| {
+        let (input, head) = verify(declaration_head(declaration_keyword), is_valid_head)(input)?;
+        let (input, _) = space0(input)?;
+        let (input, properties) = match head.kind {
+            DeclarationKind::Enum => declaration_body(|i| enum_value.parse(i)).parse(input)?,
+            DeclarationKind::Map => map_body(&mut map_entry).parse(input)?,
+            _ => declaration_body(|i| property.parse(i)).parse(input)?,
+        };
+        Ok((input, (head, properties)))
+    }
+}
+
+/// Parses the type of a map entry, `o Type` or `--> Type`, then returns (type name, is
+/// relationship) tuple
+pub(crate) fn map_entry_type(input: &str) -> CResult<&str, (&str, bool)> {
+    let contained = preceded(pair(char('o'), space1), recognize(type_reference));
+    let relationship = preceded(
+        pair(tag("-->"), space1),
+        verify(recognize(type_reference), |t: &str| {
+            property::PrimitiveType::from_name(t).is_none()
+        }),
+    );
+    context(
+        "MapEntry",
         alt((
-            into(property::string_property::string_property),
-            into(property::boolean_property::boolean_property),
-            into(property::integer_property::integer_property),
-            into(property::long_property::long_property),
-            into(property::datetime_property::datetime_property),
-            into(property::double_property::double_property),
-            into(property::concept_property),
+            contained.map(|t| (t, false)),
+            relationship.map(|t| (t, true)),
         )),
     )(input)
 }
 
-pub fn declaration<'a>(input: &'a str) -> CResult<&'a str, Declaration> {
+/// Keys of maps are String or DateTime values
+fn is_map_key((type_name, is_relationship): &(&str, bool)) -> bool {
+    !is_relationship && matches!(*type_name, "String" | "DateTime")
+}
+
+/// Parses the key and the value of a map declaration in braces, one per line, with the given
+/// entry parser. Keys are checked before the entry parser reads them.
+fn map_body<'a, O, M>(mut map_entry: M) -> impl Parser<&'a str, Vec<O>, CError<&'a str>>
+where
+    M: FnMut(&'static str, &'a str) -> CResult<&'a str, O>,
+{
+    move |input: &'a str| {
+        let (input, _) = pair(char('{'), end_of_line)(input)?;
+        let (input, _) = trivia0(input)?;
+        let (_, _) = context(
+            "MapKey",
+            peek(preceded(decorators, verify(map_entry_type, is_map_key))),
+        )(input)?;
+        let (input, key) = terminated(|i| map_entry(MAP_KEY, i), end_of_line)(input)?;
+        let (input, _) = trivia0(input)?;
+        let (input, value) = terminated(|i| map_entry(MAP_VALUE, i), end_of_line)(input)?;
+        let (input, _) = pair(trivia0, char('}'))(input)?;
+        Ok((input, vec![key, value]))
+    }
+}
+
+/// Parses an entry of a map declaration as a property with the given name, without validators
+/// or meta properties
+pub(crate) fn map_entry<'a>(name: &str) -> impl Fn(&'a str) -> CResult<&'a str, Property> + '_ {
+    move |input: &'a str| {
+        let (input, (type_name, is_relationship)) = map_entry_type(input)?;
+        let reference = all_consuming(type_reference)(type_name)
+            .map(|(_, reference)| reference)
+            .unwrap_or_else(|_| TypeReference {
+                name: type_name.to_string(),
+                namespace: None,
+            });
+        Ok((input, map_entry_property(name, reference, is_relationship)))
+    }
+}
+
+/// Builds the property of a map declaration with the given name from the type of the entry
+fn map_entry_property(name: &str, reference: TypeReference, is_relationship: bool) -> Property {
+    let primitive = match reference.namespace {
+        Some(_) => None,
+        None => property::PrimitiveType::from_name(&reference.name),
+    };
+    match primitive {
+        Some(property::PrimitiveType::StringPropertyType) => {
+            property::string_property::StringPropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        Some(property::PrimitiveType::BooleanPropertyType) => {
+            property::boolean_property::BooleanPropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        Some(property::PrimitiveType::IntegerPropertyType) => {
+            property::integer_property::IntegerPropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        Some(property::PrimitiveType::LongPropertyType) => {
+            property::long_property::LongPropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        Some(property::PrimitiveType::DateTimePropertyType) => {
+            property::datetime_property::DateTimePropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        Some(property::PrimitiveType::DoublePropertyType) => {
+            property::double_property::DoublePropertyBuilder::new(name)
+                .build()
+                .into()
+        }
+        None => {
+            let mut builder = property::ConceptPropertyBuilder::new(reference.name, name);
+            if let Some(namespace) = reference.namespace {
+                builder = builder.namespace(namespace);
+            }
+            if is_relationship {
+                builder = builder.relationship();
+            }
+            builder.build().into()
+        }
+    }
+}
+
+/// Parses a declaration starting with the given keyword, as `declaration_with` does
+fn declaration_of<'a, O, K, P>(
+    keyword: K,
+    kind: DeclarationKind,
+    property: P,
+) -> impl Parser<&'a str, (DeclarationHead<'a>, Vec<O>), CError<&'a str>>
+where
+    K: Parser<&'a str, &'a str, CError<&'a str>>,
+    P: Parser<&'a str, O, CError<&'a str>>,
+{
+    pair(
+        declaration_head(value(kind, keyword)),
+        preceded(space0, declaration_body(property)),
+    )
+}
+
+/// Parses the properties of a declaration in braces, one per line
+fn declaration_body<'a, O, P>(property: P) -> impl Parser<&'a str, Vec<O>, CError<&'a str>>
+where
+    P: Parser<&'a str, O, CError<&'a str>>,
+{
     let properties = context(
         "Properties",
         fold_many0(
-            delimited(space0, any_proeprty, tuple((space0, line_ending))),
+            delimited(trivia0, property, end_of_line),
             Vec::new,
-            |mut acc: Vec<_>, item: Property| {
+            |mut acc: Vec<_>, item: O| {
                 acc.push(item);
                 acc
             },
@@ -104,39 +1095,153 @@ pub fn declaration<'a>(input: &'a str) -> CResult<&'a str, Declaration> {
 
     let no_props = context(
         "NoProperties",
-        tuple((char('{'), multispace0, char('}'))).map(|_| Vec::new()),
+        tuple((char('{'), trivia0, char('}'))).map(|_| Vec::new()),
     );
     let props = context(
         "Properties",
-        tuple((
-            char('{'),
-            space0,
-            line_ending,
-            properties,
-            multispace0,
-            char('}'),
-        ))
-        .map(|(_, _, _, props, _, _)| props),
+        tuple((char('{'), end_of_line, properties, trivia0, char('}')))
+            .map(|(_, _, props, _, _)| props),
     );
 
-    let concept = tuple((
-        keywords::concept,
-        space1,
-        token,
-        space0,
-        alt((props, no_props)),
-    ))
-    .map(|(_, _, name, _, props)| (name, props));
+    alt((props, no_props))
+}
 
+/// Parses a declaration, meta properties with keywords of the dialect in use can follow the
+/// built-in meta properties of a property
+pub fn declaration(input: &str) -> CResult<&str, Declaration> {
     context(
         "Declaration",
-        concept.map(|(declaration_name, properties)| Declaration {
-            name: declaration_name.to_string(),
-            properties,
+        alt((
+            declaration_with_properties(keywords::concept, DeclarationKind::Concept),
+            declaration_with_properties(keywords::event, DeclarationKind::Event),
+            enum_declaration,
+            asset_declaration,
+            map_declaration,
+        )),
+    )(input)
+}
+
+/// Parses a declaration with properties starting with the given keyword. The identifying
+/// property is a String property of the declaration, unless it extends another one.
+fn declaration_with_properties<'a, K>(
+    keyword: K,
+    kind: DeclarationKind,
+) -> impl Parser<&'a str, Declaration, CError<&'a str>>
+where
+    K: Parser<&'a str, &'a str, CError<&'a str>>,
+{
+    verify(
+        declaration_of(
+            keyword,
+            kind,
+            pair(decorators, any_proeprty.and(meta_extensions)),
+        ),
+        |(head, properties)| {
+            // Properties of super types are only known once models are loaded
+            head.super_type.is_some()
+                || head
+                    .identified
+                    .and_then(IdentifiedRef::name)
+                    .is_none_or(|identified| {
+                        properties.iter().any(|(_, (property, _))| {
+                            property.name() == identified && matches!(property, Property::String(_))
+                        })
+                    })
+        },
+    )
+    .map(|(head, properties)| {
+        let (property_decorators, properties) = properties.into_iter().unzip();
+        let declaration = with_meta_extensions(head.kind, head.name, properties);
+        head.declare(declaration, property_decorators)
+    })
+}
+
+/// Parses an asset declaration, which can name the property identifying its instances as
+/// concepts and events can
+pub fn asset_declaration(input: &str) -> CResult<&str, Declaration> {
+    context(
+        "AssetDeclaration",
+        declaration_with_properties(keywords::asset, DeclarationKind::Asset),
+    )(input)
+}
+
+/// Parses an enum declaration, its properties are values like `o RED`. Enums can't be
+/// abstract, extend another declaration or be identified.
+pub fn enum_declaration(input: &str) -> CResult<&str, Declaration> {
+    context(
+        "EnumDeclaration",
+        verify(
+            declaration_of(
+                keywords::enumeration,
+                DeclarationKind::Enum,
+                pair(
+                    decorators,
+                    property::enum_property::enum_property.map(Property::from),
+                ),
+            ),
+            |(head, _)| is_valid_head(head),
+        )
+        .map(|(head, values)| {
+            let (value_decorators, values) = values.into_iter().unzip();
+            let declaration = Declaration::new(head.kind, head.name, values);
+            head.declare(declaration, value_decorators)
         }),
     )(input)
 }
 
+/// Parses a map declaration, its properties are the key and the value of its entries like
+/// `o String` and `o Address`. Keys are String or DateTime, values are of any type or
+/// relationships. Maps can't be abstract, extend another declaration or be identified.
+pub fn map_declaration(input: &str) -> CResult<&str, Declaration> {
+    let entry = |name, input| pair(decorators, map_entry(name)).parse(input);
+    context(
+        "MapDeclaration",
+        verify(declaration_head(map_keyword), is_valid_head).and(preceded(space0, map_body(entry))),
+    )
+    .map(|(head, entries)| {
+        let (entry_decorators, entries) = entries.into_iter().unzip();
+        let declaration = Declaration::new(head.kind, head.name, entries);
+        head.declare(declaration, entry_decorators)
+    })
+    .parse(input)
+}
+
+/// Sets the decorators of a declaration and of its properties, those of the properties are
+/// in the order of the properties
+pub(crate) fn with_decorators(
+    mut declaration: Declaration,
+    decorators: Vec<Decorator>,
+    property_decorators: Vec<Vec<Decorator>>,
+) -> Declaration {
+    declaration.decorators = decorators;
+    for (property, decorators) in declaration.properties.iter_mut().zip(property_decorators) {
+        *property.decorators_mut() = decorators;
+    }
+    declaration
+}
+
+/// Builds a declaration, moving the extension meta properties of its properties onto it
+pub(crate) fn with_meta_extensions(
+    kind: DeclarationKind,
+    name: &str,
+    properties: Vec<(Property, Vec<MetaExtension>)>,
+) -> Declaration {
+    let mut metas = Vec::new();
+    let properties = properties
+        .into_iter()
+        .map(|(property, property_metas)| {
+            metas.extend(property_metas.into_iter().map(|mut m: MetaExtension| {
+                m.property = property.name().to_string();
+                m
+            }));
+            property
+        })
+        .collect();
+    let mut declaration = Declaration::new(kind, name, properties);
+    declaration.meta_extensions = metas;
+    declaration
+}
+
 #[cfg(test)]
 mod test {
 
@@ -147,10 +1252,7 @@ mod test {
             super::declaration(input),
             Ok((
                 "",
-                super::Declaration {
-                    name: String::from("MyConcept"),
-                    properties: Vec::new(),
-                }
+                super::Declaration::new(super::DeclarationKind::Concept, "MyConcept", Vec::new())
             )),
             "Should parse a declaration with no proeprties"
         );
@@ -165,20 +1267,22 @@ mod test {
             super::declaration(input),
             Ok((
                 "",
-                super::Declaration {
-                    name: String::from("MyConcept"),
-                    properties: vec![super::Property::String(
+                super::Declaration::new(
+                    super::DeclarationKind::Concept,
+                    "MyConcept",
+                    vec![super::Property::String(
                         crate::parser::property::string_property::StringProperty {
-                            class: String::from("StringProperty"),
+                            class: "StringProperty",
                             name: String::from("name"),
                             is_array: false,
                             is_optional: false,
                             default_value: None,
                             regex_validator: None,
                             length_validator: None,
+                            decorators: Vec::new(),
                         }
-                    )],
-                }
+                    )]
+                )
             )),
             "Should parse a declaration with one property"
         );
@@ -195,39 +1299,652 @@ mod test {
             super::declaration(input),
             Ok((
                 "",
-                super::Declaration {
-                    name: String::from("MyConcept"),
-                    properties: vec![
+                super::Declaration::new(
+                    super::DeclarationKind::Concept,
+                    "MyConcept",
+                    vec![
                         super::Property::String(
                             crate::parser::property::string_property::StringProperty {
-                                class: String::from("StringProperty"),
+                                class: "StringProperty",
                                 name: String::from("name"),
                                 is_array: false,
                                 is_optional: false,
                                 default_value: None,
                                 regex_validator: None,
                                 length_validator: None,
+                                decorators: Vec::new(),
                             }
                         ),
                         super::Property::Boolean(
                             crate::parser::property::boolean_property::BooleanProperty {
                                 name: String::from("applied"),
-                                class: String::from("BooleanProperty"),
+                                class: "BooleanProperty",
                                 is_array: false,
                                 is_optional: false,
                                 default_value: None,
+                                decorators: Vec::new(),
                             }
                         ),
                         super::Property::Concept(crate::parser::property::Property {
                             name: String::from("address"),
                             is_array: false,
+                            is_relationship: false,
                             is_optional: false,
-                            class: String::from("Address")
+                            class: String::from("Address"),
+                            namespace: None,
+                            decorators: Vec::new(),
                         })
-                    ],
-                }
+                    ]
+                )
             )),
             "Should parse a declaration with one property"
         );
     }
+
+    #[test]
+    fn test_property_dispatch() {
+        let cases = [
+            ("o String[] name length=[1,]", "StringProperty"),
+            ("o Boolean flag default=true", "BooleanProperty"),
+            ("o Integer count range=[0,]", "IntegerProperty"),
+            ("o Long id", "LongProperty"),
+            ("o DateTime at optional", "DateTimeProperty"),
+            ("o Double score default=1.5", "DoubleProperty"),
+            ("o Strings names", "ObjectProperty"),
+        ];
+        for (input, class) in cases {
+            let (remains, property) = super::any_proeprty(input).unwrap();
+            assert_eq!(
+                (remains, super::CommonProperty::class(&property)),
+                ("", class),
+                "Should parse {} by its type",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let input = "concept MyConcept {
+          o String name
+          o Address address
+        }";
+        assert_eq!(
+            super::declaration(input).map(|(_, dec)| dec),
+            Ok(super::DeclarationBuilder::new("MyConcept")
+                .add_property(
+                    crate::parser::property::string_property::StringPropertyBuilder::new("name")
+                        .build()
+                )
+                .add_property(
+                    crate::parser::property::ConceptPropertyBuilder::new("Address", "address")
+                        .build()
+                )
+                .build()),
+            "Should build the same declaration as parsed"
+        );
+    }
+
+    #[test]
+    fn test_common_property() {
+        use crate::parser::property::{CommonProperty, Validator};
+
+        let (_, declaration) = super::declaration(
+            "concept MyConcept {
+          o String[] name optional regex=/abc.*/ length=[1, 10]
+          o Address address
+        }",
+        )
+        .unwrap();
+
+        let name = &declaration.properties[0];
+        assert_eq!(name.name(), "name");
+        assert_eq!(name.class(), "StringProperty");
+        assert_eq!(name.type_name(), "String");
+        assert!(name.is_optional());
+        assert!(name.is_array());
+        assert!(
+            matches!(
+                name.validators().as_slice(),
+                [Validator::Regex(_), Validator::Length(_)]
+            ),
+            "Should list both validators of a string property"
+        );
+
+        let address = &declaration.properties[1];
+        assert_eq!(address.name(), "address");
+        assert_eq!(address.type_name(), "Address");
+        assert!(!address.is_optional());
+        assert!(!address.is_array());
+        assert!(address.validators().is_empty());
+    }
+
+    #[test]
+    fn test_property_lookup() {
+        let (_, mut declaration) = super::declaration(
+            "concept MyConcept {
+          o String name
+          o Address address
+        }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            declaration.property("address"),
+            Some(&declaration.properties[1]),
+            "Should find a property by its name"
+        );
+        assert_eq!(
+            declaration.property("unknown"),
+            None,
+            "Should not find a property that is not defined"
+        );
+
+        declaration.properties.remove(0);
+        assert_eq!(
+            declaration.property("address"),
+            None,
+            "Should not find a property at a stale position"
+        );
+        declaration.reindex();
+        assert_eq!(
+            declaration.property("address"),
+            Some(&declaration.properties[0]),
+            "Should find a property after reindexing"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "concept MyConcept {
+  o String name optional
+  o Address[] addresses
+}";
+        assert_eq!(
+            super::declaration(input).map(|(_, dec)| dec.to_string()),
+            Ok(String::from(input)),
+            "Should render the declaration as it is parsed"
+        );
+        assert_eq!(
+            super::declaration("concept MyConcept {}").map(|(_, dec)| dec.to_string()),
+            Ok(String::from("concept MyConcept {\n}")),
+            "Should render a declaration with no properties"
+        );
+    }
+
+    #[test]
+    fn test_decorators() {
+        let input = "@deprecated(\"use Person\")
+@Term(\"Customer\")
+concept Customer {
+  @deprecated
+  o String name optional
+  o Address[] addresses
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert!(declaration.is_deprecated());
+        assert!(declaration.is_property_deprecated("name"));
+        assert!(!declaration.is_property_deprecated("addresses"));
+        assert_eq!(
+            declaration.decorators_of(None).count(),
+            2,
+            "Should keep the decorators of the declaration apart"
+        );
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render decorators above what they decorate"
+        );
+
+        let (_, plain) = super::declaration("concept Customer {}").unwrap();
+        assert!(!plain.is_deprecated());
+    }
+
+    #[test]
+    fn test_enum_declaration() {
+        use crate::parser::property::CommonProperty;
+
+        let input = "@Term(\"Colour\")
+enum Color {
+  o RED
+  @deprecated
+  o GREEN
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(declaration.kind, super::DeclarationKind::Enum);
+        assert_eq!(
+            declaration
+                .properties
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>(),
+            vec!["RED", "GREEN"]
+        );
+        assert!(declaration.is_property_deprecated("GREEN"));
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render the enum as it is parsed"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["properties"],
+            serde_json::json!([
+                { "$class": "EnumProperty", "name": "RED" },
+                {
+                    "$class": "EnumProperty",
+                    "name": "GREEN",
+                    "decorators": [{ "$class": "Decorator", "name": "deprecated" }],
+                },
+            ]),
+            "Should serialize decorators on the values they are written on"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["decorators"],
+            serde_json::json!([{
+                "$class": "Decorator",
+                "name": "Term",
+                "arguments": [{ "$class": "DecoratorString", "value": "Colour" }],
+            }])
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["$class"],
+            "EnumDeclaration"
+        );
+        assert!(
+            super::declaration("enum Color {\n  o String name\n}").is_err(),
+            "Should not take typed properties in enums"
+        );
+        assert!(
+            super::declaration("concept Color {\n  o RED\n}").is_err(),
+            "Should not take enum values in concepts"
+        );
+    }
+
+    #[test]
+    fn test_asset_declaration() {
+        let input = "asset Vehicle identified by vin {
+  o String vin
+  o Integer seats optional
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(declaration.kind, super::DeclarationKind::Asset);
+        assert_eq!(
+            declaration.identified,
+            Some(super::Identified::By {
+                name: String::from("vin")
+            })
+        );
+        assert_eq!(declaration.properties.len(), 2);
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render the asset as it is parsed"
+        );
+        let serialized = serde_json::to_value(&declaration).unwrap();
+        assert_eq!(serialized["$class"], "AssetDeclaration");
+        assert_eq!(
+            serialized["identified"],
+            serde_json::json!({ "$class": "IdentifiedBy", "name": "vin" })
+        );
+
+        let (_, unidentified) = super::declaration("asset Vehicle {}").unwrap();
+        assert_eq!(unidentified.identified, None);
+        assert!(
+            super::declaration("asset Vehicle identified by vin {\n  o Integer vin\n}").is_err(),
+            "Should identify by a String property"
+        );
+        assert!(
+            super::declaration("asset Vehicle identified by vin {\n  o String id\n}").is_err(),
+            "Should identify by a property of the asset"
+        );
+    }
+
+    #[test]
+    fn test_identified_concept() {
+        let input = "concept Order identified by orderId {
+  o String orderId
+}";
+        let (_, declaration) = super::declaration(input).unwrap();
+        assert_eq!(declaration.kind, super::DeclarationKind::Concept);
+        assert_eq!(
+            declaration.identified,
+            Some(super::Identified::By {
+                name: String::from("orderId")
+            })
+        );
+        assert_eq!(declaration.to_string(), input);
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["identified"],
+            serde_json::json!({ "$class": "IdentifiedBy", "name": "orderId" })
+        );
+        assert!(
+            super::declaration("event Shipped identified by id {\n  o String id\n}").is_ok(),
+            "Should parse identified events"
+        );
+        assert!(
+            super::declaration("concept Order identified by id {\n  o Long id\n}").is_err(),
+            "Should identify concepts by a String property too"
+        );
+        assert!(
+            super::declaration("enum Color identified by id {\n  o RED\n}").is_err(),
+            "Should not identify enums"
+        );
+    }
+
+    #[test]
+    fn test_system_identified() {
+        let input = "asset Car identified {
+  o String model
+}";
+        let (_, declaration) = super::declaration(input).unwrap();
+        assert_eq!(declaration.identified, Some(super::Identified::System));
+        assert_eq!(
+            declaration.identified.as_ref().map(|i| i.property_name()),
+            Some("$identifier"),
+            "Should be identified by the system identifier"
+        );
+        assert_eq!(declaration.to_string(), input);
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["identified"],
+            serde_json::json!({ "$class": "Identified" })
+        );
+        assert_eq!(
+            super::declaration("concept Car identified{}").map(|(_, d)| d.identified),
+            Ok(Some(super::Identified::System)),
+            "Should parse system identified concepts"
+        );
+    }
+
+    #[test]
+    fn test_event_declaration() {
+        let input = "@Topic(\"orders\")
+event OrderPlaced {
+  o String orderId
+  o DateTime placedAt optional
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(declaration.kind, super::DeclarationKind::Event);
+        assert_eq!(declaration.properties.len(), 2);
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render the event as it is parsed"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["$class"],
+            "EventDeclaration"
+        );
+    }
+
+    #[test]
+    fn test_map_declaration_v2() {
+        use crate::parser::options::{with_options, LanguageVersion, ParserOptions};
+
+        let v2 = ParserOptions {
+            language_version: LanguageVersion::V2,
+            ..ParserOptions::default()
+        };
+        assert!(
+            with_options(&v2, || super::declaration(
+                "map AddressBook {\n  o String\n  o Address\n}"
+            ))
+            .is_err(),
+            "Should only declare maps since Concerto 3.x"
+        );
+        assert!(
+            crate::parser::parse_with(
+                "namespace org.acme\n\nmap AddressBook {\n  o String\n  o String\n}\n",
+                &v2
+            )
+            .is_err(),
+            "Should reject maps in Concerto 2.x models"
+        );
+    }
+
+    #[test]
+    fn test_map_declaration() {
+        use crate::parser::property::CommonProperty;
+
+        let input = "@Term(\"Address book\")
+map AddressBook {
+  o String
+  @Term(\"Home\")
+  o org.acme@1.0.0.Address
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(declaration.kind, super::DeclarationKind::Map);
+        assert_eq!(
+            declaration
+                .properties
+                .iter()
+                .map(|p| (p.name(), p.type_name()))
+                .collect::<Vec<_>>(),
+            vec![(super::MAP_KEY, "String"), (super::MAP_VALUE, "Address")]
+        );
+        assert_eq!(declaration.decorators_of(Some(super::MAP_VALUE)).count(), 1);
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render the map as it is parsed"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap(),
+            serde_json::json!({
+                "$class": "MapDeclaration",
+                "name": "AddressBook",
+                "key": { "$class": "StringMapKeyType" },
+                "value": {
+                    "$class": "ObjectMapValueType",
+                    "type": {
+                        "$class": "TypeIdentifier",
+                        "name": "Address",
+                        "namespace": "org.acme@1.0.0",
+                    },
+                    "decorators": [{
+                        "$class": "Decorator",
+                        "name": "Term",
+                        "arguments": [{ "$class": "DecoratorString", "value": "Home" }],
+                    }],
+                },
+                "decorators": [{
+                    "$class": "Decorator",
+                    "name": "Term",
+                    "arguments": [{ "$class": "DecoratorString", "value": "Address book" }],
+                }],
+            }),
+            "Should serialize the key and the value as map entry types"
+        );
+        #[cfg(feature = "deserialize")]
+        assert_eq!(
+            serde_json::from_value::<super::Declaration>(
+                serde_json::to_value(&declaration).unwrap()
+            )
+            .unwrap(),
+            declaration,
+            "Should read the key and the value back as properties"
+        );
+
+        let (_, owners) =
+            super::declaration("map Owners {\n  o DateTime\n  --> Person\n}").unwrap();
+        assert!(
+            owners.property(super::MAP_VALUE).unwrap().is_relationship(),
+            "Should take relationships as values"
+        );
+        let json = serde_json::to_value(&owners).unwrap();
+        assert_eq!(
+            (&json["key"]["$class"], &json["value"]["$class"]),
+            (
+                &serde_json::json!("DateTimeMapKeyType"),
+                &serde_json::json!("RelationshipMapValueType")
+            )
+        );
+        #[cfg(feature = "deserialize")]
+        assert_eq!(
+            serde_json::from_value::<super::Declaration>(json).unwrap(),
+            owners
+        );
+        for (input, message) in [
+            (
+                "map Ages {\n  o Integer\n  o String\n}",
+                "Should only take String and DateTime keys",
+            ),
+            (
+                "map Owners {\n  --> Person\n  o String\n}",
+                "Should not take relationships as keys",
+            ),
+            ("map Names {\n  o String\n}", "Should take a value"),
+            (
+                "map Names {\n  o String\n  o String\n  o String\n}",
+                "Should take one value",
+            ),
+            (
+                "map Names {\n  o String name\n  o String\n}",
+                "Should not name entries",
+            ),
+            (
+                "map Names {\n  o String\n  o String[]\n}",
+                "Should not take arrays",
+            ),
+            (
+                "abstract map Names {\n  o String\n  o String\n}",
+                "Should not be abstract",
+            ),
+        ] {
+            assert!(super::declaration(input).is_err(), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_abstract() {
+        let input = "@Term(\"Party\")
+abstract concept Party {
+  o String name
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert!(declaration.is_abstract);
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should render the modifier after the decorators"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["isAbstract"],
+            true
+        );
+
+        let (_, asset) =
+            super::declaration("abstract asset Vehicle identified by vin {\n  o String vin\n}")
+                .unwrap();
+        assert!(asset.is_abstract);
+        assert!(super::declaration("abstract event Happened {}").is_ok_and(|(_, d)| d.is_abstract));
+
+        let (_, concrete) = super::declaration("concept Person {}").unwrap();
+        assert!(!concrete.is_abstract);
+        assert_eq!(
+            serde_json::to_value(&concrete).unwrap().get("isAbstract"),
+            None,
+            "Should leave out the flag of concrete declarations"
+        );
+        assert!(
+            super::declaration("abstract enum Color {\n  o RED\n}").is_err(),
+            "Should not take abstract enums"
+        );
+    }
+
+    #[test]
+    fn test_extends() {
+        use super::TypeReference;
+        use crate::parser::{namespace::Namespace, version::SemanticVersion};
+
+        let input = "abstract concept Manager extends Employee {
+  o String team
+}";
+        let (remains, declaration) = super::declaration(input).unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(
+            declaration.super_type,
+            Some(TypeReference {
+                name: String::from("Employee"),
+                namespace: None,
+            })
+        );
+        assert_eq!(declaration.to_string(), input);
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["superType"],
+            serde_json::json!({ "$class": "TypeIdentifier", "name": "Employee" })
+        );
+
+        let input = "asset Car extends org.acme@1.0.0.Vehicle identified by vin {\n}";
+        let (_, declaration) = super::declaration(input).unwrap();
+        assert_eq!(
+            declaration.super_type,
+            Some(TypeReference {
+                name: String::from("Vehicle"),
+                namespace: Some(Namespace::new(
+                    "org.acme",
+                    SemanticVersion::Version((1, 0, 0).into())
+                )),
+            }),
+            "Should take fully qualified super types"
+        );
+        assert_eq!(
+            declaration.to_string(),
+            input,
+            "Should identify assets by properties of their super type"
+        );
+        assert_eq!(
+            serde_json::to_value(&declaration).unwrap()["superType"],
+            serde_json::json!({
+                "$class": "TypeIdentifier",
+                "name": "Vehicle",
+                "namespace": "org.acme@1.0.0",
+            })
+        );
+        assert!(
+            super::declaration("enum Color extends Base {\n  o RED\n}").is_err(),
+            "Should not extend enums"
+        );
+    }
+
+    #[cfg(feature = "deserialize")]
+    #[test]
+    fn test_deserialize_property() {
+        let boolean: super::Property = serde_json::from_value(serde_json::json!({
+          "$class": "BooleanProperty",
+          "name": "active",
+          "isArray": false,
+          "isOptional": false,
+        }))
+        .unwrap();
+        assert!(
+            matches!(boolean, super::Property::Boolean(_)),
+            "Should pick the property type by its class"
+        );
+
+        let date: super::Property = serde_json::from_value(serde_json::json!({
+          "$class": "DateTimeProperty",
+          "name": "born",
+          "isArray": false,
+          "isOptional": false,
+        }))
+        .unwrap();
+        assert!(
+            matches!(date, super::Property::DateTime(_)),
+            "Should pick the property type by its class"
+        );
+
+        let unknown: Result<super::Property, _> = serde_json::from_value(serde_json::json!({
+          "$class": "UnknownProperty",
+          "name": "born",
+          "isArray": false,
+          "isOptional": false,
+        }));
+        assert!(unknown.is_err(), "Should not deserialize unknown classes");
+    }
 }
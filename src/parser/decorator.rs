@@ -0,0 +1,243 @@
+//! Decorators, like `@deprecated` or `@Term("Vehicle")`, written on their own lines above a
+//! declaration or a property. Arguments are strings, numbers, booleans or type references,
+//! tools give them meaning.
+
+use std::borrow::Cow;
+
+use nom::{
+    branch::alt,
+    character::complete::{char, one_of, satisfy},
+    combinator::{map_res, not, opt, peek},
+    error::context,
+    multi::{many0, separated_list0},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    Parser,
+};
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use super::{
+    common::{
+        boolean_value,
+        numeric::{decimal_value, double_value},
+        string::{escape_value, string_value},
+        token,
+    },
+    declaration::{type_reference, TypeReference},
+    scan::{multispace0, space0},
+    CResult,
+};
+
+/// Name of the decorator marking declarations and properties as deprecated
+pub const DEPRECATED: &str = "deprecated";
+
+/// Name of the decorator giving an example value of a property, like `@example("Ann")`
+pub const EXAMPLE: &str = "example";
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class", rename = "Decorator")]
+pub struct Decorator {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub arguments: Vec<DecoratorArgument>,
+}
+
+/// An argument of a decorator, serialized as its metamodel class
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
+pub enum DecoratorArgument {
+    /// `"Vehicle"`, kept without its quotes
+    #[serde(rename = "DecoratorString")]
+    String { value: String },
+    #[serde(rename = "DecoratorNumber")]
+    Number { value: f64 },
+    #[serde(rename = "DecoratorBoolean")]
+    Boolean { value: bool },
+    /// A type name, like `Person` or `Person[]`
+    #[serde(rename = "DecoratorTypeReference")]
+    TypeReference {
+        #[serde(rename = "type")]
+        type_reference: TypeReference,
+        #[serde(rename = "isArray")]
+        is_array: bool,
+    },
+}
+
+impl Decorator {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Adds a string argument
+    pub fn with_string(mut self, value: impl Into<String>) -> Self {
+        self.arguments.push(DecoratorArgument::String {
+            value: value.into(),
+        });
+        self
+    }
+}
+
+impl DecoratorArgument {
+    /// The argument as text, strings without their quotes
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            Self::String { value } => Cow::Borrowed(value),
+            argument => Cow::Owned(argument.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Decorator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<_> = self.arguments.iter().map(|a| a.to_string()).collect();
+            write!(f, "({})", arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for DecoratorArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String { value } => write!(f, "\"{}\"", escape_value(value, '"')),
+            Self::Number { value } => write!(f, "{}", value),
+            Self::Boolean { value } => write!(f, "{}", value),
+            Self::TypeReference {
+                type_reference,
+                is_array: true,
+            } => write!(f, "{}[]", type_reference),
+            Self::TypeReference { type_reference, .. } => write!(f, "{}", type_reference),
+        }
+    }
+}
+
+/// Numbers start with a digit, a sign or a dot, so names like `inf` are type references
+fn number(input: &str) -> CResult<&str, f64> {
+    preceded(
+        peek(one_of("+-.0123456789")),
+        alt((double_value, map_res(decimal_value, str::parse))),
+    )(input)
+}
+
+fn argument(input: &str) -> CResult<&str, DecoratorArgument> {
+    let boolean = terminated(
+        boolean_value,
+        not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+    );
+    context(
+        "DecoratorArgument",
+        alt((
+            string_value.map(|value| DecoratorArgument::String {
+                value: value.into_owned(),
+            }),
+            number.map(|value| DecoratorArgument::Number { value }),
+            boolean.map(|value| DecoratorArgument::Boolean { value }),
+            pair(type_reference, opt(pair(char('['), char(']')))).map(|(type_reference, array)| {
+                DecoratorArgument::TypeReference {
+                    type_reference,
+                    is_array: array.is_some(),
+                }
+            }),
+        )),
+    )(input)
+}
+
+/// Parses `@name` or `@name(argument, ...)`
+pub(crate) fn decorator(input: &str) -> CResult<&str, Decorator> {
+    let arguments = delimited(
+        pair(char('('), space0),
+        separated_list0(tuple((space0, char(','), space0)), argument),
+        pair(space0, char(')')),
+    );
+    context(
+        "Decorator",
+        preceded(char('@'), pair(token, opt(arguments))),
+    )
+    .map(|(name, arguments)| Decorator {
+        name: name.to_string(),
+        arguments: arguments.unwrap_or_default(),
+    })
+    .parse(input)
+}
+
+/// Decorators and the whitespace after each of them, they are moved onto the declaration or
+/// the property following them by the declaration parser
+pub(crate) fn decorators(input: &str) -> CResult<&str, Vec<Decorator>> {
+    many0(terminated(decorator, multispace0))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decorator, DecoratorArgument};
+    use crate::parser::declaration::TypeReference;
+
+    #[test]
+    fn test_decorator() {
+        assert_eq!(
+            super::decorator("@deprecated\n"),
+            Ok(("\n", Decorator::new("deprecated")))
+        );
+        let (remains, decorator) =
+            super::decorator("@Term(\"A, b\" , 1,true, -2.5, Person[], trueish)").unwrap();
+        assert_eq!(remains, "");
+        assert_eq!(
+            decorator.arguments,
+            vec![
+                DecoratorArgument::String {
+                    value: String::from("A, b")
+                },
+                DecoratorArgument::Number { value: 1.0 },
+                DecoratorArgument::Boolean { value: true },
+                DecoratorArgument::Number { value: -2.5 },
+                DecoratorArgument::TypeReference {
+                    type_reference: TypeReference {
+                        name: String::from("Person"),
+                        namespace: None,
+                    },
+                    is_array: true,
+                },
+                DecoratorArgument::TypeReference {
+                    type_reference: TypeReference {
+                        name: String::from("trueish"),
+                        namespace: None,
+                    },
+                    is_array: false,
+                },
+            ],
+            "Should parse arguments by their type"
+        );
+        assert_eq!(
+            decorator.to_string(),
+            "@Term(\"A, b\", 1, true, -2.5, Person[], trueish)"
+        );
+        assert_eq!(
+            serde_json::to_value(super::Decorator::new("Term").with_string("Car \"X\"")).unwrap(),
+            serde_json::json!({
+                "$class": "Decorator",
+                "name": "Term",
+                "arguments": [{ "$class": "DecoratorString", "value": "Car \"X\"" }]
+            }),
+            "Should serialize decorators as the metamodel does"
+        );
+        assert_eq!(
+            super::Decorator::new("Term")
+                .with_string("Car \"X\"")
+                .to_string(),
+            "@Term(\"Car \\\"X\\\"\")",
+            "Should escape quotes in string arguments"
+        );
+        assert!(super::decorator("@(1)").is_err(), "Should need a name");
+
+        let (remains, decorators) = super::decorators("@a\n  @b()\n  concept").unwrap();
+        assert_eq!(remains, "concept");
+        assert_eq!(decorators, vec![Decorator::new("a"), Decorator::new("b")]);
+    }
+}
@@ -0,0 +1,315 @@
+//! Dialects register extra declaration and meta property keywords, so files of languages
+//! derived from Concerto can be parsed without changing the grammar. A callback turns each
+//! extension into a JSON node kept in the AST.
+
+use nom::{
+    bytes::complete::{tag, take_till, take_till1},
+    combinator::{opt, recognize},
+    error::context,
+    multi::many0,
+    sequence::{pair, preceded, tuple},
+    Err as NomErr, Parser,
+};
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use super::{
+    common::{string::string_value, token},
+    declaration::{Declaration, DeclarationKind},
+    error::{CError, CErrorKind, SourceError},
+    options::ParserOptions,
+    scan::{space0, space1},
+    CResult, Model,
+};
+
+/// Node built by a dialect callback, or the reason the input is rejected
+pub type ExtensionResult = Result<serde_json::Value, String>;
+
+type DeclarationCallback = Arc<dyn Fn(&str, &str) -> ExtensionResult + Send + Sync>;
+type MetaCallback = Arc<dyn Fn(Option<&str>) -> ExtensionResult + Send + Sync>;
+
+/// A declaration with an extension keyword, like `view PersonView { ... }`
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct Extension {
+    pub keyword: String,
+    /// Everything after the name, a braced block or the rest of the line
+    pub body: String,
+    pub node: serde_json::Value,
+}
+
+/// A meta property with an extension keyword, like `sensitive` or `pii=true`
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct MetaExtension {
+    /// Name of the property the meta property is defined on
+    pub property: String,
+    pub keyword: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub value: Option<String>,
+    pub node: serde_json::Value,
+}
+
+impl std::fmt::Display for MetaExtension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.keyword, value),
+            None => write!(f, "{}", self.keyword),
+        }
+    }
+}
+
+/// Extra keywords and the callbacks building their nodes. Built-in keywords always take
+/// precedence, and extension meta properties come after the built-in ones of a property.
+#[derive(Clone, Default)]
+pub struct Dialect {
+    declarations: HashMap<String, DeclarationCallback>,
+    metas: HashMap<String, MetaCallback>,
+}
+
+impl std::fmt::Debug for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dialect")
+            .field("declarations", &self.declarations.keys())
+            .field("metas", &self.metas.keys())
+            .finish()
+    }
+}
+
+impl Dialect {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a declaration keyword, the callback gets the name and the body of the
+    /// declaration
+    pub fn declaration(
+        mut self,
+        keyword: impl Into<String>,
+        callback: impl Fn(&str, &str) -> ExtensionResult + Send + Sync + 'static,
+    ) -> Self {
+        self.declarations.insert(keyword.into(), Arc::new(callback));
+        self
+    }
+
+    /// Registers a meta property keyword, the callback gets the value after `=` if there is
+    /// one
+    pub fn meta(
+        mut self,
+        keyword: impl Into<String>,
+        callback: impl Fn(Option<&str>) -> ExtensionResult + Send + Sync + 'static,
+    ) -> Self {
+        self.metas.insert(keyword.into(), Arc::new(callback));
+        self
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Dialect>> = const { RefCell::new(None) };
+}
+
+/// Parses a model with the keywords of the given dialect
+pub fn parse(
+    source: &str,
+    options: &ParserOptions,
+    dialect: &Dialect,
+) -> Result<Model, SourceError> {
+    struct Restore(Option<Dialect>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|c| *c.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(CURRENT.with(|c| c.borrow_mut().replace(dialect.clone())));
+    super::parse_with(source, options)
+}
+
+fn extension_error(input: &str, message: String) -> NomErr<CError<&str>> {
+    NomErr::Failure(CError {
+        code: CErrorKind::Extension(message),
+        input,
+    })
+}
+
+fn not_registered(input: &str) -> NomErr<CError<&str>> {
+    NomErr::Error(CError {
+        code: CErrorKind::Context("NotAnExtensionKeyword"),
+        input,
+    })
+}
+
+/// A block in braces, braces can be nested
+fn braced_block(input: &str) -> CResult<&str, &str> {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Ok((&input[i + 1..], &input[..i + 1])),
+            '}' => depth -= 1,
+            _ if depth == 0 => break,
+            _ => (),
+        }
+    }
+    Err(NomErr::Error(CError {
+        code: CErrorKind::ExpectedFound(String::from("}"), String::from("end of input")),
+        input,
+    }))
+}
+
+/// Parses a declaration that starts with a keyword of the dialect in use
+pub(crate) fn extension_declaration(input: &str) -> CResult<&str, Declaration> {
+    let (remains, (keyword, _, name, _, body)) = context(
+        "ExtensionDeclaration",
+        tuple((
+            token,
+            space1,
+            token,
+            space0,
+            braced_block.or(take_till(|c| c == '\r' || c == '\n')),
+        )),
+    )(input)?;
+
+    let callback = CURRENT.with(|c| {
+        c.borrow()
+            .as_ref()
+            .and_then(|d| d.declarations.get(keyword).cloned())
+    });
+    let body = body.trim_end();
+    let node = match callback {
+        Some(callback) => callback(name, body).map_err(|e| extension_error(input, e))?,
+        None => return Err(not_registered(input)),
+    };
+
+    let mut declaration = Declaration::new(DeclarationKind::Extension, name, Vec::new());
+    declaration.extension = Some(Extension {
+        keyword: keyword.to_string(),
+        body: body.to_string(),
+        node,
+    });
+    Ok((remains, declaration))
+}
+
+fn meta_extension(input: &str) -> CResult<&str, MetaExtension> {
+    let (remains, (keyword, value)) = pair(
+        token,
+        opt(preceded(
+            tag("="),
+            recognize(string_value).or(take_till1(|c: char| c.is_whitespace())),
+        )),
+    )(input)?;
+
+    let callback = CURRENT.with(|c| {
+        c.borrow()
+            .as_ref()
+            .and_then(|d| d.metas.get(keyword).cloned())
+    });
+    let node = match callback {
+        Some(callback) => callback(value).map_err(|e| extension_error(input, e))?,
+        None => return Err(not_registered(input)),
+    };
+
+    Ok((
+        remains,
+        MetaExtension {
+            property: String::new(),
+            keyword: keyword.to_string(),
+            value: value.map(String::from),
+            node,
+        },
+    ))
+}
+
+/// Meta properties with keywords of the dialect in use, the property name is filled in by the
+/// declaration parser
+pub(crate) fn meta_extensions(input: &str) -> CResult<&str, Vec<MetaExtension>> {
+    many0(preceded(space1, meta_extension))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Dialect;
+    use crate::parser::{declaration::DeclarationKind, options::ParserOptions};
+
+    fn dialect() -> Dialect {
+        Dialect::new()
+            .declaration("view", |name, body| {
+                Ok(serde_json::json!({ "view": name, "fields": body.matches(',').count() + 1 }))
+            })
+            .meta("sensitive", |_| Ok(serde_json::json!(true)))
+            .meta("pii", |value| match value {
+                Some("true") | Some("false") => Ok(serde_json::json!(value == Some("true"))),
+                _ => Err(String::from("pii should be true or false")),
+            })
+    }
+
+    #[test]
+    fn test_dialect() {
+        let input = "namespace test@1.0.0
+
+concept Person {
+  o String name optional sensitive pii=true
+  o String email
+}
+
+view PersonView { name, email }
+";
+        assert!(
+            crate::parser::parse(input).is_err(),
+            "Should not parse extensions without a dialect"
+        );
+
+        let model = super::parse(input, &ParserOptions::default(), &dialect()).unwrap();
+        let person = model.declaration("Person").unwrap();
+        assert_eq!(
+            person
+                .meta_extensions
+                .iter()
+                .map(|m| (m.property.as_str(), m.keyword.as_str(), m.node.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("name", "sensitive", serde_json::json!(true)),
+                ("name", "pii", serde_json::json!(true)),
+            ],
+            "Should build nodes of extension meta properties"
+        );
+
+        let view = model.declaration("PersonView").unwrap();
+        assert_eq!(view.kind, DeclarationKind::Extension);
+        assert_eq!(
+            view.extension.as_ref().map(|e| e.node.clone()),
+            Some(serde_json::json!({ "view": "PersonView", "fields": 2 })),
+            "Should build nodes of extension declarations"
+        );
+
+        assert_eq!(
+            model.to_string(),
+            input,
+            "Should render extensions as they are parsed"
+        );
+        assert!(
+            crate::parser::parse(input).is_err(),
+            "Should stop using the dialect after parsing"
+        );
+    }
+
+    #[test]
+    fn test_dialect_errors() {
+        let error = super::parse(
+            "namespace test@1.0.0\n\nconcept Person {\n  o String name pii=maybe\n}\n",
+            &ParserOptions::default(),
+            &dialect(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.kind,
+            crate::parser::error::CErrorKind::Extension(String::from(
+                "pii should be true or false"
+            )),
+            "Should report errors of callbacks"
+        );
+        assert_eq!((error.line, error.column), (4, 17));
+    }
+}
@@ -1,4 +1,9 @@
-use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
+use nom::{
+    combinator::all_consuming,
+    error::{ContextError, ErrorKind, FromExternalError, ParseError},
+};
+
+use crate::parser::common::token;
 
 /// Errors occuring during parse operations, Concerto parse error type
 #[derive(Debug, PartialEq)]
@@ -21,8 +26,8 @@ impl<I: std::fmt::Debug + std::fmt::Display> ParseError<I> for CError<I> {
     fn from_error_kind(input: I, kind: ErrorKind) -> Self {
         match kind {
             ErrorKind::Space => {
-                let mut found = input.to_string();
-                found.truncate(5);
+                // Copying the whole input would be quadratic, properties end on a failed space
+                let found = format!("{:.5}", input);
                 Self {
                     code: CErrorKind::ExpectedFound(String::from("Space"), found),
                     input,
@@ -51,8 +56,51 @@ impl<I, E> FromExternalError<I, E> for CError<I> {
     }
 }
 
-/// Kinds of errors while parsing Concerto files
+/// A parse error that doesn't borrow the input, with the position it occurred at.
+/// Line and column are 1-based, column counts characters.
 #[derive(Debug, PartialEq)]
+pub struct SourceError {
+    pub kind: CErrorKind,
+    /// Byte offset in the source
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourceError {
+    /// Locates an error returned by parsing `source`
+    pub fn new(source: &str, error: nom::Err<CError<&str>>) -> Self {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => Self::at(source, e.code, e.input),
+            nom::Err::Incomplete(_) => {
+                Self::at(source, CErrorKind::NomError(ErrorKind::Complete), "")
+            }
+        }
+    }
+
+    fn at(source: &str, kind: CErrorKind, remains: &str) -> Self {
+        let offset = source.len() - remains.len();
+        let consumed = &source[..offset];
+        let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            kind,
+            offset,
+            line: consumed.matches('\n').count() + 1,
+            column: consumed[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: error {:?}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// Kinds of errors while parsing Concerto files
+#[derive(Debug, PartialEq, Clone)]
 pub enum CErrorKind {
     /// Surfacing nom errors
     NomError(ErrorKind),
@@ -62,4 +110,49 @@ pub enum CErrorKind {
     StringPropertyWrongMeta,
     /// With context
     Context(&'static str),
+    /// Parsed definitions don't form a valid model
+    InvalidModel(BuildError),
+    /// A dialect callback rejected an extension
+    Extension(String),
+    /// Number doesn't fit its type, type name and the number as written
+    NumericOverflow(&'static str, String),
+    /// DateTime matching the pattern that isn't a real date or time, with the `chrono` feature
+    InvalidDateTime(String),
+}
+
+/// Errors occuring while building a model programmatically
+#[derive(Debug, PartialEq, Clone)]
+pub enum BuildError {
+    /// Every model should have a namespace
+    MissingNamespace,
+    /// Names should start with a letter and include alphanumerical characters
+    InvalidIdentifier(String),
+    /// Fragments of different namespaces can't be merged, expected and found namespaces
+    NamespaceMismatch(String, String),
+    /// A declaration with the same name is already in the model
+    DuplicateDeclaration(String),
+}
+
+impl BuildError {
+    pub(crate) fn check_identifier(name: &str) -> Result<(), Self> {
+        match all_consuming(token)(name) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Self::InvalidIdentifier(name.to_string())),
+        }
+    }
 }
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingNamespace => write!(f, "model has no namespace"),
+            Self::InvalidIdentifier(name) => write!(f, "invalid identifier: {}", name),
+            Self::NamespaceMismatch(expected, found) => {
+                write!(f, "expected namespace {}, found {}", expected, found)
+            }
+            Self::DuplicateDeclaration(name) => write!(f, "duplicate declaration: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,171 @@
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use super::{error::SourceError, options::ParserOptions, parse_with, Model};
+
+/// Errors while reading and parsing a model, with the path of the file when there is one
+#[derive(Debug)]
+pub enum FileError {
+    /// Model couldn't be read
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Content isn't a valid model
+    Parse {
+        path: Option<PathBuf>,
+        error: SourceError,
+    },
+}
+
+impl FileError {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Io { path, .. } | Self::Parse { path, .. } => path.as_deref(),
+        }
+    }
+
+    fn with_path(self, file: &Path) -> Self {
+        match self {
+            Self::Io { source, .. } => Self::Io {
+                path: Some(file.to_path_buf()),
+                source,
+            },
+            Self::Parse { error, .. } => Self::Parse {
+                path: Some(file.to_path_buf()),
+                error,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self
+            .path()
+            .map_or_else(|| String::from("<input>"), |p| p.display().to_string());
+        match self {
+            Self::Io { source, .. } => write!(f, "{}: {}", path, source),
+            Self::Parse { error, .. } => write!(f, "{}:{}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { error, .. } => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(value: io::Error) -> Self {
+        Self::Io {
+            path: None,
+            source: value,
+        }
+    }
+}
+
+impl From<SourceError> for FileError {
+    fn from(value: SourceError) -> Self {
+        Self::Parse {
+            path: None,
+            error: value,
+        }
+    }
+}
+
+/// Reads the whole model from a reader and parses it
+pub fn parse_reader(reader: impl BufRead) -> Result<Model, FileError> {
+    parse_reader_with(reader, &ParserOptions::default())
+}
+
+pub fn parse_reader_with(
+    mut reader: impl BufRead,
+    options: &ParserOptions,
+) -> Result<Model, FileError> {
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+    Ok(parse_with(&source, options)?)
+}
+
+/// Reads and parses a model file, errors carry the path of the file
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Model, FileError> {
+    parse_file_with(path, &ParserOptions::default())
+}
+
+pub fn parse_file_with(
+    path: impl AsRef<Path>,
+    options: &ParserOptions,
+) -> Result<Model, FileError> {
+    let path = path.as_ref();
+    read_and_parse(path, options).map_err(|e| e.with_path(path))
+}
+
+fn read_and_parse(path: &Path, options: &ParserOptions) -> Result<Model, FileError> {
+    let source = std::fs::read_to_string(path)?;
+    Ok(parse_with(&source, options)?)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_parse_reader() {
+        let input = "namespace test@1.0.0
+
+        concept Person {
+          o String name
+        }
+        ";
+        assert_eq!(
+            super::parse_reader(input.as_bytes()).ok(),
+            crate::parser::model(input).ok().map(|(_, model)| model),
+            "Should parse a model from a reader"
+        );
+
+        let error = super::parse_reader("namespace test@1.0.0\nconcept {}".as_bytes())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("<input>:2:1: "),
+            "Should point at the error, got {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let path = std::env::temp_dir().join(format!("concerto-file-{}.cto", std::process::id()));
+        std::fs::write(&path, "namespace test@1.0.0\n\nconcept Person {}\n").unwrap();
+        assert!(
+            super::parse_file(&path)
+                .unwrap()
+                .declaration("Person")
+                .is_some(),
+            "Should parse a model file"
+        );
+
+        std::fs::write(&path, "namespace test@1.0.0\n\nconcept {}\n").unwrap();
+        let error = super::parse_file(&path).unwrap_err();
+        assert_eq!(
+            error.path(),
+            Some(path.as_path()),
+            "Should carry the file path"
+        );
+        assert!(matches!(error, super::FileError::Parse { .. }));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(
+                super::parse_file(&path),
+                Err(super::FileError::Io { path: Some(_), .. })
+            ),
+            "Should report missing files with their path"
+        );
+    }
+}
@@ -0,0 +1,515 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1, take_while1},
+    character::complete::char,
+    combinator::{all_consuming, map_opt, opt, verify},
+    error::context,
+    multi::separated_list1,
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    Parser,
+};
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use super::{
+    namespace::{
+        import_fqn, namespace_name, unversioned_namespaces, FullyQualifiedName, Namespace,
+    },
+    version::version_identifier,
+};
+use crate::parser::{
+    common::{keywords, token},
+    options::{self, LanguageVersion},
+    scan::{space0, space1},
+    CResult,
+};
+
+/// Brings types defined in another namespace into the model. Imports ending with
+/// `from <uri>` keep where the model of the namespace can be fetched from in `uri`.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
+pub enum Import {
+    /// `import org.acme@1.0.0.Address`
+    #[serde(rename = "ImportType")]
+    Type {
+        namespace: Namespace,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uri: Option<String>,
+    },
+    /// `import org.acme@1.0.0.*`, every type of the namespace
+    #[serde(rename = "ImportAll")]
+    All {
+        namespace: Namespace,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uri: Option<String>,
+    },
+    /// `import org.acme@1.0.0.{Address, Person as Owner}`, types lists all imported names as
+    /// the namespace declares them and `aliased_types` the ones given another local name
+    #[serde(rename = "ImportTypes")]
+    Types {
+        namespace: Namespace,
+        types: Vec<String>,
+        #[serde(
+            rename = "aliasedTypes",
+            default,
+            skip_serializing_if = "Vec::is_empty"
+        )]
+        aliased_types: Vec<AliasedType>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uri: Option<String>,
+    },
+}
+
+/// A type imported under another name, written `Address as Addr`
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class", rename = "AliasedType")]
+pub struct AliasedType {
+    pub name: String,
+    #[serde(rename = "aliasedName")]
+    pub aliased_name: String,
+}
+
+impl Import {
+    /// The namespace, with the version, the import refers to
+    pub fn namespace(&self) -> &Namespace {
+        match self {
+            Import::Type { namespace, .. }
+            | Import::All { namespace, .. }
+            | Import::Types { namespace, .. } => namespace,
+        }
+    }
+
+    pub(crate) fn namespace_mut(&mut self) -> &mut Namespace {
+        match self {
+            Import::Type { namespace, .. }
+            | Import::All { namespace, .. }
+            | Import::Types { namespace, .. } => namespace,
+        }
+    }
+
+    /// Where the model of the imported namespace can be fetched from
+    pub fn uri(&self) -> Option<&str> {
+        match self {
+            Import::Type { uri, .. } | Import::All { uri, .. } | Import::Types { uri, .. } => {
+                uri.as_deref()
+            }
+        }
+    }
+
+    fn with_uri(mut self, from: Option<&str>) -> Self {
+        match &mut self {
+            Import::Type { uri, .. } | Import::All { uri, .. } | Import::Types { uri, .. } => {
+                *uri = from.map(String::from)
+            }
+        }
+        self
+    }
+
+    /// Whether the import brings the given type name into the model. Wildcard imports bring
+    /// any name, whether the namespace declares it is only known from the imported model.
+    pub fn imports(&self, type_name: &str) -> bool {
+        self.imported_name(type_name).is_some()
+    }
+
+    /// Name in the imported namespace of a type named `local_name` in the model, which only
+    /// differs for aliased types
+    pub fn imported_name<'a>(&'a self, local_name: &'a str) -> Option<&'a str> {
+        match self {
+            Import::Type { name, .. } => (name == local_name).then_some(name.as_str()),
+            Import::All { .. } => Some(local_name),
+            Import::Types {
+                types,
+                aliased_types,
+                ..
+            } => match aliased_types.iter().find(|a| a.aliased_name == local_name) {
+                Some(aliased) => Some(&aliased.name),
+                None => types
+                    .iter()
+                    .find(|t| *t == local_name && !aliased_types.iter().any(|a| &a.name == *t))
+                    .map(String::as_str),
+            },
+        }
+    }
+
+    /// Name in the model of a type of the imported namespace, if the import brings it
+    pub fn local_name<'a>(&'a self, type_name: &'a str) -> Option<&'a str> {
+        match self {
+            Import::Type { name, .. } => (name == type_name).then_some(name.as_str()),
+            Import::All { .. } => Some(type_name),
+            Import::Types {
+                types,
+                aliased_types,
+                ..
+            } => {
+                let name = types.iter().find(|t| *t == type_name)?;
+                match aliased_types.iter().find(|a| &a.name == name) {
+                    Some(aliased) => Some(&aliased.aliased_name),
+                    None => Some(name),
+                }
+            }
+        }
+    }
+}
+
+impl From<FullyQualifiedName> for Import {
+    fn from(value: FullyQualifiedName) -> Self {
+        Import::Type {
+            namespace: Namespace::new(value.namespace_name(), value.version().clone()),
+            name: value.type_name().to_string(),
+            uri: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Import {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let namespace = self.namespace();
+        match namespace.declared_version() {
+            Some(version) => write!(f, "import {}@{}.", namespace.name(), version)?,
+            None => write!(f, "import {}.", namespace.name())?,
+        }
+        match self {
+            Import::Type { name, .. } => write!(f, "{}", name),
+            Import::All { .. } => write!(f, "*"),
+            Import::Types { types, .. } => {
+                let types = types
+                    .iter()
+                    .map(|t| match self.local_name(t) {
+                        Some(local) if local != t => format!("{} as {}", t, local),
+                        _ => t.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, "{{{}}}", types.join(", "))
+            }
+        }?;
+        match self.uri() {
+            Some(uri) => write!(f, " from {}", uri),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The namespace an import refers to and the dot that follows it, like `org.acme@1.0.0.`
+fn imported_namespace(input: &str) -> CResult<&str, Namespace> {
+    // The version runs up to the last dot, pre-release tags can have dots
+    let version = map_opt(
+        take_while1(|c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')),
+        |v: &str| {
+            let (_, version) = all_consuming(version_identifier)(v.strip_suffix('.')?).ok()?;
+            Some(version)
+        },
+    );
+    let unversioned = terminated(
+        verify(namespace_name, |_: &str| unversioned_namespaces()),
+        char('.'),
+    )
+    .map(Namespace::unversioned);
+    context(
+        "ImportedNamespace",
+        alt((
+            separated_pair(namespace_name, char('@'), version)
+                .map(|(name, version)| Namespace::new(name, version)),
+            unversioned,
+        )),
+    )(input)
+}
+
+fn import_all(input: &str) -> CResult<&str, Import> {
+    context(
+        "ImportAll",
+        terminated(imported_namespace, char('*')).map(|namespace| Import::All {
+            namespace,
+            uri: None,
+        }),
+    )(input)
+}
+
+/// A type name in braces, optionally followed by `as` and the name it has in the model
+fn imported_type(input: &str) -> CResult<&str, (&str, Option<&str>)> {
+    pair(
+        token,
+        opt(preceded(
+            tuple((space1, tag("as"), space1)),
+            verify(token, |_: &str| aliased_imports()),
+        )),
+    )(input)
+}
+
+/// Whether imported types can be aliased in the running parse, they can since Concerto 3.x
+fn aliased_imports() -> bool {
+    options::current().language_version != LanguageVersion::V2
+}
+
+/// The braced list of imported types, like `{Address, Person as Owner}`
+pub(crate) fn imported_types(input: &str) -> CResult<&str, Vec<(&str, Option<&str>)>> {
+    delimited(
+        pair(char('{'), space0),
+        separated_list1(tuple((space0, char(','), space0)), imported_type),
+        pair(space0, char('}')),
+    )(input)
+}
+
+/// Import of the listed types of a namespace, each with the name it has in the model if
+/// it is aliased
+pub(crate) fn types_import(namespace: Namespace, types: &[(&str, Option<&str>)]) -> Import {
+    Import::Types {
+        namespace,
+        types: types.iter().map(|(name, _)| name.to_string()).collect(),
+        aliased_types: types
+            .iter()
+            .filter_map(|(name, alias)| {
+                alias.map(|alias| AliasedType {
+                    name: name.to_string(),
+                    aliased_name: alias.to_string(),
+                })
+            })
+            .collect(),
+        uri: None,
+    }
+}
+
+fn import_types(input: &str) -> CResult<&str, Import> {
+    context(
+        "ImportTypes",
+        pair(imported_namespace, imported_types)
+            .map(|(namespace, types)| types_import(namespace, &types)),
+    )(input)
+}
+
+/// `from` and the URI following it, up to the next whitespace
+fn from_uri(input: &str) -> CResult<&str, &str> {
+    context(
+        "ImportFrom",
+        preceded(
+            tuple((space1, tag("from"), space1)),
+            take_till1(|c: char| c.is_whitespace()),
+        ),
+    )(input)
+}
+
+pub fn import(input: &str) -> CResult<&str, Import> {
+    context(
+        "Import",
+        preceded(
+            pair(keywords::import, space1),
+            pair(
+                alt((
+                    import_all,
+                    import_types,
+                    import_fqn.map(|(namespace, name)| Import::Type {
+                        namespace,
+                        name,
+                        uri: None,
+                    }),
+                )),
+                opt(from_uri),
+            ),
+        )
+        .map(|(import, uri)| import.with_uri(uri)),
+    )(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Import, Namespace};
+    use crate::parser::version::SemanticVersion;
+
+    #[test]
+    fn test_import() {
+        assert_eq!(
+            super::import("import org.acme@1.2.0.Address"),
+            Ok((
+                "",
+                Import::Type {
+                    namespace: Namespace::new(
+                        "org.acme",
+                        SemanticVersion::Version((1, 2, 0).into())
+                    ),
+                    name: "Address".to_string(),
+                    uri: None,
+                }
+            )),
+            "Should parse import of a type from a dotted namespace"
+        );
+        assert!(
+            super::import("import org.acme.Address").is_err(),
+            "Should not parse import without a version"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            super::import("import  org.acme@1.2.0-beta.Address").map(|(_, i)| i.to_string()),
+            Ok(String::from("import org.acme@1.2.0-beta.Address")),
+            "Should render import"
+        );
+        for input in [
+            "import org.acme@1.2.0-beta.1.*",
+            "import org.acme@1.2.0.{Address, Person}",
+            "import org.acme@1.2.0.{Address as Addr, Person}",
+        ] {
+            assert_eq!(
+                super::import(input).map(|(_, i)| i.to_string()),
+                Ok(String::from(input)),
+                "Should render import as it is parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_import_kinds() {
+        let namespace = Namespace::new("org.acme", SemanticVersion::Version((1, 0, 0).into()));
+        assert_eq!(
+            super::import("import org.acme@1.0.0.*"),
+            Ok((
+                "",
+                Import::All {
+                    namespace: namespace.clone(),
+                    uri: None,
+                }
+            )),
+            "Should parse wildcard import"
+        );
+        assert_eq!(
+            super::import("import org.acme@1.0.0.{ Address,Person }"),
+            Ok((
+                "",
+                Import::Types {
+                    namespace: namespace.clone(),
+                    types: vec![String::from("Address"), String::from("Person")],
+                    aliased_types: Vec::new(),
+                    uri: None,
+                }
+            )),
+            "Should parse braced import of several types"
+        );
+        assert!(
+            super::import("import org.acme@1.0.0.{}").is_err(),
+            "Should not parse braced import of no types"
+        );
+        assert_eq!(
+            super::import("import org.acme@1.0.0-beta.2.*").map(|(_, i)| i.namespace().clone()),
+            Ok(Namespace::new(
+                "org.acme",
+                SemanticVersion::VersionWithRelease((1, 0, 0).into(), String::from("beta.2"))
+            )),
+            "Should keep dots of the pre-release in the version"
+        );
+
+        let all = Import::All {
+            namespace: namespace.clone(),
+            uri: None,
+        };
+        assert!(all.imports("Address"));
+        let types = Import::Types {
+            namespace,
+            types: vec![String::from("Address")],
+            aliased_types: Vec::new(),
+            uri: None,
+        };
+        assert!(types.imports("Address") && !types.imports("Person"));
+        assert_eq!(
+            serde_json::to_value(&types).unwrap(),
+            serde_json::json!({
+              "$class": "ImportTypes",
+              "namespace": "org.acme@1.0.0",
+              "types": ["Address"],
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&all).unwrap(),
+            serde_json::json!({ "$class": "ImportAll", "namespace": "org.acme@1.0.0" })
+        );
+    }
+
+    #[test]
+    fn test_aliased_import() {
+        use crate::parser::options::{LanguageVersion, ParserOptions};
+
+        let (_, import) = super::import("import org.acme@1.0.0.{Address as Addr, Person}").unwrap();
+        assert_eq!(
+            import,
+            Import::Types {
+                namespace: Namespace::new("org.acme", SemanticVersion::Version((1, 0, 0).into())),
+                types: vec![String::from("Address"), String::from("Person")],
+                aliased_types: vec![super::AliasedType {
+                    name: String::from("Address"),
+                    aliased_name: String::from("Addr"),
+                }],
+                uri: None,
+            },
+            "Should parse aliased type in braced import"
+        );
+        assert_eq!(import.imported_name("Addr"), Some("Address"));
+        assert_eq!(import.imported_name("Person"), Some("Person"));
+        assert_eq!(
+            import.imported_name("Address"),
+            None,
+            "Should only bring aliased types in under their alias"
+        );
+        assert_eq!(import.local_name("Address"), Some("Addr"));
+        assert_eq!(
+            serde_json::to_value(&import).unwrap()["aliasedTypes"],
+            serde_json::json!([
+              { "$class": "AliasedType", "name": "Address", "aliasedName": "Addr" },
+            ])
+        );
+
+        let v2 = ParserOptions {
+            language_version: LanguageVersion::V2,
+            ..ParserOptions::default()
+        };
+        let model = |import: &str| {
+            crate::parser::parse_with(&format!("namespace org.people\n{}\n", import), &v2)
+        };
+        assert!(
+            model("import org.acme@1.0.0.{Address as Addr, Person}").is_err(),
+            "Should only alias imported types since Concerto 3.x"
+        );
+        assert!(model("import org.acme@1.0.0.{Address, Person}").is_ok());
+    }
+
+    #[test]
+    fn test_from_uri() {
+        let (_, import) = super::import(
+            "import org.acme@1.0.0.{Address, Person} from https://models.example.com/acme.cto",
+        )
+        .unwrap();
+        assert_eq!(import.uri(), Some("https://models.example.com/acme.cto"));
+        assert!(import.imports("Person"));
+        assert_eq!(
+            import.to_string(),
+            "import org.acme@1.0.0.{Address, Person} from https://models.example.com/acme.cto",
+            "Should render the URI after the imported types"
+        );
+        assert_eq!(
+            serde_json::to_value(&import).unwrap()["uri"],
+            serde_json::json!("https://models.example.com/acme.cto")
+        );
+        assert_eq!(
+            super::import("import org.acme@1.0.0.Address\nconcept").map(|(_, i)| i.uri().is_none()),
+            Ok(true),
+            "Should not take what follows on the next line as a URI"
+        );
+
+        let permissive = crate::parser::options::ParserOptions::permissive();
+        let parsed = crate::parser::options::with_options(&permissive, || {
+            super::import("import org.acme.Address from https://models.example.com/acme.cto")
+                .map(|(_, i)| i)
+        });
+        assert_eq!(
+            parsed,
+            Ok(Import::Type {
+                namespace: Namespace::unversioned("org.acme"),
+                name: String::from("Address"),
+                uri: Some(String::from("https://models.example.com/acme.cto")),
+            }),
+            "Should parse URI of imports written without a version"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use nom::{
+    combinator::{all_consuming, consumed},
+    multi::many0,
+    sequence::delimited,
+};
+use std::ops::Range;
+
+use super::{
+    comment::trivia0,
+    error::SourceError,
+    options::{with_options, ParserOptions},
+    span::{declaration_spans, model_with_spans_with, shift, spanned_declaration, ModelSpans},
+    Model,
+};
+
+/// A parsed model kept together with its source and spans, so that edits only parse the
+/// declarations they touch again. Meant for editor integrations working on large files.
+#[derive(Debug, Clone)]
+pub struct IncrementalModel {
+    source: String,
+    model: Model,
+    spans: ModelSpans,
+    /// Options the source is parsed with, again on every edit
+    options: ParserOptions,
+}
+
+impl IncrementalModel {
+    pub fn parse(source: impl Into<String>) -> Result<Self, SourceError> {
+        Self::parse_with(source, &ParserOptions::default())
+    }
+
+    pub fn parse_with(
+        source: impl Into<String>,
+        options: &ParserOptions,
+    ) -> Result<Self, SourceError> {
+        let source = source.into();
+        let (model, spans) = parse_all(&source, options)?;
+        Ok(Self {
+            source,
+            model,
+            spans,
+            options: *options,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn spans(&self) -> &ModelSpans {
+        &self.spans
+    }
+
+    /// Replaces the bytes in `range` with `text` and updates the model. Declarations touched
+    /// by the edit are parsed again and spliced into the model, the whole source is parsed if
+    /// the edit reaches outside of declarations. Returns the positions of the parsed
+    /// declarations in `Model::declarations`.
+    /// If the edited source isn't a valid model, nothing changes and the error is returned.
+    /// Panics if the range is out of bounds or not on character boundaries, like
+    /// `String::replace_range`.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<Range<usize>, SourceError> {
+        let mut source = self.source.clone();
+        source.replace_range(range.clone(), text);
+        let delta = text.len() as isize - range.len() as isize;
+
+        let touched: Vec<usize> = self
+            .spans
+            .declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.span.start <= range.end && range.start <= d.span.end)
+            .map(|(i, _)| i)
+            .collect();
+
+        let reparsed = match (touched.first(), touched.last()) {
+            (Some(&first), Some(&last)) => {
+                let start = self.spans.declarations[first].span.start.min(range.start);
+                let end = self.spans.declarations[last].span.end.max(range.end);
+                let options = self.options;
+                with_options(&options, || {
+                    self.reparse(&source, first..last + 1, start..end, delta)
+                })
+            }
+            _ => None,
+        };
+
+        let reparsed = match reparsed {
+            Some(reparsed) => reparsed,
+            None => {
+                let (model, spans) = parse_all(&source, &self.options)?;
+                self.model = model;
+                self.spans = spans;
+                0..self.model.declarations.len()
+            }
+        };
+        self.source = source;
+
+        Ok(reparsed)
+    }
+
+    /// Parses the declarations in `old_range` of the source again, `None` if the edited text
+    /// there isn't only declarations
+    fn reparse(
+        &mut self,
+        source: &str,
+        declarations: Range<usize>,
+        old_range: Range<usize>,
+        delta: isize,
+    ) -> Option<Range<usize>> {
+        let new_end = old_range.end.checked_add_signed(delta)?;
+        let region = source.get(old_range.start..new_end)?;
+
+        let (_, parsed) = all_consuming(many0(delimited(
+            trivia0,
+            consumed(spanned_declaration),
+            trivia0,
+        )))(region)
+        .ok()?;
+
+        let mut new_spans = Vec::with_capacity(parsed.len());
+        let mut new_declarations = Vec::with_capacity(parsed.len());
+        for (part, (declaration, properties)) in parsed {
+            let mut spans = declaration_spans(region, part, &properties);
+            spans.shift(old_range.start as isize);
+            new_spans.push(spans);
+            new_declarations.push(declaration);
+        }
+        let reparsed = declarations.start..declarations.start + new_declarations.len();
+
+        for spans in self.spans.declarations[declarations.end..].iter_mut() {
+            spans.shift(delta);
+        }
+        for import in self.spans.imports.iter_mut() {
+            if import.start >= old_range.end {
+                *import = shift(import, delta);
+            }
+        }
+        if let Some(header) = self.spans.header.as_mut() {
+            if header.start >= old_range.end {
+                *header = shift(header, delta);
+            }
+        }
+        if self.spans.namespace.start >= old_range.end {
+            self.spans.namespace = shift(&self.spans.namespace, delta);
+        }
+
+        self.spans
+            .declarations
+            .splice(declarations.clone(), new_spans);
+        self.model
+            .declarations
+            .splice(declarations, new_declarations);
+        self.model.reindex();
+
+        Some(reparsed)
+    }
+}
+
+/// Parses a whole source with spans, failing on input that isn't part of the model
+pub(crate) fn parse_all(
+    source: &str,
+    options: &ParserOptions,
+) -> Result<(Model, ModelSpans), SourceError> {
+    all_consuming(|input| model_with_spans_with(input, options))(source)
+        .map(|(_, parsed)| parsed)
+        .map_err(|e| SourceError::new(source, e))
+}
+
+#[cfg(test)]
+mod test {
+    const INPUT: &str = "namespace test@1.0.0
+
+concept Person {
+  o String name
+}
+
+concept Address {
+  o String street
+}
+
+concept Country {
+  o String code
+}
+";
+
+    #[test]
+    fn test_edit() {
+        let mut incremental = super::IncrementalModel::parse(INPUT).unwrap();
+
+        let at = INPUT.find("o String street").unwrap() + "o String street".len();
+        assert_eq!(
+            incremental.edit(at..at, "\n  o Integer number optional"),
+            Ok(1..2),
+            "Should parse only the edited declaration"
+        );
+        assert_eq!(
+            incremental.model(),
+            &crate::parser::model(incremental.source()).unwrap().1,
+            "Should splice the declaration into the model"
+        );
+        let (_, (_, spans)) = crate::parser::span::model_with_spans(incremental.source()).unwrap();
+        assert_eq!(
+            incremental.spans(),
+            &spans,
+            "Should shift the spans of the following declarations"
+        );
+        assert_eq!(
+            incremental
+                .model()
+                .declaration("Country")
+                .map(|d| d.name.as_str()),
+            Some("Country"),
+            "Should keep the lookup index up to date"
+        );
+
+        let at = incremental.source().find("concept Country").unwrap();
+        assert_eq!(
+            incremental.edit(at..at, "concept City {}\n\n"),
+            Ok(2..4),
+            "Should parse declarations added next to an edited one"
+        );
+        assert_eq!(
+            incremental.model(),
+            &crate::parser::model(incremental.source()).unwrap().1,
+        );
+
+        let at = incremental.source().find("test@").unwrap();
+        assert_eq!(
+            incremental.edit(at..at + 4, "demo"),
+            Ok(0..4),
+            "Should parse everything when the edit is outside declarations"
+        );
+        assert_eq!(incremental.model().namespace.name(), "demo");
+
+        let before = incremental.source().to_string();
+        let at = before.find("concept Person").unwrap();
+        assert!(
+            incremental.edit(at..at + 7, "concpt").is_err(),
+            "Should report an invalid edit"
+        );
+        assert_eq!(
+            incremental.source(),
+            before,
+            "Should not change anything after an invalid edit"
+        );
+    }
+}
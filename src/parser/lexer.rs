@@ -0,0 +1,349 @@
+//! Two-phase parsing. A byte-level lexer splits the source into lexemes once, and parsers
+//! over lexemes find definitions, declarations and property lines without looking at each
+//! character again. Versions, values and meta properties are parsed from the text of their
+//! lexemes by the character parsers, so both phases build the same model. Whatever the
+//! lexeme parsers don't accept is left to the character parser, which also reports errors.
+
+use nom::{
+    branch::alt,
+    combinator::{map_opt, opt, verify},
+    multi::{fold_many0, many0_count, many1_count},
+    sequence::{pair, preceded, terminated, tuple},
+    IResult, Parser,
+};
+
+use super::{
+    declaration::{self, Declaration, DeclarationKind, Property},
+    dialect::{meta_extensions, MetaExtension},
+    import::{self, Import},
+    namespace::{self, Namespace},
+    Model, ModelBuilder,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Kind {
+    /// An ASCII letter or `_` followed by letters, digits and `_`, like `token`. Words of
+    /// other scripts are left to the character parser.
+    Word,
+    Number,
+    /// Quoted string or regex, with its delimiters
+    Quoted,
+    /// A run of spaces and tabs
+    Space,
+    /// `\n` or `\r\n`
+    Newline,
+    Punctuation(u8),
+    /// Anything else, none of the lexeme parsers accept it
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lexeme {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+type Lexemes<'l> = &'l [Lexeme];
+type LResult<'l, O> = IResult<Lexemes<'l>, O, ()>;
+
+/// Parses a model in two phases, `None` if it has to be left to the character parser
+pub(crate) fn parse(source: &str) -> Option<Model> {
+    let lexemes = lex(source);
+    let (rest, builder) = terminated(
+        fold_many0(
+            preceded(blank0, |i| definition(source, i)),
+            ModelBuilder::new,
+            |builder, definition| match definition {
+                Definition::Namespace(ns) => builder.with_namespace(ns),
+                Definition::Import(i) => builder.add_import(i),
+                Definition::Declaration(d) => builder.add_declaration(*d),
+            },
+        ),
+        blank0,
+    )(&lexemes)
+    .ok()?;
+    match rest.is_empty() {
+        true => builder.build().ok(),
+        false => None,
+    }
+}
+
+/// Splits a source into lexemes, every byte is in one of them
+fn lex(source: &str) -> Vec<Lexeme> {
+    let bytes = source.as_bytes();
+    // Declarations take a few lexemes per byte of a property line
+    let mut lexemes = Vec::with_capacity(bytes.len() / 3);
+    let mut start = 0;
+    while start < bytes.len() {
+        let run = |from: usize, f: fn(u8) -> bool| {
+            from + bytes[from..].iter().take_while(|b| f(**b)).count()
+        };
+        let (kind, end) = match bytes[start] {
+            b' ' | b'\t' => (Kind::Space, run(start, |b| b == b' ' || b == b'\t')),
+            b'\n' => (Kind::Newline, start + 1),
+            b'\r' if bytes.get(start + 1) == Some(&b'\n') => (Kind::Newline, start + 2),
+            b if b.is_ascii_alphabetic() || b == b'_' => (
+                Kind::Word,
+                run(start, |b| b.is_ascii_alphanumeric() || b == b'_'),
+            ),
+            b if b.is_ascii_digit() => (Kind::Number, run(start, |b| b.is_ascii_digit())),
+            delimiter @ (b'"' | b'\'' | b'/') => match quoted(bytes, start, delimiter) {
+                Some(end) => (Kind::Quoted, end),
+                None => (Kind::Unknown, bytes.len()),
+            },
+            b if b.is_ascii_punctuation() => (Kind::Punctuation(b), start + 1),
+            _ => {
+                let width = source[start..].chars().next().map_or(1, char::len_utf8);
+                (Kind::Unknown, start + width)
+            }
+        };
+        lexemes.push(Lexeme { kind, start, end });
+        start = end;
+    }
+    lexemes
+}
+
+/// End of a quoted string or regex starting at `start`, after its closing delimiter
+fn quoted(bytes: &[u8], start: usize, delimiter: u8) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == delimiter => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+enum Definition {
+    Namespace(Namespace),
+    Import(Import),
+    Declaration(Box<Declaration>),
+}
+
+fn definition<'l>(source: &str, input: Lexemes<'l>) -> LResult<'l, Definition> {
+    alt((
+        map_opt(keyword_line(source, "namespace"), |text| {
+            let (_, ns) =
+                nom::combinator::all_consuming(namespace::namespace_identifier)(text).ok()?;
+            Some(Definition::Namespace(ns))
+        }),
+        map_opt(keyword_line(source, "import"), |text| {
+            let (_, i) = nom::combinator::all_consuming(import::import)(text).ok()?;
+            Some(Definition::Import(i))
+        }),
+        (|i| concept(source, i)).map(|d| Definition::Declaration(Box::new(d))),
+    ))(input)
+}
+
+/// A keyword and the lexemes up to the next whitespace, like `import org.acme@1.0.0.Address`
+fn keyword_line<'l, 's>(
+    source: &'s str,
+    keyword: &'static str,
+) -> impl FnMut(Lexemes<'l>) -> LResult<'l, &'s str> {
+    move |input: Lexemes<'l>| {
+        let (rest, _) = tuple((
+            word(source, keyword),
+            kind(Kind::Space),
+            many1_count(verify(any, |l: &Lexeme| {
+                !matches!(l.kind, Kind::Space | Kind::Newline)
+            })),
+        ))(input)?;
+        let consumed = &input[..input.len() - rest.len()];
+        Ok((rest, text(source, consumed)))
+    }
+}
+
+/// `concept Name { ... }`, with a property per line
+fn concept<'l>(source: &str, input: Lexemes<'l>) -> LResult<'l, Declaration> {
+    let (input, (_, _, name, _, _)) = tuple((
+        word(source, "concept"),
+        kind(Kind::Space),
+        kind(Kind::Word),
+        space0,
+        punctuation(b'{'),
+    ))(input)?;
+
+    let no_properties = pair(blank0, punctuation(b'}')).map(|_| Vec::new());
+    let properties = tuple((
+        space0,
+        kind(Kind::Newline),
+        fold_many0(
+            |i| property_line(source, i),
+            Vec::new,
+            |mut acc: Vec<_>, property| {
+                acc.push(property);
+                acc
+            },
+        ),
+        blank0,
+        punctuation(b'}'),
+    ))
+    .map(|(_, _, properties, _, _)| properties);
+
+    let (input, properties) = alt((properties, no_properties))(input)?;
+    let name = &source[name.start..name.end];
+    Ok((
+        input,
+        declaration::with_meta_extensions(DeclarationKind::Concept, name, properties),
+    ))
+}
+
+/// `o Type[] name` and the meta properties of a property, up to the end of the line
+fn property_line<'l>(
+    source: &str,
+    input: Lexemes<'l>,
+) -> LResult<'l, (Property, Vec<MetaExtension>)> {
+    let (input, (_, _, _, class, _, array, name)) = tuple((
+        space0,
+        word(source, "o"),
+        space0,
+        kind(Kind::Word),
+        space0,
+        opt(tuple((
+            punctuation(b'['),
+            space0,
+            punctuation(b']'),
+            space0,
+        ))),
+        kind(Kind::Word),
+    ))(input)?;
+
+    // Meta properties are parsed by the character parsers, up to where they stop
+    let metas = &source[name.end..];
+    let class = &source[class.start..class.end];
+    let name = &source[name.start..name.end];
+    let (remains, property) =
+        declaration::property_metas(metas, class, name, array.is_some()).map_err(|_| error())?;
+    let (remains, extensions) = meta_extensions(remains).map_err(|_| error())?;
+
+    let offset = source.len() - remains.len();
+    let resumed = input
+        .iter()
+        .position(|l| l.start == offset)
+        .map_or(&input[input.len()..], |i| &input[i..]);
+    if resumed.is_empty() && offset != source.len() {
+        return Err(error());
+    }
+    let (input, _) = pair(space0, kind(Kind::Newline))(resumed)?;
+    Ok((input, (property, extensions)))
+}
+
+fn any<'l>(input: Lexemes<'l>) -> LResult<'l, &'l Lexeme> {
+    match input.split_first() {
+        Some((first, rest)) => Ok((rest, first)),
+        None => Err(error()),
+    }
+}
+
+fn kind<'l>(expected: Kind) -> impl Fn(Lexemes<'l>) -> LResult<'l, &'l Lexeme> {
+    move |input: Lexemes<'l>| verify(any, |l: &Lexeme| l.kind == expected)(input)
+}
+
+fn punctuation<'l>(expected: u8) -> impl Fn(Lexemes<'l>) -> LResult<'l, &'l Lexeme> {
+    kind(Kind::Punctuation(expected))
+}
+
+fn word<'l, 's>(
+    source: &'s str,
+    expected: &'static str,
+) -> impl Fn(Lexemes<'l>) -> LResult<'l, ()> + 's {
+    move |input: Lexemes<'l>| {
+        verify(kind(Kind::Word), |l: &Lexeme| {
+            &source[l.start..l.end] == expected
+        })
+        .map(|_| ())
+        .parse(input)
+    }
+}
+
+fn space0<'l>(input: Lexemes<'l>) -> LResult<'l, ()> {
+    opt(kind(Kind::Space)).map(|_| ()).parse(input)
+}
+
+/// Spaces and line endings
+fn blank0<'l>(input: Lexemes<'l>) -> LResult<'l, ()> {
+    many0_count(alt((kind(Kind::Space), kind(Kind::Newline))))
+        .map(|_| ())
+        .parse(input)
+}
+
+/// Source text from the first to the last of the lexemes
+fn text<'s>(source: &'s str, lexemes: Lexemes<'_>) -> &'s str {
+    match (lexemes.first(), lexemes.last()) {
+        (Some(first), Some(last)) => &source[first.start..last.end],
+        _ => "",
+    }
+}
+
+fn error() -> nom::Err<()> {
+    nom::Err::Error(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{dialect::Dialect, options::ParserOptions};
+
+    #[test]
+    fn test_parse() {
+        let source = "namespace test@1.0.0-beta.1
+import other@2.1.0.Address
+
+concept Person {
+  o String name default=\"a b\" regex=/[A-Z][a-z]+/ length=[1, 32] optional
+  o Boolean active default=true
+  o Integer age range=[0,150]
+  o Long total range=[,100] default=-1 optional
+  o Double ratio default=1.5e3 range=[-1.0 , 1e4]
+  o DateTime born default=2000-01-01 optional
+  o Address[] addresses optional
+  o Person [] children
+}
+
+concept Empty {
+}
+concept Empty2 {}
+";
+        assert_eq!(
+            super::parse(source),
+            Some(crate::parser::parse(source).unwrap()),
+            "Should build the same model as the character parser"
+        );
+
+        for left in [
+            "namespace test@1.0.0\nconcept Person {\n  o String name\n\n  o String email\n}\n",
+            "namespace test@1.0.0\nconcept Person {\n  o String name default=1\n}\n",
+            "namespace test@1.0.0\nconcept Person { o String name }\n",
+            "namespace test@1.0.0\nconcept Person {\n  oString name\n}\n",
+            "namespace test@1.0.0\nconcept Person {\r  o String name\n}\n",
+            "namespace test@1.0.0\n\nview Person { name }\n",
+        ] {
+            assert_eq!(super::parse(left), None, "Should leave {:?}", left);
+        }
+    }
+
+    #[test]
+    fn test_two_phase_option() {
+        let two_phase = ParserOptions {
+            two_phase: true,
+            ..ParserOptions::default()
+        };
+        let invalid = "namespace test@1.0.0\nconcept Person {\n  o String name default=1\n}\n";
+        assert_eq!(
+            crate::parser::parse_with(invalid, &two_phase),
+            crate::parser::parse(invalid),
+            "Should report the errors of the character parser"
+        );
+
+        let dialect = Dialect::new().meta("sensitive", |_| Ok(serde_json::json!(true)));
+        let source = "namespace test@1.0.0\nconcept Person {\n  o String name sensitive\n}\n";
+        assert_eq!(
+            crate::parser::dialect::parse(source, &two_phase, &dialect)
+                .map(|m| m.declarations[0].meta_extensions.len()),
+            Ok(1),
+            "Should parse meta properties of the dialect in use"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Opt-in memoization of sub-parsers. Backtracking on inputs from fuzzers or untrusted users
+//! can run the same parser at the same position over and over, with memoization each parser
+//! runs at most once per offset of the source and later runs reuse its result.
+
+use std::{any::Any, cell::RefCell, collections::HashMap};
+
+use nom::Err as NomErr;
+
+use super::{
+    error::{CError, CErrorKind},
+    CResult,
+};
+
+/// Result of a parser with positions relative to where it ran, so it doesn't borrow the input
+enum Memo<O> {
+    Parsed {
+        consumed: usize,
+        output: O,
+    },
+    Failed {
+        at: usize,
+        code: CErrorKind,
+        failure: bool,
+    },
+}
+
+impl<O: Clone> Memo<O> {
+    fn restore<'a>(&self, input: &'a str) -> CResult<&'a str, O> {
+        match self {
+            Memo::Parsed { consumed, output } => Ok((&input[*consumed..], output.clone())),
+            Memo::Failed { at, code, failure } => {
+                let error = CError {
+                    code: code.clone(),
+                    input: &input[*at..],
+                };
+                match failure {
+                    true => Err(NomErr::Failure(error)),
+                    false => Err(NomErr::Error(error)),
+                }
+            }
+        }
+    }
+}
+
+/// Results of the parse running on this thread, by parser name and offset in the source
+struct Table {
+    start: usize,
+    end: usize,
+    results: HashMap<(&'static str, usize), Box<dyn Any>>,
+}
+
+impl Table {
+    /// Offset of the input in the source, only inputs running to the end of it are memoized
+    fn offset(&self, input: &str) -> Option<usize> {
+        let start = input.as_ptr() as usize;
+        (start >= self.start && start + input.len() == self.end).then(|| start - self.start)
+    }
+}
+
+thread_local! {
+    static TABLE: RefCell<Option<Table>> = const { RefCell::new(None) };
+}
+
+/// Restores the table of an enclosing parse when dropped, even if parsing panics
+struct Restore(Option<Table>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        TABLE.with(|t| *t.borrow_mut() = previous);
+    }
+}
+
+/// Whether `suffix` points into `input` and runs to its end
+fn within(input: &str, suffix: &str) -> bool {
+    let (start, end) = (
+        input.as_ptr() as usize,
+        input.as_ptr() as usize + input.len(),
+    );
+    let suffix_start = suffix.as_ptr() as usize;
+    suffix_start >= start && suffix_start + suffix.len() == end
+}
+
+/// Runs `parse` with the results of memoized parsers kept for positions in `source`
+pub(crate) fn with_source<T>(source: &str, parse: impl FnOnce() -> T) -> T {
+    let start = source.as_ptr() as usize;
+    let table = Table {
+        start,
+        end: start + source.len(),
+        results: HashMap::new(),
+    };
+    let _restore = Restore(TABLE.with(|t| t.replace(Some(table))));
+    parse()
+}
+
+/// Runs `parser` at most once per offset of the source while memoization is on, and as is
+/// otherwise
+pub(crate) fn memoized<'a, O, P>(
+    name: &'static str,
+    mut parser: P,
+) -> impl FnMut(&'a str) -> CResult<&'a str, O>
+where
+    O: Clone + 'static,
+    P: FnMut(&'a str) -> CResult<&'a str, O>,
+{
+    move |input: &'a str| {
+        let offset = TABLE.with(|t| t.borrow().as_ref().and_then(|t| t.offset(input)));
+        let Some(offset) = offset else {
+            return parser(input);
+        };
+        let cached = TABLE.with(|t| {
+            t.borrow()
+                .as_ref()
+                .and_then(|t| t.results.get(&(name, offset)))
+                .and_then(|memo| memo.downcast_ref::<Memo<O>>())
+                .map(|memo| memo.restore(input))
+        });
+        if let Some(result) = cached {
+            return result;
+        }
+
+        let result = parser(input);
+        let memo = match &result {
+            Ok((remains, output)) => Memo::Parsed {
+                consumed: input.len() - remains.len(),
+                output: output.clone(),
+            },
+            Err(NomErr::Error(e)) | Err(NomErr::Failure(e)) if within(input, e.input) => {
+                Memo::Failed {
+                    at: input.len() - e.input.len(),
+                    code: e.code.clone(),
+                    failure: matches!(result, Err(NomErr::Failure(_))),
+                }
+            }
+            _ => return result,
+        };
+        TABLE.with(|t| {
+            if let Some(t) = t.borrow_mut().as_mut() {
+                t.results.insert((name, offset), Box::new(memo));
+            }
+        });
+        result
+    }
+}
+
+/// Number of results kept by the parse running on this thread
+#[cfg(test)]
+fn len() -> usize {
+    TABLE.with(|t| t.borrow().as_ref().map_or(0, |t| t.results.len()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use nom::bytes::complete::tag;
+
+    use crate::parser::CResult;
+
+    #[test]
+    fn test_memoized() {
+        let runs = Cell::new(0);
+        let counted = |input| -> CResult<&str, &str> {
+            runs.set(runs.get() + 1);
+            tag("concept")(input)
+        };
+        let mut parser = super::memoized("Counted", counted);
+        let source = "concept Person";
+
+        assert_eq!(parser(source), Ok((" Person", "concept")));
+        assert_eq!(parser(source), Ok((" Person", "concept")));
+        assert_eq!(runs.get(), 2, "Should not memoize outside of a parse");
+
+        super::with_source(source, || {
+            assert_eq!(parser(source), Ok((" Person", "concept")));
+            assert_eq!(parser(source), Ok((" Person", "concept")));
+            assert!(parser(&source[1..]).is_err());
+            assert!(parser(&source[1..]).is_err());
+            assert_eq!(super::len(), 2);
+            assert_eq!(
+                parser("concept"),
+                Ok(("", "concept")),
+                "Should run on input from elsewhere"
+            );
+        });
+        assert_eq!(runs.get(), 5, "Should run once per offset");
+        assert_eq!(super::len(), 0);
+    }
+}
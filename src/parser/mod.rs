@@ -1,89 +1,1347 @@
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod borrowed;
+pub mod comment;
 pub mod common;
+pub mod cst;
 pub mod declaration;
+pub mod decorator;
+pub mod dialect;
 pub mod error;
+pub mod file;
+pub mod import;
+pub mod incremental;
+mod lexer;
+mod memo;
 pub mod namespace;
+pub mod options;
 pub mod property;
+pub mod report;
+mod scan;
+pub mod span;
+pub mod tokens;
 pub mod version;
 
 use nom::{
-    branch::alt, character::complete::multispace0, error::context, multi::fold_many0,
-    sequence::delimited, IResult, Parser,
+    branch::alt,
+    combinator::opt,
+    error::context,
+    multi::fold_many0,
+    sequence::{delimited, preceded, terminated},
+    Err as NomErr, IResult, Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use std::collections::HashMap;
+
+use property::CommonProperty;
+use scan::multispace0;
 
 /// Concerto parse result type
 pub type CResult<I, O> = IResult<I, O, error::CError<I>>;
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(
+    feature = "deserialize",
+    derive(Deserialize),
+    serde(from = "ModelDefinition")
+)]
+#[serde(tag = "$class")]
 pub struct Model {
+    /// Comment block leading the file, typically a license header, kept as written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
     pub namespace: namespace::Namespace,
+    pub imports: Vec<import::Import>,
     pub declarations: Vec<declaration::Declaration>,
+    /// Declaration positions by name, built as declarations are added
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+}
+
+/// Deserialized fields of a `Model`, the lookup index is built when converting
+#[cfg(feature = "deserialize")]
+#[derive(Deserialize)]
+#[serde(tag = "$class", rename = "Model")]
+struct ModelDefinition {
+    #[serde(default)]
+    header: Option<String>,
+    namespace: namespace::Namespace,
+    #[serde(default)]
+    imports: Vec<import::Import>,
+    declarations: Vec<declaration::Declaration>,
+}
+
+#[cfg(feature = "deserialize")]
+impl From<ModelDefinition> for Model {
+    fn from(value: ModelDefinition) -> Self {
+        let mut model = Model {
+            header: value.header,
+            namespace: value.namespace,
+            imports: value.imports,
+            declarations: Vec::with_capacity(value.declarations.len()),
+            index: HashMap::with_capacity(value.declarations.len()),
+        };
+        for declaration in value.declarations {
+            model.add_declaration(declaration);
+        }
+        model
+    }
+}
+
+impl PartialEq for Model {
+    fn eq(&self, other: &Self) -> bool {
+        self.header == other.header
+            && self.namespace == other.namespace
+            && self.imports == other.imports
+            && self.declarations == other.declarations
+    }
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(header) = &self.header {
+            writeln!(f, "{}\n", header)?;
+        }
+        writeln!(f, "{}", self.namespace)?;
+        if !self.imports.is_empty() {
+            writeln!(f)?;
+            for import in self.imports.iter() {
+                writeln!(f, "{}", import)?;
+            }
+        }
+        for declaration in self.declarations.iter() {
+            writeln!(f)?;
+            writeln!(f, "{}", declaration)?;
+        }
+        Ok(())
+    }
+}
+
+impl Model {
+    /// Counts of the declarations, properties and imports of the model
+    pub fn stats(&self) -> crate::stats::Stats {
+        crate::stats::Stats::of_model(self)
+    }
+
+    /// Adds a declaration to the end of the model, keeping the lookup index up to date
+    pub fn add_declaration(&mut self, declaration: declaration::Declaration) {
+        self.index
+            .insert(declaration.name.clone(), self.declarations.len());
+        self.declarations.push(declaration);
+    }
+
+    /// Normalized copy of the model, so models that differ only in how they are written
+    /// print and serialize the same. Imports, declarations, and properties are sorted by
+    /// name, meta properties by property and keyword, and validators that don't restrict
+    /// anything are dropped. Default values are kept, as they change instances.
+    pub fn canonicalize(&self) -> Model {
+        let mut model = self.clone();
+        model.imports.sort_by_cached_key(|i| i.to_string());
+        model.imports.dedup();
+        model.declarations.sort_by(|a, b| a.name.cmp(&b.name));
+        for declaration in &mut model.declarations {
+            declaration
+                .properties
+                .sort_by(|a, b| a.name().cmp(b.name()));
+            for property in &mut declaration.properties {
+                property.drop_unbounded_validators();
+            }
+            declaration
+                .meta_extensions
+                .sort_by(|a, b| (&a.property, &a.keyword).cmp(&(&b.property, &b.keyword)));
+        }
+        model.reindex();
+        model
+    }
+
+    /// Appends the imports and declarations of a fragment of the same namespace, for
+    /// namespaces split across files. Nothing is added if a declaration is already defined.
+    pub fn extend(&mut self, fragment: Model) -> Result<(), error::BuildError> {
+        if fragment.namespace != self.namespace {
+            return Err(error::BuildError::NamespaceMismatch(
+                format!("{}@{}", self.namespace.name(), self.namespace.version()),
+                format!(
+                    "{}@{}",
+                    fragment.namespace.name(),
+                    fragment.namespace.version()
+                ),
+            ));
+        }
+        for (i, declaration) in fragment.declarations.iter().enumerate() {
+            if self.declaration(&declaration.name).is_some()
+                || fragment.declarations[..i]
+                    .iter()
+                    .any(|d| d.name == declaration.name)
+            {
+                return Err(error::BuildError::DuplicateDeclaration(
+                    declaration.name.clone(),
+                ));
+            }
+        }
+
+        for import in fragment.imports {
+            if !self.imports.contains(&import) {
+                self.imports.push(import);
+            }
+        }
+        for declaration in fragment.declarations {
+            self.add_declaration(declaration);
+        }
+        Ok(())
+    }
+
+    /// Finds a declaration by its name, using the index built on insert. Call `reindex` after
+    /// changing `declarations` directly.
+    pub fn declaration(&self, name: &str) -> Option<&declaration::Declaration> {
+        self.index
+            .get(name)
+            .and_then(|i| self.declarations.get(*i))
+            .filter(|d| d.name == name)
+    }
+
+    /// Fully qualified name of a type as seen from the model, either declared in it or
+    /// imported. Primitive and unknown types have none.
+    pub fn fully_qualified_name(&self, type_name: &str) -> Option<namespace::FullyQualifiedName> {
+        let (namespace, type_name) = match self.declaration(type_name) {
+            Some(_) => (&self.namespace, type_name),
+            None => self
+                .imports
+                .iter()
+                .find_map(|i| Some((i.namespace(), i.imported_name(type_name)?)))?,
+        };
+        Some(namespace::FullyQualifiedName::from((
+            namespace.name().to_string(),
+            namespace.version().clone(),
+            type_name.to_string(),
+        )))
+    }
+
+    /// All properties of all declarations, paired with the declaration they belong to
+    pub fn properties(
+        &self,
+    ) -> impl Iterator<Item = (&declaration::Declaration, &declaration::Property)> {
+        self.declarations
+            .iter()
+            .flat_map(|d| d.properties.iter().map(move |p| (d, p)))
+    }
+
+    /// Compares models by meaning, the order imports, declarations and properties are
+    /// defined in doesn't matter
+    pub fn semantically_equals(&self, other: &Self) -> bool {
+        self.namespace == other.namespace
+            && self.imports.len() == other.imports.len()
+            && self.imports.iter().all(|i| other.imports.contains(i))
+            && self.declarations.len() == other.declarations.len()
+            && self.declarations.iter().all(|d| {
+                other
+                    .declaration(&d.name)
+                    .is_some_and(|o| d.semantically_equals(o))
+            })
+    }
+
+    /// Finds a property by a path of names like `Person/mainAddress/city`, starting from a
+    /// declaration and walking through the types of concept properties. Only types declared
+    /// in this model are walked, see `ModelManager::resolve_path` to follow imports.
+    pub fn resolve_path(&self, path: &str) -> Option<&declaration::Property> {
+        resolve_path_with(self, path, |model, name| {
+            model.declaration(name).map(|d| (model, d))
+        })
+    }
+
+    /// Puts the names of the model in NFC, so names written with different code points for the
+    /// same characters refer to each other. Parsing does this for sources that aren't ASCII.
+    pub fn normalize_identifiers(&mut self) {
+        use common::identifier::{nfc, normalize};
+
+        let name = nfc(self.namespace.name()).into_owned();
+        self.namespace = namespace::Namespace::new(name, self.namespace.version().clone());
+        for import in &mut self.imports {
+            match import {
+                import::Import::Type { name, .. } => normalize(name),
+                import::Import::All { .. } => {}
+                import::Import::Types {
+                    types,
+                    aliased_types,
+                    ..
+                } => {
+                    types.iter_mut().for_each(normalize);
+                    for aliased in aliased_types {
+                        normalize(&mut aliased.name);
+                        normalize(&mut aliased.aliased_name);
+                    }
+                }
+            }
+            let namespace = import.namespace_mut();
+            *namespace = namespace::Namespace::new(
+                nfc(namespace.name()).into_owned(),
+                namespace.version().clone(),
+            );
+        }
+        for declaration in &mut self.declarations {
+            normalize(&mut declaration.name);
+            if let Some(declaration::Identified::By { name }) = &mut declaration.identified {
+                normalize(name);
+            }
+            if let Some(super_type) = &mut declaration.super_type {
+                normalize(&mut super_type.name);
+            }
+            for property in &mut declaration.properties {
+                normalize(property.name_mut());
+                if let declaration::Property::Concept(p) = property {
+                    normalize(&mut p.class);
+                }
+                for decorator in property.decorators_mut() {
+                    normalize(&mut decorator.name);
+                }
+            }
+            for decorator in &mut declaration.decorators {
+                normalize(&mut decorator.name);
+            }
+            for meta in &mut declaration.meta_extensions {
+                normalize(&mut meta.property);
+            }
+        }
+        self.reindex();
+    }
+
+    /// Builds the lookup indexes of the model and its declarations again after
+    /// `declarations` or their properties were changed directly
+    pub fn reindex(&mut self) {
+        for declaration in &mut self.declarations {
+            declaration.reindex();
+        }
+        self.index = self
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (d.name.clone(), i))
+            .collect();
+    }
+
+    /// Declarations of the given kind, in the order they are defined
+    pub fn declarations_of_kind(
+        &self,
+        kind: declaration::DeclarationKind,
+    ) -> impl Iterator<Item = &declaration::Declaration> {
+        self.declarations.iter().filter(move |d| d.kind == kind)
+    }
+}
+
+/// Walks a property path, `resolve` finds a declaration by name as seen from a model and
+/// returns it with the model it is declared in
+pub(crate) fn resolve_path_with<'a>(
+    model: &'a Model,
+    path: &str,
+    resolve: impl Fn(&'a Model, &str) -> Option<(&'a Model, &'a declaration::Declaration)>,
+) -> Option<&'a declaration::Property> {
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let (mut model, mut declaration) = resolve(model, segments.next()?)?;
+    let mut property = declaration.property(segments.next()?)?;
+
+    for segment in segments {
+        (model, declaration) = match property {
+            declaration::Property::Concept(p) => resolve(model, p.type_name())?,
+            _ => return None,
+        };
+        property = declaration.property(segment)?;
+    }
+
+    Some(property)
 }
 
 enum Definition {
     Namespace(namespace::Namespace),
-    Declaration(declaration::Declaration),
+    Import(import::Import),
+    Declaration(Box<declaration::Declaration>),
 }
 
-struct ModelBuilder {
-    pub namespace: Option<namespace::Namespace>,
-    pub declarations: Vec<declaration::Declaration>,
+/// Builds a `Model` in code, without generating CTO text and parsing it back.
+/// A namespace is required, and all declaration and property names should be valid tokens.
+#[derive(Debug, Default)]
+pub struct ModelBuilder {
+    header: Option<String>,
+    namespace: Option<namespace::Namespace>,
+    imports: Vec<import::Import>,
+    declarations: Vec<declaration::Declaration>,
 }
 
 impl ModelBuilder {
     pub fn new() -> Self {
         Self {
+            header: None,
             namespace: None,
+            imports: Vec::new(),
             declarations: Vec::new(),
         }
     }
 
-    pub fn with_namespace(&mut self, ns: namespace::Namespace) -> &Self {
+    /// Comment block printed above the namespace, like a license header
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.header = Some(header.into());
+        self
+    }
+
+    pub fn with_namespace(mut self, ns: namespace::Namespace) -> Self {
         self.namespace = Some(ns);
         self
     }
 
-    pub fn add_declaration(&mut self, dec: declaration::Declaration) -> &Self {
+    pub fn add_import(mut self, import: import::Import) -> Self {
+        self.imports.push(import);
+        self
+    }
+
+    pub fn add_declaration(mut self, dec: declaration::Declaration) -> Self {
         self.declarations.push(dec);
         self
     }
 
-    pub fn build(self) -> Model {
-        Model {
-            namespace: self.namespace.unwrap(),
-            declarations: self.declarations,
+    pub fn build(self) -> Result<Model, error::BuildError> {
+        let namespace = self.namespace.ok_or(error::BuildError::MissingNamespace)?;
+
+        for declaration in self.declarations.iter() {
+            error::BuildError::check_identifier(&declaration.name)?;
+            for property in declaration.properties.iter() {
+                error::BuildError::check_identifier(property.name())?;
+            }
+        }
+
+        let mut model = Model {
+            header: self.header,
+            namespace,
+            imports: self.imports,
+            declarations: Vec::with_capacity(self.declarations.len()),
+            index: HashMap::with_capacity(self.declarations.len()),
+        };
+        for declaration in self.declarations {
+            model.add_declaration(declaration);
         }
+
+        Ok(model)
     }
 }
 
-pub fn model<'a>(input: &'a str) -> CResult<&'a str, Model> {
+pub fn model(input: &str) -> CResult<&str, Model> {
     let definition = alt((
-        namespace::namespace_identifier.map(|ns| Definition::Namespace(ns)),
-        declaration::declaration.map(|dec| Definition::Declaration(dec)),
+        namespace::namespace_identifier.map(Definition::Namespace),
+        import::import.map(Definition::Import),
+        declaration::declaration.map(|d| Definition::Declaration(Box::new(d))),
+        dialect::extension_declaration.map(|d| Definition::Declaration(Box::new(d))),
     ));
+    let (input_after_header, header) = opt(comment::header)(input)?;
+    // Definitions go straight into the builder, instead of a list that is copied again
     let definitions = fold_many0(
-        delimited(multispace0, definition, multispace0),
-        Vec::new,
-        |mut acc, item| {
-            acc.push(item);
-            acc
+        preceded(comment::trivia0, definition),
+        || match header {
+            Some(header) => ModelBuilder::new().with_header(header),
+            None => ModelBuilder::new(),
+        },
+        |model_builder, def| match def {
+            Definition::Declaration(d) => model_builder.add_declaration(*d),
+            Definition::Namespace(ns) => model_builder.with_namespace(ns),
+            Definition::Import(i) => model_builder.add_import(i),
         },
     );
-    context(
-        "Model",
-        definitions.map(|defs| {
-            let mut model_builder = ModelBuilder::new();
-            for def in defs {
-                match def {
-                    Definition::Declaration(d) => {
-                        model_builder.add_declaration(d);
-                    }
-                    Definition::Namespace(ns) => {
-                        model_builder.with_namespace(ns);
+    let (remains, model_builder) =
+        context("Model", terminated(definitions, comment::trivia0))(input_after_header)?;
+
+    build(input, remains, model_builder)
+}
+
+/// Parses the whole source as a model, trailing input other than whitespace is an error
+pub fn parse(source: &str) -> Result<Model, error::SourceError> {
+    parse_with(source, &options::ParserOptions::default())
+}
+
+/// Parses a model with the given strictness toggles
+pub fn parse_with(
+    source: &str,
+    options: &options::ParserOptions,
+) -> Result<Model, error::SourceError> {
+    parse_with_report(source, options).map(|(model, _)| model)
+}
+
+/// Parses a model like `parse_with`, reporting the bytes consumed, declarations parsed and
+/// the time spent in each phase
+pub fn parse_with_report(
+    source: &str,
+    options: &options::ParserOptions,
+) -> Result<(Model, report::ParseReport), error::SourceError> {
+    if !options.crlf {
+        if let Some(offset) = source.find('\r') {
+            return Err(error::SourceError::new(
+                source,
+                NomErr::Error(error::CError {
+                    code: error::CErrorKind::ExpectedFound(
+                        String::from("\\n"),
+                        String::from("\\r"),
+                    ),
+                    input: &source[offset..],
+                }),
+            ));
+        }
+    }
+
+    let mut report = report::ParseReport::default();
+    #[cfg(feature = "allocations")]
+    let allocations = report::PeakAllocations::start();
+    let parse = || match options.trailing_input {
+        true => model(source),
+        false => nom::combinator::all_consuming(model)(source),
+    };
+    let parsed = options::with_options(options, || {
+        if options.two_phase {
+            if let Some(model) = report.time(report::Phase::Lex, || lexer::parse(source)) {
+                return Ok(("", model));
+            }
+        }
+        report.time(report::Phase::Parse, || match options.memoize {
+            true => memo::with_source(source, parse),
+            false => parse(),
+        })
+    });
+    let (remains, mut model) = parsed.map_err(|e| error::SourceError::new(source, e))?;
+    if !source.is_ascii() {
+        report.time(report::Phase::Normalize, || model.normalize_identifiers());
+    }
+    report.bytes_consumed = source.len() - remains.len();
+    report.declarations = model.declarations.len();
+    #[cfg(feature = "allocations")]
+    {
+        report.peak_allocated = allocations.peak();
+    }
+    Ok((model, report))
+}
+
+/// Parses a single declaration with the decorators above it, like `concept Person { ... }`
+pub fn parse_declaration(source: &str) -> Result<declaration::Declaration, error::SourceError> {
+    parse_snippet(
+        source,
+        alt((declaration::declaration, dialect::extension_declaration)),
+    )
+}
+
+/// Parses a single property line, like `o String name optional`
+pub fn parse_property(source: &str) -> Result<declaration::Property, error::SourceError> {
+    parse_snippet(source, declaration::any_proeprty)
+}
+
+/// Parses a version, like `1.0.0-beta.1`
+pub fn parse_version(source: &str) -> Result<version::SemanticVersion, error::SourceError> {
+    parse_snippet(source, version::version_identifier)
+}
+
+/// Parses a single decorator, like `@Term("Person")`
+pub fn parse_decorator(source: &str) -> Result<decorator::Decorator, error::SourceError> {
+    parse_snippet(source, decorator::decorator)
+}
+
+/// Parses the whole of a snippet with the parser of a single node, whitespace around the
+/// node is allowed. Snippets are parsed with the default options, also when parsed while
+/// another parse is running, e.g. by a dialect.
+fn parse_snippet<'a, O>(
+    source: &'a str,
+    parser: impl Parser<&'a str, O, error::CError<&'a str>>,
+) -> Result<O, error::SourceError> {
+    let mut snippet = nom::combinator::all_consuming(delimited(multispace0, parser, multispace0));
+    options::with_options(&options::ParserOptions::default(), || snippet(source))
+        .map(|(_, node)| node)
+        .map_err(|e| error::SourceError::new(source, e))
+}
+
+/// Builds a parsed model, failing at the start of the input if the definitions don't form a
+/// valid model
+pub(crate) fn build<'a>(
+    input: &'a str,
+    remains: &'a str,
+    model_builder: ModelBuilder,
+) -> CResult<&'a str, Model> {
+    match model_builder.build() {
+        Ok(model) => Ok((remains, model)),
+        Err(e) => Err(NomErr::Error(error::CError {
+            code: error::CErrorKind::InvalidModel(e),
+            input,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{declaration::DeclarationBuilder, namespace::Namespace, version::SemanticVersion};
+
+    #[test]
+    fn test_builder() {
+        let input = "namespace test@1.0.0
+
+        concept Person {
+          o String name
+        }
+        ";
+        let person = DeclarationBuilder::new("Person")
+            .add_property(
+                super::property::string_property::StringPropertyBuilder::new("name").build(),
+            )
+            .build();
+
+        assert_eq!(
+            super::model(input).map(|(_, model)| model),
+            super::ModelBuilder::new()
+                .with_namespace(Namespace::new(
+                    "test",
+                    SemanticVersion::Version((1, 0, 0).into())
+                ))
+                .add_declaration(person)
+                .build()
+                .map_err(|_| unreachable!()),
+            "Should build the same model as parsed"
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        assert_eq!(
+            super::ModelBuilder::new().build(),
+            Err(super::error::BuildError::MissingNamespace),
+            "Should not build a model without a namespace"
+        );
+        assert_eq!(
+            super::ModelBuilder::new()
+                .with_namespace(Namespace::new(
+                    "test",
+                    SemanticVersion::Version((1,).into())
+                ))
+                .add_declaration(DeclarationBuilder::new("1Person").build())
+                .build(),
+            Err(super::error::BuildError::InvalidIdentifier(String::from(
+                "1Person"
+            ))),
+            "Should not build a model with invalid names"
+        );
+    }
+
+    #[test]
+    fn test_declaration_lookup() {
+        let input = "namespace test@1.0.0
+
+        concept Person {
+          o String name
+        }
+
+        concept Address {
+          o String street
+        }
+        ";
+        let (_, mut model) = super::model(input).unwrap();
+
+        assert_eq!(
+            model.declaration("Address").map(|d| d.name.as_str()),
+            Some("Address"),
+            "Should find a declaration by its name"
+        );
+        assert_eq!(
+            model.declaration("Unknown"),
+            None,
+            "Should not find a declaration that is not defined"
+        );
+        assert_eq!(
+            model
+                .declarations_of_kind(super::declaration::DeclarationKind::Concept)
+                .count(),
+            2,
+            "Should list declarations of a kind"
+        );
+
+        model.declarations.remove(0);
+        model.reindex();
+        assert_eq!(
+            model.declaration("Address").map(|d| d.name.as_str()),
+            Some("Address"),
+            "Should find a declaration after declarations are changed directly"
+        );
+
+        model.add_declaration(DeclarationBuilder::new("Person").build());
+        assert_eq!(
+            model.declaration("Person").map(|d| d.properties.len()),
+            Some(0),
+            "Should find an added declaration"
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        use super::property::CommonProperty;
+
+        // `Café` with a precomposed `é` and with `e` and a combining accent
+        let input = "namespace 카페@1.0.0
+
+        concept Caf\u{e9} {
+          o String 名前
+          o Cafe\u{301}[] _nächste optional
+        }
+        ";
+        let model = super::parse(input).unwrap();
+        let declaration = model.declaration("Café").unwrap();
+
+        assert_eq!(model.namespace.name(), "카페");
+        assert_eq!(
+            declaration
+                .properties
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>(),
+            vec!["名前", "_nächste"]
+        );
+        assert_eq!(
+            model.to_string().matches("Caf\u{e9}").count(),
+            2,
+            "Should normalize names to NFC"
+        );
+        assert!(
+            super::parse("namespace test@1.0.0\n\nconcept 1Person {\n}\n").is_err(),
+            "Should not parse names starting with a digit"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "namespace test@1.0.0
+
+import org.acme@1.0.0.Country
+
+concept Person {
+  o String name
+  o Integer age optional
+  o Address mainAddress
+}
+
+concept Address {
+  o String city default=\"Dublin\"
+}
+";
+        let (_, model) = super::model(input).unwrap();
+        assert_eq!(
+            model.to_string(),
+            input,
+            "Should render the model as it is parsed"
+        );
+        assert_eq!(
+            super::model(&model.to_string()).map(|(_, parsed)| parsed),
+            Ok(model),
+            "Should parse the rendered model back"
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let header = "/*
+ * Copyright Acme Ltd.
+ * Licensed under the Apache License, Version 2.0
+ */
+// SPDX-License-Identifier: Apache-2.0";
+        let input = format!("{}\n\nnamespace test@1.0.0\n", header);
+        let model = super::parse(&input).unwrap();
+        assert_eq!(model.header.as_deref(), Some(header));
+        assert_eq!(
+            model.to_string(),
+            input,
+            "Should print the header as written"
+        );
+        assert_eq!(
+            crate::format::format(&model),
+            input,
+            "Should keep the header when formatting"
+        );
+
+        let cramped = format!("  {}\nnamespace test@1.0.0\n", header);
+        let cst = super::cst::Cst::parse(cramped.as_str()).unwrap();
+        assert_eq!(cst.model(), &model);
+        assert_eq!(
+            crate::format::format_cst(&cst, &Default::default()),
+            input,
+            "Should not repeat the header as trivia"
+        );
+        assert_eq!(
+            super::borrowed::model(&input).map(|(_, m)| m.into_owned()),
+            Ok(model)
+        );
+        assert_eq!(
+            super::parse("namespace test@1.0.0\n/* trailing */\n").map(|m| m.header),
+            Ok(None),
+            "Should skip comments after the namespace instead of keeping them as the header"
+        );
+    }
+
+    #[test]
+    fn test_line_comments() {
+        let commented = "namespace test@1.0.0 // the namespace
+// Types of the test namespace
+
+concept Person { // people
+  // The full name
+  o String name // trailing
+
+  o Integer age optional
+  // nothing after this
+}
+// between declarations
+concept Empty {
+  // no properties
+}
+";
+        let plain = "namespace test@1.0.0
+
+concept Person {
+  o String name
+  o Integer age optional
+}
+
+concept Empty {}
+";
+        assert!(super::parse(plain).is_ok());
+        assert_eq!(super::parse(commented), super::parse(plain));
+        assert_eq!(
+            super::span::model_with_spans(commented)
+                .map(|(_, (model, _))| model)
+                .ok(),
+            super::parse(plain).ok(),
+            "Should skip comments when keeping spans"
+        );
+        assert_eq!(
+            super::borrowed::model(commented).map(|(_, m)| m.into_owned()),
+            Ok(super::parse(plain).unwrap()),
+            "Should skip comments in the borrowed parser"
+        );
+    }
+
+    #[test]
+    fn test_snippets() {
+        use super::CommonProperty;
+
+        let declaration =
+            super::parse_declaration("\n@deprecated\nconcept Person {\n  o String name\n}\n")
+                .unwrap();
+        assert!(declaration.is_deprecated());
+        assert!(super::parse_declaration("concept Person {}\nconcept Other {}").is_err());
+
+        let property = super::parse_property("o Integer age range=[0,] optional").unwrap();
+        assert_eq!(property.name(), "age");
+        let error = super::parse_property("o Integer age optional extra").unwrap_err();
+        assert_eq!(
+            error.column, 24,
+            "Should fail where the snippet stops parsing"
+        );
+
+        assert_eq!(
+            super::parse_version("1.2.3-beta.1").unwrap().to_string(),
+            "1.2.3-beta.1"
+        );
+        assert!(super::parse_version("1.2.x").is_err());
+
+        let decorator = super::parse_decorator("@Term(\"Person\")").unwrap();
+        assert_eq!(
+            decorator,
+            super::decorator::Decorator::new("Term").with_string("Person")
+        );
+        assert!(super::parse_decorator("@Term @Other").is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let input = "namespace test@1.0.0-beta
+        import org.acme@1.2.0.Address
+
+        concept Person {
+          o Address mainAddress optional
+        }
+        ";
+        let (_, model) = super::model(input).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+              "$class": "Model",
+              "namespace": "test@1.0.0-beta",
+              "imports": [
+                {
+                  "$class": "ImportType",
+                  "namespace": "org.acme@1.2.0",
+                  "name": "Address"
+                }
+              ],
+              "declarations": [
+                {
+                  "$class": "ConceptDeclaration",
+                  "name": "Person",
+                  "properties": [
+                    {
+                      "$class": "ObjectProperty",
+                      "name": "mainAddress",
+                      "isArray": false,
+                      "isOptional": true,
+                      "type": {
+                        "$class": "TypeIdentifier",
+                        "name": "Address"
+                      }
                     }
+                  ]
+                }
+              ]
+            }),
+            serde_json::to_value(model).unwrap(),
+        )
+    }
+
+    #[cfg(feature = "deserialize")]
+    #[test]
+    fn test_deserialize() {
+        let input = "namespace test@1.0.0-beta
+        import org.acme@1.0.0.Country
+
+        concept Person {
+          o String name regex=/abc.*/ length=[1, 10]
+          o Boolean active default=true
+          o Integer age optional range=[0,]
+          o Long id default=3147483647
+          o Double height range=[0.5, 3.0]
+          o DateTime born default=2024-01-04
+          o Address[] addresses
+        }
+        ";
+        let (_, model) = super::model(input).unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+
+        let deserialized: super::Model = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, model, "Should deserialize a serialized model");
+        assert!(
+            deserialized.declaration("Person").is_some(),
+            "Should build the lookup index when deserializing"
+        );
+    }
+
+    #[test]
+    fn test_semantically_equals() {
+        let (_, model) = super::model(
+            "namespace test@1.0.0
+
+            concept Person {
+              o String name
+              o Integer age optional
+            }
+
+            concept Address {
+              o String street
+            }
+            ",
+        )
+        .unwrap();
+        let (_, reordered) = super::model(
+            "namespace test@1.0.0
+            concept Address {
+                o String   street
+            }
+            concept Person {
+                o Integer age   optional
+                o String name
+            }
+            ",
+        )
+        .unwrap();
+        let (_, changed) = super::model(
+            "namespace test@1.0.0
+            concept Address {
+                o String street
+            }
+            concept Person {
+                o Integer age
+                o String name
+            }
+            ",
+        )
+        .unwrap();
+
+        assert_ne!(model, reordered);
+        assert!(
+            model.semantically_equals(&reordered),
+            "Should ignore the order of declarations and properties"
+        );
+        assert!(
+            !model.semantically_equals(&changed),
+            "Should compare properties"
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        use super::{options::ParserOptions, property::CommonProperty};
+
+        let duplicate_metas = "namespace test@1.0.0
+
+concept Person {
+  o String name default=\"a\" default=\"b\"
+}
+";
+        assert!(
+            super::parse_with(duplicate_metas, &ParserOptions::default()).is_ok(),
+            "Should allow duplicate metas by default"
+        );
+        assert!(
+            super::parse_with(duplicate_metas, &ParserOptions::strict()).is_err(),
+            "Should reject duplicate metas when strict"
+        );
+
+        let crlf = "namespace test@1.0.0\r\n\r\nconcept Person {\r\n  o String name\r\n}\r\n";
+        assert_eq!(
+            super::parse(crlf).map(|m| m.declarations.len()),
+            Ok(1),
+            "Should accept CRLF line endings by default"
+        );
+        assert_eq!(
+            super::parse_with(crlf, &ParserOptions::strict()).map_err(|e| (e.line, e.column)),
+            Err((1, 21)),
+            "Should point at the first CR when strict"
+        );
+
+        let keyword_case = "NAMESPACE test@1.0.0
+
+Concept Person {
+  o String name Optional
+}
+";
+        assert!(
+            super::parse(keyword_case).is_err(),
+            "Should only accept lower case keywords by default"
+        );
+        assert_eq!(
+            super::parse_with(keyword_case, &ParserOptions::permissive())
+                .map(|m| m.declarations[0].properties[0].is_optional()),
+            Ok(true),
+            "Should accept keywords in any case when permissive"
+        );
+
+        let trailing = "namespace test@1.0.0\n\nconcept Person {}\n\n%%";
+        assert!(
+            super::parse(trailing).is_err(),
+            "Should reject trailing input by default"
+        );
+        assert!(
+            super::parse_with(trailing, &ParserOptions::permissive()).is_ok(),
+            "Should ignore trailing input when permissive"
+        );
+        assert!(
+            super::parse(keyword_case).is_err(),
+            "Should restore the default options after parsing"
+        );
+
+        let overflow = "namespace test@1.0.0\n\nconcept Person {\n  o Long id default=999999999999999999999999999999999999999999\n}\n";
+        assert_eq!(
+            super::parse(overflow).map_err(|e| (e.kind, e.line)),
+            Err((
+                super::error::CErrorKind::NumericOverflow(
+                    "Long",
+                    String::from("999999999999999999999999999999999999999999")
+                ),
+                4
+            )),
+            "Should point at the number that doesn't fit"
+        );
+        assert!(
+            super::parse_with(overflow, &ParserOptions::permissive()).is_ok(),
+            "Should saturate when permissive"
+        );
+        let double_overflow =
+            "namespace test@1.0.0\n\nconcept Person {\n  o Double height range=[0.0, 1e400]\n}\n";
+        assert_eq!(
+            super::parse(double_overflow).map_err(|e| e.kind),
+            Err(super::error::CErrorKind::NumericOverflow(
+                "Double",
+                String::from("1e400")
+            )),
+            "Should reject doubles too large for f64"
+        );
+
+        let memoize = ParserOptions {
+            memoize: true,
+            ..ParserOptions::default()
+        };
+        let imports = "namespace test@1.0.0-beta\nimport other@2.1.0.Address\n\nconcept Person {\n  o String name default=\"a\" optional\n  o Double height range=[0.0,] optional\n  o Address address\n}\n";
+        assert_eq!(
+            super::parse_with(imports, &memoize),
+            super::parse(imports),
+            "Should parse the same model when memoizing"
+        );
+        assert_eq!(
+            super::parse_with(duplicate_metas, &memoize).is_ok(),
+            super::parse(duplicate_metas).is_ok(),
+        );
+
+        let exclusive = "namespace test@1.0.0\n\nconcept Person {\n  o Double height range=(0.0, 3.0]\n  o Integer age range=[0,150)\n  o String name length=(1, 10]\n}\n";
+        assert!(
+            super::parse(exclusive).is_err(),
+            "Should only take inclusive ranges by default"
+        );
+        let exclusive_ranges = ParserOptions {
+            exclusive_ranges: true,
+            ..ParserOptions::default()
+        };
+        let without_length = exclusive.replace("length=(", "length=[");
+        let model = super::parse_with(&without_length, &exclusive_ranges).unwrap();
+        assert_eq!(
+            model.declarations[0]
+                .properties
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "o Double height range=(0.0, 3.0]",
+                "o Integer age range=[0, 150)",
+                "o String name length=[1, 10]",
+            ],
+            "Should keep which ends are excluded"
+        );
+        assert!(
+            super::parse_with(exclusive, &exclusive_ranges).is_err(),
+            "Should not exclude ends of lengths"
+        );
+    }
+
+    #[test]
+    fn test_language_version() {
+        use super::{
+            namespace::unversioned,
+            options::{LanguageVersion, ParserOptions},
+        };
+
+        let legacy = "namespace org.acme\n\nimport org.acme.base.Address\n\nconcept Person {\n  o Address address\n}\n";
+        assert!(
+            super::parse(legacy).is_err(),
+            "Should only take versioned namespaces by default"
+        );
+        assert!(
+            super::parse("namespace org.acme\n").is_err(),
+            "Should not take unversioned namespaces by default"
+        );
+        let v2 = ParserOptions {
+            language_version: LanguageVersion::V2,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            super::parse_with("namespace org.acme\n", &v2)
+                .map(|m| m.namespace.declared_version().cloned()),
+            Ok(None),
+            "Should keep namespaces without a version"
+        );
+        let model = super::parse_with(legacy, &v2).unwrap();
+        assert_eq!(model.namespace.name(), "org.acme");
+        assert_eq!(model.namespace.version(), &unversioned());
+        assert_eq!(
+            model.to_string(),
+            legacy,
+            "Should print legacy models as written"
+        );
+        assert_eq!(model.imports[0].namespace().name(), "org.acme.base");
+        assert!(model.imports[0].imports("Address"));
+        assert_eq!(
+            super::parse_with(
+                legacy,
+                &ParserOptions {
+                    two_phase: true,
+                    ..v2
                 }
+            ),
+            Ok(model),
+            "Should read unversioned namespaces when lexing first"
+        );
+        assert!(
+            super::parse_with("namespace org.acme@1.0.0\n", &v2).is_ok(),
+            "Should still take versioned namespaces"
+        );
+    }
+
+    #[test]
+    fn test_options_entry_points() {
+        use super::options::{with_options, LanguageVersion, ParserOptions};
+
+        let legacy =
+            "namespace org.acme\n\nconcept Person {\n  o Integer age default=99999999999\n}\n";
+        let options = ParserOptions {
+            language_version: LanguageVersion::V2,
+            numeric_overflow: super::options::NumericOverflow::Saturate,
+            ..ParserOptions::default()
+        };
+        let model = super::parse_with(legacy, &options).unwrap();
+        assert!(super::borrowed::model(legacy).is_err());
+        assert_eq!(
+            super::borrowed::model_with(legacy, &options).map(|(_, m)| m.into_owned()),
+            Ok(model.clone()),
+            "Should read borrowed parts back with the options of the parse"
+        );
+        #[cfg(feature = "arena")]
+        {
+            let bump = bumpalo::Bump::new();
+            assert_eq!(
+                super::arena::model_with(&bump, legacy, &options).map(|(_, m)| m.into_owned()),
+                Ok(model.clone())
+            );
+        }
+        assert_eq!(
+            super::span::model_with_spans_with(legacy, &options).map(|(_, (m, _))| m),
+            Ok(model.clone())
+        );
+        assert!(super::cst::Cst::parse(legacy).is_err());
+        assert_eq!(
+            super::cst::Cst::parse_with(legacy, &options).map(|cst| cst.model().clone()),
+            Ok(model.clone())
+        );
+        let mut incremental =
+            super::incremental::IncrementalModel::parse_with(legacy, &options).unwrap();
+        let at = legacy.find("age").unwrap();
+        assert_eq!(
+            incremental.edit(at..at + 3, "years"),
+            Ok(0..1),
+            "Should parse edits with the same options"
+        );
+
+        assert!(
+            with_options(&options, || super::parse_declaration(
+                "concept Person {\n  o Integer age default=99999999999\n}"
+            ))
+            .is_err(),
+            "Should not take the options of a surrounding parse for snippets"
+        );
+        assert!(
+            with_options(&options, || super::parse("namespace org.acme\n")).is_err(),
+            "Should parse with the options of the innermost call"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let (_, mut first) = super::model(
+            "namespace test@1.0.0
+            import places@1.0.0.Country
+            import places@1.0.0.Address
+
+            concept Person {
+              o String name
+              o Integer age range=[0,]
+              o Address address
+            }
+
+            concept Company {
+              o String name
+            }
+            ",
+        )
+        .unwrap();
+        if let super::declaration::Property::String(p) = &mut first.declarations[0].properties[0] {
+            // Not expressible in CTO, can come from builders and metamodel JSON
+            p.length_validator = Some(super::property::string_property::StringLengthValidator {
+                min_length: None,
+                max_length: None,
+            });
+        }
+        let (_, second) = super::model(
+            "namespace test@1.0.0
+            import places@1.0.0.Address
+            import places@1.0.0.Country
+
+            concept Company {
+              o String name
+            }
+
+            concept Person {
+              o Address address
+              o Integer age range=[0,]
+              o String name
+            }
+            ",
+        )
+        .unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(
+            first.canonicalize(),
+            second.canonicalize(),
+            "Should not depend on the order of definitions"
+        );
+        assert_eq!(
+            first.canonicalize().to_string(),
+            "namespace test@1.0.0
+
+import places@1.0.0.Address
+import places@1.0.0.Country
+
+concept Company {
+  o String name
+}
+
+concept Person {
+  o Address address
+  o Integer age range=[0,]
+  o String name
+}
+",
+            "Should sort and drop unbounded validators"
+        );
+        assert!(first.canonicalize().declaration("Person").is_some());
+    }
+
+    #[test]
+    fn test_extend() {
+        let (_, mut model) = super::model(
+            "namespace test@1.0.0
+            import org.acme@1.0.0.Address
+
+            concept Person {
+              o Address address
+            }
+            ",
+        )
+        .unwrap();
+        let (_, fragment) = super::model(
+            "namespace test@1.0.0
+            import org.acme@1.0.0.Address
+            import org.acme@1.0.0.Country
+
+            concept Company {
+              o Address address
+              o Country country
             }
-            model_builder.build()
-        }),
-    )(input)
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(model.extend(fragment.clone()), Ok(()));
+        assert_eq!(
+            model
+                .declarations
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Person", "Company"],
+            "Should append declarations"
+        );
+        assert_eq!(model.imports.len(), 2, "Should not duplicate imports");
+        assert!(model.declaration("Company").is_some());
+
+        assert_eq!(
+            model.extend(fragment),
+            Err(super::error::BuildError::DuplicateDeclaration(
+                String::from("Company")
+            )),
+            "Should not add declarations twice"
+        );
+        assert_eq!(model.declarations.len(), 2);
+
+        let (_, other) = super::model("namespace other@1.0.0\n").unwrap();
+        assert_eq!(
+            model.extend(other),
+            Err(super::error::BuildError::NamespaceMismatch(
+                String::from("test@1.0.0"),
+                String::from("other@1.0.0")
+            )),
+            "Should only extend with fragments of the same namespace"
+        );
+    }
 }
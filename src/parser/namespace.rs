@@ -1,22 +1,79 @@
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{anychar, space1},
-    combinator::{into, recognize},
+    character::complete::anychar,
+    combinator::{into, map_opt, opt, recognize, verify},
     error::context,
     multi::{many_till, separated_list1},
-    sequence::{pair, preceded, separated_pair, tuple},
+    sequence::{pair, preceded, tuple},
     Parser,
 };
 
 use super::common::token;
-use super::version::{pre_release_token, version_identifier, version_number, SemanticVersion};
-use crate::parser::{common::keywords, CResult};
+use super::version::{
+    pre_release_token, version_identifier, version_number, SemanticVersion, VersionNumber,
+};
+use crate::parser::{
+    common::keywords,
+    memo::memoized,
+    options::{self, LanguageVersion},
+    scan::space1,
+    CResult,
+};
 
+/// A namespace and its version, which legacy models written as `namespace org.acme` leave out
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Namespace {
     name: String,
-    version: SemanticVersion,
+    version: Option<SemanticVersion>,
+}
+
+/// Version unversioned namespaces are resolved and compared as
+static UNVERSIONED: SemanticVersion = SemanticVersion::Version(VersionNumber::ZERO);
+
+impl Namespace {
+    pub fn new(name: impl Into<String>, version: SemanticVersion) -> Self {
+        Self {
+            name: name.into(),
+            version: Some(version),
+        }
+    }
+
+    /// A namespace written without a version
+    pub fn unversioned(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version, `0.0.0` for namespaces written without one
+    pub fn version(&self) -> &SemanticVersion {
+        self.version.as_ref().unwrap_or(&UNVERSIONED)
+    }
+
+    /// The version as it is written, if it is
+    pub fn declared_version(&self) -> Option<&SemanticVersion> {
+        self.version.as_ref()
+    }
+
+    /// `org.acme@1.0.0`, or `org.acme` without a version
+    fn qualified(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "namespace {}", self.qualified())
+    }
 }
 
 impl serde::Serialize for Namespace {
@@ -24,13 +81,34 @@ impl serde::Serialize for Namespace {
     where
         S: serde::Serializer,
     {
-        let s = format!("{}@{}", self.name, String::from(&self.version));
-        serializer.serialize_str(&s)
+        serializer.collect_str(&self.qualified())
+    }
+}
+
+#[cfg(feature = "deserialize")]
+impl<'de> serde::Deserialize<'de> for Namespace {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        let parsed = nom::combinator::all_consuming(namespace_version)(s.as_str())
+            .map(|(_, ns)| Namespace::from(ns))
+            .ok();
+        parsed.ok_or_else(|| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"namespace")
+        })
     }
 }
 
 impl From<(String, SemanticVersion)> for Namespace {
     fn from(value: (String, SemanticVersion)) -> Self {
+        Namespace::new(value.0, value.1)
+    }
+}
+
+impl From<(String, Option<SemanticVersion>)> for Namespace {
+    fn from(value: (String, Option<SemanticVersion>)) -> Self {
         Namespace {
             name: value.0,
             version: value.1,
@@ -45,6 +123,20 @@ pub struct FullyQualifiedName {
     type_name: String,
 }
 
+impl FullyQualifiedName {
+    pub fn namespace_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &SemanticVersion {
+        &self.version
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+}
+
 impl From<(String, SemanticVersion, String)> for FullyQualifiedName {
     fn from(value: (String, SemanticVersion, String)) -> Self {
         FullyQualifiedName {
@@ -55,26 +147,69 @@ impl From<(String, SemanticVersion, String)> for FullyQualifiedName {
     }
 }
 
+impl std::fmt::Display for FullyQualifiedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}.{}", self.name, self.version, self.type_name)
+    }
+}
+
+impl serde::Serialize for FullyQualifiedName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "deserialize")]
+impl<'de> serde::Deserialize<'de> for FullyQualifiedName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        let parsed = nom::combinator::all_consuming(fqn)(s.as_str())
+            .map(|(_, fqn)| fqn)
+            .ok();
+        parsed.ok_or_else(|| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"fully qualified name")
+        })
+    }
+}
+
 /// Namespaces are tokens and can be dot separated
-fn namespace_name<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn namespace_name(input: &str) -> CResult<&str, &str> {
     context(
         "NamespaceToken",
         recognize(separated_list1(tag("."), token)),
     )(input)
 }
 
-fn namespace_version<'a>(input: &'a str) -> CResult<&'a str, (String, SemanticVersion)> {
+/// Version unversioned namespaces are resolved as, in Concerto 2.x models
+pub fn unversioned() -> SemanticVersion {
+    UNVERSIONED.clone()
+}
+
+/// Whether namespaces and imports can leave out the version in the running parse
+pub(crate) fn unversioned_namespaces() -> bool {
+    options::current().language_version == LanguageVersion::V2
+}
+
+/// A namespace name, with or without a version. Namespace definitions only leave it out with
+/// `LanguageVersion::V2`, metamodel JSON of those models does too.
+fn namespace_version(input: &str) -> CResult<&str, (String, Option<SemanticVersion>)> {
     context(
         "Namespace",
-        separated_pair(namespace_name, tag("@"), version_identifier)
+        pair(namespace_name, opt(preceded(tag("@"), version_identifier)))
             .map(|(name, ver)| (name.to_string(), ver)),
     )(input)
 }
 
-fn fqn_no_prerelease<'a>(input: &'a str) -> CResult<&'a str, FullyQualifiedName> {
+fn fqn_no_prerelease(input: &str) -> CResult<&str, FullyQualifiedName> {
     context(
         "FQNNoPrerelease",
-        tuple((token, tag("@"), version_number, tag("."), token)).map(
+        tuple((namespace_name, tag("@"), version_number, tag("."), token)).map(
             |(namespace_name, _, version_number, _, type_name)| {
                 (
                     namespace_name.to_string(),
@@ -89,7 +224,7 @@ fn fqn_no_prerelease<'a>(input: &'a str) -> CResult<&'a str, FullyQualifiedName>
 
 /// Try to pick up the last dot delimited bit of the pre-release
 /// since dots are valid prerelease characters.
-fn prerelease_dot_token<'a>(input: &'a str) -> CResult<&'a str, (&'a str, &'a str)> {
+fn prerelease_dot_token(input: &str) -> CResult<&str, (&str, &str)> {
     // First try to pick up the last bit separated by a dot
     let (_, (_, (_, token))) = context(
         "PrereleaseDotToken::Token",
@@ -106,11 +241,11 @@ fn prerelease_dot_token<'a>(input: &'a str) -> CResult<&'a str, (&'a str, &'a st
     Ok((rest, (&(pre_with_token[..end_of_pre]), token)))
 }
 
-fn fqn_with_prerelease<'a>(input: &'a str) -> CResult<&'a str, FullyQualifiedName> {
+fn fqn_with_prerelease(input: &str) -> CResult<&str, FullyQualifiedName> {
     context(
         "FQNWithPrerelease",
         tuple((
-            token,
+            namespace_name,
             tag("@"),
             version_number,
             tag("-"),
@@ -129,17 +264,55 @@ fn fqn_with_prerelease<'a>(input: &'a str) -> CResult<&'a str, FullyQualifiedNam
     )(input)
 }
 
-pub fn fqn<'a>(input: &'a str) -> CResult<&'a str, FullyQualifiedName> {
+/// A fully qualified name without a version, the type being the last dot separated token
+fn fqn_unversioned(input: &str) -> CResult<&str, (Namespace, String)> {
     context(
+        "FQNUnversioned",
+        map_opt(namespace_name, |name: &str| {
+            name.rsplit_once('.').map(|(namespace_name, type_name)| {
+                (
+                    Namespace::unversioned(namespace_name),
+                    type_name.to_string(),
+                )
+            })
+        }),
+    )(input)
+}
+
+/// The namespace and the type name of a fully qualified name as imports write it, which can
+/// leave out the version with `LanguageVersion::V2`
+pub(crate) fn import_fqn(input: &str) -> CResult<&str, (Namespace, String)> {
+    let mut versioned = fqn.map(|fqn| {
+        (
+            Namespace::new(fqn.namespace_name(), fqn.version().clone()),
+            fqn.type_name().to_string(),
+        )
+    });
+    match unversioned_namespaces() {
+        true => alt((versioned, fqn_unversioned))(input),
+        false => versioned.parse(input),
+    }
+}
+
+pub fn fqn(input: &str) -> CResult<&str, FullyQualifiedName> {
+    memoized(
         "FullyQualifiedName",
-        alt((fqn_with_prerelease, fqn_no_prerelease)),
+        context(
+            "FullyQualifiedName",
+            alt((fqn_with_prerelease, fqn_no_prerelease)),
+        ),
     )(input)
 }
 
-pub fn namespace_identifier<'a>(input: &'a str) -> CResult<&'a str, Namespace> {
+pub fn namespace_identifier(input: &str) -> CResult<&str, Namespace> {
     context(
         "NamespaceDefinition",
-        preceded(pair(keywords::namespace, space1), into(namespace_version)),
+        preceded(
+            pair(keywords::namespace, space1),
+            into(verify(namespace_version, |(_, version)| {
+                version.is_some() || unversioned_namespaces()
+            })),
+        ),
     )(input)
 }
 
@@ -196,6 +369,19 @@ mod test {
             )),
             "Should parse fully qualified name with pre-release with dots"
         );
+        assert_eq!(
+            super::fqn("org.acme@1.0.0.Foo"),
+            Ok((
+                "",
+                (
+                    "org.acme".to_string(),
+                    SemanticVersion::Version((1, 0, 0).into()),
+                    "Foo".to_string(),
+                )
+                    .into()
+            )),
+            "Should parse fully qualified name with a dotted namespace"
+        );
     }
 
     #[test]
@@ -206,7 +392,7 @@ mod test {
                 "",
                 (
                     "test".to_string(),
-                    SemanticVersion::Version((12, 13, 14).into())
+                    Some(SemanticVersion::Version((12, 13, 14).into()))
                 )
             )),
         );
@@ -216,10 +402,18 @@ mod test {
                 "",
                 (
                     "test".to_string(),
-                    SemanticVersion::VersionWithRelease((12, 13, 14).into(), "pre".to_string())
+                    Some(SemanticVersion::VersionWithRelease(
+                        (12, 13, 14).into(),
+                        "pre".to_string()
+                    ))
                 )
             ))
         );
+        assert_eq!(
+            super::namespace_version("org.acme"),
+            Ok(("", ("org.acme".to_string(), None))),
+            "Should leave the version out when it isn't written"
+        );
     }
 
     #[test]
@@ -247,4 +441,44 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            super::namespace_identifier("namespace  test@1.0.2-beta").map(|(_, ns)| ns.to_string()),
+            Ok(String::from("namespace test@1.0.2-beta")),
+        );
+        assert_eq!(
+            super::fqn("test@12.13.14-pre.bar123").map(|(_, fqn)| fqn.to_string()),
+            Ok(String::from("test@12.13.14-pre.bar123")),
+        );
+    }
+
+    #[test]
+    fn test_unversioned_namespace() {
+        use crate::parser::options::{with_options, LanguageVersion, ParserOptions};
+
+        assert!(
+            super::namespace_identifier("namespace org.acme").is_err(),
+            "Should only leave the version out with Concerto 2.x"
+        );
+        let v2 = ParserOptions {
+            language_version: LanguageVersion::V2,
+            ..ParserOptions::default()
+        };
+        let (_, namespace) =
+            with_options(&v2, || super::namespace_identifier("namespace org.acme")).unwrap();
+        assert_eq!(namespace, super::Namespace::unversioned("org.acme"));
+        assert_eq!(namespace.declared_version(), None);
+        assert_eq!(
+            namespace.version(),
+            &super::unversioned(),
+            "Should resolve as version 0.0.0"
+        );
+        assert_eq!(namespace.to_string(), "namespace org.acme");
+        assert_eq!(
+            serde_json::to_value(&namespace).unwrap(),
+            serde_json::json!("org.acme")
+        );
+    }
 }
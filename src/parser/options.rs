@@ -0,0 +1,123 @@
+use std::cell::Cell;
+
+/// Toggles for how strictly models are parsed. The default keeps the behavior the parser
+/// always had: meta properties defined twice overwrite each other, CRLF line endings are
+/// accepted, keywords are case sensitive and no input can follow the model.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct ParserOptions {
+    /// Meta properties can be defined more than once, the last one is kept
+    pub duplicate_metas: bool,
+    /// Lines can end with `\r\n`
+    pub crlf: bool,
+    /// Keywords can be written in any case, like `Concept` or `OPTIONAL`
+    pub case_insensitive_keywords: bool,
+    /// Input that isn't part of the model can follow it, and is ignored
+    pub trailing_input: bool,
+    /// Version, fully qualified name and meta property parsers run at most once per position
+    /// of the source, guarding against backtracking on hostile input at the cost of memory
+    pub memoize: bool,
+    /// What happens to Integer and Long values that don't fit their type
+    pub numeric_overflow: NumericOverflow,
+    /// The source is split into lexemes first and only values are parsed character by
+    /// character, models the lexeme parsers don't accept are parsed as usual
+    pub two_phase: bool,
+    /// Ranges can exclude their ends with parentheses, like `range=(0, 100]`
+    pub exclusive_ranges: bool,
+    /// Version of the Concerto language the models are written in
+    pub language_version: LanguageVersion,
+}
+
+/// Versions of the Concerto language with grammar differences the parser knows about
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum LanguageVersion {
+    /// Namespaces and imports can leave out the version, as in models written before
+    /// versioned namespaces. Those are read as version `0.0.0`. Map declarations and aliased
+    /// imports, added in 3.x, are rejected.
+    V2,
+    /// Namespaces and imports are versioned, maps can be declared and imported types aliased
+    #[default]
+    V3,
+}
+
+/// Handling of numbers too large or too small for the type of their property
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum NumericOverflow {
+    /// Fails with `CErrorKind::NumericOverflow`
+    #[default]
+    Reject,
+    /// Clamps the value to the largest or smallest value of the type
+    Saturate,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            duplicate_metas: true,
+            crlf: true,
+            case_insensitive_keywords: false,
+            trailing_input: false,
+            memoize: false,
+            numeric_overflow: NumericOverflow::Reject,
+            two_phase: false,
+            exclusive_ranges: false,
+            language_version: LanguageVersion::V3,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Rejects everything that isn't written exactly as the grammar describes
+    pub fn strict() -> Self {
+        Self {
+            duplicate_metas: false,
+            crlf: false,
+            case_insensitive_keywords: false,
+            trailing_input: false,
+            memoize: false,
+            numeric_overflow: NumericOverflow::Reject,
+            two_phase: false,
+            exclusive_ranges: false,
+            language_version: LanguageVersion::V3,
+        }
+    }
+
+    /// Accepts everything the toggles allow, and models written for Concerto 2.x
+    pub fn permissive() -> Self {
+        Self {
+            duplicate_metas: true,
+            crlf: true,
+            case_insensitive_keywords: true,
+            trailing_input: true,
+            memoize: false,
+            numeric_overflow: NumericOverflow::Saturate,
+            two_phase: false,
+            exclusive_ranges: true,
+            language_version: LanguageVersion::V2,
+        }
+    }
+}
+
+thread_local! {
+    // Parsers are plain functions, the options of the running parse are kept per thread
+    static CURRENT: Cell<ParserOptions> = Cell::new(ParserOptions::default());
+}
+
+/// Options of the parse running on this thread
+pub(crate) fn current() -> ParserOptions {
+    CURRENT.with(|c| c.get())
+}
+
+/// Restores the previous options when dropped, even if parsing panics
+struct Restore(ParserOptions);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|c| c.set(self.0));
+    }
+}
+
+/// Runs `parse` with the given options in effect for all parsers it calls
+pub(crate) fn with_options<T>(options: &ParserOptions, parse: impl FnOnce() -> T) -> T {
+    let _restore = Restore(CURRENT.with(|c| c.replace(*options)));
+    parse()
+}
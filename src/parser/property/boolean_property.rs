@@ -1,49 +1,182 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
     sequence::{preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
-    common::{boolean_value, keywords},
-    property::internal::{primitive_property, PrimitiveType},
+    common::{
+        boolean_value,
+        keywords::{self, peek_keyword, Keyword},
+    },
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, fmt_property_head, fmt_property_optional, primitive_property,
+            PrimitiveType,
+        },
+        CommonProperty, PropertyClass,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct BooleanProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "boolean_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<bool>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
+}
+
+#[cfg(feature = "deserialize")]
+fn boolean_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "BooleanProperty")
 }
 
+impl CommonProperty for BooleanProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::BooleanPropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+}
+
+impl std::fmt::Display for BooleanProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default={}", default_value)?;
+        }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `BooleanProperty` in code.
+#[derive(Debug)]
+pub struct BooleanPropertyBuilder {
+    property: BooleanProperty,
+}
+
+impl BooleanPropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            property: BooleanProperty {
+                class: "BooleanProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                decorators: Vec::new(),
+            },
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: bool) -> Self {
+        self.property.default_value = Some(value);
+        self
+    }
+
+    pub fn build(self) -> BooleanProperty {
+        self.property
+    }
+}
+
+#[derive(Clone)]
 enum BooleanMetaProperty {
     Default(bool),
     Optional,
 }
 
-pub fn boolean_property<'a>(input: &'a str) -> CResult<&'a str, BooleanProperty> {
-    let default = preceded(space1, boolean_default_value).map(|x| BooleanMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| BooleanMetaProperty::Optional);
+pub fn boolean_property(input: &str) -> CResult<&str, BooleanProperty> {
+    let (input, (property_name, is_array)) = context(
+        "BooleanProperty",
+        primitive_property(PrimitiveType::BooleanPropertyType),
+    )(input)?;
+    boolean_property_metas(input, property_name, is_array)
+}
 
-    let property_meta = context("PropertyMeta", alt((default, optional)));
+/// Parses the meta properties following `o Boolean name` of a property
+pub(crate) fn boolean_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, BooleanProperty> {
+    let property_meta = memoized(
+        "BooleanPropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Default => boolean_default_value
+                    .map(BooleanMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| BooleanMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "BooleanProperty",
-        primitive_property(PrimitiveType::BooleanPropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 2,
                 property_meta,
@@ -52,30 +185,33 @@ pub fn boolean_property<'a>(input: &'a str) -> CResult<&'a str, BooleanProperty>
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = BooleanProperty {
-                    class: String::from("BooleanProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use BooleanMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = BooleanProperty {
+                class: "BooleanProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use BooleanMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn boolean_default_value<'a>(input: &'a str) -> CResult<&'a str, bool> {
+pub fn boolean_default_value(input: &str) -> CResult<&str, bool> {
     into(context(
         "BooleanDefaultValue",
         preceded(
@@ -91,11 +227,12 @@ mod test {
     #[test]
     fn test_serialize_without_default() {
         let a = super::BooleanProperty {
-            class: String::from("BooleanProperty"),
+            class: "BooleanProperty",
             name: String::from("aProperty"),
             is_array: false,
             is_optional: true,
             default_value: None,
+            decorators: Vec::new(),
         };
 
         assert_eq!(
@@ -112,11 +249,12 @@ mod test {
     #[test]
     fn test_serialize_with_default() {
         let a = super::BooleanProperty {
-            class: String::from("BooleanProperty"),
+            class: "BooleanProperty",
             name: String::from("aProperty"),
             is_array: false,
             is_optional: true,
             default_value: Some(false),
+            decorators: Vec::new(),
         };
 
         assert_eq!(
@@ -125,7 +263,7 @@ mod test {
               "name": "aProperty",
               "isArray": false,
               "isOptional": true,
-              "default": false,
+              "defaultValue": false,
             }),
             serde_json::to_value(a).unwrap(),
         )
@@ -139,10 +277,11 @@ mod test {
                 "",
                 super::BooleanProperty {
                     name: String::from("foo"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse boolean with no meta properties"
@@ -154,10 +293,11 @@ mod test {
                 "",
                 super::BooleanProperty {
                     name: String::from("foo"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: None,
                     is_optional: false,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse boolean with array flag"
@@ -169,10 +309,11 @@ mod test {
                 "",
                 super::BooleanProperty {
                     name: String::from("baz"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: Some(false),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse boolean with false default value"
@@ -184,10 +325,11 @@ mod test {
                 "",
                 super::BooleanProperty {
                     name: String::from("baz"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: Some(true),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse boolean with true default value"
@@ -199,10 +341,11 @@ mod test {
                 "",
                 super::BooleanProperty {
                     name: String::from("baz"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: Some(true),
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse boolean with optional flag"
@@ -214,13 +357,42 @@ mod test {
                 " default=42",
                 super::BooleanProperty {
                     name: String::from("baz"),
-                    class: String::from("BooleanProperty"),
+                    class: "BooleanProperty",
                     default_value: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should not parse boolean with wrong default value"
         );
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::boolean_property("o Boolean[] baz default=true").map(|(_, prop)| prop),
+            Ok(super::BooleanPropertyBuilder::new("baz")
+                .array()
+                .default_value(true)
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o Boolean foo";
+        assert_eq!(
+            super::boolean_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o Boolean[] baz default=true optional";
+        assert_eq!(
+            super::boolean_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
 }
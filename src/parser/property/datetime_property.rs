@@ -1,50 +1,190 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
     sequence::{preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
-    common::{datetime::datetime_value, keywords},
-    property::internal::{primitive_property, PrimitiveType},
+    common::{
+        datetime::datetime_value,
+        keywords::{self, peek_keyword, Keyword},
+    },
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, fmt_property_head, fmt_property_optional, primitive_property,
+            PrimitiveType,
+        },
+        CommonProperty, PropertyClass,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct DateTimeProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "datetime_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
+}
+
+#[cfg(feature = "deserialize")]
+fn datetime_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "DateTimeProperty")
+}
+
+impl CommonProperty for DateTimeProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::DateTimePropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+}
+
+impl DateTimeProperty {
+    /// The default value as a timestamp, dates without a time are midnight UTC
+    #[cfg(feature = "chrono")]
+    pub fn default_timestamp(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        crate::parser::common::datetime::timestamp(self.default_value.as_deref()?)
+    }
+}
+
+impl std::fmt::Display for DateTimeProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default={}", default_value)?;
+        }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `DateTimeProperty` in code.
+#[derive(Debug)]
+pub struct DateTimePropertyBuilder {
+    property: DateTimeProperty,
 }
 
+impl DateTimePropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            property: DateTimeProperty {
+                class: "DateTimeProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                decorators: Vec::new(),
+            },
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: impl Into<String>) -> Self {
+        self.property.default_value = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> DateTimeProperty {
+        self.property
+    }
+}
+
+#[derive(Clone)]
 enum DateTimeMetaProperty {
     Default(String),
     Optional,
 }
 
-pub fn datetime_property<'a>(input: &'a str) -> CResult<&'a str, DateTimeProperty> {
-    let default =
-        preceded(space1, datetime_default_value).map(|x| DateTimeMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| DateTimeMetaProperty::Optional);
+pub fn datetime_property(input: &str) -> CResult<&str, DateTimeProperty> {
+    let (input, (property_name, is_array)) = context(
+        "DateTimeProperty",
+        primitive_property(PrimitiveType::DateTimePropertyType),
+    )(input)?;
+    datetime_property_metas(input, property_name, is_array)
+}
 
-    let property_meta = context("PropertyMeta", alt((default, optional)));
+/// Parses the meta properties following `o DateTime name` of a property
+pub(crate) fn datetime_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, DateTimeProperty> {
+    let property_meta = memoized(
+        "DateTimePropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Default => datetime_default_value
+                    .map(DateTimeMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| DateTimeMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "DateTimeProperty",
-        primitive_property(PrimitiveType::DateTimePropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 2,
                 property_meta,
@@ -53,30 +193,33 @@ pub fn datetime_property<'a>(input: &'a str) -> CResult<&'a str, DateTimePropert
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = DateTimeProperty {
-                    class: String::from("DateTimeProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use DateTimeMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = DateTimeProperty {
+                class: "DateTimeProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use DateTimeMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn datetime_default_value<'a>(input: &'a str) -> CResult<&'a str, String> {
+pub fn datetime_default_value(input: &str) -> CResult<&str, String> {
     into(context(
         "DateTimeDefaultValue",
         preceded(
@@ -96,10 +239,11 @@ mod test {
                 "",
                 super::DateTimeProperty {
                     name: String::from("foo"),
-                    class: String::from("DateTimeProperty"),
+                    class: "DateTimeProperty",
                     default_value: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse datetime with no meta properties"
@@ -111,10 +255,11 @@ mod test {
                 "",
                 super::DateTimeProperty {
                     name: String::from("baz"),
-                    class: String::from("DateTimeProperty"),
+                    class: "DateTimeProperty",
                     default_value: Some(String::from("2024-01-04T18:39:55+02:30")),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse datetime with default value"
@@ -126,10 +271,11 @@ mod test {
                 "",
                 super::DateTimeProperty {
                     name: String::from("baz"),
-                    class: String::from("DateTimeProperty"),
+                    class: "DateTimeProperty",
                     default_value: Some(String::from("2024-01-04T18:39:55+02:30")),
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse datetime with optional flag"
@@ -141,10 +287,11 @@ mod test {
                 "",
                 super::DateTimeProperty {
                     name: String::from("baz"),
-                    class: String::from("DateTimeProperty"),
+                    class: "DateTimeProperty",
                     default_value: Some(String::from("2024-01-04T18:39:55+02:30")),
                     is_optional: true,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse datetime with array flag"
@@ -156,13 +303,43 @@ mod test {
                 " default=42",
                 super::DateTimeProperty {
                     name: String::from("baz"),
-                    class: String::from("DateTimeProperty"),
+                    class: "DateTimeProperty",
                     default_value: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should not parse datetime with wring default value"
         );
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::datetime_property("o DateTime baz optional default=2024-01-04")
+                .map(|(_, prop)| prop),
+            Ok(super::DateTimePropertyBuilder::new("baz")
+                .optional()
+                .default_value("2024-01-04")
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o DateTime foo";
+        assert_eq!(
+            super::datetime_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o DateTime baz default=2024-01-04T18:39:55+02:30 optional";
+        assert_eq!(
+            super::datetime_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
 }
@@ -1,60 +1,83 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
     sequence::{preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
-    common::{keywords, numeric::double_value},
-    property::internal::{primitive_property, ranged_parser, PrimitiveType, Ranged},
+    common::{
+        keywords::{self, peek_keyword, Keyword},
+        numeric::double_value,
+    },
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, exclusive_ranges, fmt_property_head, fmt_property_optional, fmt_ranged,
+            is_inclusive, primitive_property, ranged_parser, PrimitiveType, Ranged,
+        },
+        CommonProperty, PropertyClass, Validator,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct DoubleProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "double_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<f64>,
-    #[serde(rename = "range")]
+    #[serde(rename = "validator")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain_validator: Option<DoubleDomainValidator>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
 pub struct DoubleDomainValidator {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lower: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub upper: Option<f64>,
+    /// The lower bound is excluded, like in `range=(0, 100]`
+    #[serde(rename = "lowerExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub lower_exclusive: bool,
+    /// The upper bound is excluded, like in `range=[0, 100)`
+    #[serde(rename = "upperExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub upper_exclusive: bool,
 }
 
-impl serde::Serialize for DoubleDomainValidator {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&String::from(self))
-    }
-}
-
-impl From<&DoubleDomainValidator> for String {
-    fn from(value: &DoubleDomainValidator) -> Self {
-        match (value.lower, value.upper) {
-            (None, None) => Self::from(""),
-            (Some(lower), Some(upper)) => format!("[{}, {}]", lower, upper),
-            (None, Some(upper)) => format!("[, {}]", upper),
-            (Some(lower), None) => format!("[{},]", lower),
-        }
+impl std::fmt::Display for DoubleDomainValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exclusive = (self.lower_exclusive, self.upper_exclusive);
+        fmt_ranged(f, "range", &self.lower, &self.upper, exclusive)
     }
 }
 
@@ -63,9 +86,119 @@ impl From<Ranged<f64>> for DoubleDomainValidator {
         Self {
             lower: value.start,
             upper: value.end,
+            lower_exclusive: value.start_exclusive,
+            upper_exclusive: value.end_exclusive,
+        }
+    }
+}
+#[cfg(feature = "deserialize")]
+fn double_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "DoubleProperty")
+}
+
+impl CommonProperty for DoubleProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::DoublePropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+
+    fn validators(&self) -> Vec<Validator<'_>> {
+        self.domain_validator
+            .iter()
+            .map(Validator::DoubleDomain)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for DoubleProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default={:?}", default_value)?;
+        }
+        if let Some(domain_validator) = &self.domain_validator {
+            if domain_validator.lower.is_some() || domain_validator.upper.is_some() {
+                write!(f, " {}", domain_validator)?;
+            }
+        }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `DoubleProperty` in code.
+#[derive(Debug)]
+pub struct DoublePropertyBuilder {
+    property: DoubleProperty,
+}
+
+impl DoublePropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            property: DoubleProperty {
+                class: "DoubleProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                domain_validator: None,
+                decorators: Vec::new(),
+            },
         }
     }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: f64) -> Self {
+        self.property.default_value = Some(value);
+        self
+    }
+
+    pub fn range(mut self, lower: Option<f64>, upper: Option<f64>) -> Self {
+        self.property.domain_validator = Some(DoubleDomainValidator {
+            lower,
+            upper,
+            lower_exclusive: false,
+            upper_exclusive: false,
+        });
+        self
+    }
+
+    pub fn build(self) -> DoubleProperty {
+        self.property
+    }
 }
+
+#[derive(Clone)]
 enum DoubleMetaProperty {
     Default(f64),
     Domain(DoubleDomainValidator),
@@ -75,21 +208,43 @@ enum DoubleMetaProperty {
 /// Parses a primitive DoubleProperty with its default meta properties.
 /// If a meta property is defined twice, second one will overwrite the first.
 /// Meta property parser will only run three times.
-pub fn double_property<'a>(input: &'a str) -> CResult<&'a str, DoubleProperty> {
-    let domain = context(
-        "DoubleDomainValidator",
-        preceded(space1, double_domain_validator),
-    )
-    .map(|x| DoubleMetaProperty::Domain(x));
-    let default = preceded(space1, double_default_value).map(|x| DoubleMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| DoubleMetaProperty::Optional);
+pub fn double_property(input: &str) -> CResult<&str, DoubleProperty> {
+    let (input, (property_name, is_array)) = context(
+        "DoubleProperty",
+        primitive_property(PrimitiveType::DoublePropertyType),
+    )(input)?;
+    double_property_metas(input, property_name, is_array)
+}
 
-    let property_meta = context("PropertyMeta", alt((domain, default, optional)));
+/// Parses the meta properties following `o Double name` of a property
+pub(crate) fn double_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, DoubleProperty> {
+    let property_meta = memoized(
+        "DoublePropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Range => context("DoubleDomainValidator", double_domain_validator)
+                    .map(DoubleMetaProperty::Domain)
+                    .parse(input),
+                Keyword::Default => double_default_value
+                    .map(DoubleMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| DoubleMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "DoubleProperty",
-        primitive_property(PrimitiveType::DoublePropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 3,
                 property_meta,
@@ -98,32 +253,35 @@ pub fn double_property<'a>(input: &'a str) -> CResult<&'a str, DoubleProperty> {
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = DoubleProperty {
-                    class: String::from("DoubleProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    domain_validator: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use DoubleMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Domain(x) => prop.domain_validator = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = DoubleProperty {
+                class: "DoubleProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                domain_validator: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use DoubleMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Domain(x) => prop.domain_validator = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn double_default_value<'a>(input: &'a str) -> CResult<&'a str, f64> {
+pub fn double_default_value(input: &str) -> CResult<&str, f64> {
     into(context(
         "DoubleDefaultValue",
         preceded(
@@ -133,8 +291,8 @@ pub fn double_default_value<'a>(input: &'a str) -> CResult<&'a str, f64> {
     ))(input)
 }
 
-pub fn double_domain_validator<'a>(input: &'a str) -> CResult<&'a str, DoubleDomainValidator> {
-    match ranged_parser(input, keywords::range, double_value) {
+pub fn double_domain_validator(input: &str) -> CResult<&str, DoubleDomainValidator> {
+    match ranged_parser(input, keywords::range, double_value, exclusive_ranges()) {
         Err(e) => Err(e),
         Ok((remains, ranged)) => Ok((remains, ranged.into())),
     }
@@ -149,12 +307,13 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("foo"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with no meta properties"
@@ -165,12 +324,13 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("foo"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with optional flag"
@@ -181,12 +341,13 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: Some(42.0),
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with default value only"
@@ -197,15 +358,18 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: Some(super::DoubleDomainValidator {
                         lower: Some(0.0),
-                        upper: Some(10.0)
+                        upper: Some(10.0),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with range only"
@@ -216,15 +380,18 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: Some(-42.0e3),
                     domain_validator: Some(super::DoubleDomainValidator {
                         lower: None,
-                        upper: Some(100.4)
+                        upper: Some(100.4),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with both default and range"
@@ -237,15 +404,18 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: Some(-42.0e3),
                     domain_validator: Some(super::DoubleDomainValidator {
                         lower: None,
-                        upper: Some(100.4)
+                        upper: Some(100.4),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with both default and range and with optional flag"
@@ -256,15 +426,18 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: Some(42.5e-3),
                     domain_validator: Some(super::DoubleDomainValidator {
                         lower: None,
-                        upper: Some(100.0)
+                        upper: Some(100.0),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with both default and range in a different order"
@@ -275,15 +448,18 @@ mod test {
             Ok((
                 "",
                 super::DoubleProperty {
-                    class: String::from("DoubleProperty"),
+                    class: "DoubleProperty",
                     name: String::from("baz"),
                     default_value: Some(42.5e-3),
                     domain_validator: Some(super::DoubleDomainValidator {
                         lower: None,
-                        upper: Some(100.0)
+                        upper: Some(100.0),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse double with array flag"
@@ -291,9 +467,10 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_serialize() {
         let a = super::DoubleProperty {
-            class: String::from("DoubleProperty"),
+            class: "DoubleProperty",
             name: String::from("aProperty"),
             is_array: false,
             is_optional: true,
@@ -301,7 +478,10 @@ mod test {
             domain_validator: Some(super::DoubleDomainValidator {
                 lower: Some(0.01),
                 upper: None,
+                lower_exclusive: false,
+                upper_exclusive: false,
             }),
+            decorators: Vec::new(),
         };
 
         assert_eq!(
@@ -310,10 +490,41 @@ mod test {
               "name": "aProperty",
               "isArray": false,
               "isOptional": true,
-              "default": 3.14,
-              "range": "[0.01,]"
+              "defaultValue": 3.14,
+              "validator": {
+                "$class": "DoubleDomainValidator",
+                "lower": 0.01
+              }
             }),
             serde_json::to_value(a).unwrap(),
         )
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::double_property("o Double baz optional range=[0.5,]").map(|(_, prop)| prop),
+            Ok(super::DoublePropertyBuilder::new("baz")
+                .optional()
+                .range(Some(0.5), None)
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o Double foo";
+        assert_eq!(
+            super::double_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o Double[] baz default=2.5 range=[0.5, 1.0] optional";
+        assert_eq!(
+            super::double_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
 }
@@ -0,0 +1,123 @@
+use nom::{
+    character::complete::char,
+    error::context,
+    sequence::{preceded, tuple},
+    Parser,
+};
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::parser::{
+    common::token,
+    decorator::Decorator,
+    property::{CommonProperty, PropertyClass},
+    scan::{space0, space1},
+    CResult,
+};
+
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
+/// A value of an enum declaration, written `o RED`
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct EnumProperty {
+    #[serde(rename = "$class")]
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "enum_property_class")
+    )]
+    pub class: PropertyClass,
+    pub name: String,
+    /// Decorators written above the value
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
+}
+
+#[cfg(feature = "deserialize")]
+fn enum_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "EnumProperty")
+}
+
+impl EnumProperty {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            class: "EnumProperty",
+            name: name.into(),
+            decorators: Vec::new(),
+        }
+    }
+}
+
+impl CommonProperty for EnumProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    /// Values have no type, this is empty
+    fn type_name(&self) -> &str {
+        ""
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        false
+    }
+
+    fn is_array(&self) -> bool {
+        false
+    }
+}
+
+impl std::fmt::Display for EnumProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "o {}", self.name)
+    }
+}
+
+pub fn enum_property(input: &str) -> CResult<&str, EnumProperty> {
+    context(
+        "EnumProperty",
+        preceded(tuple((space0, char('o'), space1)), token).map(EnumProperty::new),
+    )(input)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_enum_property() {
+        assert_eq!(
+            super::enum_property("o RED"),
+            Ok(("", super::EnumProperty::new("RED"))),
+            "Should parse enum value"
+        );
+        assert_eq!(
+            super::enum_property("o String name"),
+            Ok((" name", super::EnumProperty::new("String"))),
+            "Should leave what follows the value to the declaration"
+        );
+        assert_eq!(
+            serde_json::to_value(super::EnumProperty::new("RED")).unwrap(),
+            serde_json::json!({
+              "$class": "EnumProperty",
+              "name": "RED",
+            }),
+        );
+        assert_eq!(
+            super::EnumProperty::new("RED").to_string(),
+            "o RED",
+            "Should render the value as it is parsed"
+        );
+    }
+}
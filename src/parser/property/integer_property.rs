@@ -1,60 +1,83 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
     sequence::{preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
-    common::{keywords, numeric::integer_value},
-    property::internal::{primitive_property, ranged_parser, PrimitiveType, Ranged},
+    common::{
+        keywords::{self, peek_keyword, Keyword},
+        numeric::integer_value,
+    },
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, exclusive_ranges, fmt_property_head, fmt_property_optional, fmt_ranged,
+            is_inclusive, primitive_property, ranged_parser, PrimitiveType, Ranged,
+        },
+        CommonProperty, PropertyClass, Validator,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct IntegerProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "integer_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<i32>,
-    #[serde(rename = "range")]
+    #[serde(rename = "validator")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain_validator: Option<IntegerDomainValidator>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
 pub struct IntegerDomainValidator {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lower: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub upper: Option<i32>,
+    /// The lower bound is excluded, like in `range=(0, 100]`
+    #[serde(rename = "lowerExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub lower_exclusive: bool,
+    /// The upper bound is excluded, like in `range=[0, 100)`
+    #[serde(rename = "upperExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub upper_exclusive: bool,
 }
 
-impl serde::Serialize for IntegerDomainValidator {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&String::from(self))
-    }
-}
-
-impl From<&IntegerDomainValidator> for String {
-    fn from(value: &IntegerDomainValidator) -> Self {
-        match (value.lower, value.upper) {
-            (None, None) => Self::from(""),
-            (Some(lower), Some(upper)) => format!("[{}, {}]", lower, upper),
-            (None, Some(upper)) => format!("[, {}]", upper),
-            (Some(lower), None) => format!("[{},]", lower),
-        }
+impl std::fmt::Display for IntegerDomainValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exclusive = (self.lower_exclusive, self.upper_exclusive);
+        fmt_ranged(f, "range", &self.lower, &self.upper, exclusive)
     }
 }
 
@@ -63,9 +86,119 @@ impl From<Ranged<i32>> for IntegerDomainValidator {
         Self {
             lower: value.start,
             upper: value.end,
+            lower_exclusive: value.start_exclusive,
+            upper_exclusive: value.end_exclusive,
+        }
+    }
+}
+#[cfg(feature = "deserialize")]
+fn integer_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "IntegerProperty")
+}
+
+impl CommonProperty for IntegerProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::IntegerPropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+
+    fn validators(&self) -> Vec<Validator<'_>> {
+        self.domain_validator
+            .iter()
+            .map(Validator::IntegerDomain)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for IntegerProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default={:?}", default_value)?;
+        }
+        if let Some(domain_validator) = &self.domain_validator {
+            if domain_validator.lower.is_some() || domain_validator.upper.is_some() {
+                write!(f, " {}", domain_validator)?;
+            }
         }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `IntegerProperty` in code.
+#[derive(Debug)]
+pub struct IntegerPropertyBuilder {
+    property: IntegerProperty,
+}
+
+impl IntegerPropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            property: IntegerProperty {
+                class: "IntegerProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                domain_validator: None,
+                decorators: Vec::new(),
+            },
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: i32) -> Self {
+        self.property.default_value = Some(value);
+        self
+    }
+
+    pub fn range(mut self, lower: Option<i32>, upper: Option<i32>) -> Self {
+        self.property.domain_validator = Some(IntegerDomainValidator {
+            lower,
+            upper,
+            lower_exclusive: false,
+            upper_exclusive: false,
+        });
+        self
+    }
+
+    pub fn build(self) -> IntegerProperty {
+        self.property
     }
 }
+
+#[derive(Clone)]
 enum IntegerMetaProperty {
     Default(i32),
     Domain(IntegerDomainValidator),
@@ -75,21 +208,43 @@ enum IntegerMetaProperty {
 /// Parses a primitive IntegerProperty with its default meta properties.
 /// If a meta property is defined twice, second one will overwrite the first.
 /// Meta property parser will only run three times.
-pub fn integer_property<'a>(input: &'a str) -> CResult<&'a str, IntegerProperty> {
-    let domain = context(
-        "IntegerDomainValidator",
-        preceded(space1, integer_domain_validator),
-    )
-    .map(|x| IntegerMetaProperty::Domain(x));
-    let default = preceded(space1, integer_default_value).map(|x| IntegerMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| IntegerMetaProperty::Optional);
+pub fn integer_property(input: &str) -> CResult<&str, IntegerProperty> {
+    let (input, (property_name, is_array)) = context(
+        "IntegerProperty",
+        primitive_property(PrimitiveType::IntegerPropertyType),
+    )(input)?;
+    integer_property_metas(input, property_name, is_array)
+}
 
-    let property_meta = context("PropertyMeta", alt((domain, default, optional)));
+/// Parses the meta properties following `o Integer name` of a property
+pub(crate) fn integer_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, IntegerProperty> {
+    let property_meta = memoized(
+        "IntegerPropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Range => context("IntegerDomainValidator", integer_domain_validator)
+                    .map(IntegerMetaProperty::Domain)
+                    .parse(input),
+                Keyword::Default => integer_default_value
+                    .map(IntegerMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| IntegerMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "IntegerProperty",
-        primitive_property(PrimitiveType::IntegerPropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 3,
                 property_meta,
@@ -98,32 +253,35 @@ pub fn integer_property<'a>(input: &'a str) -> CResult<&'a str, IntegerProperty>
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = IntegerProperty {
-                    class: String::from("IntegerProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    domain_validator: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use IntegerMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Domain(x) => prop.domain_validator = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = IntegerProperty {
+                class: "IntegerProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                domain_validator: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use IntegerMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Domain(x) => prop.domain_validator = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn integer_default_value<'a>(input: &'a str) -> CResult<&'a str, i32> {
+pub fn integer_default_value(input: &str) -> CResult<&str, i32> {
     into(context(
         "IntegerDefaultValue",
         preceded(
@@ -133,8 +291,8 @@ pub fn integer_default_value<'a>(input: &'a str) -> CResult<&'a str, i32> {
     ))(input)
 }
 
-pub fn integer_domain_validator<'a>(input: &'a str) -> CResult<&'a str, IntegerDomainValidator> {
-    match ranged_parser(input, keywords::range, integer_value) {
+pub fn integer_domain_validator(input: &str) -> CResult<&str, IntegerDomainValidator> {
+    match ranged_parser(input, keywords::range, integer_value, exclusive_ranges()) {
         Err(e) => Err(e),
         Ok((remains, ranged)) => Ok((remains, ranged.into())),
     }
@@ -149,12 +307,13 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("foo"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with no meta properties"
@@ -165,12 +324,13 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("foo"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse array flag"
@@ -181,12 +341,13 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("baz"),
                     default_value: Some(42),
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with default value only"
@@ -197,15 +358,18 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: Some(super::IntegerDomainValidator {
                         lower: Some(0),
-                        upper: Some(10)
+                        upper: Some(10),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with range only"
@@ -216,15 +380,18 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: Some(super::IntegerDomainValidator {
                         lower: Some(0),
-                        upper: Some(10)
+                        upper: Some(10),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with optional flag"
@@ -235,15 +402,18 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("baz"),
                     default_value: Some(-42),
                     domain_validator: Some(super::IntegerDomainValidator {
                         lower: None,
-                        upper: Some(100)
+                        upper: Some(100),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with both default and range"
@@ -254,18 +424,83 @@ mod test {
             Ok((
                 "",
                 super::IntegerProperty {
-                    class: String::from("IntegerProperty"),
+                    class: "IntegerProperty",
                     name: String::from("baz"),
                     default_value: Some(42),
                     domain_validator: Some(super::IntegerDomainValidator {
                         lower: None,
-                        upper: Some(100)
+                        upper: Some(100),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse integer with both default and range in a different order"
         );
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::integer_property("o Integer[] baz optional default=3 range=[-1, 10]")
+                .map(|(_, prop)| prop),
+            Ok(super::IntegerPropertyBuilder::new("baz")
+                .array()
+                .optional()
+                .default_value(3)
+                .range(Some(-1), Some(10))
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o Integer foo";
+        assert_eq!(
+            super::integer_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o Integer[] baz default=-3 range=[-1, 10] optional";
+        assert_eq!(
+            super::integer_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o Integer baz range=[1,]";
+        assert_eq!(
+            super::integer_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let a = super::IntegerPropertyBuilder::new("aProperty")
+            .array()
+            .default_value(42)
+            .range(Some(-1), Some(100))
+            .build();
+
+        assert_eq!(
+            serde_json::json!({
+              "$class": "IntegerProperty",
+              "name": "aProperty",
+              "isArray": true,
+              "isOptional": false,
+              "defaultValue": 42,
+              "validator": {
+                "$class": "IntegerDomainValidator",
+                "lower": -1,
+                "upper": 100
+              }
+            }),
+            serde_json::to_value(a).unwrap(),
+        )
+    }
 }
@@ -1,15 +1,20 @@
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{char, space0},
+    character::complete::char,
+    combinator::{opt, recognize, value, verify},
     error::context,
     sequence::{delimited, preceded, separated_pair, terminated, tuple},
     Parser,
 };
 
-use crate::parser::{common::token, error::CError, CResult};
+use crate::parser::{
+    common::token, declaration::type_reference, error::CError, options, property::CommonProperty,
+    scan::space0, CResult,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum PrimitiveType {
     StringPropertyType,
     BooleanPropertyType,
@@ -19,6 +24,21 @@ pub enum PrimitiveType {
     DateTimePropertyType,
 }
 
+impl PrimitiveType {
+    /// The primitive type of a type name, `None` for declarations
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "String" => Some(Self::StringPropertyType),
+            "Boolean" => Some(Self::BooleanPropertyType),
+            "Long" => Some(Self::LongPropertyType),
+            "Double" => Some(Self::DoublePropertyType),
+            "Integer" => Some(Self::IntegerPropertyType),
+            "DateTime" => Some(Self::DateTimePropertyType),
+            _ => None,
+        }
+    }
+}
+
 impl<'a> From<&'a str> for PrimitiveType {
     fn from(value: &'a str) -> Self {
         match value {
@@ -33,7 +53,7 @@ impl<'a> From<&'a str> for PrimitiveType {
     }
 }
 
-impl<'a> From<PrimitiveType> for &'a str {
+impl From<PrimitiveType> for &str {
     fn from(value: PrimitiveType) -> Self {
         use PrimitiveType::*;
         match value {
@@ -47,30 +67,45 @@ impl<'a> From<PrimitiveType> for &'a str {
     }
 }
 
-enum AnnotatedType {
-    Single,
-    Array,
-}
-
-/// Parses a generic proeprty type then returns (type name, the name of the defined type, is array) tuple
-pub fn generic_property<'a>(input: &'a str) -> CResult<&'a str, (&'a str, &'a str, bool)> {
-    let single_type = delimited(tuple((space0, char('o'), space0)), token, space0)
-        .map(|class: &'a str| (class, AnnotatedType::Single));
-    let array_type = delimited(
-        tuple((space0, char('o'), space0)),
+/// Parses a marker, a type optionally annotated as an array with `[]` and the name of a
+/// property, returns (type name, the name of the property, is array) tuple
+fn annotated_property<'a, M, O, T>(
+    marker: M,
+    type_name: T,
+) -> impl Parser<&'a str, (&'a str, &'a str, bool), CError<&'a str>>
+where
+    M: Parser<&'a str, O, CError<&'a str>>,
+    T: Parser<&'a str, &'a str, CError<&'a str>>,
+{
+    tuple((
+        space0,
+        marker,
+        space0,
+        type_name,
+        space0,
+        opt(tuple((char('['), space0, char(']'), space0))),
         token,
-        tuple((space0, char('['), space0, char(']'), space0)),
-    )
-    .map(|class: &'a str| (class, AnnotatedType::Array));
+    ))
+    .map(|(_, _, _, class, _, array, name)| (class, name, array.is_some()))
+}
 
+/// Parses a generic proeprty type, which can be fully qualified, then returns (type name, the name of the defined type, is array) tuple
+pub fn generic_property(input: &str) -> CResult<&str, (&str, &str, bool)> {
     context(
         "GenericProperty",
-        tuple((alt((array_type, single_type)), token)).map(|((class, annotated), name)| {
-            match annotated {
-                AnnotatedType::Array => (class, name, true),
-                AnnotatedType::Single => (class, name, false),
-            }
-        }),
+        annotated_property(char('o'), recognize(type_reference)),
+    )(input)
+}
+
+/// Parses `--> Type name` of a relationship, with the same array annotation as properties,
+/// then returns (type name, the name of the defined type, is array) tuple
+pub fn relationship_property(input: &str) -> CResult<&str, (&str, &str, bool)> {
+    context(
+        "RelationshipProperty",
+        verify(
+            annotated_property(tag("-->"), recognize(type_reference)),
+            |(class, _, _)| PrimitiveType::from_name(class).is_none(),
+        ),
     )(input)
 }
 
@@ -80,36 +115,118 @@ pub fn primitive_property<'a>(
 ) -> impl Fn(&'a str) -> CResult<&'a str, (&'a str, bool)> {
     move |input: &'a str| {
         let type_tag: &'a str = primitive_type.into();
-        let single_type = tuple((space0, char('o'), space0, tag(type_tag), space0))
-            .map(|_| AnnotatedType::Single);
-        let array_type = tuple((
-            space0,
-            char('o'),
-            space0,
-            tag(type_tag),
-            space0,
-            char('['),
-            space0,
-            char(']'),
-            space0,
-        ))
-        .map(|_| AnnotatedType::Array);
-
         context(
             "PrimitiveProperty",
-            tuple((alt((array_type, single_type)), token)).map(
-                |(annotated, name)| match annotated {
-                    AnnotatedType::Array => (name, true),
-                    AnnotatedType::Single => (name, false),
-                },
-            ),
+            annotated_property(char('o'), tag(type_tag))
+                .map(|(_, name, is_array)| (name, is_array)),
         )(input)
     }
 }
 
+/// Whether each kind of meta property is defined once, always true if the parser options
+/// allow duplicates
+pub(crate) fn distinct_metas<T>(metas: &[T]) -> bool {
+    options::current().duplicate_metas
+        || metas.iter().enumerate().all(|(i, meta)| {
+            metas[..i]
+                .iter()
+                .all(|other| std::mem::discriminant(meta) != std::mem::discriminant(other))
+        })
+}
+
+/// Deserializes a `$class` field, failing if it isn't the class of the property being
+/// deserialized so that untagged property variants can be told apart.
+#[cfg(feature = "deserialize")]
+pub(crate) fn expect_class<'de, D>(
+    deserializer: D,
+    expected: super::PropertyClass,
+) -> Result<super::PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let class: std::borrow::Cow<'de, str> = serde::Deserialize::deserialize(deserializer)?;
+    match class == expected {
+        true => Ok(expected),
+        false => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&class),
+            &expected,
+        )),
+    }
+}
+
+/// Writes the `o Type[] name` part of a property
+pub(crate) fn fmt_property_head(
+    f: &mut std::fmt::Formatter<'_>,
+    property: &dyn CommonProperty,
+) -> std::fmt::Result {
+    fmt_typed_property_head(f, property, &property.type_name())
+}
+
+/// Writes the `o Type[] name` part of a property with the type written another way, like
+/// fully qualified
+pub(crate) fn fmt_typed_property_head(
+    f: &mut std::fmt::Formatter<'_>,
+    property: &dyn CommonProperty,
+    type_name: &dyn std::fmt::Display,
+) -> std::fmt::Result {
+    let array = if property.is_array() { "[]" } else { "" };
+    let marker = if property.is_relationship() {
+        "-->"
+    } else {
+        "o"
+    };
+    write!(f, "{} {}{} {}", marker, type_name, array, property.name())
+}
+
+/// Writes the optional flag, which comes after all the other meta properties
+pub(crate) fn fmt_property_optional(
+    f: &mut std::fmt::Formatter<'_>,
+    property: &dyn CommonProperty,
+) -> std::fmt::Result {
+    match property.is_optional() {
+        true => write!(f, " optional"),
+        false => Ok(()),
+    }
+}
+
+/// Writes a ranged meta property like `range=[1, 10]`, nothing if both ends are open.
+/// Excluded ends are written with parentheses, like `range=(0, 10]`.
+pub(crate) fn fmt_ranged<T: std::fmt::Debug>(
+    f: &mut std::fmt::Formatter<'_>,
+    keyword: &str,
+    start: &Option<T>,
+    end: &Option<T>,
+    (start_exclusive, end_exclusive): (bool, bool),
+) -> std::fmt::Result {
+    let open = if start_exclusive { '(' } else { '[' };
+    let close = if end_exclusive { ')' } else { ']' };
+    match (start, end) {
+        (None, None) => Ok(()),
+        (Some(start), Some(end)) => {
+            write!(f, "{}={}{:?}, {:?}{}", keyword, open, start, end, close)
+        }
+        (None, Some(end)) => write!(f, "{}={}, {:?}{}", keyword, open, end, close),
+        (Some(start), None) => write!(f, "{}={}{:?},{}", keyword, open, start, close),
+    }
+}
+
 pub(crate) struct Ranged<T> {
     pub(crate) start: Option<T>,
     pub(crate) end: Option<T>,
+    /// Written with `(`, only with `ParserOptions::exclusive_ranges`
+    pub(crate) start_exclusive: bool,
+    /// Written with `)`, only with `ParserOptions::exclusive_ranges`
+    pub(crate) end_exclusive: bool,
+}
+
+/// Whether ranges can exclude their ends in the running parse
+pub(crate) fn exclusive_ranges() -> bool {
+    options::current().exclusive_ranges
+}
+
+/// Whether a bound excludes its end, for serde to leave inclusive ends out
+pub(crate) fn is_inclusive(exclusive: &bool) -> bool {
+    !exclusive
 }
 
 pub(crate) fn ranged_parser<
@@ -121,40 +238,48 @@ pub(crate) fn ranged_parser<
     input: &'a str,
     keyword: KV,
     parser: P,
+    exclusive: bool,
 ) -> CResult<&'a str, Ranged<T>> {
     let only_start = context(
         "RangedOnlyStart",
         terminated(parser, tuple((space0, char(','), space0))),
     )
-    .map(|start: T| Ranged {
-        start: Some(start),
-        end: None,
-    });
+    .map(|start: T| (Some(start), None));
 
     let only_end = context(
         "RangedOnlyEnd",
         preceded(tuple((space0, char(','), space0)), parser),
     )
-    .map(|end: T| Ranged {
-        end: Some(end),
-        start: None,
-    });
+    .map(|end: T| (None, Some(end)));
 
     let full = context(
         "RangedFull",
         separated_pair(parser, tuple((space0, char(','), space0)), parser),
     )
-    .map(|(start, end): (T, T)| Ranged {
-        start: Some(start),
-        end: Some(end),
-    });
+    .map(|(start, end): (T, T)| (Some(start), Some(end)));
 
+    // Parentheses exclude an end, in dialects that allow them
+    let bracket = |inclusive: char, excluding: char| {
+        alt((
+            value(false, char(inclusive)),
+            value(true, verify(char(excluding), move |_| exclusive)),
+        ))
+    };
     context(
         "RangedMetaProperty",
-        delimited(
-            tuple((keyword, space0, char('='), space0, char('['), space0)),
-            alt((full, only_start, only_end)),
-            tuple((space0, char(']'))),
-        ),
+        tuple((
+            preceded(
+                tuple((keyword, space0, char('='), space0)),
+                bracket('[', '('),
+            ),
+            delimited(space0, alt((full, only_start, only_end)), space0),
+            bracket(']', ')'),
+        ))
+        .map(|(start_exclusive, (start, end), end_exclusive)| Ranged {
+            start,
+            end,
+            start_exclusive,
+            end_exclusive,
+        }),
     )(input)
 }
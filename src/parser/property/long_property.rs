@@ -1,73 +1,243 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
     sequence::{preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
-    common::{keywords, numeric::long_value},
-    property::internal::{primitive_property, ranged_parser, PrimitiveType, Ranged},
+    common::{
+        keywords::{self, peek_keyword, Keyword},
+        numeric::long_value,
+    },
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, exclusive_ranges, fmt_property_head, fmt_property_optional, fmt_ranged,
+            is_inclusive, primitive_property, ranged_parser, PrimitiveType, Ranged,
+        },
+        CommonProperty, PropertyClass, Validator,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
+/// Values of Long properties, wide enough for data that exceeds i64 with the `i128` feature
+#[cfg(not(feature = "i128"))]
+pub type LongValue = i64;
+/// Values of Long properties, wide enough for data that exceeds i64 with the `i128` feature
+#[cfg(feature = "i128")]
+pub type LongValue = i128;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct LongProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "long_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub default_value: Option<i64>,
-    #[serde(rename = "range")]
+    #[cfg_attr(
+        all(feature = "deserialize", feature = "i128"),
+        serde(default, deserialize_with = "json_long_value")
+    )]
+    pub default_value: Option<LongValue>,
+    #[serde(rename = "validator")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain_validator: Option<LongDomainValidator>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
 pub struct LongDomainValidator {
-    pub lower: Option<i64>,
-    pub upper: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        all(feature = "deserialize", feature = "i128"),
+        serde(default, deserialize_with = "json_long_value")
+    )]
+    pub lower: Option<LongValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        all(feature = "deserialize", feature = "i128"),
+        serde(default, deserialize_with = "json_long_value")
+    )]
+    pub upper: Option<LongValue>,
+    /// The lower bound is excluded, like in `range=(0, 100]`
+    #[serde(rename = "lowerExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub lower_exclusive: bool,
+    /// The upper bound is excluded, like in `range=[0, 100)`
+    #[serde(rename = "upperExclusive")]
+    #[serde(default, skip_serializing_if = "is_inclusive")]
+    pub upper_exclusive: bool,
 }
 
-impl serde::Serialize for LongDomainValidator {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&String::from(self))
+impl std::fmt::Display for LongDomainValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exclusive = (self.lower_exclusive, self.upper_exclusive);
+        fmt_ranged(f, "range", &self.lower, &self.upper, exclusive)
     }
 }
 
-impl From<&LongDomainValidator> for String {
-    fn from(value: &LongDomainValidator) -> Self {
-        match (value.lower, value.upper) {
-            (None, None) => Self::from(""),
-            (Some(lower), Some(upper)) => format!("[{}, {}]", lower, upper),
-            (None, Some(upper)) => format!("[, {}]", upper),
-            (Some(lower), None) => format!("[{},]", lower),
+impl From<Ranged<LongValue>> for LongDomainValidator {
+    fn from(value: Ranged<LongValue>) -> Self {
+        Self {
+            lower: value.start,
+            upper: value.end,
+            lower_exclusive: value.start_exclusive,
+            upper_exclusive: value.end_exclusive,
         }
     }
 }
+#[cfg(feature = "deserialize")]
+fn long_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "LongProperty")
+}
+
+/// Reads i128 values as JSON numbers, `Property` is untagged and serde can't buffer i128
+#[cfg(all(feature = "deserialize", feature = "i128"))]
+fn json_long_value<'de, D>(deserializer: D) -> Result<Option<LongValue>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::{de::Error, Deserialize};
 
-impl From<Ranged<i64>> for LongDomainValidator {
-    fn from(value: Ranged<i64>) -> Self {
+    Option::<serde_json::Number>::deserialize(deserializer)?
+        .map(|n| {
+            n.as_i64()
+                .map(LongValue::from)
+                .or_else(|| n.as_u64().map(LongValue::from))
+                .ok_or_else(|| D::Error::custom(format!("invalid Long {}", n)))
+        })
+        .transpose()
+}
+
+impl CommonProperty for LongProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::LongPropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+
+    fn validators(&self) -> Vec<Validator<'_>> {
+        self.domain_validator
+            .iter()
+            .map(Validator::LongDomain)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for LongProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default={:?}", default_value)?;
+        }
+        if let Some(domain_validator) = &self.domain_validator {
+            if domain_validator.lower.is_some() || domain_validator.upper.is_some() {
+                write!(f, " {}", domain_validator)?;
+            }
+        }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `LongProperty` in code.
+#[derive(Debug)]
+pub struct LongPropertyBuilder {
+    property: LongProperty,
+}
+
+impl LongPropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
         Self {
-            lower: value.start,
-            upper: value.end,
+            property: LongProperty {
+                class: "LongProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                domain_validator: None,
+                decorators: Vec::new(),
+            },
         }
     }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: LongValue) -> Self {
+        self.property.default_value = Some(value);
+        self
+    }
+
+    pub fn range(mut self, lower: Option<LongValue>, upper: Option<LongValue>) -> Self {
+        self.property.domain_validator = Some(LongDomainValidator {
+            lower,
+            upper,
+            lower_exclusive: false,
+            upper_exclusive: false,
+        });
+        self
+    }
+
+    pub fn build(self) -> LongProperty {
+        self.property
+    }
 }
+
+#[derive(Clone)]
 enum LongMetaProperty {
-    Default(i64),
+    Default(LongValue),
     Domain(LongDomainValidator),
     Optional,
 }
@@ -75,21 +245,43 @@ enum LongMetaProperty {
 /// Parses a primitive LongProperty with its default meta properties.
 /// If a meta property is defined twice, second one will overwrite the first.
 /// Meta property parser will only run three times.
-pub fn long_property<'a>(input: &'a str) -> CResult<&'a str, LongProperty> {
-    let domain = context(
-        "LongDomainValidator",
-        preceded(space1, long_domain_validator),
-    )
-    .map(|x| LongMetaProperty::Domain(x));
-    let default = preceded(space1, long_default_value).map(|x| LongMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| LongMetaProperty::Optional);
+pub fn long_property(input: &str) -> CResult<&str, LongProperty> {
+    let (input, (property_name, is_array)) = context(
+        "LongProperty",
+        primitive_property(PrimitiveType::LongPropertyType),
+    )(input)?;
+    long_property_metas(input, property_name, is_array)
+}
 
-    let property_meta = context("PropertyMeta", alt((domain, default, optional)));
+/// Parses the meta properties following `o Long name` of a property
+pub(crate) fn long_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, LongProperty> {
+    let property_meta = memoized(
+        "LongPropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Range => context("LongDomainValidator", long_domain_validator)
+                    .map(LongMetaProperty::Domain)
+                    .parse(input),
+                Keyword::Default => long_default_value
+                    .map(LongMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| LongMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "LongProperty",
-        primitive_property(PrimitiveType::LongPropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 3,
                 property_meta,
@@ -98,32 +290,35 @@ pub fn long_property<'a>(input: &'a str) -> CResult<&'a str, LongProperty> {
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = LongProperty {
-                    class: String::from("LongProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    domain_validator: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use LongMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Domain(x) => prop.domain_validator = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = LongProperty {
+                class: "LongProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                domain_validator: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use LongMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Domain(x) => prop.domain_validator = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn long_default_value<'a>(input: &'a str) -> CResult<&'a str, i64> {
+pub fn long_default_value(input: &str) -> CResult<&str, LongValue> {
     into(context(
         "LongDefaultValue",
         preceded(
@@ -133,8 +328,8 @@ pub fn long_default_value<'a>(input: &'a str) -> CResult<&'a str, i64> {
     ))(input)
 }
 
-pub fn long_domain_validator<'a>(input: &'a str) -> CResult<&'a str, LongDomainValidator> {
-    match ranged_parser(input, keywords::range, long_value) {
+pub fn long_domain_validator(input: &str) -> CResult<&str, LongDomainValidator> {
+    match ranged_parser(input, keywords::range, long_value, exclusive_ranges()) {
         Err(e) => Err(e),
         Ok((remains, ranged)) => Ok((remains, ranged.into())),
     }
@@ -149,12 +344,13 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("foo"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with no meta properties"
@@ -165,12 +361,13 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: Some(42),
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with default value only"
@@ -181,12 +378,13 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: Some(42),
                     domain_validator: None,
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with optional flag"
@@ -197,12 +395,13 @@ mod test {
             Ok((
                 " default='Hello'",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should not parse long with wrong default value"
@@ -213,15 +412,18 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: Some(super::LongDomainValidator {
                         lower: Some(0),
-                        upper: Some(10)
+                        upper: Some(10),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with range only"
@@ -232,15 +434,18 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: Some(super::LongDomainValidator {
                         lower: Some(0),
-                        upper: Some(10)
+                        upper: Some(10),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with array flag"
@@ -251,15 +456,18 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: Some(-42),
                     domain_validator: Some(super::LongDomainValidator {
                         lower: None,
-                        upper: Some(100)
+                        upper: Some(100),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with both default and range"
@@ -270,12 +478,13 @@ mod test {
             Ok((
                 " \tdefault  =  'Hello'    range=[,100]",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: None,
                     domain_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should not parse long with wrong default value even though other meta is correct"
@@ -286,18 +495,75 @@ mod test {
             Ok((
                 "",
                 super::LongProperty {
-                    class: String::from("LongProperty"),
+                    class: "LongProperty",
                     name: String::from("baz"),
                     default_value: Some(42),
                     domain_validator: Some(super::LongDomainValidator {
                         lower: None,
-                        upper: Some(100)
+                        upper: Some(100),
+                        lower_exclusive: false,
+                        upper_exclusive: false,
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse long with both default and range in a different order"
         );
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::long_property("o Long baz default=3147483647 range=[,3147483648]")
+                .map(|(_, prop)| prop),
+            Ok(super::LongPropertyBuilder::new("baz")
+                .default_value(3147483647)
+                .range(None, Some(3147483648))
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o Long foo";
+        assert_eq!(
+            super::long_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o Long baz default=3147483647 range=[, 3147483648] optional";
+        assert_eq!(
+            super::long_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let a = super::LongPropertyBuilder::new("aProperty")
+            .array()
+            .default_value(3147483647)
+            .range(Some(0), Some(3147483648))
+            .build();
+
+        assert_eq!(
+            serde_json::json!({
+              "$class": "LongProperty",
+              "name": "aProperty",
+              "isArray": true,
+              "isOptional": false,
+              "defaultValue": 3147483647_i64,
+              "validator": {
+                "$class": "LongDomainValidator",
+                "lower": 0,
+                "upper": 3147483648_i64
+              }
+            }),
+            serde_json::to_value(a).unwrap(),
+        )
+    }
 }
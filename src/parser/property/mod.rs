@@ -1,67 +1,358 @@
-mod internal;
+pub(crate) mod internal;
 
 pub mod boolean_property;
 pub mod datetime_property;
 pub mod double_property;
+pub mod enum_property;
 pub mod integer_property;
 pub mod long_property;
 pub mod string_property;
 
 use nom::{
-    character::complete::space1, error::context, multi::fold_many_m_n, sequence::preceded, Parser,
+    combinator::all_consuming, error::context, multi::fold_many_m_n, sequence::preceded, Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
-use crate::parser::{common::keywords, property::internal::generic_property, CResult};
+pub use internal::PrimitiveType;
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+use crate::parser::{
+    common::keywords,
+    declaration::{type_reference, TypeReference},
+    decorator::Decorator,
+    namespace::Namespace,
+    property::internal::{generic_property, relationship_property},
+    scan::space1,
+    CResult,
+};
+
+/// Accessors shared by every kind of property, so consumers don't have to match on each
+/// property type to get to the common bits.
+pub trait CommonProperty {
+    /// Metamodel class of the property, e.g. `StringProperty` or `ObjectProperty`
+    fn class(&self) -> &str;
+    /// Type of the property as written in CTO, e.g. `String` or `Address`
+    fn type_name(&self) -> &str;
+    fn name(&self) -> &str;
+    fn is_optional(&self) -> bool;
+    fn is_array(&self) -> bool;
+    /// Decorators written above the property
+    fn decorators(&self) -> &[Decorator];
+    /// Written with `-->`, the value points to an instance instead of containing it
+    fn is_relationship(&self) -> bool {
+        false
+    }
+    /// Validators defined on the property, empty if none or if the type doesn't take any
+    fn validators(&self) -> Vec<Validator<'_>> {
+        Vec::new()
+    }
+}
+
+/// Metamodel class of a primitive property, e.g. `StringProperty`. Classes are fixed, so
+/// properties don't allocate them.
+// An alias, as serde would otherwise borrow `&'static str` fields from what's deserialized
+pub type PropertyClass = &'static str;
+
+/// Any validator a property can have
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Validator<'a> {
+    Regex(&'a string_property::StringRegexValidator),
+    Length(&'a string_property::StringLengthValidator),
+    IntegerDomain(&'a integer_property::IntegerDomainValidator),
+    LongDomain(&'a long_property::LongDomainValidator),
+    DoubleDomain(&'a double_property::DoubleDomainValidator),
+}
+
+impl std::fmt::Display for Validator<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Regex(v) => v.fmt(f),
+            Self::Length(v) => v.fmt(f),
+            Self::IntegerDomain(v) => v.fmt(f),
+            Self::LongDomain(v) => v.fmt(f),
+            Self::DoubleDomain(v) => v.fmt(f),
+        }
+    }
+}
+
+/// A property typed by another declaration, `class` holds the name of that type
+#[derive(Debug, PartialEq, Clone)]
 pub struct Property {
-    #[serde(rename = "$class")]
     pub class: String,
+    /// Namespace of the type when it is written fully qualified, like
+    /// `o org.acme@1.0.0.Address address`
+    pub namespace: Option<Namespace>,
     pub name: String,
-    #[serde(rename = "isOptional")]
     pub is_optional: bool,
-    #[serde(rename = "isArray")]
     pub is_array: bool,
+    /// Written `--> Type name`, a `RelationshipProperty` in the metamodel
+    pub is_relationship: bool,
+    /// Decorators written above the property
+    pub decorators: Vec<Decorator>,
+}
+
+/// Reference to a declaration as it appears in the metamodel
+#[derive(Serialize)]
+#[serde(tag = "$class")]
+struct TypeIdentifier<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<&'a Namespace>,
+}
+
+#[cfg(feature = "deserialize")]
+#[derive(Deserialize)]
+#[serde(rename = "ObjectProperty")]
+struct ObjectProperty {
+    #[serde(rename = "$class")]
+    #[serde(deserialize_with = "object_property_class")]
+    class: PropertyClass,
+    name: String,
+    #[serde(rename = "isOptional")]
+    is_optional: bool,
+    #[serde(rename = "isArray")]
+    is_array: bool,
+    #[serde(rename = "type")]
+    type_identifier: OwnedTypeIdentifier,
+    #[serde(default)]
+    decorators: Vec<Decorator>,
+}
+
+#[cfg(feature = "deserialize")]
+#[derive(Deserialize)]
+struct OwnedTypeIdentifier {
+    name: String,
+    #[serde(default)]
+    namespace: Option<Namespace>,
+}
+
+#[cfg(feature = "deserialize")]
+fn object_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let class: std::borrow::Cow<'de, str> = serde::Deserialize::deserialize(deserializer)?;
+    match class.as_ref() {
+        "ObjectProperty" => Ok("ObjectProperty"),
+        "RelationshipProperty" => Ok("RelationshipProperty"),
+        _ => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&class),
+            &"ObjectProperty or RelationshipProperty",
+        )),
+    }
+}
+
+#[cfg(feature = "deserialize")]
+impl<'de> serde::Deserialize<'de> for Property {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let object: ObjectProperty = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Property {
+            class: object.type_identifier.name,
+            namespace: object.type_identifier.namespace,
+            name: object.name,
+            is_optional: object.is_optional,
+            is_array: object.is_array,
+            is_relationship: object.class == "RelationshipProperty",
+            decorators: object.decorators,
+        })
+    }
+}
+
+/// Concept typed properties are `ObjectProperty` in the metamodel, or `RelationshipProperty`,
+/// with the type as a reference
+impl serde::Serialize for Property {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let fields = if self.decorators.is_empty() { 5 } else { 6 };
+        let mut state = serializer.serialize_struct("ObjectProperty", fields)?;
+        state.serialize_field("$class", self.class())?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("isOptional", &self.is_optional)?;
+        state.serialize_field("isArray", &self.is_array)?;
+        state.serialize_field(
+            "type",
+            &TypeIdentifier {
+                name: &self.class,
+                namespace: self.namespace.as_ref(),
+            },
+        )?;
+        if !self.decorators.is_empty() {
+            state.serialize_field("decorators", &self.decorators)?;
+        }
+        state.end()
+    }
+}
+
+impl Property {
+    /// The type of the property, fully qualified if it is written so
+    pub fn type_reference(&self) -> TypeReference {
+        TypeReference {
+            name: self.class.clone(),
+            namespace: self.namespace.clone(),
+        }
+    }
+}
+
+impl CommonProperty for Property {
+    fn class(&self) -> &str {
+        match self.is_relationship {
+            true => "RelationshipProperty",
+            false => "ObjectProperty",
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        &self.class
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+
+    fn is_relationship(&self) -> bool {
+        self.is_relationship
+    }
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        internal::fmt_typed_property_head(f, self, &self.type_reference())?;
+        internal::fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a concept typed `Property` in code.
+#[derive(Debug)]
+pub struct ConceptPropertyBuilder {
+    property: Property,
+}
+
+impl ConceptPropertyBuilder {
+    pub fn new(class: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            property: Property {
+                class: class.into(),
+                namespace: None,
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                is_relationship: false,
+                decorators: Vec::new(),
+            },
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn relationship(mut self) -> Self {
+        self.property.is_relationship = true;
+        self
+    }
+
+    /// Qualifies the type with the namespace it is declared in
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.property.namespace = Some(namespace);
+        self
+    }
+
+    pub fn build(self) -> Property {
+        self.property
+    }
 }
 
 enum MetaProperty {
     Optional,
 }
 
-pub fn concept_property<'a>(input: &'a str) -> CResult<&'a str, Property> {
+pub fn concept_property(input: &str) -> CResult<&str, Property> {
+    let (input, (class, property_name, is_array)) = context("Property", generic_property)(input)?;
+    concept_property_metas(input, class, property_name, is_array)
+}
+
+/// Parses `--> Type name` and its meta properties, which are those of concept properties
+pub fn relationship(input: &str) -> CResult<&str, Property> {
+    let (input, (class, property_name, is_array)) = relationship_property(input)?;
+    concept_property_metas(input, class, property_name, is_array).map(|(input, property)| {
+        (
+            input,
+            Property {
+                is_relationship: true,
+                ..property
+            },
+        )
+    })
+}
+
+/// Parses the meta properties following `o Type name` of a property typed by a declaration
+pub(crate) fn concept_property_metas<'a>(
+    input: &'a str,
+    class: &str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, Property> {
     let optional = preceded(space1, keywords::optional).map(|_| MetaProperty::Optional);
 
     context(
         "Property",
-        generic_property
-            .and(fold_many_m_n(
-                0,
-                1,
-                optional,
-                Vec::new,
-                |mut acc: Vec<_>, meta_prop| {
-                    acc.push(meta_prop);
-                    acc
+        fold_many_m_n(0, 1, optional, Vec::new, |mut acc: Vec<_>, meta_prop| {
+            acc.push(meta_prop);
+            acc
+        })
+        .map(|meta_props| {
+            let reference = match all_consuming(type_reference)(class) {
+                Ok((_, reference)) => reference,
+                Err(_) => TypeReference {
+                    name: class.to_string(),
+                    namespace: None,
                 },
-            ))
-            .map(|((class, property_name, is_array), meta_props)| {
-                let mut prop = Property {
-                    class: class.to_string(),
-                    name: property_name.to_string(),
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use MetaProperty::*;
-                    match meta_prop {
-                        Optional => prop.is_optional = true,
-                    }
+            };
+            let mut prop = Property {
+                class: reference.name,
+                namespace: reference.namespace,
+                name: property_name.to_string(),
+                is_optional: false,
+                is_array,
+                is_relationship: false,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use MetaProperty::*;
+                match meta_prop {
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
@@ -76,9 +367,12 @@ mod test {
                 "",
                 super::Property {
                     class: String::from("MyType"),
+                    namespace: None,
                     name: String::from("foo"),
                     is_optional: false,
                     is_array: false,
+                    is_relationship: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse imported type with no meta properties"
@@ -90,9 +384,12 @@ mod test {
                 "",
                 super::Property {
                     class: String::from("MyType"),
+                    namespace: None,
                     name: String::from("foo"),
                     is_optional: false,
                     is_array: true,
+                    is_relationship: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse imported type with array flag"
@@ -104,9 +401,12 @@ mod test {
                 "",
                 super::Property {
                     class: String::from("MyType"),
+                    namespace: None,
                     name: String::from("baz"),
                     is_optional: true,
                     is_array: false,
+                    is_relationship: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse imported type with optional flag"
@@ -118,32 +418,142 @@ mod test {
                 "",
                 super::Property {
                     class: String::from("MyType"),
+                    namespace: None,
                     name: String::from("baz"),
                     is_optional: true,
                     is_array: true,
+                    is_relationship: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse imported type with optional and array flag"
         );
     }
 
+    #[test]
+    fn test_relationship() {
+        let input = "--> Person[] members optional";
+        let expected = super::ConceptPropertyBuilder::new("Person", "members")
+            .array()
+            .optional()
+            .relationship()
+            .build();
+        assert_eq!(
+            super::relationship(input),
+            Ok(("", expected.clone())),
+            "Should parse relationship with array and optional flags"
+        );
+        assert_eq!(
+            expected.to_string(),
+            input,
+            "Should render the relationship as it is parsed"
+        );
+        assert!(
+            super::relationship("--> String owner").is_err(),
+            "Should not point to primitive types"
+        );
+        assert!(
+            super::concept_property(input).is_err(),
+            "Should not parse relationships as contained properties"
+        );
+        assert_eq!(
+            serde_json::to_value(expected).unwrap(),
+            serde_json::json!({
+              "$class": "RelationshipProperty",
+              "name": "members",
+              "isArray": true,
+              "isOptional": true,
+              "type": {
+                "$class": "TypeIdentifier",
+                "name": "Person",
+              },
+            }),
+        );
+    }
+
+    #[test]
+    fn test_qualified_type() {
+        use crate::parser::namespace::namespace_identifier;
+
+        let input = "o org.acme@1.0.0.Address[] addresses optional";
+        let (_, namespace) = namespace_identifier("namespace org.acme@1.0.0").unwrap();
+        let expected = super::ConceptPropertyBuilder::new("Address", "addresses")
+            .namespace(namespace)
+            .array()
+            .optional()
+            .build();
+        assert_eq!(
+            super::concept_property(input),
+            Ok(("", expected.clone())),
+            "Should parse the namespace of a fully qualified type"
+        );
+        assert_eq!(
+            expected.to_string(),
+            input,
+            "Should render the type fully qualified"
+        );
+        assert_eq!(
+            super::relationship("--> org.acme@1.0.0-beta.1.Person owner")
+                .map(|(_, prop)| prop.type_reference().to_string()),
+            Ok(String::from("org.acme@1.0.0-beta.1.Person")),
+            "Should parse relationships to fully qualified types"
+        );
+        assert_eq!(
+            serde_json::to_value(&expected).unwrap()["type"],
+            serde_json::json!({
+              "$class": "TypeIdentifier",
+              "name": "Address",
+              "namespace": "org.acme@1.0.0",
+            }),
+        );
+    }
+
     #[test]
     fn test_serialize() {
         let a = super::Property {
             class: String::from("MyProperty"),
+            namespace: None,
             name: String::from("aProperty"),
             is_array: false,
+            is_relationship: false,
             is_optional: true,
+            decorators: Vec::new(),
         };
 
         assert_eq!(
             serde_json::json!({
-              "$class": "MyProperty",
+              "$class": "ObjectProperty",
               "name": "aProperty",
               "isArray": false,
               "isOptional": true,
+              "type": {
+                "$class": "TypeIdentifier",
+                "name": "MyProperty",
+              },
             }),
             serde_json::to_value(a).unwrap(),
         )
     }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::concept_property("o Address[] addresses optional").map(|(_, prop)| prop),
+            Ok(super::ConceptPropertyBuilder::new("Address", "addresses")
+                .array()
+                .optional()
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o Address[] addresses optional";
+        assert_eq!(
+            super::concept_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
 }
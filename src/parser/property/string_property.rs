@@ -1,88 +1,106 @@
 use nom::{
-    branch::alt,
-    character::complete::{char, space0, space1},
-    combinator::into,
+    bytes::complete::take_while,
+    character::complete::char,
+    combinator::{fail, into, verify},
     error::context,
     multi::fold_many_m_n,
-    sequence::{preceded, tuple},
+    sequence::{pair, preceded, tuple},
     Parser,
 };
+#[cfg(feature = "deserialize")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::parser::{
     common::{
-        keywords,
+        keywords::{self, peek_keyword, Keyword},
         numeric::positive_integer_value,
-        string::{regex_value, string_value},
+        string::{escape_value, regex_value, string_value},
     },
-    property::internal::{primitive_property, ranged_parser, PrimitiveType, Ranged},
+    decorator::Decorator,
+    memo::memoized,
+    property::{
+        internal::{
+            distinct_metas, fmt_property_head, fmt_property_optional, fmt_ranged,
+            primitive_property, ranged_parser, PrimitiveType, Ranged,
+        },
+        CommonProperty, PropertyClass, Validator,
+    },
+    scan::{space0, space1},
     CResult,
 };
 
+#[cfg(feature = "deserialize")]
+use crate::parser::property::internal::expect_class;
+
 #[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct StringProperty {
     #[serde(rename = "$class")]
-    pub class: String,
+    #[cfg_attr(
+        feature = "deserialize",
+        serde(deserialize_with = "string_property_class")
+    )]
+    pub class: PropertyClass,
     pub name: String,
     #[serde(rename = "isOptional")]
     pub is_optional: bool,
     #[serde(rename = "isArray")]
     pub is_array: bool,
-    #[serde(rename = "default")]
+    #[serde(rename = "defaultValue")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
-    #[serde(rename = "regex")]
+    #[serde(rename = "validator")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub regex_validator: Option<StringRegexValidator>,
-    #[serde(rename = "length")]
+    #[serde(rename = "lengthValidator")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub length_validator: Option<StringLengthValidator>,
+    /// Decorators written above the property
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub decorators: Vec<Decorator>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
 pub struct StringRegexValidator {
     pub pattern: String,
     pub flags: String,
 }
 
-impl serde::Serialize for StringRegexValidator {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&String::from(self))
-    }
-}
-
-impl From<&StringRegexValidator> for String {
-    fn from(value: &StringRegexValidator) -> Self {
-        value.pattern.clone()
+impl std::fmt::Display for StringRegexValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "regex=/{}/{}",
+            escape_value(&self.pattern, '/'),
+            self.flags
+        )
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[serde(tag = "$class")]
 pub struct StringLengthValidator {
+    #[serde(rename = "minLength")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_length: Option<i32>,
+    #[serde(rename = "maxLength")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_length: Option<i32>,
 }
 
-impl serde::Serialize for StringLengthValidator {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&String::from(self))
-    }
-}
-
-impl From<&StringLengthValidator> for String {
-    fn from(value: &StringLengthValidator) -> Self {
-        match (value.min_length, value.max_length) {
-            (None, None) => Self::from(""),
-            (Some(lower), Some(upper)) => format!("[{}, {}]", lower, upper),
-            (None, Some(upper)) => format!("[, {}]", upper),
-            (Some(lower), None) => format!("[{},]", lower),
-        }
+impl std::fmt::Display for StringLengthValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_ranged(
+            f,
+            "length",
+            &self.min_length,
+            &self.max_length,
+            (false, false),
+        )
     }
 }
 
@@ -94,6 +112,137 @@ impl From<Ranged<i32>> for StringLengthValidator {
         }
     }
 }
+#[cfg(feature = "deserialize")]
+fn string_property_class<'de, D>(deserializer: D) -> Result<PropertyClass, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    expect_class(deserializer, "StringProperty")
+}
+
+impl CommonProperty for StringProperty {
+    fn class(&self) -> &str {
+        self.class
+    }
+
+    fn type_name(&self) -> &str {
+        PrimitiveType::StringPropertyType.into()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decorators(&self) -> &[Decorator] {
+        &self.decorators
+    }
+
+    fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+
+    fn is_array(&self) -> bool {
+        self.is_array
+    }
+
+    fn validators(&self) -> Vec<Validator<'_>> {
+        let mut validators = Vec::new();
+        if let Some(regex) = &self.regex_validator {
+            validators.push(Validator::Regex(regex));
+        }
+        if let Some(length) = &self.length_validator {
+            validators.push(Validator::Length(length));
+        }
+        validators
+    }
+}
+
+impl std::fmt::Display for StringProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_property_head(f, self)?;
+        if let Some(default_value) = &self.default_value {
+            write!(f, " default=\"{}\"", escape_value(default_value, '"'))?;
+        }
+        if let Some(regex_validator) = &self.regex_validator {
+            write!(f, " {}", regex_validator)?;
+        }
+        if let Some(length_validator) = &self.length_validator {
+            if length_validator.min_length.is_some() || length_validator.max_length.is_some() {
+                write!(f, " {}", length_validator)?;
+            }
+        }
+        fmt_property_optional(f, self)
+    }
+}
+
+/// Builds a `StringProperty` in code.
+#[derive(Debug)]
+pub struct StringPropertyBuilder {
+    property: StringProperty,
+}
+
+impl StringPropertyBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            property: StringProperty {
+                class: "StringProperty",
+                name: name.into(),
+                is_optional: false,
+                is_array: false,
+                default_value: None,
+                regex_validator: None,
+                length_validator: None,
+                decorators: Vec::new(),
+            },
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.property.is_optional = true;
+        self
+    }
+
+    pub fn array(mut self) -> Self {
+        self.property.is_array = true;
+        self
+    }
+
+    pub fn default_value(mut self, value: impl Into<String>) -> Self {
+        self.property.default_value = Some(value.into());
+        self
+    }
+
+    pub fn regex(self, pattern: impl Into<String>) -> Self {
+        self.regex_with_flags(pattern, "")
+    }
+
+    /// A regex with JavaScript flags, like `i` for `regex=/abc.*/i`
+    pub fn regex_with_flags(
+        mut self,
+        pattern: impl Into<String>,
+        flags: impl Into<String>,
+    ) -> Self {
+        self.property.regex_validator = Some(StringRegexValidator {
+            pattern: pattern.into(),
+            flags: flags.into(),
+        });
+        self
+    }
+
+    pub fn length(mut self, min_length: Option<i32>, max_length: Option<i32>) -> Self {
+        self.property.length_validator = Some(StringLengthValidator {
+            min_length,
+            max_length,
+        });
+        self
+    }
+
+    pub fn build(self) -> StringProperty {
+        self.property
+    }
+}
+
+#[derive(Clone)]
 enum StringMetaProperty {
     Regex(StringRegexValidator),
     Default(String),
@@ -104,22 +253,46 @@ enum StringMetaProperty {
 /// Parses a primitive StringProperty with its default meta properties.
 /// If a meta property is defined twice, second one will overwrite the first.
 /// Meta property parser will only run four times.
-pub fn string_property<'a>(input: &'a str) -> CResult<&'a str, StringProperty> {
-    let length = context(
-        "StringLengthValidator",
-        preceded(space1, string_length_validator),
-    )
-    .map(|x| StringMetaProperty::Length(x));
-    let regex = preceded(space1, string_regex_validator).map(|x| StringMetaProperty::Regex(x));
-    let default = preceded(space1, string_default_value).map(|x| StringMetaProperty::Default(x));
-    let optional = preceded(space1, keywords::optional).map(|_| StringMetaProperty::Optional);
-
-    let property_meta = context("PropertyMeta", alt((length, regex, default, optional)));
+pub fn string_property(input: &str) -> CResult<&str, StringProperty> {
+    let (input, (property_name, is_array)) = context(
+        "StringProperty",
+        primitive_property(PrimitiveType::StringPropertyType),
+    )(input)?;
+    string_property_metas(input, property_name, is_array)
+}
+
+/// Parses the meta properties following `o String name` of a property
+pub(crate) fn string_property_metas<'a>(
+    input: &'a str,
+    property_name: &str,
+    is_array: bool,
+) -> CResult<&'a str, StringProperty> {
+    let property_meta = memoized(
+        "StringPropertyMeta",
+        context(
+            "PropertyMeta",
+            preceded(space1, |input: &'a str| match peek_keyword(input)?.1 {
+                Keyword::Length => context("StringLengthValidator", string_length_validator)
+                    .map(StringMetaProperty::Length)
+                    .parse(input),
+                Keyword::Regex => string_regex_validator
+                    .map(StringMetaProperty::Regex)
+                    .parse(input),
+                Keyword::Default => string_default_value
+                    .map(StringMetaProperty::Default)
+                    .parse(input),
+                Keyword::Optional => keywords::optional
+                    .map(|_| StringMetaProperty::Optional)
+                    .parse(input),
+                _ => fail(input),
+            }),
+        ),
+    );
 
     context(
         "StringProperty",
-        primitive_property(PrimitiveType::StringPropertyType)
-            .and(fold_many_m_n(
+        verify(
+            fold_many_m_n(
                 0,
                 4,
                 property_meta,
@@ -128,34 +301,37 @@ pub fn string_property<'a>(input: &'a str) -> CResult<&'a str, StringProperty> {
                     acc.push(meta_prop);
                     acc
                 },
-            ))
-            .map(|((property_name, is_array), meta_props)| {
-                let mut prop = StringProperty {
-                    class: String::from("StringProperty"),
-                    name: property_name.to_string(),
-                    default_value: None,
-                    regex_validator: None,
-                    length_validator: None,
-                    is_optional: false,
-                    is_array,
-                };
-
-                for meta_prop in meta_props {
-                    use StringMetaProperty::*;
-                    match meta_prop {
-                        Default(x) => prop.default_value = Some(x),
-                        Regex(x) => prop.regex_validator = Some(x),
-                        Length(x) => prop.length_validator = Some(x),
-                        Optional => prop.is_optional = true,
-                    }
+            ),
+            distinct_metas,
+        )
+        .map(|meta_props| {
+            let mut prop = StringProperty {
+                class: "StringProperty",
+                name: property_name.to_string(),
+                default_value: None,
+                regex_validator: None,
+                length_validator: None,
+                is_optional: false,
+                is_array,
+                decorators: Vec::new(),
+            };
+
+            for meta_prop in meta_props {
+                use StringMetaProperty::*;
+                match meta_prop {
+                    Default(x) => prop.default_value = Some(x),
+                    Regex(x) => prop.regex_validator = Some(x),
+                    Length(x) => prop.length_validator = Some(x),
+                    Optional => prop.is_optional = true,
                 }
+            }
 
-                prop
-            }),
+            prop
+        }),
     )(input)
 }
 
-pub fn string_default_value<'a>(input: &'a str) -> CResult<&'a str, String> {
+pub fn string_default_value(input: &str) -> CResult<&str, String> {
     into(context(
         "StringDefaultValue",
         preceded(
@@ -165,22 +341,35 @@ pub fn string_default_value<'a>(input: &'a str) -> CResult<&'a str, String> {
     ))(input)
 }
 
-pub fn string_regex_validator<'a>(input: &'a str) -> CResult<&'a str, StringRegexValidator> {
+/// Flags of a JavaScript regex following the closing slash, like `gu` in `/abc/gu`, each at
+/// most once
+fn regex_flags(input: &str) -> CResult<&str, &str> {
+    context(
+        "RegexFlags",
+        verify(take_while(|c| "dgimsuvy".contains(c)), |flags: &str| {
+            flags
+                .char_indices()
+                .all(|(i, flag)| !flags[..i].contains(flag))
+        }),
+    )(input)
+}
+
+pub fn string_regex_validator(input: &str) -> CResult<&str, StringRegexValidator> {
     context(
         "StringRegexValidator",
         preceded(
             tuple((keywords::regex, space0, char('='), space0)),
-            regex_value,
+            pair(regex_value, regex_flags),
         )
-        .map(|s| StringRegexValidator {
-            pattern: s,
-            flags: "".to_string(),
+        .map(|(pattern, flags)| StringRegexValidator {
+            pattern: pattern.into_owned(),
+            flags: flags.to_string(),
         }),
     )(input)
 }
 
-pub fn string_length_validator<'a>(input: &'a str) -> CResult<&'a str, StringLengthValidator> {
-    match ranged_parser(input, keywords::length, positive_integer_value) {
+pub fn string_length_validator(input: &str) -> CResult<&str, StringLengthValidator> {
+    match ranged_parser(input, keywords::length, positive_integer_value, false) {
         Err(e) => Err(e),
         Ok((remains, ranged)) => Ok((remains, ranged.into())),
     }
@@ -195,13 +384,14 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("foo"),
                     default_value: None,
                     regex_validator: None,
                     length_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with no meta properties"
@@ -212,13 +402,14 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("foo"),
                     default_value: None,
                     regex_validator: None,
                     length_validator: None,
                     is_optional: true,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with optional flag"
@@ -229,13 +420,14 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: Some(String::from("Hello World")),
                     regex_validator: None,
                     length_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with default value only"
@@ -246,7 +438,7 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: None,
                     regex_validator: Some(super::StringRegexValidator {
@@ -256,6 +448,7 @@ mod test {
                     length_validator: None,
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with regex value only"
@@ -266,7 +459,7 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: None,
                     regex_validator: Some(super::StringRegexValidator {
@@ -276,6 +469,7 @@ mod test {
                     length_validator: None,
                     is_optional: false,
                     is_array: true,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with array flag"
@@ -286,7 +480,7 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: None,
                     regex_validator: None,
@@ -296,6 +490,7 @@ mod test {
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with length only"
@@ -308,7 +503,7 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: Some(String::from("Hello World")),
                     regex_validator: Some(super::StringRegexValidator {
@@ -321,6 +516,7 @@ mod test {
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with both default and regex and length"
@@ -333,7 +529,7 @@ mod test {
             Ok((
                 "",
                 super::StringProperty {
-                    class: String::from("StringProperty"),
+                    class: "StringProperty",
                     name: String::from("baz"),
                     default_value: Some(String::from("Hello World")),
                     regex_validator: Some(super::StringRegexValidator {
@@ -346,6 +542,7 @@ mod test {
                     }),
                     is_optional: false,
                     is_array: false,
+                    decorators: Vec::new(),
                 }
             )),
             "Should parse string with both default and regex and length in a different order"
@@ -355,7 +552,7 @@ mod test {
     #[test]
     fn test_serialize() {
         let a = super::StringProperty {
-            class: String::from("StringProperty"),
+            class: "StringProperty",
             name: String::from("aProperty"),
             is_array: true,
             is_optional: false,
@@ -365,6 +562,7 @@ mod test {
                 flags: "".into(),
             }),
             length_validator: None,
+            decorators: Vec::new(),
         };
 
         assert_eq!(
@@ -373,8 +571,110 @@ mod test {
               "name": "aProperty",
               "isArray": true,
               "isOptional": false,
-              "default": "Hello world",
-              "regex": "abc.*"
+              "defaultValue": "Hello world",
+              "validator": {
+                "$class": "StringRegexValidator",
+                "pattern": "abc.*",
+                "flags": ""
+              }
+            }),
+            serde_json::to_value(a).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            super::string_property(
+                "o String[] baz optional default=\"Hello\" regex=/abc.*/ length=[1, 10]"
+            )
+            .map(|(_, prop)| prop),
+            Ok(super::StringPropertyBuilder::new("baz")
+                .array()
+                .optional()
+                .default_value("Hello")
+                .regex("abc.*")
+                .length(Some(1), Some(10))
+                .build()),
+            "Should build the same property as parsed"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "o String foo";
+        assert_eq!(
+            super::string_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input =
+            "o String[] baz default=\"Hello \\\" World\" regex=/abc\\/.*/ length=[1, 10] optional";
+        assert_eq!(
+            super::string_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+        let input = "o String baz length=[, 10]";
+        assert_eq!(
+            super::string_property(input).map(|(_, prop)| prop.to_string()),
+            Ok(String::from(input)),
+            "Should render the property as it is parsed"
+        );
+    }
+
+    #[test]
+    fn test_regex_flags() {
+        let input = "o String code regex=/[a-z]+/gu optional";
+        let expected = super::StringPropertyBuilder::new("code")
+            .regex_with_flags("[a-z]+", "gu")
+            .optional()
+            .build();
+        assert_eq!(
+            super::string_property(input),
+            Ok(("", expected.clone())),
+            "Should parse flags after the pattern"
+        );
+        assert_eq!(
+            expected.to_string(),
+            input,
+            "Should render the flags after the pattern"
+        );
+        assert_eq!(
+            serde_json::to_value(&expected).unwrap()["validator"],
+            serde_json::json!({
+              "$class": "StringRegexValidator",
+              "pattern": "[a-z]+",
+              "flags": "gu"
+            }),
+        );
+        assert!(
+            super::string_regex_validator("regex=/abc/ii").is_err(),
+            "Should not repeat flags"
+        );
+        assert_eq!(
+            super::string_regex_validator("regex=/abc/x").map(|(rest, v)| (rest, v.flags)),
+            Ok(("x", String::new())),
+            "Should leave what isn't a flag"
+        );
+    }
+
+    #[test]
+    fn test_serialize_length() {
+        let a = super::StringPropertyBuilder::new("aProperty")
+            .length(Some(1), None)
+            .build();
+
+        assert_eq!(
+            serde_json::json!({
+              "$class": "StringProperty",
+              "name": "aProperty",
+              "isArray": false,
+              "isOptional": false,
+              "lengthValidator": {
+                "$class": "StringLengthValidator",
+                "minLength": 1
+              }
             }),
             serde_json::to_value(a).unwrap(),
         )
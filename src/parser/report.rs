@@ -0,0 +1,172 @@
+//! What parsing a model cost, for build pipelines that monitor and budget parse times
+
+use std::time::{Duration, Instant};
+
+/// Steps of parsing a model
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Phase {
+    /// Lexing and parsing lexemes, with the `two_phase` option
+    Lex,
+    /// Parsing character by character, skipped when the lexeme parsers accept the model
+    Parse,
+    /// Normalizing the names of sources that aren't ASCII
+    Normalize,
+}
+
+/// Costs of parsing one model
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ParseReport {
+    /// Bytes of the source that are part of the model, less than its length only when
+    /// trailing input is allowed
+    pub bytes_consumed: usize,
+    pub declarations: usize,
+    /// Time spent in each phase that ran, in the order they ran
+    pub phases: Vec<(Phase, Duration)>,
+    /// Most bytes allocated at once while parsing, over what was allocated before. `None`
+    /// unless `TrackingAllocator` is the global allocator.
+    #[cfg(feature = "allocations")]
+    pub peak_allocated: Option<usize>,
+}
+
+impl ParseReport {
+    /// Total time of all phases
+    pub fn duration(&self) -> Duration {
+        self.phases.iter().map(|(_, d)| *d).sum()
+    }
+
+    /// Runs a phase, adding the time it took
+    pub(crate) fn time<T>(&mut self, phase: Phase, run: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = run();
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+}
+
+#[cfg(feature = "allocations")]
+pub use allocations::TrackingAllocator;
+
+#[cfg(feature = "allocations")]
+pub(crate) use allocations::PeakAllocations;
+
+#[cfg(feature = "allocations")]
+mod allocations {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+    static CURRENT: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    /// The system allocator, counting the bytes allocated so parse reports can include the
+    /// peak. Allocations of all threads are counted, so reports of models parsed in parallel
+    /// overlap.
+    ///
+    /// ```ignore
+    /// #[global_allocator]
+    /// static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+    /// ```
+    pub struct TrackingAllocator;
+
+    unsafe impl GlobalAlloc for TrackingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let pointer = System.alloc(layout);
+            if !pointer.is_null() {
+                INSTALLED.store(true, Ordering::Relaxed);
+                let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+                PEAK.fetch_max(current, Ordering::Relaxed);
+            }
+            pointer
+        }
+
+        unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+            System.dealloc(pointer, layout);
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let moved = System.realloc(pointer, layout, new_size);
+            if !moved.is_null() {
+                match new_size.checked_sub(layout.size()) {
+                    Some(grown) => {
+                        let current = CURRENT.fetch_add(grown, Ordering::Relaxed) + grown;
+                        PEAK.fetch_max(current, Ordering::Relaxed);
+                    }
+                    None => {
+                        CURRENT.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+                    }
+                }
+            }
+            moved
+        }
+    }
+
+    /// Measures the peak from when it is started
+    pub(crate) struct PeakAllocations {
+        baseline: usize,
+    }
+
+    impl PeakAllocations {
+        pub(crate) fn start() -> Self {
+            let baseline = CURRENT.load(Ordering::Relaxed);
+            PEAK.store(baseline, Ordering::Relaxed);
+            Self { baseline }
+        }
+
+        pub(crate) fn peak(&self) -> Option<usize> {
+            INSTALLED
+                .load(Ordering::Relaxed)
+                .then(|| PEAK.load(Ordering::Relaxed).saturating_sub(self.baseline))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{options::ParserOptions, parse_with_report};
+
+    #[cfg(feature = "allocations")]
+    #[global_allocator]
+    static ALLOCATOR: super::TrackingAllocator = super::TrackingAllocator;
+
+    #[test]
+    fn test_report() {
+        let source = "namespace test@1.0.0
+
+concept Person {
+  o String name
+}
+
+concept Address {
+  o String street
+}
+";
+        let (_, report) = parse_with_report(source, &ParserOptions::default()).unwrap();
+        assert_eq!(report.bytes_consumed, source.len());
+        assert_eq!(report.declarations, 2);
+        assert_eq!(
+            report.phases.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            vec![super::Phase::Parse]
+        );
+        #[cfg(feature = "allocations")]
+        assert!(report.peak_allocated.is_some_and(|peak| peak > 0));
+
+        let options = ParserOptions {
+            two_phase: true,
+            ..ParserOptions::permissive()
+        };
+        let (_, report) = parse_with_report(&format!("{}}}", source), &options).unwrap();
+        assert_eq!(
+            report.bytes_consumed,
+            source.len(),
+            "Should not count trailing input"
+        );
+        assert_eq!(
+            report.phases.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            vec![super::Phase::Lex, super::Phase::Parse],
+            "Should parse by character what the lexeme parsers don't accept"
+        );
+    }
+}
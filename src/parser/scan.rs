@@ -0,0 +1,133 @@
+//! Scanning of whitespace runs, tokens and string delimiters, which take most of the time
+//! spent parsing large files. With the `memchr` feature, runs are scanned a byte at a time
+//! instead of a character at a time, and delimiters are searched for with the SIMD searches
+//! of `memchr`. Either way they match and fail exactly like the `nom` parsers they replace.
+
+#[cfg(not(feature = "memchr"))]
+mod scanner {
+    use nom::{
+        bytes::complete::take_while,
+        character::complete::{self, satisfy},
+        combinator::recognize,
+        sequence::pair,
+    };
+
+    use crate::parser::{
+        common::identifier::{is_identifier_continue, is_identifier_start},
+        CResult,
+    };
+
+    pub(crate) fn space0(input: &str) -> CResult<&str, &str> {
+        complete::space0(input)
+    }
+
+    pub(crate) fn space1(input: &str) -> CResult<&str, &str> {
+        complete::space1(input)
+    }
+
+    pub(crate) fn multispace0(input: &str) -> CResult<&str, &str> {
+        complete::multispace0(input)
+    }
+
+    pub(crate) fn identifier(input: &str) -> CResult<&str, &str> {
+        recognize(pair(
+            satisfy(is_identifier_start),
+            take_while(is_identifier_continue),
+        ))(input)
+    }
+
+    pub(crate) fn find_delimiter(body: &str, delimiter: char) -> Option<usize> {
+        body.find([delimiter, '\\'])
+    }
+}
+
+#[cfg(feature = "memchr")]
+mod scanner {
+    use nom::error::{ErrorKind, ParseError};
+
+    use crate::parser::{
+        common::identifier::{is_identifier_continue, is_identifier_start},
+        CResult,
+    };
+
+    fn is_space(b: u8) -> bool {
+        b == b' ' || b == b'\t'
+    }
+
+    pub(crate) fn space0(input: &str) -> CResult<&str, &str> {
+        Ok(split(input, run(input, is_space)))
+    }
+
+    pub(crate) fn space1(input: &str) -> CResult<&str, &str> {
+        split1(input, run(input, is_space), ErrorKind::Space)
+    }
+
+    pub(crate) fn multispace0(input: &str) -> CResult<&str, &str> {
+        let length = run(input, |b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'));
+        Ok(split(input, length))
+    }
+
+    pub(crate) fn identifier(input: &str) -> CResult<&str, &str> {
+        if !input.starts_with(is_identifier_start) {
+            return Err(nom::Err::Error(ParseError::from_error_kind(
+                input,
+                ErrorKind::Satisfy,
+            )));
+        }
+        // Only characters past the ASCII run are looked up in the Unicode tables
+        let ascii = run(input, |b| b.is_ascii_alphanumeric() || b == b'_');
+        let rest = &input[ascii..];
+        let length = ascii
+            + rest
+                .find(|c| !is_identifier_continue(c))
+                .unwrap_or(rest.len());
+        Ok(split(input, length))
+    }
+
+    pub(crate) fn find_delimiter(body: &str, delimiter: char) -> Option<usize> {
+        // Delimiters are ASCII, so they are never part of another character
+        memchr::memchr2(delimiter as u8, b'\\', body.as_bytes())
+    }
+
+    /// Length of the leading bytes matching `f`, `f` only matches ASCII bytes
+    fn run(input: &str, f: fn(u8) -> bool) -> usize {
+        input.bytes().position(|b| !f(b)).unwrap_or(input.len())
+    }
+
+    /// Remains and the first `length` bytes, like the `nom` parsers return them
+    fn split(input: &str, length: usize) -> (&str, &str) {
+        (&input[length..], &input[..length])
+    }
+
+    fn split1(input: &str, length: usize, kind: ErrorKind) -> CResult<&str, &str> {
+        match length {
+            0 => Err(nom::Err::Error(ParseError::from_error_kind(input, kind))),
+            _ => Ok(split(input, length)),
+        }
+    }
+}
+
+pub(crate) use scanner::*;
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_scan() {
+        assert_eq!(super::space0(" \t x"), Ok(("x", " \t ")));
+        assert_eq!(super::space0("\nx"), Ok(("\nx", "")));
+        assert_eq!(super::space1(" \nx"), Ok(("\nx", " ")));
+        assert!(super::space1("x").is_err(), "Should need a space");
+        assert_eq!(super::multispace0(" \r\n\tx "), Ok(("x ", " \r\n\t")));
+        assert_eq!(super::multispace0("é"), Ok(("é", "")));
+        assert_eq!(super::identifier("ab1c d"), Ok((" d", "ab1c")));
+        assert_eq!(super::identifier("aé-b"), Ok(("-b", "aé")));
+        assert_eq!(super::identifier("_a\u{301}1."), Ok((".", "_a\u{301}1")));
+        assert!(
+            super::identifier("1a").is_err(),
+            "Should start with a letter"
+        );
+        assert_eq!(super::find_delimiter("aé\"b", '"'), Some(3));
+        assert_eq!(super::find_delimiter("a\\\"", '"'), Some(1));
+        assert_eq!(super::find_delimiter("abc", '\''), None);
+    }
+}
@@ -0,0 +1,237 @@
+use nom::{
+    branch::alt,
+    combinator::{consumed, opt},
+    error::context,
+    multi::fold_many0,
+    sequence::{delimited, pair},
+    Parser,
+};
+use std::ops::Range;
+
+use super::{
+    comment,
+    declaration::{any_proeprty, declaration_with, map_entry, Declaration, Property},
+    decorator::decorators,
+    import, namespace,
+    options::{with_options, ParserOptions},
+    property::enum_property::enum_property,
+    CResult, Model, ModelBuilder,
+};
+
+/// Byte ranges of the parts of a model in its source. Spans are kept next to the `Model`,
+/// in the same order as its imports and declarations, so the AST is the same whether or not
+/// spans are needed.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct ModelSpans {
+    pub header: Option<Range<usize>>,
+    pub namespace: Range<usize>,
+    pub imports: Vec<Range<usize>>,
+    pub declarations: Vec<DeclarationSpans>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DeclarationSpans {
+    pub span: Range<usize>,
+    /// Spans of the properties with their decorators, in the order they are defined
+    pub properties: Vec<Range<usize>>,
+}
+
+impl DeclarationSpans {
+    /// Moves the spans by the given number of bytes
+    pub(crate) fn shift(&mut self, by: isize) {
+        self.span = shift(&self.span, by);
+        for property in self.properties.iter_mut() {
+            *property = shift(property, by);
+        }
+    }
+}
+
+pub(crate) fn shift(range: &Range<usize>, by: isize) -> Range<usize> {
+    range.start.saturating_add_signed(by)..range.end.saturating_add_signed(by)
+}
+
+/// Range of a slice of the input, relative to the start of the input
+pub(crate) fn range_of(input: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - input.as_ptr() as usize;
+    start..start + part.len()
+}
+
+/// Parses a declaration along with the sources of its properties
+pub(crate) fn spanned_declaration(input: &str) -> CResult<&str, (Declaration, Vec<&str>)> {
+    context(
+        "Declaration",
+        declaration_with(
+            consumed(pair(decorators, any_proeprty)),
+            consumed(pair(decorators, enum_property.map(Property::from))),
+            |name, input| consumed(pair(decorators, map_entry(name))).parse(input),
+        )
+        .map(|(head, properties)| {
+            let (sources, properties): (Vec<_>, Vec<_>) = properties.into_iter().unzip();
+            let (property_decorators, properties) = properties.into_iter().unzip();
+            let declaration = Declaration::new(head.kind, head.name, properties);
+            (head.declare(declaration, property_decorators), sources)
+        }),
+    )(input)
+}
+
+/// Spans of a declaration parsed from `part`, relative to the start of `input`
+pub(crate) fn declaration_spans(input: &str, part: &str, properties: &[&str]) -> DeclarationSpans {
+    DeclarationSpans {
+        span: range_of(input, part),
+        properties: properties.iter().map(|p| range_of(input, p)).collect(),
+    }
+}
+
+enum SpannedDefinition<'a> {
+    Namespace(&'a str, namespace::Namespace),
+    Import(&'a str, import::Import),
+    Declaration(&'a str, Box<(Declaration, Vec<&'a str>)>),
+}
+
+/// Parses a model, and the spans of its parts
+pub fn model_with_spans(input: &str) -> CResult<&str, (Model, ModelSpans)> {
+    model_with_spans_with(input, &ParserOptions::default())
+}
+
+/// Like `model_with_spans`, with options as `borrowed::model_with` takes them
+pub fn model_with_spans_with<'a>(
+    input: &'a str,
+    options: &ParserOptions,
+) -> CResult<&'a str, (Model, ModelSpans)> {
+    with_options(options, || spanned_model(input))
+}
+
+fn spanned_model(input: &str) -> CResult<&str, (Model, ModelSpans)> {
+    let definition = alt((
+        consumed(namespace::namespace_identifier)
+            .map(|(s, ns)| SpannedDefinition::Namespace(s, ns)),
+        consumed(import::import).map(|(s, i)| SpannedDefinition::Import(s, i)),
+        consumed(spanned_declaration).map(|(s, d)| SpannedDefinition::Declaration(s, Box::new(d))),
+    ));
+    let definitions = fold_many0(
+        delimited(comment::trivia0, definition, comment::trivia0),
+        Vec::new,
+        |mut acc, item| {
+            acc.push(item);
+            acc
+        },
+    );
+    let (after_header, header) = opt(comment::header)(input)?;
+    let (remains, defs) = context("Model", definitions)(after_header)?;
+
+    let mut model_builder = ModelBuilder::new();
+    let mut spans = ModelSpans::default();
+    if let Some(header) = header {
+        spans.header = Some(range_of(input, header));
+        model_builder = model_builder.with_header(header);
+    }
+    for def in defs {
+        match def {
+            SpannedDefinition::Namespace(source, ns) => {
+                spans.namespace = range_of(input, source);
+                model_builder = model_builder.with_namespace(ns);
+            }
+            SpannedDefinition::Import(source, i) => {
+                spans.imports.push(range_of(input, source));
+                model_builder = model_builder.add_import(i);
+            }
+            SpannedDefinition::Declaration(source, declaration) => {
+                let (d, properties) = *declaration;
+                spans
+                    .declarations
+                    .push(declaration_spans(input, source, &properties));
+                model_builder = model_builder.add_declaration(d);
+            }
+        }
+    }
+
+    super::build(input, remains, model_builder).map(|(remains, model)| (remains, (model, spans)))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_model_with_spans() {
+        let input = "namespace test@1.0.0
+import org.acme@1.0.0.Address
+
+concept Person {
+  o String name
+  o Address address optional
+}
+";
+        let (_, (model, spans)) = super::model_with_spans(input).unwrap();
+
+        assert_eq!(
+            model,
+            crate::parser::model(input).unwrap().1,
+            "Should parse the same model"
+        );
+        assert_eq!(&input[spans.namespace.clone()], "namespace test@1.0.0");
+        assert_eq!(
+            &input[spans.imports[0].clone()],
+            "import org.acme@1.0.0.Address"
+        );
+        assert!(input[spans.declarations[0].span.clone()].starts_with("concept Person {"));
+        assert!(input[spans.declarations[0].span.clone()].ends_with('}'));
+        assert_eq!(
+            spans.declarations[0]
+                .properties
+                .iter()
+                .map(|p| &input[p.clone()])
+                .collect::<Vec<_>>(),
+            vec!["o String name", "o Address address optional"],
+            "Should span properties without surrounding whitespace"
+        );
+    }
+
+    #[test]
+    fn test_declaration_kinds() {
+        let input = "namespace test@1.0.0
+
+enum Color {
+  o RED
+  o GREEN
+}
+
+asset Car identified by vin {
+  o String vin
+  o Color color
+}
+
+event CarSold {
+  o Car car
+}
+
+map Fleet {
+  o String
+  --> Car
+}
+";
+        let (remains, (model, spans)) = super::model_with_spans(input).unwrap();
+
+        assert_eq!(remains, "");
+        assert_eq!(
+            model,
+            crate::parser::model(input).unwrap().1,
+            "Should parse the same model"
+        );
+        assert_eq!(
+            spans
+                .declarations
+                .iter()
+                .flat_map(|d| d.properties.iter().map(|p| &input[p.clone()]))
+                .collect::<Vec<_>>(),
+            vec![
+                "o RED",
+                "o GREEN",
+                "o String vin",
+                "o Color color",
+                "o Car car",
+                "o String",
+                "--> Car"
+            ],
+            "Should span the values of enums and the entries of maps as properties"
+        );
+    }
+}
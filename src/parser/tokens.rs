@@ -0,0 +1,281 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped, tag, take_until, take_while},
+    character::complete::{
+        alpha1, alphanumeric1, anychar, char, digit1, multispace1, none_of, not_line_ending,
+        one_of, satisfy,
+    },
+    combinator::{opt, recognize},
+    multi::{many0, many1},
+    sequence::{delimited, pair, tuple},
+    IResult, Parser,
+};
+use std::ops::Range;
+
+use super::common::identifier::{is_identifier_continue, is_identifier_start};
+
+/// Words the grammar gives a meaning to, highlighted as keywords
+const KEYWORDS: [&str; 23] = [
+    "namespace",
+    "import",
+    "from",
+    "concept",
+    "asset",
+    "participant",
+    "transaction",
+    "event",
+    "enum",
+    "abstract",
+    "extends",
+    "identified",
+    "by",
+    "o",
+    "optional",
+    "default",
+    "regex",
+    "length",
+    "range",
+    "true",
+    "false",
+    "map",
+    "scalar",
+];
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    /// A quoted string, including the quotes
+    String,
+    /// A regular expression with its slashes and flags, as in `regex=/[a-z]+/i`
+    Regex,
+    Number,
+    /// The version after `@` in namespaces and imports
+    Version,
+    /// Line `//` or block `/* */` comment
+    Comment,
+    Punctuation,
+    Whitespace,
+    /// Anything else, so that every byte of the source is in a token
+    Unknown,
+}
+
+/// A token and where it is in the source
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+impl Token {
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.clone()]
+    }
+}
+
+/// Splits a source into tokens for syntax highlighting. No model is built and the source
+/// doesn't need to be valid, the tokens cover every byte of it in order.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut rest = source;
+    while !rest.is_empty() {
+        let significant: Vec<_> = tokens
+            .iter()
+            .rev()
+            .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+            .take(2)
+            .map(|t| t.text(source))
+            .collect();
+        let contextual = match significant.as_slice() {
+            ["@", ..] => version(rest).ok(),
+            ["=", "regex", ..] => regex(rest).ok(),
+            _ => None,
+        };
+        let (remains, (kind, text)) = match contextual {
+            Some(token) => token,
+            None => token(rest).expect("Any character makes a token"),
+        };
+
+        let start = source.len() - rest.len();
+        tokens.push(Token {
+            kind,
+            span: start..start + text.len(),
+        });
+        rest = remains;
+    }
+    tokens
+}
+
+fn token(input: &str) -> IResult<&str, (TokenKind, &str)> {
+    alt((
+        multispace1.map(|t| (TokenKind::Whitespace, t)),
+        comment.map(|t| (TokenKind::Comment, t)),
+        string.map(|t| (TokenKind::String, t)),
+        number.map(|t| (TokenKind::Number, t)),
+        word.map(|t| match KEYWORDS.contains(&t) {
+            true => (TokenKind::Keyword, t),
+            false => (TokenKind::Identifier, t),
+        }),
+        alt((tag("-->"), recognize(one_of("{}[]()=,.@;:-<>*/"))))
+            .map(|t| (TokenKind::Punctuation, t)),
+        recognize(anychar).map(|t| (TokenKind::Unknown, t)),
+    ))(input)
+}
+
+fn comment(input: &str) -> IResult<&str, &str> {
+    alt((
+        recognize(pair(tag("//"), not_line_ending)),
+        recognize(tuple((tag("/*"), take_until("*/"), tag("*/")))),
+    ))(input)
+}
+
+fn string(input: &str) -> IResult<&str, &str> {
+    recognize(delimited(
+        char('"'),
+        opt(escaped(none_of("\\\"\n"), '\\', anychar)),
+        char('"'),
+    ))(input)
+}
+
+fn number(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((
+        opt(char('-')),
+        digit1,
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)
+}
+
+fn word(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        satisfy(|c| c == '$' || is_identifier_start(c)),
+        take_while(|c| c == '$' || is_identifier_continue(c)),
+    ))(input)
+}
+
+/// Semantic version, in imports the type name that follows the version isn't part of it
+fn version(input: &str) -> IResult<&str, (TokenKind, &str)> {
+    let identifier = || recognize(many1(alt((alphanumeric1, tag("-")))));
+    let not_type_name = || {
+        recognize(pair(
+            satisfy(|c| !c.is_ascii_uppercase()),
+            opt(identifier()),
+        ))
+    };
+    let dotted = || recognize(pair(identifier(), many0(pair(char('.'), not_type_name()))));
+    recognize(tuple((
+        digit1,
+        char('.'),
+        digit1,
+        char('.'),
+        digit1,
+        opt(pair(char('-'), dotted())),
+        opt(pair(char('+'), dotted())),
+    )))
+    .map(|t| (TokenKind::Version, t))
+    .parse(input)
+}
+
+fn regex(input: &str) -> IResult<&str, (TokenKind, &str)> {
+    recognize(tuple((
+        delimited(
+            char('/'),
+            opt(escaped(none_of("\\/\n"), '\\', anychar)),
+            char('/'),
+        ),
+        opt(alpha1),
+    )))
+    .map(|t| (TokenKind::Regex, t))
+    .parse(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::TokenKind::*;
+
+    fn tokens(source: &str) -> Vec<(super::TokenKind, &str)> {
+        super::tokenize(source)
+            .into_iter()
+            .filter(|t| t.kind != Whitespace)
+            .map(|t| (t.kind, t.text(source)))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let source = "namespace org.acme@1.0.0-beta
+// People
+concept Person {
+  o String name default=\"J. \\\"Doe\\\"\" regex=/[A-Z]\\/.*/i
+  o Double weight range=[-1.5, 2e3] /* kg */
+}
+";
+        assert_eq!(
+            tokens(source),
+            vec![
+                (Keyword, "namespace"),
+                (Identifier, "org"),
+                (Punctuation, "."),
+                (Identifier, "acme"),
+                (Punctuation, "@"),
+                (Version, "1.0.0-beta"),
+                (Comment, "// People"),
+                (Keyword, "concept"),
+                (Identifier, "Person"),
+                (Punctuation, "{"),
+                (Keyword, "o"),
+                (Identifier, "String"),
+                (Identifier, "name"),
+                (Keyword, "default"),
+                (Punctuation, "="),
+                (String, "\"J. \\\"Doe\\\"\""),
+                (Keyword, "regex"),
+                (Punctuation, "="),
+                (Regex, "/[A-Z]\\/.*/i"),
+                (Keyword, "o"),
+                (Identifier, "Double"),
+                (Identifier, "weight"),
+                (Keyword, "range"),
+                (Punctuation, "="),
+                (Punctuation, "["),
+                (Number, "-1.5"),
+                (Punctuation, ","),
+                (Number, "2e3"),
+                (Punctuation, "]"),
+                (Comment, "/* kg */"),
+                (Punctuation, "}"),
+            ]
+        );
+        assert_eq!(
+            super::tokenize(source)
+                .iter()
+                .map(|t| t.text(source))
+                .collect::<std::string::String>(),
+            source,
+            "Should be lossless"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_invalid() {
+        let source = "import org.acme@1.0.0-rc.1.Person\nconcept # \"open";
+        assert_eq!(
+            tokens(source),
+            vec![
+                (Keyword, "import"),
+                (Identifier, "org"),
+                (Punctuation, "."),
+                (Identifier, "acme"),
+                (Punctuation, "@"),
+                (Version, "1.0.0-rc.1"),
+                (Punctuation, "."),
+                (Identifier, "Person"),
+                (Keyword, "concept"),
+                (Unknown, "#"),
+                (Unknown, "\""),
+                (Identifier, "open")
+            ],
+            "Should tokenize invalid sources"
+        );
+    }
+}
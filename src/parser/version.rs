@@ -5,27 +5,85 @@ use nom::{
         complete::{alpha1, digit1, u128},
         is_alphanumeric,
     },
-    combinator::{eof, not, recognize},
-    error::context,
+    combinator::{all_consuming, not, opt, recognize, value},
+    error::{context, ErrorKind},
+    multi::separated_list1,
     sequence::{pair, preceded, tuple},
-    Parser,
+    Err as NomErr, Parser,
 };
+use std::{cmp::Ordering, ops::Bound};
 
-use crate::parser::CResult;
+use crate::parser::{
+    error::CErrorKind,
+    memo::memoized,
+    scan::{space0, space1},
+    CResult,
+};
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, PartialOrd, Ord)]
 pub struct VersionNumber {
     major: u128,
     minor: u128,
     patch: u128,
 }
 
+impl VersionNumber {
+    pub(crate) const ZERO: VersionNumber = VersionNumber {
+        major: 0,
+        minor: 0,
+        patch: 0,
+    };
+
+    pub fn major(&self) -> u128 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u128 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u128 {
+        self.patch
+    }
+}
+
 impl From<&VersionNumber> for String {
     fn from(value: &VersionNumber) -> Self {
         format!("{}.{}.{}", value.major, value.minor, value.patch)
     }
 }
 
+impl std::fmt::Display for VersionNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl serde::Serialize for VersionNumber {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "deserialize")]
+impl<'de> serde::Deserialize<'de> for VersionNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        let parsed = nom::combinator::all_consuming(version_number)(s.as_str())
+            .map(|(_, v)| v)
+            .ok();
+        parsed.ok_or_else(|| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"version")
+        })
+    }
+}
+
 impl From<(u128,)> for VersionNumber {
     fn from(value: (u128,)) -> Self {
         VersionNumber {
@@ -64,30 +122,127 @@ pub enum SemanticVersion {
     VersionWithRelease(VersionNumber, String),
 }
 
+impl SemanticVersion {
+    pub fn number(&self) -> &VersionNumber {
+        match self {
+            SemanticVersion::Version(v) => v,
+            SemanticVersion::VersionWithRelease(v, _) => v,
+        }
+    }
+
+    pub fn pre_release(&self) -> Option<&str> {
+        match self {
+            SemanticVersion::Version(_) => None,
+            SemanticVersion::VersionWithRelease(_, r) => Some(r),
+        }
+    }
+}
+
+/// Precedence as described in https://semver.org/#spec-item-11
+/// A pre-release version has lower precedence than its normal version.
+impl Ord for SemanticVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.number().cmp(other.number()) {
+            Ordering::Equal => match (self.pre_release(), other.pre_release()) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre_release(a, b),
+            },
+            ordering => ordering,
+        }
+    }
+}
+
+impl PartialOrd for SemanticVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dot separated identifiers are compared one by one, numerically if both are numbers,
+/// numbers are lower than others, which are compared lexically.
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a_identifiers = a.split('.');
+    let mut b_identifiers = b.split('.');
+    loop {
+        match (a_identifiers.next(), b_identifiers.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u128>(), y.parse::<u128>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
 impl From<&SemanticVersion> for String {
     fn from(value: &SemanticVersion) -> Self {
         match value {
-            SemanticVersion::Version(v) => format!("{}", String::from(v)),
+            SemanticVersion::Version(v) => String::from(v).to_string(),
             SemanticVersion::VersionWithRelease(v, r) => format!("{}-{}", String::from(v), r),
         }
     }
 }
 
-fn major_only_version<'a>(input: &'a str) -> CResult<&'a str, VersionNumber> {
+impl std::fmt::Display for SemanticVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SemanticVersion::Version(v) => write!(f, "{}", v),
+            SemanticVersion::VersionWithRelease(v, r) => write!(f, "{}-{}", v, r),
+        }
+    }
+}
+
+impl serde::Serialize for SemanticVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "deserialize")]
+impl<'de> serde::Deserialize<'de> for SemanticVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        let parsed = nom::combinator::all_consuming(version_identifier)(s.as_str())
+            .map(|(_, v)| v)
+            .ok();
+        parsed.ok_or_else(|| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"semantic version")
+        })
+    }
+}
+
+fn major_only_version(input: &str) -> CResult<&str, VersionNumber> {
     context(
         "VersionMajorOnly",
         digit1.and_then(u128).map(|m| (m,).into()),
     )(input)
 }
 
-fn major_minor_version<'a>(input: &'a str) -> CResult<&'a str, VersionNumber> {
+fn major_minor_version(input: &str) -> CResult<&str, VersionNumber> {
     context(
         "VersionMajorMinor",
         tuple((u128, tag("."), u128)).map(|(maj, _, min)| (maj, min).into()),
     )(input)
 }
 
-fn major_minor_patch_version<'a>(input: &'a str) -> CResult<&'a str, VersionNumber> {
+fn major_minor_patch_version(input: &str) -> CResult<&str, VersionNumber> {
     context(
         "VersionMajorMinorPatch",
         tuple((u128, tag("."), u128, tag("."), u128))
@@ -96,18 +251,21 @@ fn major_minor_patch_version<'a>(input: &'a str) -> CResult<&'a str, VersionNumb
 }
 
 /// Parses a semantic version, without the pre-release part
-pub fn version_number<'a>(input: &'a str) -> CResult<&'a str, VersionNumber> {
-    context(
-        "Version",
-        alt((
-            major_minor_patch_version,
-            major_minor_version,
-            major_only_version,
-        )),
+pub fn version_number(input: &str) -> CResult<&str, VersionNumber> {
+    memoized(
+        "VersionNumber",
+        context(
+            "Version",
+            alt((
+                major_minor_patch_version,
+                major_minor_version,
+                major_only_version,
+            )),
+        ),
     )(input)
 }
 
-fn pre_release_allowed<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+fn pre_release_allowed(input: &str) -> CResult<&str, &str> {
     let allowed = ".-";
     take_while::<_, _, _>(|c: char| is_alphanumeric(c as u8) || allowed.contains(c))(input)
 }
@@ -115,7 +273,7 @@ fn pre_release_allowed<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
 /// Parses hyphen followed by at least one alpha numeric character, and dots and dashes.
 /// Numeric idenfifiers MUST NOT include leading zeros, single zero is fine.
 /// https://semver.org/#spec-item-9
-pub(crate) fn pre_release_token<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn pre_release_token(input: &str) -> CResult<&str, &str> {
     let leading_no_zero = context(
         "PreReleaseNoLeadingZero",
         alt((
@@ -144,25 +302,289 @@ pub(crate) fn pre_release_token<'a>(input: &'a str) -> CResult<&'a str, &'a str>
     context("PreReleaseToken", combined)(input)
 }
 
-fn pre_release<'a>(input: &'a str) -> CResult<&'a str, &'a str> {
+pub(crate) fn pre_release(input: &str) -> CResult<&str, &str> {
     context("PreRelease", preceded(tag("-"), pre_release_token))(input)
 }
 
 /// A version can be provided as major, major.minor, major.minor.patch and
 /// each with a pre-release tag attached with an hyphen
-pub fn version_identifier<'a>(input: &'a str) -> CResult<&'a str, SemanticVersion> {
+pub fn version_identifier(input: &str) -> CResult<&str, SemanticVersion> {
+    memoized("VersionIdentifier", semantic_version)(input)
+}
+
+fn semantic_version(input: &str) -> CResult<&str, SemanticVersion> {
     let (remains, (ver, maybe_pre)) =
-        context("Version", version_number.and(alt((pre_release, eof))))(input)?;
+        context("Version", version_number.and(opt(pre_release)))(input)?;
 
-    match maybe_pre.len() {
-        0 => Ok((remains, SemanticVersion::Version(ver))),
-        _ => Ok((
+    match maybe_pre {
+        None => Ok((remains, SemanticVersion::Version(ver))),
+        Some(pre) => Ok((
             remains,
-            SemanticVersion::VersionWithRelease(ver, maybe_pre.to_string()),
+            SemanticVersion::VersionWithRelease(ver, pre.to_string()),
         )),
     }
 }
 
+/// Comparison operators of a version requirement
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum VersionOperator {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl std::fmt::Display for VersionOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use VersionOperator::*;
+        let op = match self {
+            Exact => "=",
+            Greater => ">",
+            GreaterEq => ">=",
+            Less => "<",
+            LessEq => "<=",
+            Tilde => "~",
+            Caret => "^",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// A single comparison like `>=1.2.3` or `^1.2`, missing minor and patch numbers are kept
+/// as `None` since `~1` and `~1.0` mean different things.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VersionComparator {
+    pub operator: VersionOperator,
+    pub major: u128,
+    pub minor: Option<u128>,
+    pub patch: Option<u128>,
+    pub pre_release: Option<String>,
+}
+
+impl VersionComparator {
+    fn version(
+        major: u128,
+        minor: u128,
+        patch: u128,
+        pre_release: Option<&String>,
+    ) -> SemanticVersion {
+        let number = (major, minor, patch).into();
+        match pre_release {
+            None => SemanticVersion::Version(number),
+            Some(r) => SemanticVersion::VersionWithRelease(number, r.clone()),
+        }
+    }
+
+    /// Lowest version the comparator mentions, missing numbers are zero
+    fn lower(&self) -> SemanticVersion {
+        Self::version(
+            self.major,
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+            self.pre_release.as_ref(),
+        )
+    }
+
+    /// First version after the ones the comparator mentions, bumping the last given number
+    fn next(&self) -> SemanticVersion {
+        match (self.minor, self.patch) {
+            (None, _) => Self::version(self.major + 1, 0, 0, None),
+            (Some(minor), None) => Self::version(self.major, minor + 1, 0, None),
+            (Some(minor), Some(patch)) => Self::version(self.major, minor, patch + 1, None),
+        }
+    }
+
+    /// Version range the comparator accepts as lower and upper bounds
+    fn bounds(&self) -> (Bound<SemanticVersion>, Bound<SemanticVersion>) {
+        use VersionOperator::*;
+        let partial = self.minor.is_none() || self.patch.is_none();
+        match self.operator {
+            Exact if partial => (Bound::Included(self.lower()), Bound::Excluded(self.next())),
+            Exact => (Bound::Included(self.lower()), Bound::Included(self.lower())),
+            Greater if partial => (Bound::Included(self.next()), Bound::Unbounded),
+            Greater => (Bound::Excluded(self.lower()), Bound::Unbounded),
+            GreaterEq => (Bound::Included(self.lower()), Bound::Unbounded),
+            Less => (Bound::Unbounded, Bound::Excluded(self.lower())),
+            LessEq if partial => (Bound::Unbounded, Bound::Excluded(self.next())),
+            LessEq => (Bound::Unbounded, Bound::Included(self.lower())),
+            Tilde => {
+                let upper = match self.minor {
+                    None => Self::version(self.major + 1, 0, 0, None),
+                    Some(minor) => Self::version(self.major, minor + 1, 0, None),
+                };
+                (Bound::Included(self.lower()), Bound::Excluded(upper))
+            }
+            Caret => {
+                // Bump the first non-zero number, or the last given one if they are all zero
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => Self::version(0, 0, patch + 1, None),
+                    (0, Some(minor), _) if minor > 0 => Self::version(0, minor + 1, 0, None),
+                    (0, Some(_), None) => Self::version(0, 1, 0, None),
+                    (major, _, _) => Self::version(major + 1, 0, 0, None),
+                };
+                (Bound::Included(self.lower()), Bound::Excluded(upper))
+            }
+        }
+    }
+
+    pub fn matches(&self, version: &SemanticVersion) -> bool {
+        let (lower, upper) = self.bounds();
+        let above = match lower {
+            Bound::Included(l) => version >= &l,
+            Bound::Excluded(l) => version > &l,
+            Bound::Unbounded => true,
+        };
+        let below = match upper {
+            Bound::Included(u) => version <= &u,
+            Bound::Excluded(u) => version < &u,
+            Bound::Unbounded => true,
+        };
+        above && below
+    }
+}
+
+impl std::fmt::Display for VersionComparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.operator, self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{}", minor)?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        if let Some(pre_release) = &self.pre_release {
+            write!(f, "-{}", pre_release)?;
+        }
+        Ok(())
+    }
+}
+
+/// Version requirement as used by npm, all comparators have to match. A requirement with no
+/// comparators, written as `*`, matches any version.
+/// Pre-release versions only match if a comparator with the same version number has a
+/// pre-release tag, so `^1.0.0` doesn't match `1.1.0-alpha`.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct VersionRequirement {
+    pub comparators: Vec<VersionComparator>,
+}
+
+impl VersionRequirement {
+    /// Requires versions compatible with the given one, as in `^1.2.3`
+    pub fn caret(version: &SemanticVersion) -> Self {
+        Self::with_operator(VersionOperator::Caret, version)
+    }
+
+    /// Requires exactly the given version
+    pub fn exact(version: &SemanticVersion) -> Self {
+        Self::with_operator(VersionOperator::Exact, version)
+    }
+
+    fn with_operator(operator: VersionOperator, version: &SemanticVersion) -> Self {
+        let number = version.number();
+        Self {
+            comparators: vec![VersionComparator {
+                operator,
+                major: number.major,
+                minor: Some(number.minor),
+                patch: Some(number.patch),
+                pre_release: version.pre_release().map(String::from),
+            }],
+        }
+    }
+
+    pub fn matches(&self, version: &SemanticVersion) -> bool {
+        let pre_release_allowed = version.pre_release().is_none()
+            || self
+                .comparators
+                .iter()
+                .any(|c| c.pre_release.is_some() && c.lower().number() == version.number());
+
+        pre_release_allowed && self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+impl std::fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.comparators.is_empty() {
+            true => write!(f, "*"),
+            false => {
+                let comparators: Vec<String> =
+                    self.comparators.iter().map(|c| c.to_string()).collect();
+                write!(f, "{}", comparators.join(" "))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for VersionRequirement {
+    type Err = CErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(version_requirement)(s.trim()) {
+            Ok((_, requirement)) => Ok(requirement),
+            Err(NomErr::Error(e)) | Err(NomErr::Failure(e)) => Err(e.code),
+            Err(NomErr::Incomplete(_)) => Err(CErrorKind::NomError(ErrorKind::Complete)),
+        }
+    }
+}
+
+fn version_operator(input: &str) -> CResult<&str, VersionOperator> {
+    use VersionOperator::*;
+    context(
+        "VersionOperator",
+        alt((
+            value(GreaterEq, tag(">=")),
+            value(LessEq, tag("<=")),
+            value(Greater, tag(">")),
+            value(Less, tag("<")),
+            value(Exact, tag("=")),
+            value(Tilde, tag("~")),
+            value(Caret, tag("^")),
+        )),
+    )(input)
+}
+
+/// A comparator is an optional operator followed by a version, minor and patch numbers can be
+/// left out. A bare version is an exact requirement.
+fn version_comparator(input: &str) -> CResult<&str, VersionComparator> {
+    context(
+        "VersionComparator",
+        tuple((
+            opt(version_operator),
+            space0,
+            u128,
+            opt(preceded(tag("."), u128)),
+            opt(preceded(tag("."), u128)),
+            opt(pre_release),
+        ))
+        .map(
+            |(operator, _, major, minor, patch, pre_release)| VersionComparator {
+                operator: operator.unwrap_or(VersionOperator::Exact),
+                major,
+                minor,
+                patch,
+                pre_release: pre_release.map(String::from),
+            },
+        ),
+    )(input)
+}
+
+/// Parses comparators separated by whitespace or commas, or `*` for any version
+pub fn version_requirement(input: &str) -> CResult<&str, VersionRequirement> {
+    let separator = alt((recognize(tuple((space0, tag(","), space0))), space1));
+    context(
+        "VersionRequirement",
+        alt((
+            value(VersionRequirement::default(), tag("*")),
+            separated_list1(separator, version_comparator)
+                .map(|comparators| VersionRequirement { comparators }),
+        )),
+    )(input)
+}
+
 #[cfg(test)]
 mod test {
     use super::SemanticVersion;
@@ -268,5 +690,142 @@ mod test {
             )),
             "Should parse major.minor.patch version_identifier with pre-release tag when tag has dots",
         );
+        assert_eq!(
+            super::version_identifier("1.0.0\n"),
+            Ok(("\n", SemanticVersion::Version((1, 0, 0).into()))),
+            "Should parse version_identifier followed by other input",
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            SemanticVersion::Version((1, 2).into()).to_string(),
+            "1.2.0",
+            "Should render all version numbers"
+        );
+        assert_eq!(
+            SemanticVersion::VersionWithRelease((1, 2, 3).into(), "alpha.1".to_string())
+                .to_string(),
+            "1.2.3-alpha.1",
+            "Should render version with pre-release tag"
+        );
+    }
+
+    #[test]
+    fn test_ordering() {
+        let v = |s: &str| super::version_identifier(s).unwrap().1;
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.2.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                v(pair[0]) < v(pair[1]),
+                "Should order {} before {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_requirement() {
+        use super::VersionRequirement;
+
+        let matches = |requirement: &str, version: &str| {
+            requirement
+                .parse::<VersionRequirement>()
+                .unwrap()
+                .matches(&super::version_identifier(version).unwrap().1)
+        };
+        assert!(matches("*", "3.1.4"), "Should match any version with *");
+        assert!(matches("1.2.3", "1.2.3"), "Should match exact version");
+        assert!(
+            !matches("1.2.3", "1.2.4"),
+            "Should not match other versions"
+        );
+        assert!(
+            matches("1.2", "1.2.9"),
+            "Should match partial exact version"
+        );
+        assert!(
+            matches("^1.2.3", "1.9.0"),
+            "Should match caret within major"
+        );
+        assert!(
+            !matches("^1.2.3", "2.0.0"),
+            "Should not match caret on next major"
+        );
+        assert!(
+            !matches("^0.2.3", "0.3.0"),
+            "Should bump minor for zero major"
+        );
+        assert!(
+            !matches("^0.0.3", "0.0.4"),
+            "Should bump patch for zero minor"
+        );
+        assert!(
+            matches("~1.2.3", "1.2.9"),
+            "Should match tilde within minor"
+        );
+        assert!(
+            !matches("~1.2.3", "1.3.0"),
+            "Should not match tilde on next minor"
+        );
+        assert!(matches("~1", "1.9.0"), "Should match tilde with only major");
+        assert!(
+            matches(">=1.0.0, <2.0.0", "1.5.0"),
+            "Should match all comparators separated by comma"
+        );
+        assert!(
+            !matches(">=1.0.0 <2.0.0", "2.0.0"),
+            "Should require all comparators separated by space"
+        );
+        assert!(matches(">1.2", "1.3.0"), "Should treat >1.2 as >=1.3.0");
+        assert!(!matches(">1.2", "1.2.5"), "Should treat >1.2 as >=1.3.0");
+        assert!(matches("<=1.2", "1.2.5"), "Should treat <=1.2 as <1.3.0");
+        assert!(
+            !matches("^1.0.0", "1.1.0-alpha"),
+            "Should not match pre-release without a pre-release comparator"
+        );
+        assert!(
+            matches("^1.1.0-alpha", "1.1.0-beta"),
+            "Should match pre-release of the same version"
+        );
+        assert!(
+            "1.x".parse::<VersionRequirement>().is_err(),
+            "Should reject invalid requirements"
+        );
+        assert_eq!(
+            ">= 1.2, <2"
+                .parse::<VersionRequirement>()
+                .unwrap()
+                .to_string(),
+            ">=1.2 <2",
+            "Should render requirement"
+        );
+    }
+
+    #[cfg(feature = "deserialize")]
+    #[test]
+    fn test_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<SemanticVersion>("\"1.2.3-alpha.1\"").unwrap(),
+            SemanticVersion::VersionWithRelease((1, 2, 3).into(), "alpha.1".to_string()),
+        );
+        assert!(
+            serde_json::from_str::<SemanticVersion>("\"1.2.3 \"").is_err(),
+            "Should not deserialize with trailing characters"
+        );
     }
 }
@@ -0,0 +1,193 @@
+use crate::parser::{
+    declaration::{Declaration, DeclarationKind, Property},
+    property::{CommonProperty, PrimitiveType},
+    Model,
+};
+
+/// Entry point to select subsets of a model, see `Model::query`
+pub struct Query<'a> {
+    model: &'a Model,
+}
+
+impl Model {
+    /// Starts a query over the model, e.g.
+    /// `model.query().declarations().of_kind(DeclarationKind::Concept).properties().optional()`
+    pub fn query(&self) -> Query<'_> {
+        Query { model: self }
+    }
+}
+
+impl<'a> Query<'a> {
+    pub fn declarations(&self) -> DeclarationQuery<'a> {
+        DeclarationQuery {
+            inner: Box::new(self.model.declarations.iter()),
+        }
+    }
+
+    pub fn properties(&self) -> PropertyQuery<'a> {
+        self.declarations().properties()
+    }
+}
+
+/// Declarations selected by a query, filters can be chained and it can be iterated over
+pub struct DeclarationQuery<'a> {
+    inner: Box<dyn Iterator<Item = &'a Declaration> + 'a>,
+}
+
+impl<'a> DeclarationQuery<'a> {
+    pub fn matching(self, predicate: impl Fn(&Declaration) -> bool + 'a) -> Self {
+        Self {
+            inner: Box::new(self.inner.filter(move |d| predicate(d))),
+        }
+    }
+
+    pub fn named(self, name: &'a str) -> Self {
+        self.matching(move |d| d.name == name)
+    }
+
+    pub fn of_kind(self, kind: DeclarationKind) -> Self {
+        self.matching(move |d| d.kind == kind)
+    }
+
+    /// Properties of the selected declarations, paired with the declaration they belong to
+    pub fn properties(self) -> PropertyQuery<'a> {
+        PropertyQuery {
+            inner: Box::new(
+                self.inner
+                    .flat_map(|d| d.properties.iter().map(move |p| (d, p))),
+            ),
+        }
+    }
+}
+
+impl<'a> Iterator for DeclarationQuery<'a> {
+    type Item = &'a Declaration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// Properties selected by a query, paired with the declaration they belong to
+pub struct PropertyQuery<'a> {
+    inner: Box<dyn Iterator<Item = (&'a Declaration, &'a Property)> + 'a>,
+}
+
+impl<'a> PropertyQuery<'a> {
+    pub fn matching(self, predicate: impl Fn(&Property) -> bool + 'a) -> Self {
+        Self {
+            inner: Box::new(self.inner.filter(move |(_, p)| predicate(p))),
+        }
+    }
+
+    pub fn named(self, name: &'a str) -> Self {
+        self.matching(move |p| p.name() == name)
+    }
+
+    pub fn of_type(self, primitive_type: PrimitiveType) -> Self {
+        self.of_type_name(primitive_type.into())
+    }
+
+    /// Properties of a type as written in CTO, e.g. `String` or `Address`
+    pub fn of_type_name(self, type_name: &'a str) -> Self {
+        self.matching(move |p| p.type_name() == type_name)
+    }
+
+    pub fn optional(self) -> Self {
+        self.matching(|p| p.is_optional())
+    }
+
+    pub fn required(self) -> Self {
+        self.matching(|p| !p.is_optional())
+    }
+
+    pub fn arrays(self) -> Self {
+        self.matching(|p| p.is_array())
+    }
+
+    pub fn with_validators(self) -> Self {
+        self.matching(|p| !p.validators().is_empty())
+    }
+}
+
+impl<'a> Iterator for PropertyQuery<'a> {
+    type Item = (&'a Declaration, &'a Property);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{
+        declaration::DeclarationKind,
+        model,
+        property::{CommonProperty, PrimitiveType},
+    };
+
+    #[test]
+    fn test_query() {
+        let (_, model) = model(
+            "namespace test@1.0.0
+
+            concept Person {
+              o String name regex=/[A-Z].*/
+              o String nickname optional
+              o Address[] addresses
+            }
+
+            concept Address {
+              o String street
+              o Integer number optional
+            }
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            model
+                .query()
+                .declarations()
+                .of_kind(DeclarationKind::Concept)
+                .properties()
+                .of_type(PrimitiveType::StringPropertyType)
+                .optional()
+                .map(|(d, p)| (d.name.as_str(), p.name()))
+                .collect::<Vec<_>>(),
+            vec![("Person", "nickname")],
+            "Should select optional strings"
+        );
+        assert_eq!(
+            model
+                .query()
+                .declarations()
+                .named("Address")
+                .properties()
+                .map(|(_, p)| p.name())
+                .collect::<Vec<_>>(),
+            vec!["street", "number"],
+            "Should select properties of a declaration"
+        );
+        assert_eq!(
+            model
+                .query()
+                .properties()
+                .of_type_name("Address")
+                .arrays()
+                .count(),
+            1,
+            "Should select properties by concept type"
+        );
+        assert_eq!(
+            model
+                .query()
+                .properties()
+                .with_validators()
+                .map(|(_, p)| p.name())
+                .collect::<Vec<_>>(),
+            vec!["name"],
+            "Should select properties with validators"
+        );
+    }
+}
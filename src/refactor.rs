@@ -0,0 +1,603 @@
+//! Refactorings that change models loaded in a `ModelManager` and keep references to the
+//! changed elements in sync
+
+use std::collections::HashMap;
+
+use crate::{
+    diff::{self, Change},
+    manager::ModelManager,
+    parser::{
+        declaration::{Declaration, Identified, Property, TypeReference},
+        decorator::{Decorator, DecoratorArgument},
+        error::BuildError,
+        import::Import,
+        namespace::Namespace,
+        property::CommonProperty,
+    },
+};
+
+/// Errors of refactorings, the models are left unchanged
+#[derive(Debug, PartialEq, Clone)]
+pub enum RefactorError {
+    /// No model of the namespace is loaded
+    UnknownNamespace(String),
+    /// The declaration to change isn't defined
+    UnknownDeclaration(String),
+    /// The property to change isn't defined on the declaration
+    UnknownProperty(String),
+    /// The declaration already has a property of the new name
+    DuplicateProperty(String),
+    /// The change would make the model invalid
+    Invalid(BuildError),
+}
+
+impl std::fmt::Display for RefactorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownNamespace(ns) => write!(f, "unknown namespace: {}", ns),
+            Self::UnknownDeclaration(name) => write!(f, "unknown declaration: {}", name),
+            Self::UnknownProperty(name) => write!(f, "unknown property: {}", name),
+            Self::DuplicateProperty(name) => write!(f, "duplicate property: {}", name),
+            Self::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RefactorError {}
+
+impl From<BuildError> for RefactorError {
+    fn from(value: BuildError) -> Self {
+        Self::Invalid(value)
+    }
+}
+
+/// Renames a declaration of a namespace, and updates every property type, `extends` clause
+/// and import that refers to it in the models of the manager. References resolve as in
+/// `ModelManager::resolve_type`, so declarations of the same name in other namespaces are
+/// left alone. Returns the number of references updated.
+pub fn rename_declaration(
+    manager: &mut ModelManager,
+    namespace: &Namespace,
+    old: &str,
+    new: &str,
+) -> Result<usize, RefactorError> {
+    BuildError::check_identifier(new)?;
+    let target = manager
+        .models()
+        .position(|m| &m.namespace == namespace)
+        .ok_or_else(|| {
+            RefactorError::UnknownNamespace(format!("{}@{}", namespace.name(), namespace.version()))
+        })?;
+    let models: Vec<_> = manager.models().collect();
+    let target_model = models[target];
+    if target_model.declaration(old).is_none() {
+        return Err(RefactorError::UnknownDeclaration(old.to_string()));
+    }
+    if target_model.declaration(new).is_some() {
+        return Err(BuildError::DuplicateDeclaration(new.to_string()).into());
+    }
+
+    let is_target = |m: &crate::parser::Model| std::ptr::eq(m, target_model);
+    let mut properties = Vec::new();
+    let mut super_types = Vec::new();
+    let mut imports = Vec::new();
+    for (mi, model) in models.iter().enumerate() {
+        for (di, declaration) in model.declarations.iter().enumerate() {
+            let extended = manager.resolve_extended_in(model, declaration);
+            let extends_old = declaration
+                .super_type
+                .as_ref()
+                .is_some_and(|s| s.name == old);
+            if extends_old && extended.is_some_and(|(m, _)| is_target(m)) {
+                super_types.push((mi, di));
+            }
+            for (pi, property) in declaration.properties.iter().enumerate() {
+                if let Property::Concept(p) = property {
+                    let resolved = manager.resolve_property_in(model, p);
+                    if p.class == old && resolved.is_some_and(|(m, _)| is_target(m)) {
+                        properties.push((mi, di, pi));
+                    }
+                }
+            }
+        }
+        for (ii, import) in model.imports.iter().enumerate() {
+            let resolved = manager.resolve_import(import);
+            if import.local_name(old).is_some() && resolved.is_some_and(is_target) && mi != target {
+                imports.push((mi, ii));
+            }
+        }
+    }
+
+    let updated = properties.len() + super_types.len() + imports.len();
+    let models = manager.models_mut();
+    for (mi, di, pi) in properties {
+        if let Property::Concept(p) = &mut models[mi].declarations[di].properties[pi] {
+            p.class = new.to_string();
+        }
+    }
+    for (mi, di) in super_types {
+        if let Some(super_type) = &mut models[mi].declarations[di].super_type {
+            super_type.name = new.to_string();
+        }
+    }
+    for (mi, ii) in imports {
+        match &mut models[mi].imports[ii] {
+            Import::Type { name, .. } => *name = new.to_string(),
+            Import::All { .. } => {}
+            Import::Types {
+                types,
+                aliased_types,
+                ..
+            } => {
+                let names = types
+                    .iter_mut()
+                    .chain(aliased_types.iter_mut().map(|a| &mut a.name));
+                names
+                    .filter(|t| *t == old)
+                    .for_each(|t| *t = new.to_string());
+            }
+        }
+    }
+    let model = &mut models[target];
+    if let Some(declaration) = model.declarations.iter_mut().find(|d| d.name == old) {
+        declaration.name = new.to_string();
+    }
+    model.reindex();
+
+    Ok(updated)
+}
+
+/// Renames a property of a declaration, and moves its decorators and the meta properties
+/// defined on it along. Returns the changes to the declaration, whose severities tell that
+/// instances written against the old name no longer validate, which is why the property
+/// must not be renamed in published versions of a namespace.
+pub fn rename_property(
+    declaration: &mut Declaration,
+    old: &str,
+    new: &str,
+) -> Result<Vec<Change>, RefactorError> {
+    BuildError::check_identifier(new)?;
+    if declaration.property(new).is_some() {
+        return Err(RefactorError::DuplicateProperty(new.to_string()));
+    }
+    let original = declaration.clone();
+    let property = declaration
+        .properties
+        .iter_mut()
+        .find(|p| p.name() == old)
+        .ok_or_else(|| RefactorError::UnknownProperty(old.to_string()))?;
+    *property.name_mut() = new.to_string();
+    if let Some(Identified::By { name }) = &mut declaration.identified {
+        if name == old {
+            *name = new.to_string();
+        }
+    }
+    for meta in declaration
+        .meta_extensions
+        .iter_mut()
+        .filter(|m| m.property == old)
+    {
+        meta.property = new.to_string();
+    }
+    declaration.reindex();
+
+    let mut changes = Vec::new();
+    diff::diff_declaration(&original, declaration, &mut changes);
+    Ok(changes)
+}
+
+/// Copy of the models with namespaces, declarations, and properties renamed to opaque
+/// identifiers like `ns1`, `T1`, and `p1`, so models can be shared without their domain
+/// vocabulary. Versions, property types, validators, and the references between models are
+/// kept, default values and dialect extension bodies are copied as they are. License headers
+/// are dropped, and decorators keep their names with placeholder arguments.
+pub fn anonymize(manager: &ModelManager) -> ModelManager {
+    let mut names = Anonymous::default();
+    let mut anonymized = ModelManager::new();
+    for model in manager.models() {
+        let mut copy = model.clone();
+        let own = model.namespace.name();
+        copy.header = None;
+        copy.namespace = Namespace::new(names.namespace(own), model.namespace.version().clone());
+        for import in &mut copy.imports {
+            let imported = import.namespace().name().to_string();
+            match import {
+                Import::Type { name, .. } => *name = names.declaration(&imported, name),
+                Import::All { .. } => {}
+                Import::Types {
+                    types,
+                    aliased_types,
+                    ..
+                } => {
+                    for name in types.iter_mut() {
+                        *name = names.declaration(&imported, name);
+                    }
+                    // Aliases are what properties refer to, and are renamed like them
+                    for aliased in aliased_types.iter_mut() {
+                        aliased.name = names.declaration(&imported, &aliased.name);
+                        aliased.aliased_name = names.declaration(&imported, &aliased.aliased_name);
+                    }
+                }
+            }
+            let namespace = import.namespace_mut();
+            *namespace = Namespace::new(
+                names.namespace(namespace.name()),
+                namespace.version().clone(),
+            );
+        }
+        let defined_in = |reference: &TypeReference| {
+            manager
+                .resolve_reference_in(model, &reference.name, reference.namespace.as_ref())
+                .map_or(own, |(m, _)| m.namespace.name())
+        };
+        for (declaration, original) in copy.declarations.iter_mut().zip(&model.declarations) {
+            declaration.name = names.declaration(own, &declaration.name);
+            if let Some(super_type) = &mut declaration.super_type {
+                let defined_in = manager
+                    .resolve_extended_in(model, original)
+                    .map_or(own, |(m, _)| m.namespace.name());
+                names.reference(defined_in, super_type);
+            }
+            names.decorators(&mut declaration.decorators, &defined_in);
+            let mut properties = HashMap::new();
+            for (index, property) in declaration.properties.iter_mut().enumerate() {
+                let name = format!("p{}", index + 1);
+                properties.insert(property.name().to_string(), name.clone());
+                if let Property::Concept(p) = property {
+                    let defined_in = manager
+                        .resolve_property_in(model, p)
+                        .map_or(own, |(m, _)| m.namespace.name());
+                    p.class = names.declaration(defined_in, &p.class);
+                    if let Some(namespace) = &mut p.namespace {
+                        *namespace = names.versioned(namespace);
+                    }
+                }
+                *property.name_mut() = name;
+                names.decorators(property.decorators_mut(), &defined_in);
+            }
+            for meta in &mut declaration.meta_extensions {
+                if let Some(name) = properties.get(&meta.property) {
+                    meta.property = name.clone();
+                }
+            }
+            if let Some(Identified::By { name }) = &mut declaration.identified {
+                if let Some(renamed) = properties.get(name) {
+                    *name = renamed.clone();
+                }
+            }
+        }
+        copy.reindex();
+        anonymized.add_model(copy);
+    }
+    anonymized
+}
+
+/// Opaque names given by `anonymize`, a name is replaced the same way in every model
+#[derive(Default)]
+struct Anonymous {
+    namespaces: HashMap<String, String>,
+    types: HashMap<(String, String), String>,
+}
+
+impl Anonymous {
+    fn namespace(&mut self, name: &str) -> String {
+        let next = format!("ns{}", self.namespaces.len() + 1);
+        self.namespaces
+            .entry(name.to_string())
+            .or_insert(next)
+            .clone()
+    }
+
+    /// Renames a namespace, keeping its version as it is written
+    fn versioned(&mut self, namespace: &Namespace) -> Namespace {
+        Namespace::from((
+            self.namespace(namespace.name()),
+            namespace.declared_version().cloned(),
+        ))
+    }
+
+    fn declaration(&mut self, namespace: &str, name: &str) -> String {
+        let next = format!("T{}", self.types.len() + 1);
+        self.types
+            .entry((namespace.to_string(), name.to_string()))
+            .or_insert(next)
+            .clone()
+    }
+
+    /// Renames a reference to a declaration of the `defined_in` namespace
+    fn reference(&mut self, defined_in: &str, reference: &mut TypeReference) {
+        reference.name = self.declaration(defined_in, &reference.name);
+        if let Some(namespace) = &mut reference.namespace {
+            *namespace = self.versioned(namespace);
+        }
+    }
+
+    /// Replaces strings and numbers given to decorators with placeholders, and renames the
+    /// types they refer to
+    fn decorators<'m>(
+        &mut self,
+        decorators: &mut [Decorator],
+        defined_in: &dyn Fn(&TypeReference) -> &'m str,
+    ) {
+        for argument in decorators.iter_mut().flat_map(|d| d.arguments.iter_mut()) {
+            match argument {
+                DecoratorArgument::String { value } => *value = String::from("..."),
+                DecoratorArgument::Number { value } => *value = 0.0,
+                DecoratorArgument::Boolean { .. } => {}
+                DecoratorArgument::TypeReference { type_reference, .. } => {
+                    let defined_in = defined_in(type_reference);
+                    self.reference(defined_in, type_reference);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{manager::ModelManager, parser::model};
+
+    #[test]
+    fn test_rename_declaration() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace people@1.0.0
+
+            concept Person {
+              o String name
+              o Person[] friends
+            }
+            ",
+            "namespace orders@1.0.0
+            import people@1.0.0.Person
+
+            concept Order {
+              o Person buyer
+            }
+
+            concept Customer extends Person {
+              o String account
+            }
+            ",
+            "namespace other@1.0.0
+
+            concept Person {
+              o String id
+            }
+
+            concept Note {
+              o Person author
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+        let people = manager.models().next().unwrap().namespace.clone();
+
+        assert_eq!(
+            super::rename_declaration(&mut manager, &people, "Person", "Party"),
+            Ok(4),
+            "Should update the local property, the import, the imported property and the \
+             extending concept"
+        );
+        let rendered: Vec<_> = manager.models().map(|m| m.to_string()).collect();
+        assert!(rendered[0].contains("concept Party {"));
+        assert!(rendered[0].contains("o Party[] friends"));
+        assert!(rendered[1].contains("import people@1.0.0.Party"));
+        assert!(rendered[1].contains("o Party buyer"));
+        assert!(rendered[1].contains("concept Customer extends Party {"));
+        assert!(
+            rendered[2].contains("concept Person {") && rendered[2].contains("o Person author"),
+            "Should not rename declarations of other namespaces"
+        );
+        assert!(manager
+            .models()
+            .next()
+            .unwrap()
+            .declaration("Party")
+            .is_some());
+
+        assert_eq!(
+            super::rename_declaration(&mut manager, &people, "Person", "Human"),
+            Err(super::RefactorError::UnknownDeclaration(String::from(
+                "Person"
+            )))
+        );
+        assert_eq!(
+            super::rename_declaration(&mut manager, &people, "Party", "1Party"),
+            Err(super::RefactorError::Invalid(
+                crate::parser::error::BuildError::InvalidIdentifier(String::from("1Party"))
+            ))
+        );
+        let missing = crate::parser::namespace::Namespace::new("missing", people.version().clone());
+        assert_eq!(
+            super::rename_declaration(&mut manager, &missing, "Party", "Human")
+                .map_err(|e| e.to_string()),
+            Err(String::from("unknown namespace: missing@1.0.0"))
+        );
+    }
+
+    #[test]
+    fn test_rename_property() {
+        use crate::diff::{Change, Severity};
+
+        let (_, mut declaration) = crate::parser::declaration::declaration(
+            "concept Person identified by name {
+              @Term(\"Name\")
+              o String name
+              o Integer age optional
+            }",
+        )
+        .unwrap();
+
+        let changes = super::rename_property(&mut declaration, "name", "fullName").unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::IdentifiedChanged {
+                    declaration: String::from("Person"),
+                    from: Some(String::from("identified by name")),
+                    to: Some(String::from("identified by fullName")),
+                },
+                Change::PropertyRemoved {
+                    declaration: String::from("Person"),
+                    property: String::from("name"),
+                },
+                Change::PropertyAdded {
+                    declaration: String::from("Person"),
+                    property: String::from("fullName"),
+                    optional: false,
+                },
+            ],
+            "Should return the changes to the declaration"
+        );
+        assert!(
+            changes.iter().all(|c| c.severity() == Severity::Major),
+            "Should report renaming as breaking"
+        );
+        assert_eq!(
+            declaration.to_string(),
+            "concept Person identified by fullName {\n  @Term(\"Name\")\n  o String fullName\n  o Integer age optional\n}",
+            "Should rename the property in place and where it identifies instances"
+        );
+        assert_eq!(
+            declaration
+                .decorators_of(Some("fullName"))
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Term"],
+            "Should move the decorators along with the property"
+        );
+        assert!(
+            declaration.property("fullName").is_some() && declaration.property("name").is_none(),
+            "Should find the property by its new name"
+        );
+        assert_eq!(
+            super::rename_property(&mut declaration, "name", "nickname"),
+            Err(super::RefactorError::UnknownProperty(String::from("name")))
+        );
+        assert_eq!(
+            super::rename_property(&mut declaration, "age", "fullName"),
+            Err(super::RefactorError::DuplicateProperty(String::from(
+                "fullName"
+            ))),
+            "Should not allow two properties of the same name"
+        );
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let mut manager = ModelManager::new();
+        for input in [
+            "namespace acme.people@1.0.0
+
+            concept Person {
+              o String name regex=/[A-Z].*/
+              o Person[] friends optional
+            }
+            ",
+            "namespace acme.orders@1.0.0
+            import acme.people@1.0.0.Person
+
+            concept Order {
+              o Integer quantity range=[1,]
+              o Person buyer
+            }
+
+            concept Customer extends Person {
+              o String account
+            }
+
+            asset Invoice identified by number {
+              o String number
+            }
+            ",
+        ] {
+            manager.add_model(model(input).unwrap().1);
+        }
+
+        let anonymized: Vec<_> = super::anonymize(&manager)
+            .models()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(
+            anonymized,
+            vec![
+                "namespace ns1@1.0.0
+
+concept T1 {
+  o String p1 regex=/[A-Z].*/
+  o T1[] p2 optional
+}
+",
+                "namespace ns2@1.0.0
+
+import ns1@1.0.0.T1
+
+concept T2 {
+  o Integer p1 range=[1,]
+  o T1 p2
+}
+
+concept T3 extends T1 {
+  o String p1
+}
+
+asset T4 identified by p1 {
+  o String p1
+}
+",
+            ],
+            "Should rename consistently across models, keeping types and validators"
+        );
+    }
+
+    #[test]
+    fn test_anonymize_decorators() {
+        let mut manager = ModelManager::new();
+        let input = "/* Falcon Corp. confidential */
+        namespace acme.sales@1.0.0
+
+        @Term(\"Falcon customer\")
+        concept Customer {
+          @Term(\"Yearly revenue\", 2024)
+          @Unit(Money, true)
+          o Double revenue
+        }
+
+        concept Money {
+          o Double amount
+        }
+        ";
+        manager.add_model(model(input).unwrap().1);
+
+        let anonymized = super::anonymize(&manager);
+        let anonymized = anonymized.models().next().unwrap();
+        let json = serde_json::to_string(anonymized).unwrap();
+        for original in ["Falcon", "revenue", "2024", "Money", "amount", "acme"] {
+            assert!(
+                !json.contains(original),
+                "Should not leak {} in {}",
+                original,
+                json
+            );
+        }
+        assert_eq!(
+            anonymized.to_string(),
+            "namespace ns1@1.0.0
+
+@Term(\"...\")
+concept T1 {
+  @Term(\"...\", 0)
+  @Unit(T2, true)
+  o Double p1
+}
+
+concept T2 {
+  o Double p1
+}
+",
+            "Should drop the header and keep the decorators with placeholder arguments"
+        );
+    }
+}
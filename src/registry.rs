@@ -0,0 +1,201 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use crate::parser::{self, file::FileError, options::ParserOptions, Model};
+
+#[derive(Debug)]
+struct Entry {
+    modified: SystemTime,
+    len: u64,
+    hash: u64,
+    model: Arc<Model>,
+}
+
+/// Parses model files on demand and caches them, so models can be looked up concurrently.
+/// A file is parsed again only if its modification time or size changed and its content
+/// hash differs. Clones share the same cache.
+#[derive(Debug, Default, Clone)]
+pub struct SharedModelRegistry {
+    entries: Arc<RwLock<HashMap<PathBuf, Entry>>>,
+    /// Options the files are parsed with
+    options: ParserOptions,
+}
+
+impl SharedModelRegistry {
+    pub fn new() -> Self {
+        Self::new_with(&ParserOptions::default())
+    }
+
+    /// A registry parsing the files with the given options
+    pub fn new_with(options: &ParserOptions) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            options: *options,
+        }
+    }
+
+    /// The model parsed from the file at the given path, from the cache if it is up to date
+    pub fn get(&self, path: impl AsRef<Path>) -> Result<Arc<Model>, FileError> {
+        let path = path.as_ref();
+        let io_error = |source| FileError::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        };
+        let metadata = std::fs::metadata(path).map_err(io_error)?;
+        let modified = metadata.modified().map_err(io_error)?;
+
+        if let Some(entry) = self.read().get(path) {
+            if entry.modified == modified && entry.len == metadata.len() {
+                return Ok(entry.model.clone());
+            }
+        }
+
+        let content = std::fs::read_to_string(path).map_err(io_error)?;
+        let hash = content_hash(&content);
+
+        // Parse without holding the lock, so lookups of other files aren't blocked
+        let cached = self
+            .read()
+            .get(path)
+            .filter(|e| e.hash == hash)
+            .map(|e| e.model.clone());
+        let model = match cached {
+            Some(model) => model,
+            None => Arc::new(
+                parser::parse_with(&content, &self.options).map_err(|error| FileError::Parse {
+                    path: Some(path.to_path_buf()),
+                    error,
+                })?,
+            ),
+        };
+        self.write().insert(
+            path.to_path_buf(),
+            Entry {
+                modified,
+                len: metadata.len(),
+                hash,
+                model: model.clone(),
+            },
+        );
+
+        Ok(model)
+    }
+
+    /// Drops the cached model of a file, it will be parsed again on the next `get`
+    pub fn invalidate(&self, path: impl AsRef<Path>) {
+        self.write().remove(path.as_ref());
+    }
+
+    /// All cached models
+    pub fn models(&self) -> Vec<Arc<Model>> {
+        self.read().values().map(|e| e.model.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    // A panic while holding the lock can't leave the map half updated, so poisoning is ignored
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    #[test]
+    fn test_registry() {
+        let dir = std::env::temp_dir().join(format!("concerto-registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("person.cto");
+        std::fs::write(
+            &path,
+            "namespace test@1.0.0
+
+            concept Person {
+              o String name
+            }
+            ",
+        )
+        .unwrap();
+
+        let registry = super::SharedModelRegistry::new();
+        let first = registry.get(&path).unwrap();
+        let second = registry.clone().get(&path).unwrap();
+        assert!(
+            Arc::ptr_eq(&first, &second),
+            "Should hand out the cached model while the file is unchanged"
+        );
+
+        std::fs::write(
+            &path,
+            "namespace test@1.0.0
+
+            concept Person {
+              o String name
+              o Integer age
+            }
+            ",
+        )
+        .unwrap();
+        let changed = registry.get(&path).unwrap();
+        assert_eq!(
+            changed.declaration("Person").map(|d| d.properties.len()),
+            Some(2),
+            "Should parse the file again after it changed"
+        );
+        assert_eq!(registry.len(), 1);
+
+        std::fs::write(&path, "namespace test@1.0.0\n\nconcept {\n").unwrap();
+        match registry.get(&path) {
+            Err(super::FileError::Parse { error, .. }) => {
+                assert_eq!(
+                    (error.line, error.column),
+                    (3, 1),
+                    "Should point at the invalid definition"
+                )
+            }
+            other => panic!("Should fail to parse, got {:?}", other),
+        }
+        std::fs::write(&path, "namespace test\n").unwrap();
+        let v2 = crate::parser::options::ParserOptions {
+            language_version: crate::parser::options::LanguageVersion::V2,
+            ..Default::default()
+        };
+        assert!(registry.get(&path).is_err());
+        assert!(
+            super::SharedModelRegistry::new_with(&v2).get(&path).is_ok(),
+            "Should parse files with the options of the registry"
+        );
+        assert!(
+            matches!(
+                registry.get(dir.join("missing.cto")),
+                Err(super::FileError::Io { .. })
+            ),
+            "Should report missing files"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
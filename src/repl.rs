@@ -0,0 +1,271 @@
+//! Interactive session that parses snippets as they are typed, for learning the grammar and
+//! triaging parse bugs. The session only deals with lines of text, the `repl` subcommand
+//! reads them from the terminal.
+
+use crate::{
+    parser::{options::ParserOptions, parse_with, Model},
+    serialize,
+};
+
+pub const HELP: &str = "Type a model, a declaration or a property. Snippets end when their braces
+close, or on a blank line.
+
+:tree :json :cto   how parsed snippets are printed
+:strict :permissive :default
+                   how strictly snippets are parsed
+:options           print the parser options
+:clear             drop the snippet typed so far
+:help :quit
+";
+
+/// Namespace declarations and properties are wrapped in to parse them
+const WRAPPER_NAMESPACE: &str = "namespace repl@1.0.0\n";
+const WRAPPER_CONCEPT: &str = "concept Repl {\n";
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Output {
+    Tree,
+    Json,
+    Cto,
+}
+
+/// What the session did with a line
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Step {
+    /// The line is part of a snippet that isn't complete yet
+    Pending,
+    /// Text to print, the result of a snippet or a command
+    Print(String),
+    Quit,
+}
+
+/// A snippet is parsed as what its first word says it is
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Snippet {
+    Model,
+    Declarations,
+    Property,
+}
+
+#[derive(Debug)]
+pub struct Repl {
+    options: ParserOptions,
+    output: Output,
+    buffer: String,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            options: ParserOptions::default(),
+            output: Output::Tree,
+            buffer: String::new(),
+        }
+    }
+
+    /// Whether lines are being added to a snippet, e.g. to prompt for more
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.trim().is_empty()
+    }
+
+    /// Feeds a line, without its line ending
+    pub fn feed(&mut self, line: &str) -> Step {
+        if !self.is_pending() {
+            if let Some(command) = line.trim().strip_prefix(':') {
+                return self.command(command);
+            }
+        }
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if !self.is_pending() {
+            self.buffer.clear();
+            return Step::Pending;
+        }
+
+        let snippet = snippet(&self.buffer);
+        let opened = self.buffer.contains('{');
+        let depth =
+            self.buffer.matches('{').count() as isize - self.buffer.matches('}').count() as isize;
+        let complete = line.trim().is_empty()
+            || match snippet {
+                Snippet::Model => false,
+                Snippet::Declarations => opened && depth <= 0,
+                Snippet::Property => true,
+            };
+        if !complete {
+            return Step::Pending;
+        }
+        let source = std::mem::take(&mut self.buffer);
+        Step::Print(self.evaluate(&source, snippet))
+    }
+
+    fn command(&mut self, command: &str) -> Step {
+        let message = match command.trim() {
+            "tree" => self.set_output(Output::Tree),
+            "json" => self.set_output(Output::Json),
+            "cto" => self.set_output(Output::Cto),
+            "strict" => self.set_options(ParserOptions::strict()),
+            "permissive" => self.set_options(ParserOptions::permissive()),
+            "default" => self.set_options(ParserOptions::default()),
+            "options" => format!("{:?}\n", self.options),
+            "clear" => {
+                self.buffer.clear();
+                String::new()
+            }
+            "help" => String::from(HELP),
+            "quit" | "q" => return Step::Quit,
+            other => format!("unknown command :{}, try :help\n", other),
+        };
+        Step::Print(message)
+    }
+
+    fn set_output(&mut self, output: Output) -> String {
+        self.output = output;
+        String::new()
+    }
+
+    fn set_options(&mut self, options: ParserOptions) -> String {
+        self.options = options;
+        format!("{:?}\n", self.options)
+    }
+
+    fn evaluate(&self, source: &str, snippet: Snippet) -> String {
+        let (wrapped, wrapper_lines) = match snippet {
+            Snippet::Model => (source.to_string(), 0),
+            Snippet::Declarations => (format!("{}{}", WRAPPER_NAMESPACE, source), 1),
+            Snippet::Property => (
+                format!("{}{}{}}}\n", WRAPPER_NAMESPACE, WRAPPER_CONCEPT, source),
+                2,
+            ),
+        };
+        let model = match parse_with(&wrapped, &self.options) {
+            Ok(model) => model,
+            // Located in the snippet, not in what it was wrapped in
+            Err(e) => {
+                return format!(
+                    "error {}:{} {:?}\n",
+                    e.line.saturating_sub(wrapper_lines).max(1),
+                    e.column,
+                    e.kind
+                )
+            }
+        };
+        match self.output {
+            Output::Tree => print_tree(&model, snippet),
+            Output::Json => print_json(&model, snippet),
+            Output::Cto => print_cto(&model, snippet),
+        }
+    }
+}
+
+fn snippet(source: &str) -> Snippet {
+    match source.split_whitespace().next() {
+        Some("namespace") => Snippet::Model,
+        Some("o") => Snippet::Property,
+        _ => Snippet::Declarations,
+    }
+}
+
+/// Lines of the tree below the wrapping nodes
+fn print_tree(model: &Model, snippet: Snippet) -> String {
+    let tree = serialize::tree(model, None);
+    let (skip, indent) = match snippet {
+        Snippet::Model => return tree,
+        Snippet::Declarations => (2, 2),
+        Snippet::Property => (3, 4),
+    };
+    tree.lines()
+        .skip(skip)
+        .map(|line| format!("{}\n", &line[indent.min(line.len())..]))
+        .collect()
+}
+
+fn print_json(model: &Model, snippet: Snippet) -> String {
+    let value = match snippet {
+        Snippet::Model => serde_json::to_value(model),
+        Snippet::Declarations => serde_json::to_value(&model.declarations),
+        Snippet::Property => serde_json::to_value(&model.declarations[0].properties[0]),
+    };
+    let json = value
+        .and_then(|v| serde_json::to_string_pretty(&v))
+        .unwrap_or_default();
+    format!("{}\n", json)
+}
+
+fn print_cto(model: &Model, snippet: Snippet) -> String {
+    match snippet {
+        Snippet::Model => model.to_string(),
+        Snippet::Declarations => model
+            .declarations
+            .iter()
+            .map(|d| format!("{}\n", d))
+            .collect(),
+        Snippet::Property => format!("{}\n", model.declarations[0].properties[0]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Step;
+
+    fn feed(repl: &mut super::Repl, lines: &str) -> Vec<Step> {
+        lines.lines().map(|line| repl.feed(line)).collect()
+    }
+
+    #[test]
+    fn test_repl() {
+        let mut repl = super::Repl::new();
+        assert_eq!(
+            feed(&mut repl, "concept Person {\n  o String name\n}"),
+            vec![
+                Step::Pending,
+                Step::Pending,
+                Step::Print(String::from(
+                    "ConceptDeclaration Person\n  StringProperty name\n    type String\n"
+                ))
+            ],
+            "Should parse declarations once their braces close"
+        );
+
+        assert_eq!(repl.feed(":cto"), Step::Print(String::new()));
+        assert_eq!(
+            repl.feed("o Integer age optional"),
+            Step::Print(String::from("o Integer age optional\n")),
+            "Should parse properties on their own"
+        );
+        assert_eq!(
+            feed(&mut repl, "namespace test@1.0.0\n\n"),
+            vec![
+                Step::Pending,
+                Step::Print(String::from("namespace test@1.0.0\n"))
+            ],
+            "Should parse models up to a blank line"
+        );
+
+        assert_eq!(
+            repl.feed("concept {"),
+            Step::Pending,
+            "Should wait for the braces to close"
+        );
+        assert_eq!(
+            repl.feed(""),
+            Step::Print(String::from("error 1:1 NomError(Eof)\n")),
+            "Should locate errors in the snippet"
+        );
+
+        repl.feed(":permissive");
+        assert_eq!(
+            repl.feed("o String name OPTIONAL"),
+            Step::Print(String::from("o String name optional\n")),
+            "Should use the options"
+        );
+        assert!(matches!(repl.feed(":frobnicate"), Step::Print(m) if m.starts_with("unknown")));
+        assert_eq!(repl.feed(":quit"), Step::Quit);
+    }
+}
@@ -0,0 +1,297 @@
+//! Fetches models of external imports, like the `ModelLoader` of the JS implementation
+
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::parser::{error::SourceError, namespace::Namespace};
+
+/// Errors while fetching the model of a namespace
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The resolver has no model of the namespace
+    NotFound(Namespace),
+    /// The model couldn't be fetched
+    Io {
+        namespace: Namespace,
+        source: io::Error,
+    },
+    /// The fetched source isn't a valid model
+    Parse {
+        namespace: Namespace,
+        error: Box<SourceError>,
+    },
+    /// The fetched model isn't of the namespace it was fetched for, or of a compatible version
+    Mismatch {
+        expected: Namespace,
+        found: Box<Namespace>,
+    },
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(namespace) => write!(
+                f,
+                "no model of {}@{}",
+                namespace.name(),
+                namespace.version()
+            ),
+            Self::Io { namespace, source } => write!(
+                f,
+                "{}@{}: {}",
+                namespace.name(),
+                namespace.version(),
+                source
+            ),
+            Self::Parse { namespace, error } => {
+                write!(f, "{}@{}:{}", namespace.name(), namespace.version(), error)
+            }
+            Self::Mismatch { expected, found } => write!(
+                f,
+                "fetched {}@{} for {}@{}",
+                found.name(),
+                found.version(),
+                expected.name(),
+                expected.version()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Source of models that aren't loaded, looked up by the namespace imports refer to
+pub trait ModelResolver {
+    /// Returns the CTO source of the model of a namespace
+    fn resolve(&self, namespace: &Namespace) -> Result<String, ResolveError>;
+}
+
+impl<F> ModelResolver for F
+where
+    F: Fn(&Namespace) -> Result<String, ResolveError>,
+{
+    fn resolve(&self, namespace: &Namespace) -> Result<String, ResolveError> {
+        self(namespace)
+    }
+}
+
+/// Keeps the models another resolver fetched in a directory, so they are fetched once.
+/// Sources are stored by their SHA-256 hash under `objects`, and `refs` maps namespaces to
+/// the hashes. A cache can be populated ahead of time and used offline, e.g. in CI.
+#[derive(Debug, Clone)]
+pub struct CachedResolver<R> {
+    inner: R,
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    offline: bool,
+}
+
+impl<R: ModelResolver> CachedResolver<R> {
+    pub fn new(inner: R, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+            ttl: None,
+            offline: false,
+        }
+    }
+
+    /// Fetches models again once their cached copy is older than the TTL. Cached models
+    /// never expire by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Only uses cached models, expired or not, and never the inner resolver
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    fn ref_path(&self, namespace: &Namespace) -> PathBuf {
+        self.dir
+            .join("refs")
+            .join(format!("{}@{}", namespace.name(), namespace.version()))
+    }
+
+    /// Cached source of a namespace, and whether it expired. Entries whose content doesn't
+    /// match their hash are ignored.
+    fn cached(&self, namespace: &Namespace) -> Option<(String, bool)> {
+        let path = self.ref_path(namespace);
+        let hash = std::fs::read_to_string(&path).ok()?;
+        let source = std::fs::read_to_string(self.dir.join("objects").join(hash.trim())).ok()?;
+        if hex_sha256(&source) != hash.trim() {
+            return None;
+        }
+        let age = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        Some((source, self.ttl.is_some_and(|ttl| age > ttl)))
+    }
+
+    fn store(&self, namespace: &Namespace, source: &str) -> io::Result<()> {
+        let hash = hex_sha256(source);
+        let objects = self.dir.join("objects");
+        std::fs::create_dir_all(&objects)?;
+        std::fs::create_dir_all(self.dir.join("refs"))?;
+        std::fs::write(objects.join(&hash), source)?;
+        std::fs::write(self.ref_path(namespace), hash)
+    }
+}
+
+impl<R: ModelResolver> ModelResolver for CachedResolver<R> {
+    fn resolve(&self, namespace: &Namespace) -> Result<String, ResolveError> {
+        let cached = self.cached(namespace);
+        match cached {
+            Some((source, expired)) if !expired || self.offline => return Ok(source),
+            None if self.offline => return Err(ResolveError::NotFound(namespace.clone())),
+            _ => {}
+        }
+        match (self.inner.resolve(namespace), cached) {
+            (Ok(source), _) => {
+                self.store(namespace, &source)
+                    .map_err(|source| ResolveError::Io {
+                        namespace: namespace.clone(),
+                        source,
+                    })?;
+                Ok(source)
+            }
+            // An expired copy beats failing when the network is down
+            (Err(ResolveError::Io { .. }), Some((source, _))) => Ok(source),
+            (Err(e), _) => Err(e),
+        }
+    }
+}
+
+pub(crate) fn hex_sha256(source: &str) -> String {
+    Sha256::digest(source.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Fetches models over HTTP(S), from URLs made of a template
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpResolver {
+    template: String,
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "http")]
+impl HttpResolver {
+    /// `{name}` and `{version}` in the template are replaced by the ones of the namespace,
+    /// e.g. `https://models.example.com/{name}@{version}.cto`
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    pub fn url(&self, namespace: &Namespace) -> String {
+        self.template
+            .replace("{name}", namespace.name())
+            .replace("{version}", &namespace.version().to_string())
+    }
+}
+
+#[cfg(feature = "http")]
+impl ModelResolver for HttpResolver {
+    fn resolve(&self, namespace: &Namespace) -> Result<String, ResolveError> {
+        let io_error = |source| ResolveError::Io {
+            namespace: namespace.clone(),
+            source,
+        };
+        match self.agent.get(&self.url(namespace)).call() {
+            Ok(response) => response.into_string().map_err(io_error),
+            Err(ureq::Error::Status(404, _)) => Err(ResolveError::NotFound(namespace.clone())),
+            Err(e) => Err(io_error(io::Error::other(e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, time::Duration};
+
+    use super::{ModelResolver, ResolveError};
+    use crate::parser::namespace::Namespace;
+
+    fn namespace(name: &str, version: &str) -> Namespace {
+        Namespace::new(
+            name,
+            crate::parser::version::version_identifier(version)
+                .unwrap()
+                .1,
+        )
+    }
+
+    #[test]
+    fn test_cached_resolver() {
+        let dir = std::env::temp_dir().join(format!("concerto-cache-{}", std::process::id()));
+        let calls = Cell::new(0);
+        let online = |n: &Namespace| {
+            calls.set(calls.get() + 1);
+            Ok(format!("namespace {}@{}\n", n.name(), n.version()))
+        };
+        let places = namespace("places", "1.0.0");
+
+        let cache = super::CachedResolver::new(online, &dir);
+        assert_eq!(cache.resolve(&places).unwrap(), "namespace places@1.0.0\n");
+        assert_eq!(cache.resolve(&places).unwrap(), "namespace places@1.0.0\n");
+        assert_eq!(calls.get(), 1, "Should fetch a namespace once");
+
+        let cache = cache.ttl(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        cache.resolve(&places).unwrap();
+        assert_eq!(calls.get(), 2, "Should fetch expired models again");
+
+        let down = |n: &Namespace| {
+            Err(ResolveError::Io {
+                namespace: n.clone(),
+                source: std::io::Error::other("offline"),
+            })
+        };
+        let cache = super::CachedResolver::new(down, &dir).offline(true);
+        assert_eq!(
+            cache.resolve(&places).unwrap(),
+            "namespace places@1.0.0\n",
+            "Should use the cache offline"
+        );
+        assert!(matches!(
+            cache.resolve(&namespace("geo", "1.0.0")),
+            Err(ResolveError::NotFound(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_http_resolver_url() {
+        let resolver = super::HttpResolver::new("https://models.example.com/{name}@{version}.cto");
+        assert_eq!(
+            resolver.url(&namespace("org.acme", "1.2.0")),
+            "https://models.example.com/org.acme@1.2.0.cto"
+        );
+    }
+}
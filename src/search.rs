@@ -0,0 +1,225 @@
+//! Full-text search over identifiers, header comments, decorator arguments and string
+//! defaults, for finding one's way around large unfamiliar model repositories
+
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+use crate::{
+    format::default_value,
+    manager::ModelManager,
+    parser::{
+        cst::{Cst, NodeId},
+        declaration::Property,
+        property::CommonProperty,
+        Model,
+    },
+};
+
+/// What part of a node matched
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Field {
+    /// Name of the namespace, an imported type, a declaration or a property
+    Name,
+    /// Type of a property
+    Type,
+    /// Comment block leading the file, reported on the namespace
+    Header,
+    /// An argument of a decorator of a declaration or a property
+    Decorator,
+    /// Default value of a string property
+    Default,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    pub node: NodeId,
+    pub field: Field,
+    /// The whole text that matched, like a name or a default value
+    pub text: String,
+    /// Span of the node in the source, for searches in a `Cst`
+    pub span: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Text(String),
+    /// Text to look for, in lowercase
+    CaseInsensitive(String),
+    Regex(Regex),
+}
+
+/// What to search for, a substring by default
+#[derive(Debug, Clone)]
+pub struct Search {
+    pattern: Pattern,
+}
+
+impl Search {
+    /// Searches for text containing `text`
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            pattern: Pattern::Text(text.into()),
+        }
+    }
+
+    /// Searches for text a regular expression matches somewhere in
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Pattern::Regex(Regex::new(pattern)?),
+        })
+    }
+
+    /// Ignores case when matching
+    pub fn case_insensitive(self) -> Self {
+        let pattern = match self.pattern {
+            Pattern::Text(text) | Pattern::CaseInsensitive(text) => {
+                Pattern::CaseInsensitive(text.to_lowercase())
+            }
+            // The expression was valid, with the flag it still is
+            Pattern::Regex(regex) => Pattern::Regex(
+                RegexBuilder::new(regex.as_str())
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap_or(regex),
+            ),
+        };
+        Self { pattern }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Text(pattern) => text.contains(pattern.as_str()),
+            Pattern::CaseInsensitive(pattern) => text.to_lowercase().contains(pattern.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// Matches in a model, in source order
+    pub fn in_model(&self, model: &Model) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut check = |node, field, text: &str| {
+            if self.is_match(text) {
+                matches.push(Match {
+                    node,
+                    field,
+                    text: text.to_string(),
+                    span: None,
+                });
+            }
+        };
+
+        if let Some(header) = &model.header {
+            check(NodeId::Namespace, Field::Header, header);
+        }
+        check(NodeId::Namespace, Field::Name, model.namespace.name());
+        for (i, import) in model.imports.iter().enumerate() {
+            let text = import.to_string();
+            let name = text.rsplit('.').next().unwrap_or_default();
+            check(NodeId::Import(i), Field::Name, name);
+        }
+        for (d, declaration) in model.declarations.iter().enumerate() {
+            let node = NodeId::Declaration(d);
+            for decorator in declaration.decorators_of(None) {
+                for argument in &decorator.arguments {
+                    check(node, Field::Decorator, &argument.text());
+                }
+            }
+            check(node, Field::Name, &declaration.name);
+            for (p, property) in declaration.properties.iter().enumerate() {
+                let node = NodeId::Property(d, p);
+                for decorator in declaration.decorators_of(Some(property.name())) {
+                    for argument in &decorator.arguments {
+                        check(node, Field::Decorator, &argument.text());
+                    }
+                }
+                check(node, Field::Type, property.type_name());
+                check(node, Field::Name, property.name());
+                if let Property::String(_) = property {
+                    if let Some(default) = default_value(property) {
+                        check(node, Field::Default, &default);
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// Matches in a losslessly parsed model, with the spans of the nodes
+    pub fn in_cst(&self, cst: &Cst) -> Vec<Match> {
+        self.in_model(cst.model())
+            .into_iter()
+            .map(|m| Match {
+                span: cst.span(m.node),
+                ..m
+            })
+            .collect()
+    }
+
+    /// Matches in every loaded model, in the order the models were added
+    pub fn in_manager<'a>(&self, manager: &'a ModelManager) -> Vec<(&'a Model, Match)> {
+        manager
+            .models()
+            .flat_map(|model| self.in_model(model).into_iter().map(move |m| (model, m)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::cst::{Cst, NodeId};
+
+    use super::{Field, Search};
+
+    const SOURCE: &str = "// Address book of Acme
+namespace org.acme@1.0.0
+import places@1.0.0.Address
+
+@Term(\"Customer address\")
+concept Contact {
+  @Term(\"Mailing address\")
+  o Address address
+  o String label default=\"Home address\"
+  o String email
+}
+";
+
+    #[test]
+    fn test_search() {
+        let cst = Cst::parse(SOURCE).unwrap();
+        let found = |search: Search| -> Vec<(NodeId, Field)> {
+            search
+                .in_cst(&cst)
+                .into_iter()
+                .map(|m| (m.node, m.field))
+                .collect()
+        };
+
+        assert_eq!(
+            found(Search::text("address")),
+            vec![
+                (NodeId::Declaration(0), Field::Decorator),
+                (NodeId::Property(0, 0), Field::Decorator),
+                (NodeId::Property(0, 0), Field::Name),
+                (NodeId::Property(0, 1), Field::Default),
+            ]
+        );
+        assert_eq!(
+            found(Search::text("address").case_insensitive()).len(),
+            7,
+            "Should match the header, the import and the type too"
+        );
+        assert_eq!(
+            found(Search::regex("^e?mail").unwrap().case_insensitive()),
+            vec![
+                (NodeId::Property(0, 0), Field::Decorator),
+                (NodeId::Property(0, 2), Field::Name)
+            ],
+            "Should match string arguments without their quotes"
+        );
+        assert!(Search::regex("(").is_err());
+
+        let matches = Search::text("label").in_cst(&cst);
+        let span = matches[0].span.clone().unwrap();
+        assert!(SOURCE[span].starts_with("o String label"));
+    }
+}
@@ -1,6 +1,616 @@
-use crate::parser::Model;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::ops::Range;
+
+use crate::{
+    format::default_value,
+    manager::ModelManager,
+    parser::{
+        cst::Cst,
+        declaration::{DeclarationKind, Property},
+        error::SourceError,
+        property::{CommonProperty, Validator},
+        span::ModelSpans,
+        Model,
+    },
+    resolver::hex_sha256,
+};
 
 pub fn print(model: &Model) -> Result<String, Box<dyn std::error::Error>> {
     let s = serde_json::to_string_pretty(model)?;
     Ok(s)
 }
+
+/// Metamodel JSON of every loaded model, in the order the models were added. Models are
+/// serialized in parallel with the `rayon` feature.
+pub fn print_all(manager: &ModelManager) -> Result<Vec<String>, serde_json::Error> {
+    manager
+        .map_models(serde_json::to_string_pretty)
+        .into_iter()
+        .collect()
+}
+
+/// Reads a model from metamodel JSON, e.g. written by other tools. JSON with an embedded
+/// content hash is only read if it matches, see `verify_integrity`.
+#[cfg(feature = "deserialize")]
+pub fn from_metamodel_json(json: &str) -> Result<Model, serde_json::Error> {
+    match verify_integrity(json) {
+        Err(IntegrityError::Json(e)) => Err(e),
+        Err(e) => Err(serde::de::Error::custom(e)),
+        Ok(_) => serde_json::from_str(json),
+    }
+}
+
+/// Member of metamodel JSON objects holding their `Integrity`
+const INTEGRITY: &str = "$integrity";
+
+/// Content hash embedded in metamodel JSON, with the version of the crate that wrote it
+#[derive(Debug, Eq, PartialEq, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Integrity {
+    /// SHA-256 of the JSON without the hash, compact and with its members in order, in hex
+    pub sha256: String,
+    /// Like `concerto-nom 0.0.3`
+    pub producer: String,
+}
+
+#[derive(Debug)]
+pub enum IntegrityError {
+    Json(serde_json::Error),
+    /// The JSON was changed, or corrupted, after the hash was embedded
+    Mismatch(Integrity),
+}
+
+impl std::fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "{}", e),
+            Self::Mismatch(integrity) => write!(
+                f,
+                "content doesn't match the hash {} embedded by {}, it was changed or corrupted",
+                integrity.sha256, integrity.producer
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(e) => Some(e),
+            Self::Mismatch(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for IntegrityError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// Metamodel JSON of a model with its content hash embedded as `$integrity`, for models
+/// distributed through registries
+pub fn print_with_integrity(model: &Model) -> Result<String, serde_json::Error> {
+    let mut json = serde_json::to_value(model)?;
+    embed_integrity(&mut json);
+    serde_json::to_string_pretty(&json)
+}
+
+/// Embeds the content hash of a JSON object in it, replacing the one it has
+pub fn embed_integrity(json: &mut Value) {
+    let Some(members) = json.as_object_mut() else {
+        return;
+    };
+    members.remove(INTEGRITY);
+    let integrity = Integrity {
+        sha256: hex_sha256(&json.to_string()),
+        producer: format!("concerto-nom {}", env!("CARGO_PKG_VERSION")),
+    };
+    json[INTEGRITY] = json!(integrity);
+}
+
+/// Checks the content hash embedded in metamodel JSON, however the JSON is formatted. JSON
+/// without one has nothing to check and gives `None`.
+pub fn verify_integrity(json: &str) -> Result<Option<Integrity>, IntegrityError> {
+    let mut json: Value = serde_json::from_str(json)?;
+    let Some(embedded) = json.as_object_mut().and_then(|m| m.remove(INTEGRITY)) else {
+        return Ok(None);
+    };
+    let integrity: Integrity = serde_json::from_value(embedded)?;
+    match hex_sha256(&json.to_string()) == integrity.sha256 {
+        true => Ok(Some(integrity)),
+        false => Err(IntegrityError::Mismatch(integrity)),
+    }
+}
+
+/// Reads a model from metamodel JSON that may be compressed, see `compression`
+#[cfg(feature = "deserialize")]
+pub fn read_metamodel_json(reader: impl std::io::Read) -> Result<Model, serde_json::Error> {
+    let json = crate::compression::read_all(reader).map_err(serde_json::Error::io)?;
+    let json = std::str::from_utf8(&json).map_err(serde::de::Error::custom)?;
+    from_metamodel_json(json)
+}
+
+/// Writes the metamodel JSON of a model, compressed
+pub fn write_metamodel_json(
+    model: &Model,
+    writer: impl std::io::Write,
+    compression: crate::compression::Compression,
+) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(model).map_err(std::io::Error::other)?;
+    crate::compression::write_all(writer, json.as_bytes(), compression)
+}
+
+/// Turns metamodel JSON back into CTO, in the canonical style of `format`
+#[cfg(feature = "deserialize")]
+pub fn metamodel_json_to_cto(json: &str) -> Result<String, serde_json::Error> {
+    from_metamodel_json(json).map(|model| crate::format::format(&model))
+}
+
+/// Links the nodes of the metamodel JSON of a model to the byte ranges of the CTO source
+/// they were parsed from, so tools editing the JSON can write their edits back into the
+/// source. Nodes are addressed by JSON Pointers into the JSON `print` writes, like
+/// `/declarations/0/properties/1`.
+#[derive(Debug, Eq, PartialEq, Clone, Default, serde_derive::Serialize)]
+pub struct SourceMap {
+    /// Pointers and source ranges, nodes come before the nodes inside them
+    pub mappings: Vec<(String, Range<usize>)>,
+}
+
+impl SourceMap {
+    pub fn new(spans: &ModelSpans) -> Self {
+        let mut mappings = Vec::new();
+        if let Some(header) = &spans.header {
+            mappings.push((String::from("/header"), header.clone()));
+        }
+        mappings.push((String::from("/namespace"), spans.namespace.clone()));
+        for (i, import) in spans.imports.iter().enumerate() {
+            mappings.push((format!("/imports/{}", i), import.clone()));
+        }
+        for (d, declaration) in spans.declarations.iter().enumerate() {
+            mappings.push((format!("/declarations/{}", d), declaration.span.clone()));
+            for (p, property) in declaration.properties.iter().enumerate() {
+                mappings.push((
+                    format!("/declarations/{}/properties/{}", d, p),
+                    property.clone(),
+                ));
+            }
+        }
+        Self { mappings }
+    }
+
+    /// Range of the source the JSON node at `pointer` was parsed from
+    pub fn range(&self, pointer: &str) -> Option<Range<usize>> {
+        self.mappings
+            .iter()
+            .find(|(p, _)| p == pointer)
+            .map(|(_, range)| range.clone())
+    }
+
+    /// Pointer of the innermost JSON node parsed from the source at `offset`
+    pub fn pointer(&self, offset: usize) -> Option<&str> {
+        self.mappings
+            .iter()
+            .rev()
+            .find(|(_, range)| range.contains(&offset))
+            .map(|(p, _)| p.as_str())
+    }
+}
+
+/// Metamodel JSON of a source, and the source map linking the JSON back to the source
+pub fn print_with_source_map(source: &str) -> Result<(String, SourceMap), SourceError> {
+    let cst = Cst::parse(source)?;
+    let json = serde_json::to_string_pretty(cst.model()).unwrap_or_default();
+    Ok((json, SourceMap::new(cst.spans())))
+}
+
+/// Indented tree of the nodes of a model, one per line, with the byte ranges of the nodes
+/// in the source when their spans are given. Meant for seeing how a source was parsed.
+pub fn tree(model: &Model, spans: Option<&ModelSpans>) -> String {
+    let span = |range: Option<&std::ops::Range<usize>>| match range {
+        Some(range) => format!(" @{}..{}", range.start, range.end),
+        None => String::new(),
+    };
+    let mut output = String::from("Model\n");
+    output.push_str(&format!(
+        "  Namespace {}@{}{}\n",
+        model.namespace.name(),
+        model.namespace.version(),
+        span(spans.map(|s| &s.namespace))
+    ));
+    for (i, import) in model.imports.iter().enumerate() {
+        let target = import.to_string();
+        output.push_str(&format!(
+            "  Import {}{}\n",
+            target.trim_start_matches("import "),
+            span(spans.and_then(|s| s.imports.get(i)))
+        ));
+    }
+    for (d, declaration) in model.declarations.iter().enumerate() {
+        let declaration_spans = spans.and_then(|s| s.declarations.get(d));
+        let class = match &declaration.extension {
+            Some(extension) => format!("ExtensionDeclaration({})", extension.keyword),
+            None => serde_json::to_value(declaration.kind)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default(),
+        };
+        output.push_str(&format!(
+            "  {} {}{}\n",
+            class,
+            declaration.name,
+            span(declaration_spans.map(|s| &s.span))
+        ));
+        for (p, property) in declaration.properties.iter().enumerate() {
+            output.push_str(&format!(
+                "    {} {}{}\n",
+                property.class(),
+                property.name(),
+                span(declaration_spans.and_then(|s| s.properties.get(p)))
+            ));
+            let array = if property.is_array() { "[]" } else { "" };
+            if !property.type_name().is_empty() {
+                output.push_str(&format!("      type {}{}\n", property.type_name(), array));
+            }
+            if property.is_optional() {
+                output.push_str("      optional\n");
+            }
+            if let Some(default) = default_value(property) {
+                output.push_str(&format!("      default {}\n", default));
+            }
+            for validator in property.validators() {
+                output.push_str(&format!("      validator {}\n", validator));
+            }
+            for meta in declaration
+                .meta_extensions
+                .iter()
+                .filter(|m| m.property == property.name())
+            {
+                output.push_str(&format!("      meta {}\n", meta));
+            }
+        }
+    }
+    output
+}
+
+/// JSON Schema (draft-07) of instances of the model, with a definition per declaration.
+/// Instances carry their fully qualified type in `$class`, as Concerto expects, and enums
+/// are strings naming one of their values. Types
+/// imported from other namespaces are only checked to be objects.
+pub fn json_schema(model: &Model) -> Value {
+    let namespace = format!("{}@{}", model.namespace.name(), model.namespace.version());
+    let definitions: Map<String, Value> = model
+        .declarations
+        .iter()
+        .map(|declaration| {
+            if declaration.kind == DeclarationKind::Enum {
+                let values: Vec<_> = declaration.properties.iter().map(|v| v.name()).collect();
+                let schema = json!({ "type": "string", "enum": values });
+                return (declaration.name.clone(), schema);
+            }
+            let mut properties = Map::new();
+            properties.insert(
+                String::from("$class"),
+                json!({ "const": format!("{}.{}", namespace, declaration.name) }),
+            );
+            let mut required = vec![String::from("$class")];
+            for property in &declaration.properties {
+                properties.insert(
+                    property.name().to_string(),
+                    property_schema(model, property),
+                );
+                if !property.is_optional() {
+                    required.push(property.name().to_string());
+                }
+            }
+            let schema = json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            });
+            (declaration.name.clone(), schema)
+        })
+        .collect();
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": namespace,
+        "definitions": definitions,
+    })
+}
+
+/// `json_schema` of every loaded model, in the order the models were added. Schemas are
+/// generated in parallel with the `rayon` feature.
+pub fn json_schemas(manager: &ModelManager) -> Vec<Value> {
+    manager.map_models(json_schema)
+}
+
+fn property_schema(model: &Model, property: &Property) -> Value {
+    let mut schema = match property {
+        Property::Boolean(_) => json!({ "type": "boolean" }),
+        Property::Integer(_) | Property::Long(_) => json!({ "type": "integer" }),
+        Property::Double(_) => json!({ "type": "number" }),
+        Property::DateTime(_) => json!({ "type": "string", "format": "date-time" }),
+        Property::String(_) | Property::Enum(_) => json!({ "type": "string" }),
+        Property::Concept(p) if p.is_relationship => json!({ "type": "string" }),
+        Property::Concept(p)
+            if p.namespace.as_ref().is_none_or(|ns| *ns == model.namespace)
+                && model.declaration(&p.class).is_some() =>
+        {
+            json!({ "$ref": format!("#/definitions/{}", p.class) })
+        }
+        Property::Concept(_) => json!({ "type": "object" }),
+    };
+    let fields = schema.as_object_mut().expect("Schema is an object");
+    for validator in property.validators() {
+        match validator {
+            Validator::Regex(regex) => {
+                fields.insert(String::from("pattern"), json!(regex.pattern));
+            }
+            Validator::Length(length) => {
+                insert_bound(fields, "minLength", length.min_length);
+                insert_bound(fields, "maxLength", length.max_length);
+            }
+            Validator::IntegerDomain(range) => {
+                insert_bound(fields, minimum(range.lower_exclusive), range.lower);
+                insert_bound(fields, maximum(range.upper_exclusive), range.upper);
+            }
+            Validator::LongDomain(range) => {
+                insert_bound(fields, minimum(range.lower_exclusive), range.lower);
+                insert_bound(fields, maximum(range.upper_exclusive), range.upper);
+            }
+            Validator::DoubleDomain(range) => {
+                insert_bound(fields, minimum(range.lower_exclusive), range.lower);
+                insert_bound(fields, maximum(range.upper_exclusive), range.upper);
+            }
+        }
+    }
+    if let Ok(Value::Object(serialized)) = serde_json::to_value(property) {
+        if let Some(default) = serialized.get("defaultValue") {
+            fields.insert(String::from("default"), default.clone());
+        }
+    }
+
+    if property.is_array() {
+        json!({ "type": "array", "items": schema })
+    } else {
+        schema
+    }
+}
+
+/// Keyword of a lower bound, draft-07 has a separate one for excluded ends
+fn minimum(exclusive: bool) -> &'static str {
+    match exclusive {
+        true => "exclusiveMinimum",
+        false => "minimum",
+    }
+}
+
+fn maximum(exclusive: bool) -> &'static str {
+    match exclusive {
+        true => "exclusiveMaximum",
+        false => "maximum",
+    }
+}
+
+fn insert_bound<T: Serialize>(fields: &mut Map<String, Value>, key: &str, bound: Option<T>) {
+    // Long bounds beyond i64 with the `i128` feature have no JSON number and are left out
+    if let Some(Ok(bound)) = bound.map(serde_json::to_value) {
+        fields.insert(key.to_string(), bound);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::parser::model;
+
+    #[test]
+    fn test_json_schema() {
+        let (_, model) = model(
+            "namespace test@1.0.0
+            import places@1.0.0.Address
+
+            concept Person {
+              o String name regex=/[A-Z].*/ length=[1, 100]
+              o Integer age optional range=[0,]
+              o Boolean active default=true
+              o Person[] friends optional
+              o Address address
+            }
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::json_schema(&model),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": "test@1.0.0",
+                "definitions": {
+                    "Person": {
+                        "type": "object",
+                        "properties": {
+                            "$class": { "const": "test@1.0.0.Person" },
+                            "name": {
+                                "type": "string",
+                                "pattern": "[A-Z].*",
+                                "minLength": 1,
+                                "maxLength": 100
+                            },
+                            "age": { "type": "integer", "minimum": 0 },
+                            "active": { "type": "boolean", "default": true },
+                            "friends": {
+                                "type": "array",
+                                "items": { "$ref": "#/definitions/Person" }
+                            },
+                            "address": { "type": "object" }
+                        },
+                        "required": ["$class", "name", "active", "address"]
+                    }
+                }
+            }),
+            "Should describe instances of every declaration"
+        );
+    }
+
+    #[test]
+    fn test_json_schemas() {
+        let mut manager = crate::manager::ModelManager::new();
+        for i in 0..16 {
+            let source = format!("namespace test{}@1.0.0\n\nconcept Person{} {{}}\n", i, i);
+            manager.add_model(crate::parser::parse(&source).unwrap());
+        }
+
+        let titles: Vec<_> = super::json_schemas(&manager)
+            .into_iter()
+            .map(|schema| schema["title"].clone())
+            .collect();
+        let expected: Vec<_> = (0..16).map(|i| json!(format!("test{}@1.0.0", i))).collect();
+        assert_eq!(
+            titles, expected,
+            "Should keep the order models were added in"
+        );
+        assert_eq!(
+            super::print_all(&manager).unwrap()[3],
+            serde_json::to_string_pretty(manager.models().nth(3).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "// Acme
+namespace test@1.0.0
+import places@1.0.0.Address
+
+concept Person {
+  o String name
+  o Address address
+}
+";
+        let (json, map) = super::print_with_source_map(source).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        for (pointer, range) in &map.mappings {
+            assert!(
+                json.pointer(pointer).is_some(),
+                "Should point into the JSON: {}",
+                pointer
+            );
+            assert!(range.end <= source.len());
+        }
+
+        let property = map.range("/declarations/0/properties/1").unwrap();
+        assert_eq!(&source[property], "o Address address");
+        assert_eq!(map.range("/header"), Some(0..7));
+        let offset = source.find("String").unwrap();
+        assert_eq!(map.pointer(offset), Some("/declarations/0/properties/0"));
+        assert_eq!(
+            map.pointer(source.find("Person").unwrap()),
+            Some("/declarations/0")
+        );
+        assert_eq!(map.pointer(source.len() - 1), None);
+    }
+
+    #[test]
+    fn test_tree() {
+        let source = "namespace test@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name default=\"J\" regex=/[A-Z]/
+  o Address[] addresses optional
+}
+";
+        let cst = crate::parser::cst::Cst::parse(source).unwrap();
+
+        assert_eq!(
+            super::tree(cst.model(), Some(cst.spans())),
+            "Model
+  Namespace test@1.0.0 @0..20
+  Import places@1.0.0.Address @21..48
+  ConceptDeclaration Person @49..142
+    StringProperty name @68..107
+      type String
+      default \"J\"
+      validator regex=/[A-Z]/
+    ObjectProperty addresses @110..140
+      type Address[]
+      optional
+"
+        );
+        assert!(
+            !super::tree(cst.model(), None).contains(" @"),
+            "Should leave spans out when there are none"
+        );
+    }
+
+    #[cfg(feature = "deserialize")]
+    #[test]
+    fn test_metamodel_json_to_cto() {
+        let source = "namespace test@1.0.0
+
+@Term(\"Person\", 2, true, Address[])
+concept Person {
+  @deprecated
+  o String name optional
+  o Integer[] scores range=[0, 10]
+  @Term(\"Home\")
+  o Address home
+}
+";
+        let json = serde_json::to_string(&crate::parser::parse(source).unwrap()).unwrap();
+        assert_eq!(super::metamodel_json_to_cto(&json).unwrap(), source);
+        assert!(super::metamodel_json_to_cto("{}").is_err());
+    }
+
+    #[test]
+    fn test_integrity() {
+        let (_, model) = model(
+            "namespace test@1.0.0
+
+            concept Person {
+              o String name default=\"Ann\"
+            }
+            ",
+        )
+        .unwrap();
+        let json = super::print_with_integrity(&model).unwrap();
+        let integrity = super::verify_integrity(&json).unwrap().unwrap();
+        assert!(integrity.producer.starts_with("concerto-nom "));
+
+        let compact =
+            serde_json::to_string(&serde_json::from_str::<serde_json::Value>(&json).unwrap())
+                .unwrap();
+        assert_eq!(
+            super::verify_integrity(&compact).unwrap(),
+            Some(integrity),
+            "Should not depend on the formatting"
+        );
+        assert!(
+            matches!(
+                super::verify_integrity(&json.replace("Ann", "Bob")),
+                Err(super::IntegrityError::Mismatch(_))
+            ),
+            "Should find changes to the content"
+        );
+        assert_eq!(
+            super::verify_integrity(&super::print(&model).unwrap()).unwrap(),
+            None,
+            "Should have nothing to check without a hash"
+        );
+        #[cfg(feature = "deserialize")]
+        {
+            assert_eq!(super::from_metamodel_json(&json).unwrap(), model);
+            let mut written = Vec::new();
+            super::write_metamodel_json(&model, &mut written, Default::default()).unwrap();
+            assert_eq!(
+                super::read_metamodel_json(written.as_slice()).unwrap(),
+                model
+            );
+            assert!(super::from_metamodel_json(&json.replace("Ann", "Bob"))
+                .unwrap_err()
+                .to_string()
+                .contains("changed or corrupted"));
+        }
+    }
+}
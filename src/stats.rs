@@ -0,0 +1,213 @@
+//! Counts over models, for reporting on large sets of models
+
+use std::collections::BTreeMap;
+
+use serde_derive::Serialize;
+
+use crate::{
+    manager::ModelManager,
+    parser::{
+        import::Import,
+        namespace::Namespace,
+        property::{CommonProperty, Validator},
+        Model,
+    },
+};
+
+/// Statistics of one or more models. Namespaces are keyed as `name@version`.
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+pub struct Stats {
+    pub models: usize,
+    /// Declarations by their kind, e.g. `concept`
+    pub declarations: BTreeMap<String, usize>,
+    pub properties: usize,
+    pub optional_properties: usize,
+    pub array_properties: usize,
+    /// Properties by their type as written, e.g. `String`
+    pub property_types: BTreeMap<String, usize>,
+    /// Validators by their keyword, e.g. `regex`
+    pub validators: BTreeMap<String, usize>,
+    pub imports: usize,
+    /// Number of models importing from each namespace
+    pub fan_in: BTreeMap<String, usize>,
+    /// Number of namespaces each model imports from
+    pub fan_out: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    pub(crate) fn of_model(model: &Model) -> Self {
+        let mut stats = Self::default();
+        stats.add(model, |import| key(import.namespace()));
+        stats
+    }
+
+    pub(crate) fn of_manager(manager: &ModelManager) -> Self {
+        let mut stats = Self::default();
+        for model in manager.models() {
+            // Imports count towards the model they resolve to, when it is loaded
+            stats.add(model, |import| {
+                manager
+                    .resolve_import(import)
+                    .map_or_else(|| key(import.namespace()), |m| key(&m.namespace))
+            });
+        }
+        stats
+    }
+
+    fn add(&mut self, model: &Model, import_key: impl Fn(&Import) -> String) {
+        self.models += 1;
+        for declaration in &model.declarations {
+            let kind = match &declaration.extension {
+                Some(extension) => extension.keyword.clone(),
+                None => declaration.kind.to_string(),
+            };
+            *self.declarations.entry(kind).or_default() += 1;
+            for property in &declaration.properties {
+                self.properties += 1;
+                self.optional_properties += usize::from(property.is_optional());
+                self.array_properties += usize::from(property.is_array());
+                if !property.type_name().is_empty() {
+                    *self
+                        .property_types
+                        .entry(property.type_name().to_string())
+                        .or_default() += 1;
+                }
+                for validator in property.validators() {
+                    let keyword = match validator {
+                        Validator::Regex(_) => "regex",
+                        Validator::Length(_) => "length",
+                        Validator::IntegerDomain(_)
+                        | Validator::LongDomain(_)
+                        | Validator::DoubleDomain(_) => "range",
+                    };
+                    *self.validators.entry(keyword.to_string()).or_default() += 1;
+                }
+            }
+        }
+
+        self.imports += model.imports.len();
+        let mut imported: Vec<_> = model.imports.iter().map(&import_key).collect();
+        imported.sort();
+        imported.dedup();
+        self.fan_out.insert(key(&model.namespace), imported.len());
+        for namespace in imported {
+            *self.fan_in.entry(namespace).or_default() += 1;
+        }
+    }
+
+    pub fn declaration_count(&self) -> usize {
+        self.declarations.values().sum()
+    }
+}
+
+fn key(namespace: &Namespace) -> String {
+    format!("{}@{}", namespace.name(), namespace.version())
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = |counts: &BTreeMap<String, usize>| {
+            counts
+                .iter()
+                .map(|(k, v)| format!("{} {}", k, v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "models        {}", self.models)?;
+        writeln!(
+            f,
+            "declarations  {} ({})",
+            self.declaration_count(),
+            counts(&self.declarations)
+        )?;
+        writeln!(
+            f,
+            "properties    {} ({} optional, {} arrays)",
+            self.properties, self.optional_properties, self.array_properties
+        )?;
+        writeln!(f, "types         {}", counts(&self.property_types))?;
+        writeln!(f, "validators    {}", counts(&self.validators))?;
+        writeln!(f, "imports       {}", self.imports)?;
+
+        let mut namespaces: Vec<_> = self.fan_in.keys().chain(self.fan_out.keys()).collect();
+        namespaces.sort();
+        namespaces.dedup();
+        let width = namespaces.iter().map(|n| n.len()).max().unwrap_or(0).max(9);
+        writeln!(f, "{:width$}  fan-in  fan-out", "namespace")?;
+        for namespace in namespaces {
+            let fan_in = self.fan_in.get(namespace).copied().unwrap_or_default();
+            match self.fan_out.get(namespace) {
+                Some(fan_out) => writeln!(f, "{:width$}  {:<6}  {}", namespace, fan_in, fan_out)?,
+                // Imported, but not loaded
+                None => writeln!(f, "{:width$}  {:<6}  -", namespace, fan_in)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{manager::ModelManager, parser::parse};
+
+    #[test]
+    fn test_stats() {
+        let people = parse(
+            "namespace people@1.0.0
+import places@1.0.0.Address
+import places@1.0.0.City
+concept Person {
+  o String name regex=/[A-Z].*/ length=[1,20]
+  o Integer age optional range=[0,]
+  o Address[] addresses
+}
+",
+        )
+        .unwrap();
+        let stats = people.stats();
+        assert_eq!(stats.declaration_count(), 1);
+        assert_eq!(
+            (
+                stats.properties,
+                stats.optional_properties,
+                stats.array_properties
+            ),
+            (3, 1, 1)
+        );
+        assert_eq!(stats.validators.get("range"), Some(&1));
+        assert_eq!(
+            stats.fan_out.get("people@1.0.0"),
+            Some(&1),
+            "Should count namespaces, not imports"
+        );
+
+        let mut manager = ModelManager::new();
+        manager.add_model(people);
+        manager.add_model(
+            parse("namespace places@1.2.0\nconcept Address {\n  o String city\n}\n").unwrap(),
+        );
+        manager.add_model(parse("namespace orders@1.0.0\nimport places@1.1.0.Address\n").unwrap());
+        let stats = manager.stats();
+        assert_eq!(stats.models, 3);
+        assert_eq!(stats.properties, 4);
+        assert_eq!(
+            stats.fan_in.get("places@1.2.0"),
+            Some(&2),
+            "Should count imports towards the models they resolve to"
+        );
+        assert_eq!(
+            stats.to_string(),
+            "models        3
+declarations  2 (concept 2)
+properties    4 (1 optional, 1 arrays)
+types         Address 1, Integer 1, String 2
+validators    length 1, range 1, regex 1
+imports       3
+namespace     fan-in  fan-out
+orders@1.0.0  0       1
+people@1.0.0  0       1
+places@1.2.0  2       0
+"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Outline of a model, e.g. for the document symbols of an editor or a table of contents
+
+use std::ops::Range;
+
+use crate::parser::{
+    declaration::DeclarationKind, property::CommonProperty, span::ModelSpans, Model,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SymbolKind {
+    Namespace,
+    Import,
+    Declaration(DeclarationKind),
+    Property,
+}
+
+/// A named node of a model, with where it is in the source
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Short description to show next to the name, like the type of a property
+    pub detail: Option<String>,
+    pub span: Range<usize>,
+    /// Properties of declarations
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// Symbols of a model in source order: the namespace, the imports and the declarations,
+/// with their properties as children. Takes the spans the model was parsed with.
+pub fn symbols(model: &Model, spans: &ModelSpans) -> Vec<DocumentSymbol> {
+    let mut symbols = vec![DocumentSymbol {
+        name: model.namespace.name().to_string(),
+        kind: SymbolKind::Namespace,
+        detail: Some(model.namespace.version().to_string()),
+        span: spans.namespace.clone(),
+        children: Vec::new(),
+    }];
+    for (import, span) in model.imports.iter().zip(&spans.imports) {
+        let text = import.to_string();
+        symbols.push(DocumentSymbol {
+            name: text.trim_start_matches("import ").to_string(),
+            kind: SymbolKind::Import,
+            detail: None,
+            span: span.clone(),
+            children: Vec::new(),
+        });
+    }
+    for (declaration, spans) in model.declarations.iter().zip(&spans.declarations) {
+        let children = declaration
+            .properties
+            .iter()
+            .zip(&spans.properties)
+            .map(|(property, span)| DocumentSymbol {
+                name: property.name().to_string(),
+                kind: SymbolKind::Property,
+                detail: Some(match property.is_array() {
+                    true => format!("{}[]", property.type_name()),
+                    false => property.type_name().to_string(),
+                }),
+                span: span.clone(),
+                children: Vec::new(),
+            })
+            .collect();
+        let detail = match &declaration.extension {
+            Some(extension) => extension.keyword.clone(),
+            None => declaration.kind.to_string(),
+        };
+        symbols.push(DocumentSymbol {
+            name: declaration.name.clone(),
+            kind: SymbolKind::Declaration(declaration.kind),
+            detail: Some(detail),
+            span: spans.span.clone(),
+            children,
+        });
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod test {
+    use super::SymbolKind;
+    use crate::parser::{cst::Cst, declaration::DeclarationKind};
+
+    #[test]
+    fn test_symbols() {
+        let source = "namespace test@1.0.0
+import places@1.0.0.Address
+concept Person {
+  o String name
+  o Address[] addresses
+}
+";
+        let cst = Cst::parse(source).unwrap();
+        let symbols = super::symbols(cst.model(), cst.spans());
+
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|s| (s.kind, s.name.as_str(), &source[s.span.clone()]))
+                .collect::<Vec<_>>(),
+            vec![
+                (SymbolKind::Namespace, "test", "namespace test@1.0.0"),
+                (
+                    SymbolKind::Import,
+                    "places@1.0.0.Address",
+                    "import places@1.0.0.Address"
+                ),
+                (
+                    SymbolKind::Declaration(DeclarationKind::Concept),
+                    "Person",
+                    "concept Person {\n  o String name\n  o Address[] addresses\n}"
+                ),
+            ]
+        );
+        assert_eq!(
+            symbols[2]
+                .children
+                .iter()
+                .map(|s| (
+                    s.name.as_str(),
+                    s.detail.as_deref(),
+                    &source[s.span.clone()]
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("name", Some("String"), "o String name"),
+                ("addresses", Some("Address[]"), "o Address[] addresses"),
+            ],
+            "Should nest properties in their declarations"
+        );
+    }
+}
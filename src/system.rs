@@ -0,0 +1,80 @@
+//! The implicit `concerto@1.0.0` namespace of the base types every declaration extends.
+//! `ModelManager` resolves it without it being loaded.
+
+use std::sync::OnceLock;
+
+use crate::parser::{
+    declaration::{Declaration, DeclarationKind},
+    namespace::Namespace,
+    parse, Model,
+};
+
+pub const NAMESPACE: &str = "concerto";
+
+/// Base type of concepts
+pub const CONCEPT: &str = "Concept";
+
+/// Base type of assets
+pub const ASSET: &str = "Asset";
+
+/// Base type of events
+pub const EVENT: &str = "Event";
+
+const SOURCE: &str = "namespace concerto@1.0.0
+
+concept Concept {
+}
+
+concept Asset {
+}
+
+concept Participant {
+}
+
+concept Transaction {
+  o DateTime timestamp
+}
+
+concept Event {
+  o DateTime timestamp
+}
+";
+
+/// Model of the system namespace
+pub fn model() -> &'static Model {
+    static MODEL: OnceLock<Model> = OnceLock::new();
+    MODEL.get_or_init(|| parse(SOURCE).expect("System model is valid"))
+}
+
+pub fn is_system(namespace: &Namespace) -> bool {
+    namespace == &model().namespace
+}
+
+/// Name of the base type of a declaration that doesn't extend another one, none for the base
+/// types themselves, for enums, for maps and for declarations of dialects
+pub(crate) fn implicit_supertype(model: &Model, declaration: &Declaration) -> Option<&'static str> {
+    match declaration.kind {
+        _ if is_system(&model.namespace) => None,
+        DeclarationKind::Concept => Some(CONCEPT),
+        DeclarationKind::Asset => Some(ASSET),
+        DeclarationKind::Event => Some(EVENT),
+        DeclarationKind::Enum | DeclarationKind::Map | DeclarationKind::Extension => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_system_model() {
+        let model = super::model();
+        assert!(super::is_system(&model.namespace));
+        assert_eq!(
+            model
+                .declarations
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Concept", "Asset", "Participant", "Transaction", "Event"]
+        );
+    }
+}
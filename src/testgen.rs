@@ -0,0 +1,145 @@
+//! Synthesizes valid models of a given size and shape, to stress the parser and the tools
+//! built on it, or to size tooling before real models of that size exist
+
+/// Size and shape of a generated model
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shape {
+    pub declarations: usize,
+    /// Primitive properties of each declaration
+    pub properties: usize,
+    /// Length of the chains of concepts where each one has a property of the next one's
+    /// type. `extends` isn't in the grammar, so this is how generated types nest.
+    pub depth: usize,
+    /// Share of the properties with validators and defaults, from 0.0 to 1.0
+    pub validator_density: f64,
+    /// The same seed always generates the same model
+    pub seed: u64,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Self {
+            declarations: 10,
+            properties: 5,
+            depth: 1,
+            validator_density: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+/// CTO source of a model of the given shape in the namespace `name@1.0.0`
+pub fn generate(name: &str, shape: &Shape) -> String {
+    let mut random = Xorshift::new(shape.seed);
+    let mut source = format!("namespace {}@1.0.0\n", name);
+    for i in 0..shape.declarations {
+        source.push_str(&format!("\nconcept Concept{} {{\n", i));
+        for j in 0..shape.properties {
+            let array = random.below(4) == 0;
+            let validated = !array && random.chance(shape.validator_density);
+            let (type_name, validators) = match random.below(6) {
+                0 => ("String", " default=\"text\" regex=/[a-z]+/ length=[1,32]"),
+                1 => ("Boolean", " default=true"),
+                2 => ("Integer", " default=1 range=[0,100]"),
+                3 => ("Long", " range=[0,]"),
+                4 => ("Double", " default=0.5 range=[0.0,1.0]"),
+                _ => ("DateTime", " default=2000-01-01"),
+            };
+            source.push_str(&format!(
+                "  o {}{} property{}{}{}\n",
+                type_name,
+                if array { "[]" } else { "" },
+                j,
+                if validated { validators } else { "" },
+                if random.below(3) == 0 {
+                    " optional"
+                } else {
+                    ""
+                },
+            ));
+        }
+        if (i + 1) % shape.depth.max(1) != 0 && i + 1 < shape.declarations {
+            source.push_str(&format!("  o Concept{} next optional\n", i + 1));
+        }
+        source.push_str("}\n");
+    }
+    source
+}
+
+/// Small deterministic generator, generated models don't need better randomness
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn new(seed: u64) -> Self {
+        // Zero is the one state xorshift never leaves
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{declaration::Property, parse};
+
+    use super::Shape;
+
+    #[test]
+    fn test_generate() {
+        let shape = Shape {
+            declarations: 20,
+            properties: 6,
+            depth: 4,
+            validator_density: 1.0,
+            seed: 7,
+        };
+        let source = super::generate("generated", &shape);
+        assert_eq!(
+            source,
+            super::generate("generated", &shape),
+            "Should generate the same model from the same seed"
+        );
+
+        let model = parse(&source).unwrap();
+        assert_eq!(model.declarations.len(), 20);
+        let nested = |d: &crate::parser::declaration::Declaration| {
+            d.properties
+                .iter()
+                .any(|p| matches!(p, Property::Concept(_)))
+        };
+        assert_eq!(
+            model.declarations.iter().filter(|d| nested(d)).count(),
+            15,
+            "Should chain four concepts at a time"
+        );
+        assert!(
+            model
+                .declarations
+                .iter()
+                .all(|d| d.properties.len() >= shape.properties),
+            "Should have the properties asked for"
+        );
+
+        let plain = super::generate(
+            "plain",
+            &Shape {
+                validator_density: 0.0,
+                ..Shape::default()
+            },
+        );
+        assert!(!plain.contains("range=") && !plain.contains("default="));
+    }
+}
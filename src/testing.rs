@@ -0,0 +1,79 @@
+//! Helpers for tests of crates that use this one
+
+use serde_json::{Map, Value};
+
+use crate::parser::Model;
+
+/// Version of the layout `normalized_json` writes, bumped whenever its output changes
+pub const NORMALIZED_JSON_VERSION: u64 = 1;
+
+/// Metamodel JSON of a model meant for snapshot tests. The model is canonicalized first, so
+/// the order things are written in doesn't matter, object keys are sorted and the layout
+/// version is pinned in the output. Snapshots only change when the model or the version do.
+pub fn normalized_json(model: &Model) -> String {
+    let model = serde_json::to_value(model.canonicalize()).unwrap_or_default();
+    let mut root = Map::new();
+    root.insert(
+        String::from("normalizedJsonVersion"),
+        Value::from(NORMALIZED_JSON_VERSION),
+    );
+    root.insert(String::from("model"), sorted(model));
+    let mut json = serde_json::to_string_pretty(&Value::Object(root)).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Sorts the keys of objects, whatever order the map of `serde_json` keeps them in
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(k, v)| (k, sorted(v))).collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sorted).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse;
+
+    #[test]
+    fn test_normalized_json() {
+        let a = parse(
+            "namespace test@1.0.0
+concept Person {
+  o String name
+  o Integer age
+}
+concept Address {
+  o String city
+}
+",
+        )
+        .unwrap();
+        let b = parse(
+            "namespace test@1.0.0
+concept Address {
+  o String city
+}
+concept Person {
+  o Integer age
+  o String name
+}
+",
+        )
+        .unwrap();
+
+        let json = super::normalized_json(&a);
+        assert_eq!(
+            json,
+            super::normalized_json(&b),
+            "Should not depend on the order of declarations"
+        );
+        assert!(json.starts_with("{\n  \"model\": {\n    \"$class\": \"Model\",\n"));
+        assert!(json.ends_with("  \"normalizedJsonVersion\": 1\n}\n"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Vocabularies give declarations and properties of a namespace localized, business-friendly
+//! terms. They are YAML files like
+//!
+//! ```yaml
+//! locale: en
+//! namespace: org.acme@1.0.0
+//! declarations:
+//!   - Vehicle: A road vehicle
+//!     properties:
+//!       - vin: Vehicle Identification Number
+//!         tooltip: VIN
+//! ```
+//!
+//! Keys next to the term, like `tooltip`, are additional terms.
+
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    format::format,
+    parser::{
+        cst::NodeId, decorator::Decorator, namespace::Namespace, property::CommonProperty, Model,
+    },
+};
+
+#[derive(Debug)]
+pub enum VocabularyError {
+    Yaml(serde_yaml::Error),
+    /// The YAML isn't laid out as a vocabulary
+    Invalid(String),
+}
+
+impl std::fmt::Display for VocabularyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(e) => write!(f, "{}", e),
+            Self::Invalid(message) => write!(f, "invalid vocabulary: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for VocabularyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Yaml(e) => Some(e),
+            Self::Invalid(_) => None,
+        }
+    }
+}
+
+/// Terms of a declaration or a property
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Terms {
+    /// Name of the declaration or the property
+    pub name: String,
+    pub term: String,
+    /// Additional terms by their key, in the order they are written in
+    pub additional: Vec<(String, String)>,
+    /// Terms of the properties of a declaration
+    pub properties: Vec<Terms>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Vocabulary {
+    pub locale: String,
+    /// Namespace the terms are for, as `name@version` or just a name for any version
+    pub namespace: String,
+    pub declarations: Vec<Terms>,
+}
+
+/// Terms of a vocabulary found in a model
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Linked<'v> {
+    /// Declarations and properties with their terms, in the order of the vocabulary
+    pub terms: Vec<(NodeId, &'v Terms)>,
+    /// Terms of declarations and properties the model doesn't have, as `Declaration` or
+    /// `Declaration.property`
+    pub unknown: Vec<String>,
+}
+
+impl Vocabulary {
+    pub fn parse(yaml: &str) -> Result<Self, VocabularyError> {
+        let root: Mapping = serde_yaml::from_str(yaml).map_err(VocabularyError::Yaml)?;
+        let field = |key: &str| {
+            root.get(key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| VocabularyError::Invalid(format!("{} is missing", key)))
+        };
+        Ok(Self {
+            locale: field("locale")?,
+            namespace: field("namespace")?,
+            declarations: terms_list(root.get("declarations"))?,
+        })
+    }
+
+    /// Whether the terms are for a namespace
+    pub fn is_for(&self, namespace: &Namespace) -> bool {
+        match self.namespace.split_once('@') {
+            Some((name, version)) => {
+                name == namespace.name() && version == namespace.version().to_string()
+            }
+            None => self.namespace == namespace.name(),
+        }
+    }
+
+    /// Terms of a declaration, or of one of its properties
+    pub fn terms(&self, declaration: &str, property: Option<&str>) -> Option<&Terms> {
+        let terms = self.declarations.iter().find(|t| t.name == declaration)?;
+        match property {
+            Some(property) => terms.properties.iter().find(|t| t.name == property),
+            None => Some(terms),
+        }
+    }
+
+    pub fn term(&self, declaration: &str, property: Option<&str>) -> Option<&str> {
+        self.terms(declaration, property).map(|t| t.term.as_str())
+    }
+
+    /// Links the terms to the declarations and properties of a model
+    pub fn link(&self, model: &Model) -> Linked<'_> {
+        let mut linked = Linked {
+            terms: Vec::new(),
+            unknown: Vec::new(),
+        };
+        for terms in &self.declarations {
+            let Some(d) = model.declarations.iter().position(|d| d.name == terms.name) else {
+                linked.unknown.push(terms.name.clone());
+                continue;
+            };
+            linked.terms.push((NodeId::Declaration(d), terms));
+            for property_terms in &terms.properties {
+                let properties = &model.declarations[d].properties;
+                match properties
+                    .iter()
+                    .position(|p| p.name() == property_terms.name)
+                {
+                    Some(p) => linked.terms.push((NodeId::Property(d, p), property_terms)),
+                    None => linked
+                        .unknown
+                        .push(format!("{}.{}", terms.name, property_terms.name)),
+                }
+            }
+        }
+        linked
+    }
+
+    /// Prints a model as CTO with its terms as `@Term("...")` decorators, and additional
+    /// terms as `@Term_key("...")`. Term decorators the model already has on those nodes are
+    /// replaced, so decorating a decorated model gives the same output.
+    pub fn decorate(&self, model: &Model) -> String {
+        let mut decorated = model.clone();
+        for (node, terms) in self.link(model).terms {
+            let decorators = match node {
+                NodeId::Declaration(d) => &mut decorated.declarations[d].decorators,
+                NodeId::Property(d, p) => decorated.declarations[d].properties[p].decorators_mut(),
+                NodeId::Namespace | NodeId::Import(_) => continue,
+            };
+            decorators.retain(|d| d.name != "Term" && !d.name.starts_with("Term_"));
+            decorators.push(Decorator::new("Term").with_string(&terms.term));
+            for (key, term) in &terms.additional {
+                decorators.push(Decorator::new(format!("Term_{}", key)).with_string(term));
+            }
+        }
+        format(&decorated)
+    }
+}
+
+/// Reads a list of entries, each a map with the name and the term as its first key
+fn terms_list(value: Option<&Value>) -> Result<Vec<Terms>, VocabularyError> {
+    let entries = match value {
+        None => return Ok(Vec::new()),
+        Some(Value::Sequence(entries)) => entries,
+        Some(_) => return Err(VocabularyError::Invalid(String::from("expected a list"))),
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let invalid = || VocabularyError::Invalid(format!("unexpected entry {:?}", entry));
+            let mut keys = entry.as_mapping().ok_or_else(invalid)?.iter();
+            let (name, term) = keys.next().ok_or_else(invalid)?;
+            let mut terms = Terms {
+                name: name.as_str().ok_or_else(invalid)?.to_string(),
+                term: term.as_str().ok_or_else(invalid)?.to_string(),
+                additional: Vec::new(),
+                properties: Vec::new(),
+            };
+            for (key, value) in keys {
+                match key.as_str().ok_or_else(invalid)? {
+                    "properties" => terms.properties = terms_list(Some(value))?,
+                    key => {
+                        let term = value.as_str().ok_or_else(invalid)?;
+                        terms.additional.push((key.to_string(), term.to_string()));
+                    }
+                }
+            }
+            Ok(terms)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{cst::NodeId, parse};
+
+    const VOCABULARY: &str = "locale: en
+namespace: org.acme@1.0.0
+declarations:
+  - Vehicle: A road vehicle
+    properties:
+      - vin: Vehicle Identification Number
+        tooltip: \"VIN, on the \\\"plate\\\"\"
+      - wheels: Wheels
+  - Truck: A vehicle capable of carrying cargo
+";
+
+    #[test]
+    fn test_vocabulary() {
+        let vocabulary = super::Vocabulary::parse(VOCABULARY).unwrap();
+        assert_eq!(vocabulary.locale, "en");
+        assert_eq!(
+            vocabulary.term("Vehicle", Some("vin")),
+            Some("Vehicle Identification Number")
+        );
+        assert_eq!(
+            vocabulary.terms("Vehicle", Some("vin")).unwrap().additional,
+            vec![(
+                String::from("tooltip"),
+                String::from("VIN, on the \"plate\"")
+            )]
+        );
+
+        let model = parse(
+            "namespace org.acme@1.0.0
+concept Vehicle {
+  o String vin
+}
+",
+        )
+        .unwrap();
+        assert!(vocabulary.is_for(&model.namespace));
+        let linked = vocabulary.link(&model);
+        assert_eq!(
+            linked
+                .terms
+                .iter()
+                .map(|(node, terms)| (*node, terms.term.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (NodeId::Declaration(0), "A road vehicle"),
+                (NodeId::Property(0, 0), "Vehicle Identification Number"),
+            ]
+        );
+        assert_eq!(
+            linked.unknown,
+            vec!["Vehicle.wheels", "Truck"],
+            "Should report terms of missing nodes"
+        );
+        assert_eq!(
+            vocabulary.decorate(&model),
+            "namespace org.acme@1.0.0
+
+@Term(\"A road vehicle\")
+concept Vehicle {
+  @Term(\"Vehicle Identification Number\")
+  @Term_tooltip(\"VIN, on the \\\"plate\\\"\")
+  o String vin
+}
+"
+        );
+
+        let decorated = parse(&vocabulary.decorate(&model)).unwrap();
+        assert_eq!(
+            decorated.declarations[0].decorators_of(Some("vin")).count(),
+            2,
+            "Should parse the terms back as decorators"
+        );
+        assert_eq!(
+            vocabulary.decorate(&decorated),
+            vocabulary.decorate(&model),
+            "Should replace the terms a model already has"
+        );
+
+        assert!(super::Vocabulary::parse("locale: en\n").is_err());
+    }
+}
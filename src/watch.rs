@@ -0,0 +1,407 @@
+//! Watches model files, and parses them again when they change. `LiveModelStore` keeps a
+//! `ModelManager` of a directory up to date, and validates again what changes can break.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    analysis,
+    manager::ModelManager,
+    parser::{
+        file::{parse_file_with, FileError},
+        namespace::Namespace,
+        options::ParserOptions,
+        Model,
+    },
+};
+
+/// Editors write files in several steps, events this close to each other are batched
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// What happened to a watched `.cto` file
+#[derive(Debug)]
+// Events are built one per changed file, boxing models wouldn't save anything
+#[allow(clippy::large_enum_variant)]
+pub enum WatchEvent {
+    /// The file was created or written, and parsed again
+    Changed {
+        path: PathBuf,
+        result: Result<Model, FileError>,
+    },
+    Removed {
+        path: PathBuf,
+    },
+    /// The watcher itself failed, e.g. the watched directory was removed
+    Error(notify::Error),
+}
+
+/// Watches a directory recursively, or a single file, for changes of `.cto` files
+pub struct ModelWatcher {
+    // Dropping the watcher stops it
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// Options changed files are parsed with
+    options: ParserOptions,
+}
+
+impl ModelWatcher {
+    pub fn new(path: impl AsRef<Path>) -> notify::Result<Self> {
+        Self::new_with(path, &ParserOptions::default())
+    }
+
+    /// A watcher parsing changed files with the given options
+    pub fn new_with(path: impl AsRef<Path>, options: &ParserOptions) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
+        Ok(Self {
+            watcher,
+            events,
+            options: *options,
+        })
+    }
+
+    /// Watches another directory or file along with the ones already watched
+    pub fn add(&mut self, path: impl AsRef<Path>) -> notify::Result<()> {
+        self.watcher.watch(path.as_ref(), RecursiveMode::Recursive)
+    }
+
+    /// Blocks until files change, and returns an event per changed file. Returns `None`
+    /// when the watcher stopped.
+    pub fn recv(&self) -> Option<Vec<WatchEvent>> {
+        loop {
+            let batch = self.batch(self.events.recv().ok()?);
+            if !batch.is_empty() {
+                return Some(batch);
+            }
+        }
+    }
+
+    /// Like `recv`, returns an empty batch if nothing changed before the timeout
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Vec<WatchEvent>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let batch = match self.events.recv_timeout(timeout) {
+                Ok(first) => self.batch(first),
+                Err(RecvTimeoutError::Timeout) => return Some(Vec::new()),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            };
+            // Reading the files, e.g. to parse them, only causes access events
+            if !batch.is_empty() {
+                return Some(batch);
+            }
+        }
+    }
+
+    /// Collects the events following the first one, and turns them into an event per file
+    fn batch(&self, first: notify::Result<notify::Event>) -> Vec<WatchEvent> {
+        let mut raw = vec![first];
+        while let Ok(event) = self.events.recv_timeout(SETTLE) {
+            raw.push(event);
+        }
+
+        let mut errors = Vec::new();
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for event in raw {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(event) => paths.extend(
+                    event
+                        .paths
+                        .into_iter()
+                        .filter(|p| p.extension().is_some_and(|e| e == "cto")),
+                ),
+                Err(e) => errors.push(WatchEvent::Error(e)),
+            }
+        }
+        paths.sort();
+        paths.dedup();
+
+        // Files are looked at once the events settled, so the last state of each counts
+        let mut events: Vec<_> = paths
+            .into_iter()
+            .map(|path| match path.exists() {
+                true => WatchEvent::Changed {
+                    result: parse_file_with(&path, &self.options),
+                    path,
+                },
+                false => WatchEvent::Removed { path },
+            })
+            .collect();
+        events.extend(errors);
+        events
+    }
+}
+
+/// What changed in a `LiveModelStore`
+#[derive(Debug)]
+pub enum StoreEvent {
+    /// The model of a file was added or replaced
+    Updated {
+        namespace: Namespace,
+        path: PathBuf,
+    },
+    /// A file was removed, and the model loaded from it with it
+    Removed {
+        namespace: Namespace,
+        path: PathBuf,
+    },
+    /// A file doesn't parse, the model loaded from it before is kept
+    Invalid(FileError),
+    /// A model was validated again, as it or a model it depends on changed
+    Validated {
+        namespace: Namespace,
+        /// Imports and types that don't resolve, empty when the model is valid
+        problems: Vec<String>,
+    },
+    Error(notify::Error),
+}
+
+/// The models of a directory, loaded and kept up to date as their files change
+pub struct LiveModelStore {
+    watcher: ModelWatcher,
+    manager: ModelManager,
+}
+
+impl LiveModelStore {
+    /// Loads the models of a directory and starts watching it. Files that don't parse are
+    /// returned, and picked up once they are fixed.
+    pub fn open(dir: impl AsRef<Path>) -> notify::Result<(Self, Vec<FileError>)> {
+        Self::open_with(dir, &ParserOptions::default())
+    }
+
+    /// Like `open`, parses the files with the given options
+    pub fn open_with(
+        dir: impl AsRef<Path>,
+        options: &ParserOptions,
+    ) -> notify::Result<(Self, Vec<FileError>)> {
+        let dir = dir.as_ref();
+        // Watching first, so changes made while loading aren't missed
+        let watcher = ModelWatcher::new_with(dir, options)?;
+        let (manager, errors) = ModelManager::from_dir_with(dir, options);
+        Ok((Self { watcher, manager }, errors))
+    }
+
+    pub fn manager(&self) -> &ModelManager {
+        &self.manager
+    }
+
+    /// Blocks until files change, applies the changes and returns what changed. Returns
+    /// `None` when the watcher stopped.
+    pub fn recv(&mut self) -> Option<Vec<StoreEvent>> {
+        let events = self.watcher.recv()?;
+        Some(self.apply(events))
+    }
+
+    /// Like `recv`, returns no events if nothing changed before the timeout
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Vec<StoreEvent>> {
+        let events = self.watcher.recv_timeout(timeout)?;
+        Some(self.apply(events))
+    }
+
+    fn apply(&mut self, events: Vec<WatchEvent>) -> Vec<StoreEvent> {
+        // Namespaces whose models change, as they were before and as they are after
+        let mut changed = Vec::new();
+        for event in &events {
+            if let WatchEvent::Changed { path, .. } | WatchEvent::Removed { path } = event {
+                if let Some(model) = self.manager.model_at(path) {
+                    changed.push(model.namespace.clone());
+                }
+            }
+        }
+        // Dependents of declarations that are removed or renamed are only in the graph as
+        // it was before the changes
+        let mut to_validate = affected(&self.manager, &changed);
+
+        let mut store_events = Vec::new();
+        for event in events {
+            match event {
+                WatchEvent::Changed {
+                    path,
+                    result: Ok(model),
+                } => {
+                    let namespace = model.namespace.clone();
+                    self.manager.add_parsed_file(&path, model);
+                    changed.push(namespace.clone());
+                    store_events.push(StoreEvent::Updated { namespace, path });
+                }
+                WatchEvent::Changed { result: Err(e), .. } => {
+                    store_events.push(StoreEvent::Invalid(e))
+                }
+                WatchEvent::Removed { path } => {
+                    let namespace = match self.manager.model_at(&path) {
+                        Some(model) => model.namespace.clone(),
+                        None => continue,
+                    };
+                    self.manager.remove_model(&namespace);
+                    store_events.push(StoreEvent::Removed { namespace, path });
+                }
+                WatchEvent::Error(e) => store_events.push(StoreEvent::Error(e)),
+            }
+        }
+
+        to_validate.extend(affected(&self.manager, &changed));
+        for model in self.manager.models() {
+            if to_validate.contains(&model.namespace) {
+                store_events.push(StoreEvent::Validated {
+                    namespace: model.namespace.clone(),
+                    problems: analysis::unresolved(&self.manager, model),
+                });
+            }
+        }
+        store_events
+    }
+}
+
+/// Namespaces of the models that a change of the given namespaces can break: the models
+/// themselves, models with declarations depending on theirs, and models importing from a
+/// namespace of the same name, whose imports may resolve differently now
+fn affected(manager: &ModelManager, changed: &[Namespace]) -> Vec<Namespace> {
+    let graph = manager.dependency_graph();
+    let mut affected: Vec<Namespace> = changed.to_vec();
+    for (node, (model, _)) in graph.nodes().iter().enumerate() {
+        if changed.contains(&model.namespace) {
+            affected.extend(
+                graph
+                    .affected_by(node)
+                    .into_iter()
+                    .map(|i| graph.nodes()[i].0.namespace.clone()),
+            );
+        }
+    }
+    for model in manager.models() {
+        let imports_changed = model.imports.iter().any(|import| {
+            changed
+                .iter()
+                .any(|namespace| import.namespace().name() == namespace.name())
+        });
+        if imports_changed {
+            affected.push(model.namespace.clone());
+        }
+    }
+    affected.sort_by_key(|namespace| namespace.to_string());
+    affected.dedup();
+    affected
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{StoreEvent, WatchEvent};
+
+    #[test]
+    fn test_model_watcher() {
+        let dir = std::env::temp_dir().join(format!("concerto-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let watcher = super::ModelWatcher::new(&dir).unwrap();
+        let path = dir.join("person.cto");
+
+        std::fs::write(
+            &path,
+            "namespace test@1.0.0\n\nconcept Person {\n  o String name\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a model").unwrap();
+        let events = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(
+                events.as_slice(),
+                [WatchEvent::Changed { path: p, result: Ok(model) }]
+                    if p == &path && model.declaration("Person").is_some()
+            ),
+            "Should parse changed model files only, got {:?}",
+            events
+        );
+
+        std::fs::write(&path, "namespace test@1.0.0\n\nconcept {\n").unwrap();
+        let events = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(
+                events.as_slice(),
+                [WatchEvent::Changed { result: Err(_), .. }]
+            ),
+            "Should report parse errors, got {:?}",
+            events
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let events = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(events.as_slice(), [WatchEvent::Removed { path: p }] if p == &path),
+            "Should report removed files, got {:?}",
+            events
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_live_model_store() {
+        let dir = std::env::temp_dir().join(format!("concerto-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("people.cto"),
+            "namespace people@1.0.0\n\nconcept Person {\n  o String name\n}\n",
+        )
+        .unwrap();
+        let orders = dir.join("orders.cto");
+        std::fs::write(
+            &orders,
+            "namespace orders@1.0.0\nimport people@1.0.0.Person\n\nconcept Order {\n  o Person buyer\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("places.cto"),
+            "namespace places@1.0.0\n\nconcept Address {\n  o String city\n}\n",
+        )
+        .unwrap();
+        let (mut store, errors) = super::LiveModelStore::open(&dir).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(store.manager().models().count(), 3);
+
+        std::fs::write(
+            dir.join("people.cto"),
+            "namespace people@1.0.0\n\nconcept Human {\n  o String name\n}\n",
+        )
+        .unwrap();
+        let events = store.recv_timeout(Duration::from_secs(5)).unwrap();
+        let summary: Vec<_> = events
+            .iter()
+            .map(|event| match event {
+                StoreEvent::Updated { namespace, .. } => format!("updated {}", namespace),
+                StoreEvent::Validated {
+                    namespace,
+                    problems,
+                } => format!("validated {} {:?}", namespace, problems),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "updated namespace people@1.0.0",
+                "validated namespace orders@1.0.0 [\"unknown type Person of Order.buyer\"]",
+                "validated namespace people@1.0.0 []",
+            ],
+            "Should validate the changed model and its dependents only"
+        );
+
+        std::fs::remove_file(&orders).unwrap();
+        let events = store.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(events.as_slice(), [StoreEvent::Removed { path, .. }] if path == &orders),
+            "Should remove the model of removed files, got {:?}",
+            events
+        );
+        assert_eq!(store.manager().models().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}